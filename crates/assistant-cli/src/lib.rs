@@ -44,6 +44,18 @@ enum Commands {
         command: TuiCommands,
     },
     
+    /// List or restore the file checkpoints of a chat session
+    Checkpoint {
+        #[command(subcommand)]
+        command: CheckpointCommands,
+    },
+    
+    /// Review, merge or discard the worktrees the agent worked in
+    Worktree {
+        #[command(subcommand)]
+        command: WorktreeCommands,
+    },
+    
 }
 
 #[derive(Subcommand)]
@@ -65,6 +77,48 @@ enum TuiCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum CheckpointCommands {
+    /// List the turns that changed files, with the files each changed
+    List {
+        /// Chat session ID
+        session_id: String,
+    },
+    
+    /// Restore files to how they were before a turn, undoing it and every later turn
+    Restore {
+        /// Chat session ID
+        session_id: String,
+        
+        /// Turn number, as shown by `checkpoint list`
+        turn: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorktreeCommands {
+    /// List the agent's worktrees in the current repository
+    List,
+    
+    /// Show what a worktree changed, including changes not committed yet
+    Diff {
+        /// Worktree name, as shown by `worktree list`
+        name: String,
+    },
+    
+    /// Merge a worktree's branch into the current branch, then remove the worktree
+    Merge {
+        /// Worktree name, as shown by `worktree list`
+        name: String,
+    },
+    
+    /// Remove a worktree and its branch, dropping its changes
+    Discard {
+        /// Worktree name, as shown by `worktree list`
+        name: String,
+    },
+}
+
 mod tool_runner;
 mod prompt_runner;
 
@@ -100,23 +154,24 @@ pub async fn run_cli() -> Result<()> {
             };
             
             let registry = ToolRegistry::new(config);
-            let all_tools = ToolRegistry::available_tools();
-            let enabled_tools = registry.enabled_tools();
-            let descriptions = ToolRegistry::tool_descriptions();
             
             println!("Available tools:\n");
             
-            for tool in all_tools {
-                let enabled = enabled_tools.contains(&tool);
-                let status = if enabled { "✓ enabled " } else { "✗ disabled" };
-                let description = descriptions.get(tool)
-                    .map(|(_, desc)| *desc)
-                    .unwrap_or("No description available");
+            for spec in ToolRegistry::specs() {
+                let status = if registry.is_enabled(spec.name) { "✓ enabled " } else { "✗ disabled" };
+                let access = if spec.read_only {
+                    "read-only  "
+                } else if spec.destructive {
+                    "destructive"
+                } else {
+                    "           "
+                };
                 
-                println!("  {} [{}] - {}", 
-                    tool.to_string().pad_to_width(20),
+                println!("  {} [{}] [{}] - {}", 
+                    spec.name.to_string().pad_to_width(20),
                     status,
-                    description
+                    access,
+                    spec.description
                 );
             }
             
@@ -228,6 +283,86 @@ pub async fn run_cli() -> Result<()> {
             }
         }
         
+        Commands::Checkpoint { command } => {
+            use assistant_core::persistence::{CheckpointManager, checkpoints};
+            
+            // Load configuration
+            let config = match cli.config.as_deref() {
+                Some(path) => Config::load(std::path::Path::new(path))?,
+                None => Config::load_default().unwrap_or_else(|_| {
+                    eprintln!("Warning: Could not load config.json, using defaults");
+                    Config::default()
+                }),
+            };
+            let manager = CheckpointManager::open(&config).await?;
+            
+            match command {
+                CheckpointCommands::List { session_id } => {
+                    let turns = manager.list(&session_id).await?;
+                    println!("{}", checkpoints::describe_turns(&turns));
+                    if !turns.is_empty() {
+                        println!("\nTo restore: assistant checkpoint restore {} <turn>", session_id);
+                    }
+                }
+                
+                CheckpointCommands::Restore { session_id, turn } => {
+                    let restored = manager.restore(&session_id, turn).await?;
+                    println!("{}", checkpoints::describe_restore(turn, &restored));
+                }
+            }
+        }
+        
+        Commands::Worktree { command } => {
+            use assistant_core::worktree::Worktrees;
+            
+            // Load configuration
+            let config = match cli.config.as_deref() {
+                Some(path) => Config::load(std::path::Path::new(path))?,
+                None => Config::load_default().unwrap_or_else(|_| {
+                    eprintln!("Warning: Could not load config.json, using defaults");
+                    Config::default()
+                }),
+            };
+            let repository = match config.session.workspace_path {
+                Some(workspace) => workspace,
+                None => std::env::current_dir()?,
+            };
+            let worktrees = Worktrees::open(&repository)?;
+            
+            match command {
+                WorktreeCommands::List => {
+                    let list = worktrees.list()?;
+                    if list.is_empty() {
+                        println!("No agent worktrees in this repository.");
+                    } else {
+                        for worktree in list {
+                            let changes = worktrees.diff(&worktree.name)
+                                .map(|diff| diff.summary())
+                                .unwrap_or_else(|e| e.to_string());
+                            println!("{:<20} {:<22} {}", worktree.name, worktree.branch, changes);
+                            println!("{:<20} {}", "", worktree.path.display());
+                        }
+                        println!("\nTo review one: assistant worktree diff <name>");
+                    }
+                }
+                
+                WorktreeCommands::Diff { name } => {
+                    let diff = worktrees.diff(&name)?;
+                    print!("{}", diff.patch);
+                    println!("{}", diff.summary());
+                }
+                
+                WorktreeCommands::Merge { name } => {
+                    println!("{}", worktrees.merge(&name)?);
+                }
+                
+                WorktreeCommands::Discard { name } => {
+                    worktrees.discard(&name)?;
+                    println!("Discarded worktree {} and its branch", name);
+                }
+            }
+        }
+        
     }
     
     Ok(())
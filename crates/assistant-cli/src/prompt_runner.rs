@@ -8,6 +8,7 @@ use assistant_core::{
     messages::{ChatMessage, DisplayContext},
     ractor::Actor,
     openai_compat::{ChatMessage as OpenAIMessage, UserContent},
+    worktree,
 };
 use tokio::sync::mpsc;
 use assistant_core::actor_init;
@@ -35,12 +36,39 @@ pub async fn run_agent_prompt(input: String, max_iterations: usize, config_path:
     
     // Initialize actor system
     let actors = actor_init::init_actor_system(config).await?;
+    if let Some(worktree) = &actors.worktree {
+        eprintln!("Working in {} on branch {}", worktree.path.display(), worktree.branch);
+        eprintln!("Review with `assistant worktree diff {}`, then `merge` or `discard` it", worktree.name);
+    }
+    
+    // Finish the worktree however the prompt ends
+    let result = run_prompt(&actors, messages, max_iterations).await;
+    
+    // A worktree the agent changed nothing in is only clutter
+    if let Some(worktree) = &actors.worktree {
+        match worktree::finish(worktree) {
+            Ok(true) => eprintln!("Removed worktree {}: nothing was changed in it", worktree.name),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to check worktree {} for changes: {}", worktree.name, e),
+        }
+    }
+    
+    result
+}
+
+/// Send the prompt to the chat and wait until it is answered
+async fn run_prompt(actors: &actor_init::ActorSystem, messages: Vec<OpenAIMessage>, max_iterations: usize) -> Result<()> {
+    // Relative paths in commands then resolve inside the worktree
+    if let Some(workspace) = &actors.workspace {
+        std::env::set_current_dir(workspace)?;
+    }
     
     // Create completion channel
     let (completion_tx, mut completion_rx) = mpsc::unbounded_channel();
     
     // Create CLI display actor
-    let cli_display = CLIDisplayActor::new(completion_tx.clone());
+    let cli_display = CLIDisplayActor::new(completion_tx.clone())
+        .with_chat_ref(actors.chat.clone());
     let (display_ref, _) = Actor::spawn(
         Some("cli_display".to_string()),
         cli_display,
@@ -82,12 +110,19 @@ pub async fn run_agent_prompt(input: String, max_iterations: usize, config_path:
     // Wait for completion
     // The tool calling loop happens automatically in ChatActor
     let mut iterations = 0;
+    let mut cancelled = false;
     loop {
         tokio::select! {
             _ = completion_rx.recv() => {
                 tracing::debug!("Received completion signal");
                 break;
             }
+            // Ctrl-C cancels the request; the chat actor then reports it as finished
+            _ = tokio::signal::ctrl_c(), if !cancelled => {
+                println!("\n⏹  Cancelling...");
+                actors.chat.send_message(ChatMessage::Cancel)?;
+                cancelled = true;
+            }
             _ = tokio::time::sleep(tokio::time::Duration::from_secs(60)) => {
                 iterations += 1;
                 if iterations >= max_iterations {
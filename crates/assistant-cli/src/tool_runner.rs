@@ -1,7 +1,7 @@
 use anyhow::{Result, anyhow};
 use assistant_core::{
     actors::{tools::*, delegator::DelegatorActor},
-    config::Config,
+    config::{Config, PermissionAction},
     messages::{ToolMessage, ChatMessage, DelegatorMessage, ToolCall},
     ractor::{Actor, ActorRef},
     serde_json::Value,
@@ -40,6 +40,10 @@ impl Actor for SimpleChatActor {
                 // Send the result through the channel
                 let _ = self.sender.send(result);
             }
+            ChatMessage::Error { id: _, error } => {
+                // e.g. a call refused by the permission policy
+                let _ = self.sender.send(format!("Error: {}", error));
+            }
             _ => {
                 // Ignore other message types
             }
@@ -90,6 +94,16 @@ pub async fn run_tool(tool_name: &str, params: Value, config_path: Option<&str>)
         // Use the DelegatorActor so the request is handled by the configured
         // sub-agent (e.g. vision model for `computer_use`).
         // ------------------------------------------------------------------
+        // Running a tool by hand is its own confirmation, so only deny rules still apply
+        let mut config = config.clone();
+        config.permissions.default_action = PermissionAction::Allow;
+        config.permissions.destructive_action = PermissionAction::Allow;
+        for rule in &mut config.permissions.rules {
+            if rule.action == PermissionAction::Ask {
+                rule.action = PermissionAction::Allow;
+            }
+        }
+        
         let delegator = DelegatorActor::new(config.clone());
         let (delegator_ref, _) = Actor::spawn(
             Some("delegator".to_string()),
@@ -100,6 +114,7 @@ pub async fn run_tool(tool_name: &str, params: Value, config_path: Option<&str>)
 
         // Build the ToolCall to route through the delegator
         let call = ToolCall {
+            id: format!("call_{}", id),
             tool_name: tool_name.to_string(),
             parameters: params.clone(),
             delegate: true,
@@ -109,6 +124,8 @@ pub async fn run_tool(tool_name: &str, params: Value, config_path: Option<&str>)
             id,
             call,
             chat_ref: chat_ref.clone(),
+            session_id: id.to_string(),
+            turn: id,
         })?;
     } else {
         // ------------------------------------------------------------------
@@ -155,6 +172,16 @@ pub async fn run_tool(tool_name: &str, params: Value, config_path: Option<&str>)
                 .await?;
                 actor_ref
             }
+            "apply_patch" => {
+                let actor = ApplyPatchActor::new(config.clone());
+                let (actor_ref, _) = Actor::spawn(
+                    Some(tool_name.to_string()),
+                    actor,
+                    config.clone(),
+                )
+                .await?;
+                actor_ref
+            }
             "glob" => {
                 let actor = GlobActor::new(config.clone());
                 let (actor_ref, _) = Actor::spawn(
@@ -195,6 +222,16 @@ pub async fn run_tool(tool_name: &str, params: Value, config_path: Option<&str>)
                 .await?;
                 actor_ref
             }
+            "git" => {
+                let actor = GitActor::new(config.clone());
+                let (actor_ref, _) = Actor::spawn(
+                    Some(tool_name.to_string()),
+                    actor,
+                    config.clone(),
+                )
+                .await?;
+                actor_ref
+            }
             "web_search" => {
                 let actor = WebSearchActor::new(config.clone());
                 let (actor_ref, _) = Actor::spawn(
@@ -235,6 +272,16 @@ pub async fn run_tool(tool_name: &str, params: Value, config_path: Option<&str>)
                 .await?;
                 actor_ref
             }
+            "checkpoint" => {
+                let actor = CheckpointActor::new(config.clone()).await?;
+                let (actor_ref, _) = Actor::spawn(
+                    Some(tool_name.to_string()),
+                    actor,
+                    config.clone(),
+                )
+                .await?;
+                actor_ref
+            }
             "knowledge_agent" => {
                 let actor = knowledge_agent::KnowledgeAgentActor::new(config.clone()).await?;
                 let (actor_ref, _) = Actor::spawn(
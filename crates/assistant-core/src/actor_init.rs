@@ -11,20 +11,25 @@ use crate::{
             read::ReadActor,
             write::WriteActor,
             edit::EditActor,
+            apply_patch::ApplyPatchActor,
             glob::GlobActor,
             grep::GrepActor,
             bash::BashActor,
+            git::GitActor,
             web_search::WebSearchActor,
             web_fetch::WebFetchActor,
             memory::MemoryActor,
             todo::TodoActor,
+            checkpoint::CheckpointActor,
             read_many_files::ReadManyFilesActor,
         },
     },
     config::Config,
     messages::{ChatMessage, DelegatorMessage, ToolMessage},
     ractor::{Actor, ActorRef},
+    worktree::{self, AgentWorktree},
 };
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// All the actor references needed for the system
@@ -38,12 +43,25 @@ pub struct ActorSystem {
     pub delegator: ActorRef<DelegatorMessage>,
     #[allow(dead_code)]
     pub persistence: Option<ActorRef<ChatPersistenceMessage>>,
+    /// The worktree the agent works in, with `session.worktree`
+    pub worktree: Option<AgentWorktree>,
+    /// With a worktree, the directory in it the session works in, which the
+    /// process should change into
+    pub workspace: Option<PathBuf>,
 }
 
 /// Initialize all actors and wire them together
-pub async fn init_actor_system(config: Config) -> Result<ActorSystem> {
+pub async fn init_actor_system(mut config: Config) -> Result<ActorSystem> {
     tracing::info!("Initializing actor system");
     
+    // Before any tool is created, so they all work in the worktree
+    let worktree = if config.session.worktree {
+        Some(worktree::isolate(&mut config, &worktree::default_location())?)
+    } else {
+        None
+    };
+    let workspace = worktree.as_ref().and(config.session.workspace_path.clone());
+    
     let config = Arc::new(config);
     
     // Create supervisor
@@ -112,6 +130,8 @@ pub async fn init_actor_system(config: Config) -> Result<ActorSystem> {
         client: client_ref,
         delegator: delegator_ref,
         persistence: Some(persistence_ref),
+        worktree,
+        workspace,
     })
 }
 
@@ -179,6 +199,20 @@ async fn register_tools(delegator_ref: &ActorRef<DelegatorMessage>, config: &Con
         })?;
     }
     
+    // Register apply_patch tool
+    if is_enabled("apply_patch") {
+        let (apply_patch_ref, _): (ActorRef<ToolMessage>, _) = Actor::spawn(
+            Some("tool_apply_patch".to_string()),
+            ApplyPatchActor::new(config.clone()),
+            config.clone(),
+        )
+        .await?;
+        delegator_ref.send_message(DelegatorMessage::RegisterTool {
+            name: "apply_patch".to_string(),
+            actor_ref: apply_patch_ref,
+        })?;
+    }
+    
     // Register glob tool
     if is_enabled("glob") {
         let (glob_ref, _): (ActorRef<ToolMessage>, _) = Actor::spawn(
@@ -221,6 +255,20 @@ async fn register_tools(delegator_ref: &ActorRef<DelegatorMessage>, config: &Con
         })?;
     }
     
+    // Register git tool
+    if is_enabled("git") {
+        let (git_ref, _): (ActorRef<ToolMessage>, _) = Actor::spawn(
+            Some("tool_git".to_string()),
+            GitActor::new(config.clone()),
+            config.clone(),
+        )
+        .await?;
+        delegator_ref.send_message(DelegatorMessage::RegisterTool {
+            name: "git".to_string(),
+            actor_ref: git_ref,
+        })?;
+    }
+    
     // Register web_search tool
     if is_enabled("web_search") {
         let (web_search_ref, _): (ActorRef<ToolMessage>, _) = Actor::spawn(
@@ -279,6 +327,21 @@ async fn register_tools(delegator_ref: &ActorRef<DelegatorMessage>, config: &Con
         })?;
     }
     
+    // Register checkpoint tool
+    if is_enabled("checkpoint") {
+        let checkpoint_actor = CheckpointActor::new(config.clone()).await?;
+        let (checkpoint_ref, _): (ActorRef<ToolMessage>, _) = Actor::spawn(
+            Some("tool_checkpoint".to_string()),
+            checkpoint_actor,
+            config.clone(),
+        )
+        .await?;
+        delegator_ref.send_message(DelegatorMessage::RegisterTool {
+            name: "checkpoint".to_string(),
+            actor_ref: checkpoint_ref,
+        })?;
+    }
+    
     // Register read_many_files tool
     if is_enabled("read_many_files") {
         let (read_many_ref, _): (ActorRef<ToolMessage>, _) = Actor::spawn(
@@ -1,13 +1,29 @@
-use ractor::{Actor, ActorRef, ActorProcessingErr};
-use std::collections::VecDeque;
+use ractor::{Actor, ActorRef, ActorProcessingErr, MessagingErr};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 use crate::config::Config;
-use crate::messages::{ChatMessage, DisplayContext, UserMessageContent};
-use crate::actors::client::ClientMessage;
+use crate::messages::{ChatMessage, ConfirmationDecision, DisplayContext, Stats, TokenUsage, UIMessage, UserMessageContent};
+use crate::actors::client::{ClientMessage, Completion};
 use crate::messages::DelegatorMessage;
 use crate::actors::chat_persistence::ChatPersistenceMessage;
-use crate::openai_compat::{ChatMessage as OpenAIMessage, Tool, FunctionDef, UserContent};
+use crate::openai_compat::{ChatMessage as OpenAIMessage, Tool, UserContent};
+use crate::actors::tools::{ToolRegistry, take_image};
+use crate::context::{self, TokenCounter};
+use crate::persistence::checkpoints::{self, CheckpointManager};
+use crate::worktree::Worktrees;
+use crate::auto_commit::{self, TurnChanges};
+use std::path::PathBuf;
 use uuid::Uuid;
 
+/// How long to wait for the model to summarize older turns
+const COMPACTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long to wait for the model to write a commit message before using a plain one
+const COMMIT_MESSAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Result recorded for a tool call that was still running when its request was cancelled
+const CANCELLED_RESULT: &str = "Cancelled by the user before it finished";
+
 /// Main chat actor managing conversation flow
 pub struct ChatActor {
     config: Config,
@@ -15,6 +31,8 @@ pub struct ChatActor {
     delegator_ref: Option<ActorRef<DelegatorMessage>>,
     persistence_ref: Option<ActorRef<ChatPersistenceMessage>>,
     session_id: String,
+    /// Definitions of the enabled tools, offered to the model
+    tools: Vec<Tool>,
 }
 
 /// Chat actor state
@@ -28,8 +46,36 @@ pub struct ChatState {
     persistence_ref: Option<ActorRef<ChatPersistenceMessage>>,
     display_refs: std::collections::HashMap<DisplayContext, ActorRef<ChatMessage>>,
     session_id: String,
-    // Track active tool calls by ID
-    active_tool_calls: std::collections::HashMap<Uuid, String>,
+    // Track active tool calls by routing ID: (tool name, provider tool_call id)
+    active_tool_calls: std::collections::HashMap<Uuid, (String, String)>,
+    /// Results of the current turn's tool calls by provider id, in call order
+    pending_tool_results: Vec<(String, Option<String>)>,
+    /// Images the current turn's tool calls returned, with the tool's name
+    pending_tool_images: Vec<(String, String)>,
+    /// Files the current request's tool calls set out to change, to commit once it completes
+    changed_paths: Vec<PathBuf>,
+    /// Those of `changed_paths` that already had uncommitted changes before the
+    /// request touched them, which its commit leaves out
+    uncommitted_paths: Vec<PathBuf>,
+    /// The latest completed request's commit, which the next one waits for
+    committing: Option<tokio::task::JoinHandle<()>>,
+    /// Token usage of the current request, including delegated sub-agents
+    request_usage: TokenUsage,
+    /// Token usage of the whole session, seeded from persisted totals
+    session_usage: TokenUsage,
+    /// Session usage broken down by sub-agent tool name
+    sub_agent_usage: HashMap<String, TokenUsage>,
+    tools_executed: usize,
+    session_started: Instant,
+    /// Counts prompt tokens against the model's context budget
+    token_counter: TokenCounter,
+    /// Opened by the first /undo
+    checkpoints: Option<CheckpointManager>,
+    /// Persisted user messages by their index in `messages`, with the id they
+    /// are stored under, so a compaction can name the first one it keeps
+    prompt_rows: Vec<(usize, Uuid)>,
+    /// The request a compaction is summarizing older turns for
+    compacting: Option<Uuid>,
 }
 
 impl Actor for ChatActor {
@@ -62,6 +108,20 @@ impl Actor for ChatActor {
             display_refs: std::collections::HashMap::new(),
             session_id: self.session_id.clone(),
             active_tool_calls: std::collections::HashMap::new(),
+            pending_tool_results: Vec::new(),
+            pending_tool_images: Vec::new(),
+            changed_paths: Vec::new(),
+            uncommitted_paths: Vec::new(),
+            request_usage: TokenUsage::default(),
+            session_usage: TokenUsage::default(),
+            sub_agent_usage: HashMap::new(),
+            tools_executed: 0,
+            session_started: Instant::now(),
+            token_counter: TokenCounter::new(&self.config.context, &self.config.model),
+            committing: None,
+            checkpoints: None,
+            prompt_rows: Vec::new(),
+            compacting: None,
         })
     }
     
@@ -71,11 +131,33 @@ impl Actor for ChatActor {
         msg: Self::Msg,
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
+        // A cancelled request's stream may still be winding down; drop what it sends
+        if let ChatMessage::AssistantResponse { id, .. } = &msg
+            && state.current_request != Some(*id)
+        {
+            tracing::info!("Ignoring response to request {}, which is no longer in progress", id);
+            return Ok(());
+        }
+        
+        // Images in tool results go to the model as image content once the turn's
+        // results are in; everywhere else the result has a placeholder instead
+        let (msg, tool_image) = match msg {
+            ChatMessage::ToolResult { id, result } => match take_image(&result) {
+                Some((result, image)) => (ChatMessage::ToolResult { id, result }, Some(image)),
+                None => (ChatMessage::ToolResult { id, result }, None),
+            },
+            msg => (msg, None),
+        };
+        
         // Send to display actors if applicable
         match &msg {
+            // Failed tool calls are reported to displays as tool results instead
+            ChatMessage::Error { id, .. } if state.active_tool_calls.contains_key(id) => {}
+            ChatMessage::StreamToken { .. } if state.current_request.is_none() => {}
             ChatMessage::StreamToken { .. } |
             ChatMessage::ToolRequest { .. } |
             ChatMessage::ToolResult { .. } |
+            ChatMessage::ToolOutput { .. } |
             ChatMessage::AssistantResponse { .. } |
             ChatMessage::Complete { .. } |
             ChatMessage::Error { .. } => {
@@ -101,13 +183,52 @@ impl Actor for ChatActor {
                     if state.session_id.is_empty() || state.session_id != provided_session_id {
                         tracing::info!("Using session_id from UserPrompt: {}", provided_session_id);
                         state.session_id = provided_session_id;
+                        Self::load_session_usage(state).await;
                     }
                 }
                 
                 tracing::info!("Received user prompt: {} (session: {})", prompt_text, state.session_id);
                 state.current_context = Some(context.clone());
+                
+                // Manual compaction is handled here and never reaches the model
+                if prompt_text.trim() == "/compact" {
+                    let response = match self.compact(&myself, state, id, true) {
+                        // Answered once the summary is back
+                        Ok(true) => return Ok(()),
+                        Ok(false) => "Nothing to compact yet".to_string(),
+                        Err(e) => format!("Failed to compact conversation: {}", e),
+                    };
+                    if let Some(display_ref) = state.display_refs.get(&context) {
+                        let _ = display_ref.send_message(ChatMessage::Complete { id, response });
+                    }
+                    return Ok(());
+                }
+                
+                // So is undoing file changes: `/undo`, `/undo <turn>` or `/undo list`
+                if let Some(argument) = prompt_text.trim().strip_prefix("/undo")
+                    && (argument.is_empty() || argument.starts_with(' '))
+                {
+                    let response = self.undo(state, argument.trim()).await;
+                    if let Some(display_ref) = state.display_refs.get(&context) {
+                        let _ = display_ref.send_message(ChatMessage::Complete { id, response });
+                    }
+                    return Ok(());
+                }
+                
+                // And `/worktree`, which describes the worktree the agent works in
+                if prompt_text.trim() == "/worktree" {
+                    let response = self.describe_worktree();
+                    if let Some(display_ref) = state.display_refs.get(&context) {
+                        let _ = display_ref.send_message(ChatMessage::Complete { id, response });
+                    }
+                    return Ok(());
+                }
+                
                 state.history.push_back(ChatMessage::UserPrompt { id, content: content.clone(), context, session_id: Some(state.session_id.clone()) });
                 state.current_request = Some(id);
+                state.request_usage = TokenUsage::default();
+                state.changed_paths.clear();
+                state.uncommitted_paths.clear();
                 
                 // Persist user prompt (currently just text)
                 if let Some(ref persistence_ref) = state.persistence_ref {
@@ -139,21 +260,18 @@ impl Actor for ChatActor {
                     },
                     name: None,
                 };
+                state.prompt_rows.push((state.messages.len(), id));
                 state.messages.push(user_msg);
                 
-                // Build tools list
-                let tools = self.build_tools();
+                match self.compact(&myself, state, id, false) {
+                    // Generated once the summary is back
+                    Ok(true) => return Ok(()),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Failed to compact conversation: {}", e),
+                }
                 
                 // Send to client for generation
-                if let Some(ref client_ref) = self.client_ref {
-                    client_ref.send_message(ClientMessage::Generate {
-                        id,
-                        messages: state.messages.clone(),
-                        tools,
-                    })?;
-                } else {
-                    return Err("Client actor not set".into());
-                }
+                self.generate(state, id)?;
             }
             
             ChatMessage::StreamToken { token } => {
@@ -166,13 +284,20 @@ impl Actor for ChatActor {
                 state.history.push_back(ChatMessage::ToolRequest { id, call });
             }
             
+            ChatMessage::ToolOutput { .. } => {
+                // Only for displays; the model sees the tool's final result
+            }
+            
             ChatMessage::ToolResult { id, result } => {
                 tracing::info!("Tool result received for request {}: {}", id, result);
                 state.history.push_back(ChatMessage::ToolResult { id, result: result.clone() });
+                state.tools_executed += 1;
                 
-                // Get the tool name from our tracking map
-                let tool_name = state.active_tool_calls.remove(&id)
-                    .unwrap_or_else(|| "unknown_tool".to_string());
+                // Look up the tool name and the provider's id for this call
+                let Some((tool_name, tool_call_id)) = state.active_tool_calls.remove(&id) else {
+                    tracing::warn!("Ignoring result for unknown tool call {}", id);
+                    return Ok(());
+                };
                 
                 // Persist tool result as a user message (following API convention)
                 if let Some(ref persistence_ref) = state.persistence_ref {
@@ -191,24 +316,43 @@ impl Actor for ChatActor {
                     tool_name, result
                 );
                 
-                // Add tool result to messages
-                let tool_msg = OpenAIMessage::Tool {
-                    content: contextualized_result,
-                    tool_call_id: id.to_string(),
-                };
-                state.messages.push(tool_msg);
+                match state.pending_tool_results.iter_mut().find(|(call_id, _)| *call_id == tool_call_id) {
+                    Some((_, slot)) => *slot = Some(contextualized_result),
+                    None => {
+                        tracing::warn!("Tool call {} is not part of the current turn", tool_call_id);
+                        return Ok(());
+                    }
+                }
+                if let Some(image) = tool_image {
+                    state.pending_tool_images.push((tool_name, image));
+                }
+                
+                // Wait until every tool call of this turn has a result
+                if state.pending_tool_results.iter().any(|(_, result)| result.is_none()) {
+                    tracing::debug!("Waiting for {} more tool results", state.active_tool_calls.len());
+                    return Ok(());
+                }
+                
+                // Add tool results to messages in the order the assistant issued the calls
+                for (tool_call_id, content) in state.pending_tool_results.drain(..) {
+                    state.messages.push(OpenAIMessage::Tool {
+                        content: content.unwrap_or_default(),
+                        tool_call_id,
+                    });
+                }
+                Self::push_tool_images(state);
+                
+                let request_id = state.current_request.unwrap_or(id);
+                match self.compact(&myself, state, request_id, false) {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Failed to compact conversation: {}", e),
+                }
                 
                 // Continue conversation
-                if let Some(ref client_ref) = self.client_ref {
-                    tracing::info!("Continuing conversation after tool result for request {}", id);
-                    let tools = self.build_tools();
-                    client_ref.send_message(ClientMessage::Generate {
-                        id,
-                        messages: state.messages.clone(),
-                        tools,
-                    })?;
-                } else {
-                    tracing::error!("No client ref to continue conversation after tool result");
+                tracing::info!("Continuing conversation after tool results for request {}", request_id);
+                if let Err(e) = self.generate(state, request_id) {
+                    tracing::error!("Failed to continue conversation after tool results: {}", e);
                 }
             }
             
@@ -227,9 +371,9 @@ impl Actor for ChatActor {
                 let openai_tool_calls = if tool_calls.is_empty() {
                     None
                 } else {
-                    Some(tool_calls.iter().enumerate().map(|(_idx, call)| {
+                    Some(tool_calls.iter().map(|call| {
                         crate::openai_compat::ToolCall {
-                            id: format!("call_{}", Uuid::new_v4()),
+                            id: call.id.clone(),
                             tool_type: "function".to_string(),
                             function: crate::openai_compat::FunctionCall {
                                 name: call.tool_name.clone(),
@@ -258,12 +402,33 @@ impl Actor for ChatActor {
                     state.messages.push(assistant_msg);
                 }
                 
-                // Now process any tool calls
+                // Now process any tool calls; results are collected until the whole turn is answered
+                if !state.pending_tool_results.is_empty() {
+                    tracing::warn!("Discarding {} unanswered tool calls from the previous turn", state.active_tool_calls.len());
+                    state.active_tool_calls.clear();
+                    state.pending_tool_images.clear();
+                }
+                state.pending_tool_results = tool_calls.iter().map(|call| (call.id.clone(), None)).collect();
+                
+                // A response without tool calls ends the request
+                if tool_calls.is_empty() {
+                    state.current_request = None;
+                }
+                
                 for call in tool_calls {
                     let tool_id = Uuid::new_v4();
+                    if self.config.session.auto_commit {
+                        // Before the call runs, so the user's own changes can be told apart
+                        let paths: Vec<PathBuf> = checkpoints::changed_paths(&call.tool_name, &call.parameters)
+                            .into_iter()
+                            .filter(|path| !state.changed_paths.contains(path))
+                            .collect();
+                        state.uncommitted_paths.extend(auto_commit::uncommitted(&self.workspace(), &paths));
+                        state.changed_paths.extend(paths);
+                    }
                     
                     // Track the tool call
-                    state.active_tool_calls.insert(tool_id, call.tool_name.clone());
+                    state.active_tool_calls.insert(tool_id, (call.tool_name.clone(), call.id.clone()));
                     
                     // Send tool request message to display actors
                     let tool_request_msg = ChatMessage::ToolRequest { 
@@ -284,6 +449,8 @@ impl Actor for ChatActor {
                             id: tool_id,
                             call,
                             chat_ref: myself.clone(),
+                            session_id: state.session_id.clone(),
+                            turn: id,
                         })?;
                     }
                 }
@@ -298,6 +465,18 @@ impl Actor for ChatActor {
                 // Clear current request
                 state.current_request = None;
                 
+                if self.config.session.auto_commit && !state.changed_paths.is_empty() {
+                    let uncommitted = std::mem::take(&mut state.uncommitted_paths);
+                    let mut paths = std::mem::take(&mut state.changed_paths);
+                    paths.retain(|path| !uncommitted.contains(path));
+                    for path in &uncommitted {
+                        tracing::warn!("Not committing {}: it had uncommitted changes before request {}", path.display(), id);
+                    }
+                    if let Err(e) = self.commit_turn(&myself, state, id, &paths) {
+                        tracing::warn!("Failed to commit the changes of request {}: {}", id, e);
+                    }
+                }
+                
                 // Trim history if needed
                 while state.history.len() > state.max_history {
                     state.history.pop_front();
@@ -307,10 +486,80 @@ impl Actor for ChatActor {
             ChatMessage::Error { id, error } => {
                 tracing::error!("Error in chat: {}", error);
                 state.history.push_back(ChatMessage::Error { id, error: error.clone() });
-                state.current_request = None;
+                if state.active_tool_calls.contains_key(&id) {
+                    // A failed tool call still needs a result before the turn can continue
+                    myself.send_message(ChatMessage::ToolResult {
+                        id,
+                        result: format!("Error: {}", error),
+                    })?;
+                } else {
+                    state.current_request = None;
+                }
                 // TODO: Handle error - notify UI
             }
             
+            ChatMessage::Cancel => {
+                self.cancel(state)?;
+            }
+            
+            ChatMessage::Usage { id, model, usage, sub_agent } => {
+                tracing::debug!("Usage for request {} from {}: {:?}", id, model, usage);
+                Self::record_usage(state, model, usage, sub_agent)?;
+            }
+            
+            ChatMessage::Compacted { id, start, end, before, force, summary } => {
+                if state.compacting != Some(id) {
+                    tracing::debug!("Dropping the summary for request {}: the session has moved on", id);
+                    return Ok(());
+                }
+                state.compacting = None;
+                
+                let response = match summary {
+                    Ok(summary) => {
+                        Self::record_usage(state, summary.model, summary.usage, None)?;
+                        let after = self.replace_with_summary(state, start, end, &summary.text)?;
+                        tracing::info!("Compacted conversation from {} to {} tokens", before, after);
+                        format!("Compacted conversation: {} → {} tokens", before, after)
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to compact conversation: {}", e);
+                        format!("Failed to compact conversation: {}", e)
+                    }
+                };
+                
+                if force {
+                    if let Some(display_ref) = state.current_context.as_ref().and_then(|context| state.display_refs.get(context)) {
+                        let _ = display_ref.send_message(ChatMessage::Complete { id, response });
+                    }
+                } else if state.current_request == Some(id) {
+                    self.generate(state, id)?;
+                }
+            }
+            
+            ChatMessage::UIUpdate(UIMessage::ShowConfirmation(request)) => {
+                // The delegator asks the user about a tool call through the active display
+                let display_ref = state.current_context.as_ref().and_then(|context| state.display_refs.get(context));
+                if let Some(display_ref) = display_ref {
+                    display_ref.send_message(ChatMessage::UIUpdate(UIMessage::ShowConfirmation(request)))?;
+                } else {
+                    tracing::warn!("No display to confirm {} call {}, denying it", request.tool_name, request.id);
+                    myself.send_message(ChatMessage::ConfirmationResponse {
+                        id: request.id,
+                        decision: ConfirmationDecision::Deny,
+                    })?;
+                }
+            }
+            
+            ChatMessage::UIUpdate(_) => {
+                // Other UI updates flow from this actor to displays, not the other way
+            }
+            
+            ChatMessage::ConfirmationResponse { id, decision } => {
+                if let Some(ref delegator_ref) = state.delegator_ref {
+                    delegator_ref.send_message(DelegatorMessage::ConfirmationResponse { id, decision })?;
+                }
+            }
+            
             ChatMessage::SetDelegatorRef(delegator_ref) => {
                 tracing::debug!("Setting delegator actor reference");
                 state.delegator_ref = Some(delegator_ref);
@@ -326,27 +575,52 @@ impl Actor for ChatActor {
                 state.display_refs.insert(context, display_ref);
             }
             
-            ChatMessage::SwitchSession { session_id, messages } => {
+            ChatMessage::SwitchSession { session_id, messages, row_ids } => {
                 tracing::info!("Switching to session: {}", session_id);
                 
                 // Clear current state
                 state.history.clear();
                 state.current_request = None;
                 state.active_tool_calls.clear();
+                state.pending_tool_results.clear();
+                state.pending_tool_images.clear();
+                state.changed_paths.clear();
+                state.uncommitted_paths.clear();
+                state.compacting = None;
+                
+                // The session switched away from has ended
+                if !state.session_id.is_empty()
+                    && state.session_id != session_id
+                    && let Some(delegator_ref) = &state.delegator_ref
+                {
+                    let _ = delegator_ref.send_message(DelegatorMessage::EndSession {
+                        session_id: state.session_id.clone(),
+                    });
+                }
                 
                 // Update session ID
                 state.session_id = session_id.clone();
+                state.request_usage = TokenUsage::default();
+                Self::load_session_usage(state).await;
+                Self::send_stats(state);
                 
                 // Set new message history
                 state.messages = messages;
                 
-                // Add system prompt if not present
-                if !state.messages.iter().any(|m| matches!(m, OpenAIMessage::System { .. })) {
+                // Add system prompt if not present (a compaction summary doesn't count)
+                let mut offset = 0;
+                if !matches!(state.messages.first(), Some(m @ OpenAIMessage::System { .. }) if !context::is_summary(m)) {
                     state.messages.insert(0, OpenAIMessage::System {
                         content: self.get_system_prompt(),
                         name: None,
                     });
+                    offset = 1;
                 }
+                state.prompt_rows = row_ids.into_iter()
+                    .enumerate()
+                    .filter_map(|(index, row)| Some((index + offset, row?)))
+                    .filter(|(index, _)| matches!(state.messages.get(*index), Some(OpenAIMessage::User { .. })))
+                    .collect();
                 
                 tracing::info!("Session switched. Loaded {} messages", state.messages.len());
             }
@@ -358,12 +632,14 @@ impl Actor for ChatActor {
 
 impl ChatActor {
     pub fn new(config: Config, session_id: String) -> Self {
+        let tools = ToolRegistry::new(config.clone()).tool_definitions();
         Self {
             config,
             client_ref: None,
             delegator_ref: None,
             persistence_ref: None,
             session_id,
+            tools,
         }
     }
     
@@ -382,6 +658,403 @@ impl ChatActor {
         self
     }
     
+    /// Cancel the request in progress: stop the model and the running tool calls, and
+    /// answer every unanswered call as cancelled so the history stays valid for the next request
+    fn cancel(&self, state: &mut ChatState) -> Result<(), ActorProcessingErr> {
+        let Some(request_id) = state.current_request.take() else {
+            tracing::debug!("No request in progress to cancel");
+            return Ok(());
+        };
+        tracing::info!("Cancelling request {}", request_id);
+        
+        if let Some(ref client_ref) = self.client_ref {
+            client_ref.send_message(ClientMessage::Cancel)?;
+        }
+        
+        let display_ref = state.current_context.as_ref()
+            .and_then(|context| state.display_refs.get(context))
+            .cloned();
+        
+        for (id, (tool_name, tool_call_id)) in state.active_tool_calls.drain() {
+            if let Some(ref delegator_ref) = state.delegator_ref {
+                delegator_ref.send_message(DelegatorMessage::Cancel { id })?;
+            }
+            if let Some(ref persistence_ref) = state.persistence_ref {
+                persistence_ref.send_message(ChatPersistenceMessage::PersistUserPrompt {
+                    id,
+                    session_id: state.session_id.clone(),
+                    prompt: format!("Tool result from {}: {}", tool_name, CANCELLED_RESULT),
+                })?;
+            }
+            if let Some(ref display_ref) = display_ref {
+                let _ = display_ref.send_message(ChatMessage::ToolResult { id, result: CANCELLED_RESULT.to_string() });
+            }
+            state.history.push_back(ChatMessage::ToolResult { id, result: CANCELLED_RESULT.to_string() });
+            tracing::debug!("Cancelled {} call {}", tool_name, tool_call_id);
+        }
+        
+        // Results that did arrive are kept; the rest are recorded as cancelled
+        for (tool_call_id, content) in state.pending_tool_results.drain(..) {
+            state.messages.push(OpenAIMessage::Tool {
+                content: content.unwrap_or_else(|| CANCELLED_RESULT.to_string()),
+                tool_call_id,
+            });
+        }
+        Self::push_tool_images(state);
+        
+        state.history.push_back(ChatMessage::Error { id: request_id, error: "Request cancelled".to_string() });
+        if let Some(display_ref) = display_ref {
+            let _ = display_ref.send_message(ChatMessage::Error { id: request_id, error: "Request cancelled".to_string() });
+        }
+        
+        Ok(())
+    }
+    
+    /// Reset session counters and seed the token totals from persistence
+    async fn load_session_usage(state: &mut ChatState) {
+        state.session_usage = TokenUsage::default();
+        state.sub_agent_usage.clear();
+        state.tools_executed = 0;
+        state.session_started = Instant::now();
+        
+        let Some(ref persistence_ref) = state.persistence_ref else {
+            return;
+        };
+        
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        if let Err(e) = persistence_ref.send_message(ChatPersistenceMessage::GetSessionUsage {
+            session_id: state.session_id.clone(),
+            reply_to: tx,
+        }) {
+            tracing::warn!("Failed to request session usage: {}", e);
+            return;
+        }
+        
+        match tokio::time::timeout(Duration::from_secs(5), rx).await {
+            Ok(Ok(records)) => {
+                for record in records {
+                    let usage = TokenUsage {
+                        prompt_tokens: record.prompt_tokens as u64,
+                        completion_tokens: record.completion_tokens as u64,
+                        cost: record.cost,
+                    };
+                    state.session_usage += usage;
+                    if record.source != "main" {
+                        *state.sub_agent_usage.entry(record.source).or_default() += usage;
+                    }
+                }
+            }
+            _ => tracing::warn!("Timed out loading usage for session {}", state.session_id),
+        }
+    }
+    
+    /// Restore the files changed in the session's last turn, or in turn `argument`
+    /// and every later one, or list the turns when `argument` is "list"
+    async fn undo(&self, state: &mut ChatState, argument: &str) -> String {
+        if state.session_id.is_empty() {
+            return "Nothing to undo: no session is active".to_string();
+        }
+        let turn = match argument {
+            "" | "list" => None,
+            turn => match turn.parse::<usize>() {
+                Ok(turn) => Some(turn),
+                Err(_) => return "Usage: /undo [<turn> | list]".to_string(),
+            },
+        };
+        let manager = match &state.checkpoints {
+            Some(manager) => manager,
+            None => match CheckpointManager::open(&self.config).await {
+                Ok(manager) => state.checkpoints.insert(manager),
+                Err(e) => return format!("Cannot open the checkpoint database: {}", e),
+            },
+        };
+        
+        if argument == "list" {
+            return match manager.list(&state.session_id).await {
+                Ok(turns) => checkpoints::describe_turns(&turns),
+                Err(e) => format!("Failed to list checkpoints: {}", e),
+            };
+        }
+        let result = match turn {
+            Some(turn) => manager.restore(&state.session_id, turn).await.map(|restored| (turn, restored)),
+            None => manager.undo_last(&state.session_id).await,
+        };
+        
+        match result {
+            Ok((turn, restored)) => {
+                let summary = checkpoints::describe_restore(turn, &restored);
+                // The model would otherwise assume its changes are still there
+                state.messages.push(OpenAIMessage::User {
+                    content: UserContent::Text(format!("[The user ran /undo. {}]", summary)),
+                    name: None,
+                });
+                summary
+            }
+            Err(e) => format!("Nothing was undone: {}", e),
+        }
+    }
+    
+    /// Commit what a completed request changed in `paths`, with a message the
+    /// model writes from the diff, or a plain one if it can't. The changes are
+    /// collected now and committed in the background once the message is written;
+    /// its usage comes back as a `Usage` message.
+    fn commit_turn(&self, myself: &ActorRef<ChatMessage>, state: &mut ChatState, id: Uuid, paths: &[PathBuf]) -> anyhow::Result<()> {
+        let Some(changes) = TurnChanges::collect(&self.workspace(), paths)? else {
+            return Ok(());
+        };
+        
+        let client_ref = self.client_ref.clone();
+        let chat_ref = myself.clone();
+        let session_id = state.session_id.clone();
+        let previous = state.committing.take();
+        state.committing = Some(tokio::spawn(async move {
+            let message = match Self::commit_message(client_ref.as_ref(), &changes.diff).await {
+                Ok(reply) => {
+                    let _ = chat_ref.send_message(ChatMessage::Usage {
+                        id,
+                        model: reply.model,
+                        usage: reply.usage,
+                        sub_agent: None,
+                    });
+                    auto_commit::clean_message(&reply.text)
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to generate a commit message: {}", e);
+                    String::new()
+                }
+            };
+            let message = if message.is_empty() { changes.fallback_message() } else { message };
+            
+            // Commits land in the order their requests completed
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            match changes.commit(&message, &session_id) {
+                Ok(commit) => tracing::info!("Committed the changes of request {} as {}: {}", id, commit, message.lines().next().unwrap_or_default()),
+                Err(e) => tracing::warn!("Failed to commit the changes of request {}: {}", id, e),
+            }
+        }));
+        Ok(())
+    }
+    
+    /// The directory the session works in
+    fn workspace(&self) -> PathBuf {
+        self.config.session.workspace_path.clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default()
+    }
+    
+    async fn commit_message(client_ref: Option<&ActorRef<ClientMessage>>, diff: &str) -> anyhow::Result<Completion> {
+        let client_ref = client_ref.ok_or_else(|| anyhow::anyhow!("Client actor not set"))?;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        client_ref.send_message(ClientMessage::Complete {
+            messages: vec![
+                OpenAIMessage::System {
+                    content: auto_commit::COMMIT_MESSAGE_PROMPT.to_string(),
+                    name: None,
+                },
+                OpenAIMessage::User {
+                    content: UserContent::Text(diff.to_string()),
+                    name: None,
+                },
+            ],
+            reply_to: tx,
+        })?;
+        tokio::time::timeout(COMMIT_MESSAGE_TIMEOUT, rx).await
+            .map_err(|_| anyhow::anyhow!("Timed out waiting for a commit message"))?
+            .map_err(|_| anyhow::anyhow!("Client dropped the commit message request"))?
+    }
+    
+    /// The agent's worktree, what it has changed so far and how to review it
+    fn describe_worktree(&self) -> String {
+        let worktree = self.config.session.workspace_path.as_deref()
+            .and_then(|workspace| Worktrees::open(workspace).ok().map(|worktrees| (workspace, worktrees)))
+            .and_then(|(workspace, worktrees)| worktrees.containing(workspace).map(|worktree| (worktree, worktrees)));
+        let Some((worktree, worktrees)) = worktree else {
+            return "The agent works in the workspace itself; set session.worktree to give it a worktree of its own".to_string();
+        };
+        
+        let changes = match worktrees.diff(&worktree.name) {
+            Ok(diff) => diff.summary(),
+            Err(e) => format!("Cannot diff the worktree: {}", e),
+        };
+        format!(
+            "Working in {} on branch {}. {}.\nReview with `assistant worktree diff {name}`, then `assistant worktree merge {name}` or `assistant worktree discard {name}` once the session is over",
+            worktree.path.display(),
+            worktree.branch,
+            changes,
+            name = worktree.name
+        )
+    }
+    
+    /// Summarize older turns once the prompt outgrows the model's context budget,
+    /// or unconditionally when `force` is set. The system prompt and the most
+    /// recent turns are kept verbatim. The model is asked in the background and
+    /// its summary comes back as a `Compacted` message for request `id`. Returns
+    /// whether a compaction was started.
+    fn compact(&self, myself: &ActorRef<ChatMessage>, state: &mut ChatState, id: Uuid, force: bool) -> anyhow::Result<bool> {
+        if state.compacting.is_some() {
+            if force {
+                anyhow::bail!("The conversation is already being compacted");
+            }
+            return Ok(false);
+        }
+        
+        let tool_tokens = state.token_counter.count_tools(&self.tools);
+        let before = state.token_counter.count_messages(&state.messages) + tool_tokens;
+        let budget = self.config.context.budget_for(&self.config.model, self.config.max_tokens);
+        if !force && before <= budget {
+            return Ok(false);
+        }
+        
+        let Some((start, end)) = context::compaction_range(&state.messages, self.config.context.keep_recent_turns) else {
+            if !force {
+                tracing::warn!("Prompt uses {} of {} tokens but there are no older turns to compact", before, budget);
+            }
+            return Ok(false);
+        };
+        let client_ref = self.client_ref.clone().ok_or_else(|| anyhow::anyhow!("Client actor not set"))?;
+        
+        tracing::info!("Compacting {} messages ({} tokens, budget {})", end - start, before, budget);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        client_ref.send_message(ClientMessage::Complete {
+            messages: vec![
+                OpenAIMessage::System {
+                    content: context::COMPACTION_PROMPT.to_string(),
+                    name: None,
+                },
+                OpenAIMessage::User {
+                    content: UserContent::Text(context::render_transcript(&state.messages[start..end])),
+                    name: None,
+                },
+            ],
+            reply_to: tx,
+        })?;
+        state.compacting = Some(id);
+        
+        let chat_ref = myself.clone();
+        tokio::spawn(async move {
+            let summary = match tokio::time::timeout(COMPACTION_TIMEOUT, rx).await {
+                Ok(Ok(Ok(summary))) => Ok(summary),
+                Ok(Ok(Err(e))) => Err(e.to_string()),
+                Ok(Err(_)) => Err("Client dropped the summary request".to_string()),
+                Err(_) => Err("Timed out waiting for summary".to_string()),
+            };
+            let _ = chat_ref.send_message(ChatMessage::Compacted { id, start, end, before, force, summary });
+        });
+        Ok(true)
+    }
+    
+    /// Replace messages `start..end` with `summary` and persist it, marked with
+    /// the first persisted prompt it keeps. Returns the prompt tokens after.
+    fn replace_with_summary(
+        &self,
+        state: &mut ChatState,
+        start: usize,
+        end: usize,
+        summary: &str,
+    ) -> Result<usize, MessagingErr<ChatPersistenceMessage>> {
+        state.messages.splice(start..end, [context::summary_message(summary)]);
+        
+        let removed = end - start - 1;
+        state.prompt_rows.retain(|(index, _)| *index < start || *index >= end);
+        for (index, _) in state.prompt_rows.iter_mut().filter(|(index, _)| *index >= end) {
+            *index -= removed;
+        }
+        
+        if let Some(ref persistence_ref) = state.persistence_ref {
+            persistence_ref.send_message(ChatPersistenceMessage::PersistCompaction {
+                session_id: state.session_id.clone(),
+                summary: summary.trim().to_string(),
+                kept_from: state.prompt_rows.iter().find(|(index, _)| *index > start).map(|(_, row)| *row),
+            })?;
+        }
+        
+        Ok(state.token_counter.count_messages(&state.messages) + state.token_counter.count_tools(&self.tools))
+    }
+    
+    /// Send the conversation to the client to generate the next response for request `id`
+    fn generate(&self, state: &ChatState, id: Uuid) -> Result<(), ActorProcessingErr> {
+        let Some(ref client_ref) = self.client_ref else {
+            return Err("Client actor not set".into());
+        };
+        client_ref.send_message(ClientMessage::Generate {
+            id,
+            messages: state.messages.clone(),
+            tools: self.tools.clone(),
+        })?;
+        Ok(())
+    }
+    
+    /// Add tokens spent for the session to its totals, persist them and show the new totals
+    fn record_usage(
+        state: &mut ChatState,
+        model: String,
+        usage: TokenUsage,
+        sub_agent: Option<String>,
+    ) -> Result<(), MessagingErr<ChatPersistenceMessage>> {
+        state.request_usage += usage;
+        state.session_usage += usage;
+        if let Some(tool_name) = &sub_agent {
+            *state.sub_agent_usage.entry(tool_name.clone()).or_default() += usage;
+        }
+        
+        if let Some(ref persistence_ref) = state.persistence_ref {
+            persistence_ref.send_message(ChatPersistenceMessage::PersistUsage {
+                session_id: state.session_id.clone(),
+                source: sub_agent.unwrap_or_else(|| "main".to_string()),
+                model,
+                usage,
+            })?;
+        }
+        
+        Self::send_stats(state);
+        Ok(())
+    }
+    
+    /// Show the model the images its tool calls returned. Tool messages only carry
+    /// text, so they follow the turn's results as a user message.
+    fn push_tool_images(state: &mut ChatState) {
+        if state.pending_tool_images.is_empty() {
+            return;
+        }
+        
+        let mut parts = vec![crate::openai_compat::ContentPart::Text {
+            text: "Images returned by the tool calls above:".to_string(),
+        }];
+        for (tool_name, image_url) in state.pending_tool_images.drain(..) {
+            parts.push(crate::openai_compat::ContentPart::Text { text: format!("From {}:", tool_name) });
+            parts.push(crate::openai_compat::ContentPart::Image {
+                image_url: crate::openai_compat::ImageUrl {
+                    url: image_url,
+                    detail: None,
+                },
+            });
+        }
+        state.messages.push(OpenAIMessage::User {
+            content: UserContent::Array(parts),
+            name: None,
+        });
+    }
+    
+    /// Send the current usage statistics to the active display
+    fn send_stats(state: &ChatState) {
+        let stats = Stats {
+            tokens_used: state.session_usage.total_tokens() as usize,
+            tools_executed: state.tools_executed,
+            session_duration: state.session_started.elapsed().as_secs(),
+            request: state.request_usage,
+            session: state.session_usage,
+            sub_agents: state.sub_agent_usage.clone(),
+        };
+        
+        if let Some(context) = &state.current_context
+            && let Some(display_ref) = state.display_refs.get(context)
+        {
+            let _ = display_ref.send_message(ChatMessage::UIUpdate(UIMessage::UpdateStats(stats)));
+        }
+    }
+    
     fn get_system_prompt(&self) -> String {
         r#"You are a helpful AI assistant with access to various tools. 
 
@@ -417,438 +1090,4 @@ Examples:
 
 Always be transparent about using the knowledge_agent - let users know when you're searching or storing information."#.to_string()
     }
-    
-    fn build_tools(&self) -> Vec<Tool> {
-        let mut tools = Vec::new();
-        
-        // Define tools based on config
-        let tool_names = [
-            "read", "edit", "write", "ls", "glob", "grep",
-            "bash", "web_search", "web_fetch", "todo", "knowledge_agent",
-            "screenshot", "desktop_control", "computer_use"
-        ];
-        
-        for tool_name in &tool_names {
-            if !self.config.tools.exclude.contains(&tool_name.to_string()) {
-                // Get tool config or use defaults
-                let tool_config = self.config.tools.configs.get(*tool_name);
-                let enabled = tool_config.map(|tc| tc.enabled).unwrap_or(true);
-                
-                if enabled {
-                    let tool = self.create_tool_definition(tool_name);
-                    tools.push(tool);
-                }
-            }
-        }
-        
-        tools
-    }
-    
-    fn create_tool_definition(&self, tool_name: &str) -> Tool {
-        // Create tool definitions matching qwen-code tools
-        let (description, parameters) = match tool_name {
-            "read" => (
-                "Read the contents of a file",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "path": {
-                            "type": "string",
-                            "description": "The path to the file to read"
-                        }
-                    },
-                    "required": ["path"]
-                })
-            ),
-            "edit" => (
-                "Edit a file by replacing content",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "file_path": {
-                            "type": "string",
-                            "description": "The path to the file to edit"
-                        },
-                        "old_string": {
-                            "type": "string",
-                            "description": "The exact string to replace"
-                        },
-                        "new_string": {
-                            "type": "string",
-                            "description": "The string to replace it with"
-                        }
-                    },
-                    "required": ["file_path", "old_string", "new_string"]
-                })
-            ),
-            "write" => (
-                "Write content to a file",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "file_path": {
-                            "type": "string",
-                            "description": "The path to the file to write"
-                        },
-                        "content": {
-                            "type": "string",
-                            "description": "The content to write to the file"
-                        }
-                    },
-                    "required": ["file_path", "content"]
-                })
-            ),
-            "ls" => (
-                "List files in a directory",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "path": {
-                            "type": "string",
-                            "description": "The directory path to list"
-                        }
-                    },
-                    "required": ["path"]
-                })
-            ),
-            "glob" => (
-                "Search for files matching a pattern",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "pattern": {
-                            "type": "string",
-                            "description": "The glob pattern to match files"
-                        },
-                        "path": {
-                            "type": "string",
-                            "description": "The base directory to search in"
-                        }
-                    },
-                    "required": ["pattern"]
-                })
-            ),
-            "grep" => (
-                "Search file contents using regex",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "pattern": {
-                            "type": "string",
-                            "description": "The regex pattern to search for"
-                        },
-                        "path": {
-                            "type": "string",
-                            "description": "The path to search in"
-                        }
-                    },
-                    "required": ["pattern"]
-                })
-            ),
-            "bash" => (
-                "Execute a bash command",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "command": {
-                            "type": "string",
-                            "description": "The command to execute"
-                        }
-                    },
-                    "required": ["command"]
-                })
-            ),
-            "web_search" => (
-                "Search the web for information. Supports complex natural language queries for comprehensive research. This tool uses a dedicated sub-agent that can perform multiple searches and fetch pages to gather detailed information",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "query": {
-                            "type": "string",
-                            "description": "The search query. Can be a simple search term or a detailed natural language request for comprehensive information gathering. The sub-agent will interpret your request and use multiple searches if needed"
-                        }
-                    },
-                    "required": ["query"]
-                })
-            ),
-            "web_fetch" => (
-                "Fetch content from a URL and process it with a prompt",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "url": {
-                            "type": "string",
-                            "description": "The URL to fetch"
-                        },
-                        "prompt": {
-                            "type": "string",
-                            "description": "What to extract or look for in the fetched content"
-                        }
-                    },
-                    "required": ["url", "prompt"]
-                })
-            ),
-            "todo" => (
-                "Manage todo list with various operations",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "operation": {
-                            "type": "string",
-                            "enum": ["list", "add", "update", "remove", "clear", "stats"],
-                            "description": "The operation to perform"
-                        },
-                        "session_id": {
-                            "type": "string",
-                            "description": "Session ID for the todo list (defaults to 'default')"
-                        },
-                        "content": {
-                            "type": "string",
-                            "description": "Content for add operation"
-                        },
-                        "priority": {
-                            "type": "string",
-                            "enum": ["low", "medium", "high"],
-                            "description": "Priority for add/update/list operations"
-                        },
-                        "status": {
-                            "type": "string",
-                            "enum": ["pending", "in_progress", "completed"],
-                            "description": "Status for update/list/clear operations"
-                        },
-                        "id": {
-                            "type": "string",
-                            "description": "Todo ID for update/remove operations"
-                        }
-                    },
-                    "required": ["operation"]
-                })
-            ),
-            "knowledge_agent" => (
-                "Search and synthesize knowledge from memories, chat history, todos, and sessions. This tool uses a dedicated sub-agent that can search across all stored information and provide comprehensive analysis",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "action": {
-                            "type": "string",
-                            "enum": ["search", "store", "get_details", "analyze", "synthesize"],
-                            "description": "The action to perform"
-                        },
-                        "query": {
-                            "type": "string",
-                            "description": "Search query (for search action)"
-                        },
-                        "content": {
-                            "type": "string",
-                            "description": "Content to store (for store action)"
-                        },
-                        "key": {
-                            "type": "string",
-                            "description": "Optional key for the stored memory (for store action)"
-                        },
-                        "metadata": {
-                            "type": "object",
-                            "description": "Optional metadata to attach to the memory (for store action)"
-                        },
-                        "topic": {
-                            "type": "string",
-                            "description": "Topic to analyze or synthesize (for analyze/synthesize actions)"
-                        },
-                        "source": {
-                            "type": "string",
-                            "enum": ["memory", "chat_history", "todo", "session", "all"],
-                            "description": "Knowledge source (for get_details action)"
-                        },
-                        "id": {
-                            "type": "string",
-                            "description": "Item ID (for get_details action)"
-                        },
-                        "limit": {
-                            "type": "integer",
-                            "description": "Maximum results to return (for search, default: 20)"
-                        },
-                        "source_filter": {
-                            "type": "array",
-                            "items": {
-                                "type": "string",
-                                "enum": ["memory", "chat_history", "todo", "session"]
-                            },
-                            "description": "Filter by specific sources (for search)"
-                        },
-                        "depth": {
-                            "type": "string",
-                            "enum": ["quick", "standard", "deep"],
-                            "description": "Analysis depth (for analyze action, default: standard)"
-                        },
-                        "include_examples": {
-                            "type": "boolean",
-                            "description": "Include examples in synthesis (for synthesize action)"
-                        }
-                    },
-                    "required": ["action"]
-                })
-            ),
-            "screenshot" => (
-                "Take a screenshot on macOS. Returns a base64 data URL of the screenshot image",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "mode": {
-                            "type": "string",
-                            "enum": ["screen", "window", "region", "interactive"],
-                            "description": "Screenshot mode (default: screen)"
-                        },
-                        "window_id": {
-                            "type": "string",
-                            "description": "Window ID for window mode (use 'list_windows' first)"
-                        },
-                        "x": {
-                            "type": "integer",
-                            "description": "X coordinate for region mode"
-                        },
-                        "y": {
-                            "type": "integer",
-                            "description": "Y coordinate for region mode"
-                        },
-                        "width": {
-                            "type": "integer",
-                            "description": "Width for region mode"
-                        },
-                        "height": {
-                            "type": "integer",
-                            "description": "Height for region mode"
-                        },
-                        "delay": {
-                            "type": "integer",
-                            "description": "Delay in seconds before taking screenshot"
-                        },
-                        "list_windows": {
-                            "type": "boolean",
-                            "description": "List available windows instead of taking screenshot"
-                        }
-                    },
-                    "required": []
-                })
-            ),
-            "desktop_control" => (
-                "Control mouse and keyboard on macOS using cliclick",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "action": {
-                            "type": "string",
-                            "enum": ["mouse_move", "mouse_click", "mouse_drag", "keyboard_type", "keyboard_key", "get_mouse_position", "check_installation"],
-                            "description": "The desktop control action to perform"
-                        },
-                        "x": {
-                            "type": "integer",
-                            "description": "X coordinate (for mouse actions)"
-                        },
-                        "y": {
-                            "type": "integer",
-                            "description": "Y coordinate (for mouse actions)"
-                        },
-                        "from_x": {
-                            "type": "integer",
-                            "description": "Starting X coordinate (for mouse_drag)"
-                        },
-                        "from_y": {
-                            "type": "integer",
-                            "description": "Starting Y coordinate (for mouse_drag)"
-                        },
-                        "to_x": {
-                            "type": "integer",
-                            "description": "Ending X coordinate (for mouse_drag)"
-                        },
-                        "to_y": {
-                            "type": "integer",
-                            "description": "Ending Y coordinate (for mouse_drag)"
-                        },
-                        "button": {
-                            "type": "string",
-                            "enum": ["left", "right", "middle"],
-                            "description": "Mouse button (default: left)"
-                        },
-                        "count": {
-                            "type": "integer",
-                            "description": "Click count for mouse_click (1=single, 2=double, 3=triple)"
-                        },
-                        "duration": {
-                            "type": "integer",
-                            "description": "Duration in milliseconds for smooth mouse movement"
-                        },
-                        "text": {
-                            "type": "string",
-                            "description": "Text to type (for keyboard_type)"
-                        },
-                        "key": {
-                            "type": "string",
-                            "description": "Key or key combination to press (e.g., 'cmd+c', 'escape', 'return')"
-                        },
-                        "delay_ms": {
-                            "type": "integer",
-                            "description": "Delay between keystrokes in milliseconds"
-                        }
-                    },
-                    "required": ["action"]
-                })
-            ),
-            "computer_use" => (
-                "Visual desktop automation agent. This tool uses a dedicated sub-agent with vision capabilities to interact with the desktop through screenshots and control actions",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "action": {
-                            "type": "string",
-                            "enum": ["describe_screen", "navigate_to", "perform_task", "type_text", "read_text", "wait_and_observe"],
-                            "description": "The computer use action to perform"
-                        },
-                        "description": {
-                            "type": "string",
-                            "description": "Natural language description of what to click/find (for navigate_to)"
-                        },
-                        "task": {
-                            "type": "string",
-                            "description": "Natural language description of the task to perform"
-                        },
-                        "text": {
-                            "type": "string",
-                            "description": "Text to type in the current focused element"
-                        },
-                        "region": {
-                            "type": "object",
-                            "properties": {
-                                "x": {"type": "integer"},
-                                "y": {"type": "integer"},
-                                "width": {"type": "integer"},
-                                "height": {"type": "integer"}
-                            },
-                            "description": "Screen region for describe_screen or read_text actions"
-                        },
-                        "duration_ms": {
-                            "type": "integer",
-                            "description": "Duration to wait in milliseconds (for wait_and_observe)"
-                        }
-                    },
-                    "required": ["action"]
-                })
-            ),
-            _ => (
-                "Unknown tool",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {}
-                })
-            )
-        };
-        
-        Tool {
-            tool_type: "function".to_string(),
-            function: FunctionDef {
-                name: tool_name.to_string(),
-                description: description.to_string(),
-                parameters,
-            },
-        }
-    }
 }
\ No newline at end of file
@@ -10,7 +10,9 @@ use sqlx::Row;
 use crate::config::Config;
 use crate::actors::client::ClientMessage;
 use crate::openai_compat::{ChatMessage as OpenAIMessage, UserContent};
+use crate::messages::TokenUsage;
 use crate::persistence::database::Database;
+use crate::persistence::schema::SessionUsageRecord;
 use crate::embeddings::{
     client::OpenAIEmbeddingClient,
     ollama::{OllamaEmbeddingClient, OllamaEmbeddingModel},
@@ -22,6 +24,7 @@ use crate::embeddings::{
 #[allow(dead_code)]
 enum DatabaseOperation {
     PersistMessage {
+        id: Uuid,
         session_id: String,
         role: String,
         content: Option<String>,
@@ -34,12 +37,23 @@ enum DatabaseOperation {
     Summarize {
         session_id: String,
     },
+    PersistUsage {
+        session_id: String,
+        source: String,
+        model: String,
+        usage: TokenUsage,
+    },
+    PersistCompaction {
+        session_id: String,
+        summary: String,
+        kept_from: Option<Uuid>,
+    },
 }
 
 /// Messages for the ChatPersistenceActor
 #[derive(Debug)]
 pub enum ChatPersistenceMessage {
-    /// Persist a user prompt
+    /// Persist a user prompt, stored under `id`
     PersistUserPrompt {
         id: Uuid,
         session_id: String,
@@ -69,6 +83,28 @@ pub enum ChatPersistenceMessage {
     SummarizeChat {
         session_id: String,
     },
+    /// Add token usage to the session totals
+    PersistUsage {
+        session_id: String,
+        /// "main" for the chat itself, otherwise the sub-agent tool name
+        source: String,
+        model: String,
+        usage: TokenUsage,
+    },
+    /// Persist the summary that replaced compacted turns, so reloading the
+    /// session starts from the summary instead of the full history
+    PersistCompaction {
+        session_id: String,
+        summary: String,
+        /// The first persisted user prompt the summary comes before, or `None`
+        /// if no kept message has been persisted yet
+        kept_from: Option<Uuid>,
+    },
+    /// Get the persisted token usage totals for a session
+    GetSessionUsage {
+        session_id: String,
+        reply_to: tokio::sync::oneshot::Sender<Vec<SessionUsageRecord>>,
+    },
     /// Get pending operations count
     GetPendingCount {
         reply_to: tokio::sync::oneshot::Sender<usize>,
@@ -148,12 +184,13 @@ impl Actor for ChatPersistenceActor {
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
         match msg {
-            ChatPersistenceMessage::PersistUserPrompt { id: _, session_id, prompt } => {
+            ChatPersistenceMessage::PersistUserPrompt { id, session_id, prompt } => {
                 tracing::info!("Persisting user prompt for session {}: {}", session_id, prompt);
                 
                 // Create operation and add to queue
                 let operation_id = Uuid::new_v4();
                 let operation = DatabaseOperation::PersistMessage {
+                    id,
                     session_id: session_id.clone(),
                     role: "user".to_string(),
                     content: Some(prompt.clone()),
@@ -177,8 +214,8 @@ impl Actor for ChatPersistenceActor {
                     
                     // Perform the operation
                     let result = match operation {
-                        DatabaseOperation::PersistMessage { session_id, role, content, tool_calls } => {
-                            actor.persist_message(&session_id, &role, content.as_deref(), tool_calls).await
+                        DatabaseOperation::PersistMessage { id, session_id, role, content, tool_calls } => {
+                            actor.persist_message(id, &session_id, &role, content.as_deref(), tool_calls).await
                         }
                         _ => unreachable!(),
                     };
@@ -219,6 +256,7 @@ impl Actor for ChatPersistenceActor {
                 // Create operation and add to queue
                 let operation_id = Uuid::new_v4();
                 let operation = DatabaseOperation::PersistMessage {
+                    id: Uuid::new_v4(),
                     session_id: session_id.clone(),
                     role: "assistant".to_string(),
                     content: Some(response),
@@ -246,8 +284,8 @@ impl Actor for ChatPersistenceActor {
                     
                     // Perform the operation
                     let result = match operation {
-                        DatabaseOperation::PersistMessage { session_id, role, content, tool_calls } => {
-                            actor.persist_message(&session_id, &role, content.as_deref(), tool_calls).await
+                        DatabaseOperation::PersistMessage { id, session_id, role, content, tool_calls } => {
+                            actor.persist_message(id, &session_id, &role, content.as_deref(), tool_calls).await
                         }
                         _ => unreachable!(),
                     };
@@ -274,6 +312,7 @@ impl Actor for ChatPersistenceActor {
                 // Create operation and add to queue
                 let operation_id = Uuid::new_v4();
                 let operation = DatabaseOperation::PersistMessage {
+                    id: Uuid::new_v4(),
                     session_id: session_id.clone(),
                     role: "tool".to_string(),
                     content: None,
@@ -297,8 +336,8 @@ impl Actor for ChatPersistenceActor {
                     
                     // Perform the operation
                     let result = match operation {
-                        DatabaseOperation::PersistMessage { session_id, role, content, tool_calls } => {
-                            actor.persist_message(&session_id, &role, content.as_deref(), tool_calls).await
+                        DatabaseOperation::PersistMessage { id, session_id, role, content, tool_calls } => {
+                            actor.persist_message(id, &session_id, &role, content.as_deref(), tool_calls).await
                         }
                         _ => unreachable!(),
                     };
@@ -355,6 +394,99 @@ impl Actor for ChatPersistenceActor {
                 });
             }
             
+            ChatPersistenceMessage::PersistUsage { session_id, source, model, usage } => {
+                tracing::debug!("Persisting {} tokens from {} for session {}", usage.total_tokens(), source, session_id);
+                
+                // Create operation and add to queue
+                let operation_id = Uuid::new_v4();
+                let operation = DatabaseOperation::PersistUsage {
+                    session_id,
+                    source,
+                    model,
+                    usage,
+                };
+                state.pending_operations.insert(operation_id, operation.clone());
+                
+                // Spawn task to perform the operation
+                let database = self.database.clone();
+                let embedding_client = self.embedding_client.clone();
+                let myself_clone = myself.clone();
+                
+                tokio::spawn(async move {
+                    let actor = ChatPersistenceActor {
+                        config: Default::default(),
+                        database,
+                        embedding_client,
+                        client_ref: None,
+                    };
+                    
+                    let result = match operation {
+                        DatabaseOperation::PersistUsage { session_id, source, model, usage } => {
+                            actor.persist_usage(&session_id, &source, &model, &usage).await
+                        }
+                        _ => unreachable!(),
+                    };
+                    
+                    let _ = myself_clone.send_message(ChatPersistenceMessage::OperationComplete {
+                        operation_id,
+                        success: result.is_ok(),
+                        error: result.err().map(|e| e.to_string()),
+                    });
+                });
+            }
+            
+            ChatPersistenceMessage::PersistCompaction { session_id, summary, kept_from } => {
+                tracing::info!("Persisting compaction summary for session {}", session_id);
+                
+                // Create operation and add to queue
+                let operation_id = Uuid::new_v4();
+                let operation = DatabaseOperation::PersistCompaction {
+                    session_id,
+                    summary,
+                    kept_from,
+                };
+                state.pending_operations.insert(operation_id, operation.clone());
+                
+                // Spawn task to perform the operation
+                let database = self.database.clone();
+                let embedding_client = self.embedding_client.clone();
+                let myself_clone = myself.clone();
+                
+                tokio::spawn(async move {
+                    let actor = ChatPersistenceActor {
+                        config: Default::default(),
+                        database,
+                        embedding_client,
+                        client_ref: None,
+                    };
+                    
+                    let result = match operation {
+                        DatabaseOperation::PersistCompaction { session_id, summary, kept_from } => {
+                            actor.persist_compaction(&session_id, &summary, kept_from).await
+                        }
+                        _ => unreachable!(),
+                    };
+                    
+                    let _ = myself_clone.send_message(ChatPersistenceMessage::OperationComplete {
+                        operation_id,
+                        success: result.is_ok(),
+                        error: result.err().map(|e| e.to_string()),
+                    });
+                });
+            }
+            
+            ChatPersistenceMessage::GetSessionUsage { session_id, reply_to } => {
+                let database = self.database.clone();
+                
+                tokio::spawn(async move {
+                    let records = database.get_session_usage(&session_id).await.unwrap_or_else(|e| {
+                        tracing::error!("Failed to load usage for session {}: {}", session_id, e);
+                        Vec::new()
+                    });
+                    let _ = reply_to.send(records);
+                });
+            }
+            
             ChatPersistenceMessage::GetPendingCount { reply_to } => {
                 let count = state.pending_operations.len();
                 tracing::debug!("Pending operations count: {}", count);
@@ -483,6 +615,7 @@ impl ChatPersistenceActor {
     
     async fn persist_message(
         &self,
+        id: Uuid,
         session_id: &str,
         role: &str,
         content: Option<&str>,
@@ -496,7 +629,7 @@ impl ChatPersistenceActor {
         // Ensure session exists first
         self.ensure_session_exists(session_id).await?;
         
-        let id = Uuid::new_v4().to_string();
+        let id = id.to_string();
         let now = Utc::now();
         
         // Generate embedding for content if available
@@ -598,6 +731,35 @@ impl ChatPersistenceActor {
         Ok(())
     }
     
+    /// Store a compaction summary, marked with the first prompt it keeps
+    async fn persist_compaction(&self, session_id: &str, summary: &str, kept_from: Option<Uuid>) -> Result<()> {
+        let id = Uuid::new_v4();
+        let content = format!("{}{}", crate::context::SUMMARY_PREFIX, summary);
+        self.persist_message(id, session_id, "system", Some(&content), None).await?;
+        
+        if let Some(kept_from) = kept_from {
+            sqlx::query("UPDATE chat_messages SET kept_from = ?1 WHERE id = ?2")
+                .bind(kept_from.to_string())
+                .bind(id.to_string())
+                .execute(self.database.pool())
+                .await?;
+        }
+        Ok(())
+    }
+    
+    async fn persist_usage(
+        &self,
+        session_id: &str,
+        source: &str,
+        model: &str,
+        usage: &TokenUsage,
+    ) -> Result<()> {
+        // Ensure session exists first
+        self.ensure_session_exists(session_id).await?;
+        
+        self.database.record_usage(session_id, source, model, usage).await
+    }
+    
     fn should_summarize(
         &self,
         session_id: &str,
@@ -1,16 +1,16 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use futures::StreamExt;
 use tokio::sync::mpsc;
-use crate::config::Config;
-use crate::messages::{ChatMessage, ToolCall};
-use crate::openai_compat::{OpenAICompatClient, ChatCompletionRequest, ChatMessage as OpenAIMessage, Tool};
+use crate::config::{Config, ModelPricing};
+use crate::messages::{ChatMessage, ToolCall, TokenUsage};
+use crate::openai_compat::{ChatProvider, ChatCompletionStream, ChatCompletionRequest, ChatMessage as OpenAIMessage, Tool};
 use uuid::Uuid;
 use std::collections::HashMap;
 
-/// Actor for OpenAI API communication
+/// Actor for LLM API communication
 pub struct ClientActor {
     config: Config,
-    client: OpenAICompatClient,
+    client: Box<dyn ChatProvider>,
 }
 
 /// Client state tracking active streams
@@ -20,7 +20,7 @@ pub struct ClientState {
     chat_ref: Option<ActorRef<ChatMessage>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum ClientMessage {
     /// Set the chat actor reference
     SetChatRef(ActorRef<ChatMessage>),
@@ -32,10 +32,27 @@ pub enum ClientMessage {
         tools: Vec<Tool>,
     },
     
+    /// Run a one-off, non-streaming completion and reply with its text and usage.
+    /// Used for internal requests such as conversation compaction, so the
+    /// result never reaches the chat actor as a response; whoever asked
+    /// reports the usage.
+    Complete {
+        messages: Vec<OpenAIMessage>,
+        reply_to: tokio::sync::oneshot::Sender<anyhow::Result<Completion>>,
+    },
+
     /// Cancel ongoing generation
     Cancel,
 }
 
+/// The answer to a `ClientMessage::Complete`
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub model: String,
+    pub usage: TokenUsage,
+}
+
 impl Actor for ClientActor {
     type Msg = ClientMessage;
     type State = ClientState;
@@ -89,6 +106,7 @@ impl Actor for ClientActor {
                     temperature: Some(self.config.temperature),
                     max_tokens: Some(self.config.max_tokens as u32),
                     stream: true,
+                    stream_options: None,
                 };
 
                 if tracing::level_enabled!(tracing::Level::INFO) {
@@ -104,10 +122,12 @@ impl Actor for ClientActor {
                     Ok(stream) => {
                         let chat_ref = state.chat_ref.clone();
                         let request_id = id;
+                        let model = self.config.model.clone();
+                        let pricing = self.config.pricing_for(&model);
                         
                         // Spawn task to handle stream
                         let handle = tokio::spawn(async move {
-                            Self::handle_stream(stream, chat_ref, request_id, cancel_rx).await;
+                            Self::handle_stream(stream, chat_ref, request_id, model, pricing, cancel_rx).await;
                         });
                         
                         state.active_stream = Some(handle);
@@ -124,6 +144,36 @@ impl Actor for ClientActor {
                 }
             }
             
+            ClientMessage::Complete { messages, reply_to } => {
+                let request = ChatCompletionRequest {
+                    model: self.config.model.clone(),
+                    messages,
+                    tools: None,
+                    temperature: Some(self.config.temperature),
+                    max_tokens: Some(self.config.max_tokens),
+                    stream: false,
+                    stream_options: None,
+                };
+
+                let result = self.client.create_chat_completion(request).await.and_then(|response| {
+                    let model = self.config.model.clone();
+                    let (prompt_tokens, completion_tokens) =
+                        (response.usage.prompt_tokens as u64, response.usage.completion_tokens as u64);
+                    let usage = TokenUsage {
+                        prompt_tokens,
+                        completion_tokens,
+                        cost: self.config.pricing_for(&model)
+                            .map(|p| p.cost(prompt_tokens, completion_tokens))
+                            .unwrap_or_default(),
+                    };
+                    match response.choices.into_iter().next().map(|choice| choice.message) {
+                        Some(OpenAIMessage::Assistant { content: Some(text), .. }) => Ok(Completion { text, model, usage }),
+                        _ => Err(anyhow::anyhow!("Completion returned no text")),
+                    }
+                });
+                let _ = reply_to.send(result);
+            }
+
             ClientMessage::Cancel => {
                 tracing::info!("Cancelling generation");
                 
@@ -142,7 +192,7 @@ impl Actor for ClientActor {
 
 impl ClientActor {
     pub fn new(config: Config) -> Self {
-        let client = OpenAICompatClient::new(&config);
+        let client = crate::openai_compat::create_provider(&config);
         
         Self {
             config,
@@ -151,13 +201,16 @@ impl ClientActor {
     }
     
     async fn handle_stream(
-        mut stream: std::pin::Pin<Box<dyn futures::Stream<Item = Result<crate::openai_compat::ChatCompletionChunk, anyhow::Error>> + Send>>,
+        mut stream: ChatCompletionStream,
         chat_ref: Option<ActorRef<ChatMessage>>,
         request_id: Uuid,
+        model: String,
+        pricing: Option<ModelPricing>,
         mut cancel_rx: mpsc::Receiver<()>,
     ) {
         let mut full_response = String::new();
         let mut pending_tool_calls: HashMap<usize, (String, String, String)> = HashMap::new();
+        let mut usage: Option<TokenUsage> = None;
         
         loop {
             tokio::select! {
@@ -174,6 +227,10 @@ impl ClientActor {
                                 if tracing::level_enabled!(tracing::Level::INFO) {
                                     tracing::info!("Received SSE chunk: {:?}", response);
                                 }
+                            // Usage normally arrives once, on the final chunk; keep the latest
+                            if let Some(reported) = response.usage.as_ref().and_then(Self::parse_usage) {
+                                usage = Some(reported);
+                            }
                             for choice in response.choices {
                                 let delta = &choice.delta;
                                 // Handle content
@@ -241,12 +298,18 @@ impl ClientActor {
             }
         }
         
-        // Build tool calls from pending_tool_calls
+        // Build tool calls from pending_tool_calls, in the order the model issued them
+        let mut pending_tool_calls: Vec<_> = pending_tool_calls.into_iter().collect();
+        pending_tool_calls.sort_by_key(|(index, _)| *index);
+        
         let mut tool_calls = Vec::new();
         for (_index, (id, name, args)) in pending_tool_calls {
-            if !name.is_empty() && !id.is_empty() {
+            if !name.is_empty() {
                 if let Ok(parameters) = serde_json::from_str(&args) {
+                    // Some providers omit ids; the result still needs one to refer to
+                    let id = if id.is_empty() { format!("call_{}", Uuid::new_v4()) } else { id };
                     tool_calls.push(ToolCall {
+                        id,
                         tool_name: name,
                         parameters,
                         delegate: false, // Will be determined by tool config
@@ -255,6 +318,21 @@ impl ClientActor {
             }
         }
         
+        // Report usage before the response so totals are current when it is displayed
+        if let Some(mut usage) = usage {
+            usage.cost = pricing
+                .map(|p| p.cost(usage.prompt_tokens, usage.completion_tokens))
+                .unwrap_or_default();
+            if let Some(ref chat_ref) = chat_ref {
+                let _ = chat_ref.send_message(ChatMessage::Usage {
+                    id: request_id,
+                    model,
+                    usage,
+                    sub_agent: None,
+                });
+            }
+        }
+        
         // Send the assistant response with content and/or tool calls
         if !full_response.is_empty() || !tool_calls.is_empty() {
             if let Some(ref chat_ref) = chat_ref {
@@ -272,4 +350,20 @@ impl ClientActor {
             }
         }
     }
-}
\ No newline at end of file
+    
+    /// Read prompt/completion token counts from a chunk's `usage` object
+    fn parse_usage(usage: &serde_json::Value) -> Option<TokenUsage> {
+        let count = |key: &str| usage.get(key).and_then(|v| v.as_u64());
+        let prompt_tokens = count("prompt_tokens");
+        let completion_tokens = count("completion_tokens");
+        if prompt_tokens.is_none() && completion_tokens.is_none() {
+            return None;
+        }
+        
+        Some(TokenUsage {
+            prompt_tokens: prompt_tokens.unwrap_or_default(),
+            completion_tokens: completion_tokens.unwrap_or_default(),
+            cost: 0.0,
+        })
+    }
+}
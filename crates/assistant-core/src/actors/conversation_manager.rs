@@ -6,6 +6,7 @@ use crate::persistence::database::Database;
 use crate::persistence::schema::{SessionSummary, ChatMessageRecord};
 use crate::openai_compat::ChatMessage as OpenAIMessage;
 use crate::messages::ChatMessage;
+use uuid::Uuid;
 
 /// Messages for the ConversationManagerActor
 #[derive(Debug)]
@@ -116,7 +117,7 @@ impl Actor for ConversationManagerActor {
                 match self.database.get_session_messages(&session_id, None, None).await {
                     Ok(records) => {
                         tracing::info!("Loaded {} records from database for session {}", records.len(), session_id);
-                        let messages = self.convert_to_openai_messages(records);
+                        let messages: Vec<_> = self.convert_to_openai_messages(records).into_iter().map(|(_, message)| message).collect();
                         tracing::info!("Converted to {} OpenAI messages for session {}", messages.len(), session_id);
                         
                         // Cache the messages
@@ -168,7 +169,7 @@ impl Actor for ConversationManagerActor {
                 // Load the conversation messages
                 match self.database.get_session_messages(&session_id, None, None).await {
                     Ok(records) => {
-                        let messages = self.convert_to_openai_messages(records);
+                        let (row_ids, messages): (Vec<_>, Vec<_>) = self.convert_to_openai_messages(records).into_iter().unzip();
                         
                         // Cache the messages
                         state.conversation_cache.insert(session_id.clone(), messages.clone());
@@ -177,6 +178,7 @@ impl Actor for ConversationManagerActor {
                         let _ = chat_ref.send_message(ChatMessage::SwitchSession {
                             session_id: session_id.clone(),
                             messages,
+                            row_ids,
                         });
                     }
                     Err(e) => {
@@ -199,11 +201,27 @@ impl ConversationManagerActor {
         Ok(Self { database })
     }
     
-    /// Convert database records to OpenAI messages
-    fn convert_to_openai_messages(&self, records: Vec<ChatMessageRecord>) -> Vec<OpenAIMessage> {
+    /// Convert database records to OpenAI messages, each with the id of its record
+    fn convert_to_openai_messages(&self, mut records: Vec<ChatMessageRecord>) -> Vec<(Option<Uuid>, OpenAIMessage)> {
+        // Start from the latest compaction summary. It is saved after the recent
+        // turns it kept, so move it back in front of the first of them.
+        let summary = records.iter().rposition(|record| {
+            record.role == "system"
+                && record.content.as_deref().is_some_and(|c| c.starts_with(crate::context::SUMMARY_PREFIX))
+        });
+        if let Some(index) = summary {
+            let summary = records.remove(index);
+            let kept = summary.kept_from.as_deref()
+                .and_then(|kept_from| records.iter().position(|record| record.id == kept_from))
+                .unwrap_or(index);
+            records.drain(..kept);
+            records.insert(0, summary);
+        }
+        
         records.into_iter()
             .filter_map(|record| {
-                match record.role.as_str() {
+                let row_id = Uuid::parse_str(&record.id).ok();
+                let message = match record.role.as_str() {
                     "user" => record.content.map(|content| OpenAIMessage::User {
                         content: crate::openai_compat::UserContent::Text(content),
                         name: None,
@@ -227,17 +245,18 @@ impl ConversationManagerActor {
                                     data.get("tool").and_then(|t| t.as_str()),
                                     data.get("result").and_then(|r| r.as_str()),
                                 ) {
-                                    return Some(OpenAIMessage::Tool {
+                                    return Some((row_id, OpenAIMessage::Tool {
                                         content: result.to_string(),
                                         tool_call_id: tool_id.to_string(),
-                                    });
+                                    }));
                                 }
                             }
                         }
                         None
                     }
                     _ => None,
-                }
+                };
+                message.map(|message| (row_id, message))
             })
             .collect()
     }
@@ -1,14 +1,32 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
-use std::collections::HashMap;
-use crate::config::Config;
-use crate::messages::{DelegatorMessage, ChatMessage};
-use crate::actors::tools::ToolMessage;
+use std::collections::{HashMap, HashSet};
+use crate::config::{Config, PermissionAction};
+use crate::messages::{ChatMessage, ConfirmationDecision, DelegatorMessage, ToolCall, UIMessage};
+use crate::permissions::{self, PermissionPolicy};
+use crate::actors::tools::{
+    ApplyPatchActor, BashActor, CheckpointActor, EditActor, ReadActor, ReadManyFilesActor, ToolDescription, ToolMessage,
+    WriteActor,
+};
+use crate::persistence::checkpoints::{self, CheckpointManager};
+use crate::file_tracker::FileTracker;
 use crate::actors::sub_agent::{SubAgentActor, SubAgentMessage};
 use uuid::Uuid;
 
+/// Tools told which session called them, through a `session_id` parameter
+const SESSION_TOOLS: &[&str] = &[
+    BashActor::NAME,
+    CheckpointActor::NAME,
+    ReadActor::NAME,
+    ReadManyFilesActor::NAME,
+    WriteActor::NAME,
+    EditActor::NAME,
+    ApplyPatchActor::NAME,
+];
+
 /// Actor that routes tools to specialized LLMs
 pub struct DelegatorActor {
     config: Config,
+    policy: PermissionPolicy,
 }
 
 /// Delegator state
@@ -20,6 +38,21 @@ pub struct DelegatorState {
     
     /// Active delegated requests
     active_requests: HashMap<Uuid, ActorRef<ChatMessage>>,
+    
+    /// Tool calls waiting for the user's confirmation
+    pending_confirmations: HashMap<Uuid, RoutedCall>,
+    /// Calls the user allowed for the rest of a session, by session id
+    approvals: HashMap<String, HashSet<(String, String)>>,
+    /// Saves files before calls change them, opened on first use
+    checkpoints: Option<CheckpointManager>,
+}
+
+/// A tool call with where it came from, kept while it waits for the user's confirmation
+struct RoutedCall {
+    call: ToolCall,
+    chat_ref: ActorRef<ChatMessage>,
+    session_id: String,
+    turn: Uuid,
 }
 
 impl Actor for DelegatorActor {
@@ -61,6 +94,9 @@ impl Actor for DelegatorActor {
             tool_actors: HashMap::new(),
             sub_agents,
             active_requests: HashMap::new(),
+            pending_confirmations: HashMap::new(),
+            approvals: HashMap::new(),
+            checkpoints: None,
         })
     }
     
@@ -76,139 +112,98 @@ impl Actor for DelegatorActor {
                 state.tool_actors.insert(name, actor_ref);
             }
             
-            DelegatorMessage::RouteToolCall { id, call, chat_ref } => {
-                tracing::info!("Routing tool call: {}", call.tool_name);
-                
-                // Check if tool should be delegated
-                let tool_config = self.config.tools.configs.get(&call.tool_name);
-                let should_delegate = tool_config
-                    .map(|tc| tc.should_delegate())
-                    .unwrap_or(false);
+            DelegatorMessage::RouteToolCall { id, call, chat_ref, session_id, turn } => {
+                let mut action = self.policy.check(&call);
+                if action == PermissionAction::Ask
+                    && state.approvals.get(&session_id).is_some_and(|approved| approved.contains(&approval_key(&call)))
+                {
+                    action = PermissionAction::Allow;
+                }
                 
-                if should_delegate {
-                    // Route to sub-agent
-                    if let Some(sub_agent_ref) = state.sub_agents.get(&call.tool_name) {
-                        tracing::info!("Delegating {} to sub-agent for request {}", call.tool_name, id);
-                        
-                        // Store the chat reference for later response
-                        state.active_requests.insert(id, chat_ref.clone());
-                        
-                        // Extract the query from parameters
-                        let query = if call.tool_name == "web_search" {
-                            call.parameters.get("query")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("")
-                                .to_string()
-                        } else if call.tool_name == "knowledge_agent" {
-                            // For knowledge_agent, extract based on action type
-                            if let Some(action) = call.parameters.get("action").and_then(|v| v.as_str()) {
-                                match action {
-                                    "search" => call.parameters.get("query")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("")
-                                        .to_string(),
-                                    "analyze" | "synthesize" => call.parameters.get("topic")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("")
-                                        .to_string(),
-                                    _ => serde_json::to_string(&call.parameters).unwrap_or_default()
-                                }
-                            } else {
-                                serde_json::to_string(&call.parameters).unwrap_or_default()
-                            }
-                        } else if call.tool_name == "computer_use" {
-                            // For computer_use, extract based on action type
-                            if let Some(action) = call.parameters.get("action").and_then(|v| v.as_str()) {
-                                match action {
-                                    "describe_screen" => {
-                                        if let Some(region) = call.parameters.get("region") {
-                                            format!("Take a screenshot of the specified region and describe what you see: {}", serde_json::to_string(region).unwrap_or_default())
-                                        } else {
-                                            "Take a screenshot of the entire screen and describe what you see".to_string()
-                                        }
-                                    }
-                                    "navigate_to" => {
-                                        let description = call.parameters.get("description")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("unknown element");
-                                        format!("Take a screenshot, find and click on: {}", description)
-                                    }
-                                    "perform_task" => {
-                                        let task = call.parameters.get("task")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("unknown task");
-                                        format!("Help me with this task: {}", task)
-                                    }
-                                    "type_text" => {
-                                        let text = call.parameters.get("text")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("");
-                                        format!("Type the following text: {}", text)
-                                    }
-                                    "read_text" => {
-                                        if let Some(region) = call.parameters.get("region") {
-                                            format!("Take a screenshot of the specified region and read any text you see: {}", serde_json::to_string(region).unwrap_or_default())
-                                        } else {
-                                            "Take a screenshot and read all visible text".to_string()
-                                        }
-                                    }
-                                    "wait_and_observe" => {
-                                        let duration = call.parameters.get("duration_ms")
-                                            .and_then(|v| v.as_u64())
-                                            .unwrap_or(1000);
-                                        let description = call.parameters.get("description")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("changes");
-                                        format!("Wait {} milliseconds and then check if: {}", duration, description)
-                                    }
-                                    _ => serde_json::to_string(&call.parameters).unwrap_or_default()
-                                }
-                            } else {
-                                serde_json::to_string(&call.parameters).unwrap_or_default()
-                            }
-                        } else {
-                            // For other tools, convert the entire parameters to a query
-                            serde_json::to_string(&call.parameters).unwrap_or_default()
-                        };
-                        
-                        tracing::info!("Sending query to sub-agent {}: {}", call.tool_name, query);
-                        
-                        // Send to sub-agent
-                        sub_agent_ref.send_message(SubAgentMessage::ExecuteQuery {
-                            id,
-                            query,
-                            reply_to: myself.clone(),
-                        })?;
-                    } else {
-                        tracing::error!("Sub-agent not found for tool: {}", call.tool_name);
+                match action {
+                    PermissionAction::Allow => {
+                        self.route(&myself, state, id, RoutedCall { call, chat_ref, session_id, turn }).await?;
+                    }
+                    PermissionAction::Deny => {
+                        tracing::info!("Permission policy denied {} call {}", call.tool_name, id);
                         chat_ref.send_message(ChatMessage::Error {
                             id,
-                            error: format!("Sub-agent not found for: {}", call.tool_name),
+                            error: format!("Permission denied: the permission policy blocks this {} call", call.tool_name),
                         })?;
                     }
-                } else {
-                    // Route to local tool actor
-                    if let Some(tool_ref) = state.tool_actors.get(&call.tool_name) {
-                        // Execute tool locally
-                        tool_ref.send_message(ToolMessage::Execute {
-                            id,
-                            params: call.parameters,
-                            chat_ref: chat_ref.clone(),
-                        })?;
-                    } else {
-                        chat_ref.send_message(ChatMessage::Error {
+                    PermissionAction::Ask => {
+                        tracing::info!("Asking for confirmation of {} call {}", call.tool_name, id);
+                        chat_ref.send_message(ChatMessage::UIUpdate(UIMessage::ShowConfirmation(
+                            permissions::confirmation_request(id, &call),
+                        )))?;
+                        state.pending_confirmations.insert(id, RoutedCall { call, chat_ref, session_id, turn });
+                    }
+                }
+            }
+            
+            DelegatorMessage::ConfirmationResponse { id, decision } => {
+                let Some(pending) = state.pending_confirmations.remove(&id) else {
+                    tracing::warn!("No pending confirmation found for {}", id);
+                    return Ok(());
+                };
+                
+                match decision {
+                    ConfirmationDecision::Allow => self.route(&myself, state, id, pending).await?,
+                    ConfirmationDecision::AllowForSession => {
+                        state.approvals
+                            .entry(pending.session_id.clone())
+                            .or_default()
+                            .insert(approval_key(&pending.call));
+                        self.route(&myself, state, id, pending).await?;
+                    }
+                    ConfirmationDecision::Deny => {
+                        pending.chat_ref.send_message(ChatMessage::Error {
                             id,
-                            error: format!("Tool not found: {}", call.tool_name),
+                            error: format!("Permission denied: the user declined this {} call", pending.call.tool_name),
                         })?;
                     }
                 }
             }
             
-            DelegatorMessage::SubAgentResponse { id, result } => {
+            DelegatorMessage::Cancel { id } => {
+                if state.pending_confirmations.remove(&id).is_some() {
+                    tracing::info!("Dropped confirmation for cancelled call {}", id);
+                }
+                // The call's id doesn't say where it went, so every actor is told; the
+                // ones not running it ignore the message
+                state.active_requests.remove(&id);
+                for sub_agent in state.sub_agents.values() {
+                    let _ = sub_agent.send_message(SubAgentMessage::Cancel { id });
+                }
+                for tool_actor in state.tool_actors.values() {
+                    let _ = tool_actor.send_message(ToolMessage::Cancel { id });
+                }
+            }
+            
+            DelegatorMessage::EndSession { session_id } => {
+                tracing::info!("Ending session {}", session_id);
+                state.approvals.remove(&session_id);
+                FileTracker::global().end_session(&session_id);
+                for tool_actor in state.tool_actors.values() {
+                    let _ = tool_actor.send_message(ToolMessage::EndSession { session_id: session_id.clone() });
+                }
+            }
+            
+            DelegatorMessage::SubAgentResponse { id, result, usage } => {
                 tracing::info!("Received sub-agent response for request {}: {}", id, result);
                 
                 // Get the chat reference for this request
                 if let Some(chat_ref) = state.active_requests.remove(&id) {
+                    // Report the sub-agent's token usage before its result
+                    if let Some(usage) = usage {
+                        chat_ref.send_message(ChatMessage::Usage {
+                            id,
+                            model: usage.model,
+                            usage: usage.usage,
+                            sub_agent: Some(usage.tool_name),
+                        })?;
+                    }
+                    
                     tracing::info!("Forwarding sub-agent result to chat actor for request {}", id);
                     // Send the result back to the chat actor
                     chat_ref.send_message(ChatMessage::ToolResult {
@@ -228,7 +223,189 @@ impl Actor for DelegatorActor {
 impl DelegatorActor {
     pub fn new(config: Config) -> Self {
         Self {
+            policy: PermissionPolicy::new(config.permissions.clone()),
             config,
         }
     }
+    
+    /// Send a permitted tool call to its sub-agent or local tool actor
+    async fn route(
+        &self,
+        myself: &ActorRef<DelegatorMessage>,
+        state: &mut DelegatorState,
+        id: Uuid,
+        routed: RoutedCall,
+    ) -> Result<(), ActorProcessingErr> {
+        let RoutedCall { call, chat_ref, session_id, turn } = routed;
+        tracing::info!("Routing tool call: {}", call.tool_name);
+        
+        // Check if tool should be delegated
+        let tool_config = self.config.tools.configs.get(&call.tool_name);
+        let should_delegate = tool_config
+            .map(|tc| tc.should_delegate())
+            .unwrap_or(false);
+        
+        if should_delegate {
+            // Route to sub-agent
+            if let Some(sub_agent_ref) = state.sub_agents.get(&call.tool_name) {
+                tracing::info!("Delegating {} to sub-agent for request {}", call.tool_name, id);
+                
+                // Store the chat reference for later response
+                state.active_requests.insert(id, chat_ref.clone());
+                
+                // Extract the query from parameters
+                let query = if call.tool_name == "web_search" {
+                    call.parameters.get("query")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                } else if call.tool_name == "knowledge_agent" {
+                    // For knowledge_agent, extract based on action type
+                    if let Some(action) = call.parameters.get("action").and_then(|v| v.as_str()) {
+                        match action {
+                            "search" => call.parameters.get("query")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("")
+                                .to_string(),
+                            "analyze" | "synthesize" => call.parameters.get("topic")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("")
+                                .to_string(),
+                            _ => serde_json::to_string(&call.parameters).unwrap_or_default()
+                        }
+                    } else {
+                        serde_json::to_string(&call.parameters).unwrap_or_default()
+                    }
+                } else if call.tool_name == "computer_use" {
+                    // For computer_use, extract based on action type
+                    if let Some(action) = call.parameters.get("action").and_then(|v| v.as_str()) {
+                        match action {
+                            "describe_screen" => {
+                                if let Some(region) = call.parameters.get("region") {
+                                    format!("Take a screenshot of the specified region and describe what you see: {}", serde_json::to_string(region).unwrap_or_default())
+                                } else {
+                                    "Take a screenshot of the entire screen and describe what you see".to_string()
+                                }
+                            }
+                            "navigate_to" => {
+                                let description = call.parameters.get("description")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("unknown element");
+                                format!("Take a screenshot, find and click on: {}", description)
+                            }
+                            "perform_task" => {
+                                let task = call.parameters.get("task")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("unknown task");
+                                format!("Help me with this task: {}", task)
+                            }
+                            "type_text" => {
+                                let text = call.parameters.get("text")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("");
+                                format!("Type the following text: {}", text)
+                            }
+                            "read_text" => {
+                                if let Some(region) = call.parameters.get("region") {
+                                    format!("Take a screenshot of the specified region and read any text you see: {}", serde_json::to_string(region).unwrap_or_default())
+                                } else {
+                                    "Take a screenshot and read all visible text".to_string()
+                                }
+                            }
+                            "wait_and_observe" => {
+                                let duration = call.parameters.get("duration_ms")
+                                    .and_then(|v| v.as_u64())
+                                    .unwrap_or(1000);
+                                let description = call.parameters.get("description")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("changes");
+                                format!("Wait {} milliseconds and then check if: {}", duration, description)
+                            }
+                            _ => serde_json::to_string(&call.parameters).unwrap_or_default()
+                        }
+                    } else {
+                        serde_json::to_string(&call.parameters).unwrap_or_default()
+                    }
+                } else {
+                    // For other tools, convert the entire parameters to a query
+                    serde_json::to_string(&call.parameters).unwrap_or_default()
+                };
+                
+                tracing::info!("Sending query to sub-agent {}: {}", call.tool_name, query);
+                
+                // Send to sub-agent
+                sub_agent_ref.send_message(SubAgentMessage::ExecuteQuery {
+                    id,
+                    query,
+                    reply_to: myself.clone(),
+                })?;
+            } else {
+                tracing::error!("Sub-agent not found for tool: {}", call.tool_name);
+                chat_ref.send_message(ChatMessage::Error {
+                    id,
+                    error: format!("Sub-agent not found for: {}", call.tool_name),
+                })?;
+            }
+        } else {
+            // Route to local tool actor
+            if state.tool_actors.contains_key(&call.tool_name) {
+                let mut params = call.parameters;
+                self.checkpoint(state, &call.tool_name, &params, &session_id, turn).await;
+                if SESSION_TOOLS.contains(&call.tool_name.as_str()) {
+                    // Checkpoints, the files a session has read, and its shell and background jobs are kept per session
+                    if let Some(params) = params.as_object_mut() {
+                        params.insert("session_id".to_string(), serde_json::Value::from(session_id.as_str()));
+                    }
+                }
+                
+                // Execute tool locally
+                state.tool_actors[&call.tool_name].send_message(ToolMessage::Execute {
+                    id,
+                    params,
+                    chat_ref: chat_ref.clone(),
+                })?;
+            } else {
+                chat_ref.send_message(ChatMessage::Error {
+                    id,
+                    error: format!("Tool not found: {}", call.tool_name),
+                })?;
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+impl DelegatorActor {
+    /// Save the files a call is about to change, so its turn can be undone. A
+    /// failure is logged rather than holding the call back.
+    async fn checkpoint(&self, state: &mut DelegatorState, tool_name: &str, params: &serde_json::Value, session_id: &str, turn: Uuid) {
+        if !self.config.session.checkpoints || session_id.is_empty() {
+            return;
+        }
+        let paths = checkpoints::changed_paths(tool_name, params);
+        if paths.is_empty() {
+            return;
+        }
+        
+        if state.checkpoints.is_none() {
+            match CheckpointManager::open(&self.config).await {
+                Ok(manager) => state.checkpoints = Some(manager),
+                Err(e) => {
+                    tracing::warn!("Cannot open the checkpoint database: {}", e);
+                    return;
+                }
+            }
+        }
+        if let Some(manager) = &state.checkpoints
+            && let Err(e) = manager.snapshot(session_id, &turn.to_string(), &paths).await
+        {
+            tracing::warn!("Failed to checkpoint files for {} call: {}", tool_name, e);
+        }
+    }
+}
+
+/// Identifies a call approved for the rest of a session
+fn approval_key(call: &ToolCall) -> (String, String) {
+    (call.tool_name.clone(), permissions::subject(call))
 }
\ No newline at end of file
@@ -1,16 +1,22 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
+use std::io::{BufRead, IsTerminal, Write};
 use tokio::sync::mpsc;
-use crate::messages::ChatMessage;
+use crate::messages::{ChatMessage, ConfirmationDecision, ConfirmationRequest, ConfirmationType, Stats, UIMessage};
 use super::DisplayActor;
 
 /// CLI display actor that formats output for terminal
 pub struct CLIDisplayActor {
     completion_tx: mpsc::UnboundedSender<()>,
+    /// Chat actor that receives answers to tool confirmations
+    chat_ref: Option<ActorRef<ChatMessage>>,
 }
 
 pub struct CLIDisplayState {
     current_tool: Option<String>,
     has_output: bool,
+    /// Whether the current tool's output was already shown while it ran
+    tool_output_shown: bool,
+    stats: Option<Stats>,
 }
 
 impl Actor for CLIDisplayActor {
@@ -26,6 +32,8 @@ impl Actor for CLIDisplayActor {
         Ok(CLIDisplayState {
             current_tool: None,
             has_output: false,
+            tool_output_shown: false,
+            stats: None,
         })
     }
     
@@ -38,7 +46,6 @@ impl Actor for CLIDisplayActor {
         match msg {
             ChatMessage::StreamToken { token } => {
                 print!("{}", token);
-                use std::io::Write;
                 let _ = std::io::stdout().flush();
                 state.has_output = true;
             }
@@ -53,10 +60,17 @@ impl Actor for CLIDisplayActor {
                 state.current_tool = Some(call.tool_name);
             }
             
+            ChatMessage::ToolOutput { id: _, output } => {
+                for line in output.lines() {
+                    println!("   │ {}", line);
+                }
+                state.tool_output_shown = true;
+            }
+            
             ChatMessage::ToolResult { id: _, result } => {
                 if let Some(tool_name) = &state.current_tool {
                     println!("✅ Tool {} completed", tool_name);
-                    if !result.trim().is_empty() {
+                    if !result.trim().is_empty() && !state.tool_output_shown {
                         // Show truncated result if it's long
                         let display_result = if result.len() > 200 {
                             format!("{}...", &result[..200])
@@ -67,6 +81,7 @@ impl Actor for CLIDisplayActor {
                     }
                 }
                 state.current_tool = None;
+                state.tool_output_shown = false;
                 println!(); // Blank line before assistant continues
             }
             
@@ -75,7 +90,6 @@ impl Actor for CLIDisplayActor {
                 if let Some(text) = content {
                     if !text.is_empty() && !state.has_output {
                         print!("{}", text);
-                        use std::io::Write;
                         let _ = std::io::stdout().flush();
                     }
                 }
@@ -94,14 +108,20 @@ impl Actor for CLIDisplayActor {
                 
                 // If no tool calls, signal completion
                 if tool_calls.is_empty() {
+                    Self::print_usage(state);
                     let _ = self.completion_tx.send(());
                 }
             }
             
-            ChatMessage::Complete { id: _, response: _ } => {
+            ChatMessage::Complete { id: _, response } => {
                 if state.has_output {
                     println!(); // Final newline
+                    state.has_output = false;
+                } else if !response.is_empty() {
+                    // Responses that weren't streamed, e.g. from /compact
+                    println!("{}", response);
                 }
+                Self::print_usage(state);
                 // Signal completion
                 let _ = self.completion_tx.send(());
             }
@@ -112,6 +132,26 @@ impl Actor for CLIDisplayActor {
                 let _ = self.completion_tx.send(());
             }
             
+            ChatMessage::UIUpdate(UIMessage::UpdateStats(stats)) => {
+                state.stats = Some(stats);
+            }
+            
+            ChatMessage::UIUpdate(UIMessage::ShowConfirmation(request)) => {
+                if state.has_output {
+                    println!();
+                    state.has_output = false;
+                }
+                Self::print_confirmation(&request);
+                
+                // Wait for the answer off the async runtime; the tool call waits with us
+                let decision = tokio::task::spawn_blocking(Self::read_decision)
+                    .await
+                    .unwrap_or(ConfirmationDecision::Deny);
+                if let Some(chat_ref) = &self.chat_ref {
+                    chat_ref.send_message(ChatMessage::ConfirmationResponse { id: request.id, decision })?;
+                }
+            }
+            
             _ => {
                 // Ignore other messages
             }
@@ -129,6 +169,66 @@ impl DisplayActor for CLIDisplayActor {
 
 impl CLIDisplayActor {
     pub fn new(completion_tx: mpsc::UnboundedSender<()>) -> Self {
-        Self { completion_tx }
+        Self { completion_tx, chat_ref: None }
+    }
+    
+    /// Set the chat actor that tool confirmations are answered to
+    pub fn with_chat_ref(mut self, chat_ref: ActorRef<ChatMessage>) -> Self {
+        self.chat_ref = Some(chat_ref);
+        self
+    }
+    
+    fn print_confirmation(request: &ConfirmationRequest) {
+        println!("\n⚠️  {} needs your permission: {}", request.tool_name, request.description);
+        match &request.confirm_type {
+            ConfirmationType::Execute { command } => println!("   $ {}", command),
+            ConfirmationType::Edit { file, diff } => {
+                println!("   File: {}", file);
+                for line in diff.lines() {
+                    println!("   {}", line);
+                }
+            }
+            ConfirmationType::WebAccess { url } => println!("   URL: {}", url),
+        }
+    }
+    
+    /// Prompt on stdin until the user answers; anything but a terminal is denied
+    fn read_decision() -> ConfirmationDecision {
+        let stdin = std::io::stdin();
+        if !stdin.is_terminal() {
+            println!("   Denied: stdin is not a terminal to confirm on");
+            return ConfirmationDecision::Deny;
+        }
+        
+        loop {
+            print!("   Allow? [y]es / [n]o / [a]lways this session: ");
+            let _ = std::io::stdout().flush();
+            
+            let mut answer = String::new();
+            if stdin.lock().read_line(&mut answer).unwrap_or(0) == 0 {
+                return ConfirmationDecision::Deny;
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "y" | "yes" => return ConfirmationDecision::Allow,
+                "a" | "always" => return ConfirmationDecision::AllowForSession,
+                "" | "n" | "no" => return ConfirmationDecision::Deny,
+                _ => {}
+            }
+        }
+    }
+    
+    /// Print the request's token usage to stderr so it doesn't mix with piped output
+    fn print_usage(state: &CLIDisplayState) {
+        if let Some(stats) = &state.stats {
+            let usage = &stats.request;
+            eprintln!(
+                "📊 Tokens: {} in / {} out | Cost: ${:.4} (session: {} tokens, ${:.4})",
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.cost,
+                stats.session.total_tokens(),
+                stats.session.cost,
+            );
+        }
     }
 }
\ No newline at end of file
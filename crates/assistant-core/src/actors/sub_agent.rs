@@ -1,7 +1,7 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use crate::config::Config;
 use crate::config::tool_config::ToolConfig;
-use crate::messages::{ChatMessage, DisplayContext, DelegatorMessage, ToolMessage};
+use crate::messages::{ChatMessage, DisplayContext, DelegatorMessage, SubAgentUsage, ToolMessage};
 use crate::actors::client::{ClientActor, ClientMessage};
 use crate::actors::sub_agent_chat::SubAgentChatActor;
 use crate::actors::tools::web_search::WebSearchActor;
@@ -21,6 +21,9 @@ pub enum SubAgentMessage {
         reply_to: ActorRef<DelegatorMessage>,
     },
     
+    /// Stop working on a request; no response is sent for it
+    Cancel { id: Uuid },
+    
     /// Forward a chat message from our internal chat actor
     ForwardChatMessage(ChatMessage),
 }
@@ -51,6 +54,9 @@ pub struct SubAgentState {
     
     /// Reply-to references for each request
     reply_refs: HashMap<Uuid, ActorRef<DelegatorMessage>>,
+    
+    /// Token usage accumulated for each request
+    usage: HashMap<Uuid, SubAgentUsage>,
 }
 
 impl Actor for SubAgentActor {
@@ -72,6 +78,9 @@ impl Actor for SubAgentActor {
         if let Some(api_key) = &self.tool_config.api_key {
             sub_config.api_key = api_key.clone();
         }
+        if let Some(provider) = &self.tool_config.provider {
+            sub_config.provider = provider.clone();
+        }
         if let Some(base_url) = &self.tool_config.base_url {
             sub_config.base_url = base_url.clone();
         }
@@ -186,6 +195,7 @@ impl Actor for SubAgentActor {
             tool_actors,
             active_requests: HashMap::new(),
             reply_refs: HashMap::new(),
+            usage: HashMap::new(),
         })
     }
     
@@ -227,6 +237,16 @@ impl Actor for SubAgentActor {
                 }
             }
             
+            SubAgentMessage::Cancel { id } => {
+                if state.reply_refs.remove(&id).is_some() {
+                    tracing::info!("Sub-agent {} cancelling request {}", self.tool_name, id);
+                    state.usage.remove(&id);
+                    if let Some(ref chat_ref) = state.chat_ref {
+                        chat_ref.send_message(ChatMessage::Cancel)?;
+                    }
+                }
+            }
+            
             SubAgentMessage::ForwardChatMessage(chat_msg) => {
                 match chat_msg {
                     ChatMessage::Complete { id, response } => {
@@ -237,6 +257,7 @@ impl Actor for SubAgentActor {
                             reply_ref.send_message(DelegatorMessage::SubAgentResponse {
                                 id,
                                 result: response,
+                                usage: state.usage.remove(&id),
                             })?;
                         } else {
                             tracing::error!("No reply reference found for completed request {}", id);
@@ -248,9 +269,19 @@ impl Actor for SubAgentActor {
                             reply_ref.send_message(DelegatorMessage::SubAgentResponse {
                                 id,
                                 result: format!("Error: {}", error),
+                                usage: state.usage.remove(&id),
                             })?;
                         }
                     }
+                    ChatMessage::Usage { id, model, usage, .. } => {
+                        let entry = state.usage.entry(id).or_insert_with(|| SubAgentUsage {
+                            tool_name: self.tool_name.clone(),
+                            model: model.clone(),
+                            usage: Default::default(),
+                        });
+                        entry.model = model;
+                        entry.usage += usage;
+                    }
                     ChatMessage::StreamToken { token } => {
                         // Could accumulate tokens if needed
                         tracing::trace!("Sub-agent stream token: {}", token);
@@ -1,9 +1,11 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use std::collections::{VecDeque, HashMap};
-use crate::config::Config;
-use crate::messages::{ChatMessage, DisplayContext, ToolMessage};
+use crate::config::{Config, PermissionAction};
+use crate::messages::{ChatMessage, DisplayContext, ToolCall, ToolMessage};
 use crate::actors::client::ClientMessage;
-use crate::openai_compat::{ChatMessage as OpenAIMessage, Tool, FunctionDef, UserContent};
+use crate::openai_compat::{ChatMessage as OpenAIMessage, Tool, UserContent};
+use crate::actors::tools::{ToolRegistry, ToolSpec};
+use crate::permissions::PermissionPolicy;
 use uuid::Uuid;
 
 /// Simplified chat actor for sub-agents that calls tools directly
@@ -13,6 +15,9 @@ pub struct SubAgentChatActor {
     client_ref: Option<ActorRef<ClientMessage>>,
     tool_actors: HashMap<String, ActorRef<ToolMessage>>,
     enable_tool_api: bool,
+    /// Checked before every tool call; sub-agents can't ask for confirmation, so
+    /// calls the policy would ask about are refused
+    policy: PermissionPolicy,
 }
 
 /// SubAgentChat actor state
@@ -24,6 +29,12 @@ pub struct SubAgentChatState {
     current_context: Option<DisplayContext>,
     display_refs: HashMap<DisplayContext, ActorRef<ChatMessage>>,
     pending_tool_calls: HashMap<Uuid, (String, ActorRef<ChatMessage>)>,
+    /// Provider tool_call ids by routing ID, for function-calling results
+    tool_call_ids: HashMap<Uuid, String>,
+    /// Messages answering the current turn's tool calls by routing ID, in call order
+    pending_tool_results: Vec<(Uuid, Option<OpenAIMessage>)>,
+    /// Screenshots the current turn's tool calls took, shown to the model after the results
+    pending_tool_images: Vec<String>,
 }
 
 impl Actor for SubAgentChatActor {
@@ -45,6 +56,9 @@ impl Actor for SubAgentChatActor {
             current_context: None,
             display_refs: HashMap::new(),
             pending_tool_calls: HashMap::new(),
+            tool_call_ids: HashMap::new(),
+            pending_tool_results: Vec::new(),
+            pending_tool_images: Vec::new(),
         })
     }
     
@@ -138,23 +152,31 @@ impl Actor for SubAgentChatActor {
                 state.history.push_back(ChatMessage::ToolRequest { id, call: call.clone() });
             }
             
+            ChatMessage::ToolOutput { .. } => {
+                // Sub-agents only act on final tool results
+            }
+            
             ChatMessage::ToolResult { id, result } => {
+                if state.current_request.is_none() {
+                    tracing::debug!("SubAgentChat ignoring tool result {} after its request ended", id);
+                    return Ok(());
+                }
                 tracing::info!("SubAgentChat tool result received for request {}: {}", id, result);
                 state.history.push_back(ChatMessage::ToolResult { id, result: result.clone() });
                 
-                // Retrieve original tool name (if any) and remove from pending
-                let tool_name_opt = state.pending_tool_calls.remove(&id).map(|(n, _)| n);
+                let tool_name = state.pending_tool_calls.remove(&id).map(|(n, _)| n).unwrap_or_default();
                 
-                if self.enable_tool_api {
+                let message = if self.enable_tool_api {
                     // ----------------------------------------------------------
                     // Standard function-calling path – feed back via Tool message
                     // ----------------------------------------------------------
-                    let tool_msg = OpenAIMessage::Tool {
+                    let tool_call_id = state.tool_call_ids.remove(&id)
+                        .unwrap_or_else(|| id.to_string());
+                    OpenAIMessage::Tool {
                         content: result.clone(),
-                        tool_call_id: id.to_string(),
-                    };
-                    state.messages.push(tool_msg);
-                } else if let Some(tool_name) = &tool_name_opt {
+                        tool_call_id,
+                    }
+                } else {
                     // ----------------------------------------------------------
                     // XML shim path – wrap the JSON result in <tool_result>
                     // ----------------------------------------------------------
@@ -171,45 +193,63 @@ impl Actor for SubAgentChatActor {
                         tool_name,
                         processed_result
                     );
-                    state.messages.push(OpenAIMessage::Assistant {
+                    OpenAIMessage::Assistant {
                         content: Some(wrapped),
                         name: None,
                         tool_calls: None,
-                    });
+                    }
+                };
+                
+                match state.pending_tool_results.iter_mut().find(|(tool_id, _)| *tool_id == id) {
+                    Some((_, slot)) => *slot = Some(message),
+                    None => {
+                        tracing::warn!("Tool call {} is not part of the current turn", id);
+                        return Ok(());
+                    }
+                }
+                // Screenshots are shown to the vision model along with the results
+                if tool_name == "screenshot" && let Some(image_url) = self.extract_image_from_result(&result) {
+                    state.pending_tool_images.push(image_url);
+                }
+                
+                // Wait until every tool call of this turn has a result
+                if state.pending_tool_results.iter().any(|(_, message)| message.is_none()) {
+                    tracing::debug!("SubAgentChat waiting for {} more tool results", state.pending_tool_calls.len());
+                    return Ok(());
+                }
+                
+                // Add the results in the order the assistant issued the calls
+                for (_, message) in state.pending_tool_results.drain(..) {
+                    state.messages.extend(message);
                 }
                 
-                // Continue conversation - handle screenshot results specially
+                // Continue conversation
                 if let Some(ref client_ref) = self.client_ref {
-                    tracing::info!("SubAgentChat continuing conversation after tool result for request {}", id);
+                    let request_id = state.current_request.unwrap_or(id);
+                    tracing::info!("SubAgentChat continuing conversation after tool results for request {}", request_id);
                     
-                    // For screenshot tool results, we need to add the image to the next message
                     let mut messages = state.messages.clone();
-                    if let Some(tool_name) = &tool_name_opt {
-                        if tool_name == "screenshot" {
-                            if let Some(image_url) = self.extract_image_from_result(&result) {
-                                // Add a user message with the screenshot image for the vision model
-                                let user_msg = OpenAIMessage::User {
-                                    content: crate::openai_compat::UserContent::Array(vec![
-                                        crate::openai_compat::ContentPart::Text { 
-                                            text: "Here is the screenshot you requested. Please describe what you see.".to_string() 
-                                        },
-                                        crate::openai_compat::ContentPart::Image {
-                                            image_url: crate::openai_compat::ImageUrl {
-                                                url: image_url,
-                                                detail: None,
-                                            }
-                                        }
-                                    ]),
-                                    name: None,
-                                };
-                                messages.push(user_msg);
-                            }
-                        }
+                    for image_url in state.pending_tool_images.drain(..) {
+                        // Add a user message with the screenshot image for the vision model
+                        messages.push(OpenAIMessage::User {
+                            content: crate::openai_compat::UserContent::Array(vec![
+                                crate::openai_compat::ContentPart::Text { 
+                                    text: "Here is the screenshot you requested. Please describe what you see.".to_string() 
+                                },
+                                crate::openai_compat::ContentPart::Image {
+                                    image_url: crate::openai_compat::ImageUrl {
+                                        url: image_url,
+                                        detail: None,
+                                    }
+                                }
+                            ]),
+                            name: None,
+                        });
                     }
                     
                     let tools = self.build_tools();
                     client_ref.send_message(ClientMessage::Generate {
-                        id,
+                        id: request_id,
                         messages,
                         tools,
                     })?;
@@ -219,6 +259,10 @@ impl Actor for SubAgentChatActor {
             }
             
             ChatMessage::AssistantResponse { id, content, tool_calls } => {
+                if state.current_request.is_none() {
+                    tracing::debug!("SubAgentChat ignoring response {} after its request ended", id);
+                    return Ok(());
+                }
                 tracing::info!("SubAgentChat assistant response for request {}: content={:?}, tool_calls={}", 
                     id, content, tool_calls.len());
                 
@@ -234,9 +278,9 @@ impl Actor for SubAgentChatActor {
                     let openai_tool_calls = if tool_calls.is_empty() {
                         None
                     } else {
-                        Some(tool_calls.iter().enumerate().map(|(_idx, call)| {
+                        Some(tool_calls.iter().map(|call| {
                             crate::openai_compat::ToolCall {
-                                id: format!("call_{}", Uuid::new_v4()),
+                                id: call.id.clone(),
                                 tool_type: "function".to_string(),
                                 function: crate::openai_compat::FunctionCall {
                                     name: call.tool_name.clone(),
@@ -254,26 +298,24 @@ impl Actor for SubAgentChatActor {
                     state.messages.push(assistant_msg);
                 }
                 
+                // Results are collected until the whole turn is answered
+                if !state.pending_tool_results.is_empty() {
+                    tracing::warn!("SubAgentChat discarding {} unanswered tool calls from the previous turn", state.pending_tool_calls.len());
+                    state.pending_tool_results.clear();
+                    state.pending_tool_images.clear();
+                    state.pending_tool_calls.clear();
+                    state.tool_call_ids.clear();
+                }
+                
                 // ------------------------------------------------------------------
                 // Process tool calls coming from the OpenAI function-calling API
                 // ------------------------------------------------------------------
                 if !tool_calls.is_empty() {
                     for call in tool_calls {
                         let tool_id = Uuid::new_v4();
-                        if let Some(tool_ref) = self.tool_actors.get(&call.tool_name) {
-                            state.pending_tool_calls.insert(tool_id, (call.tool_name.clone(), myself.clone()));
-                            tool_ref.send_message(ToolMessage::Execute {
-                                id: tool_id,
-                                params: call.parameters,
-                                chat_ref: myself.clone(),
-                            })?;
-                        } else {
-                            tracing::error!("Tool actor not found: {}", call.tool_name);
-                            myself.send_message(ChatMessage::ToolResult {
-                                id: tool_id,
-                                result: format!("Error: Tool '{}' not available", call.tool_name),
-                            })?;
-                        }
+                        state.tool_call_ids.insert(tool_id, call.id.clone());
+                        state.pending_tool_results.push((tool_id, None));
+                        self.call_tool(&myself, state, tool_id, call)?;
                     }
                 } else if self.enable_tool_api && content.is_some() {
                     // When using function-calling API and no tool calls, this is the final response
@@ -317,20 +359,9 @@ impl Actor for SubAgentChatActor {
                         }
                         for (tool_name, params) in xml_calls {
                             let tool_id = Uuid::new_v4();
-                            if let Some(tool_ref) = self.tool_actors.get(&tool_name) {
-                                state.pending_tool_calls.insert(tool_id, (tool_name.clone(), myself.clone()));
-                                tool_ref.send_message(ToolMessage::Execute {
-                                    id: tool_id,
-                                    params,
-                                    chat_ref: myself.clone(),
-                                })?;
-                            } else {
-                                tracing::error!("Tool actor not found: {}", tool_name);
-                                myself.send_message(ChatMessage::ToolResult {
-                                    id: tool_id,
-                                    result: format!("Error: Tool '{}' not available", tool_name),
-                                })?;
-                            }
+                            let call = ToolCall { id: tool_id.to_string(), tool_name, parameters: params, delegate: false };
+                            state.pending_tool_results.push((tool_id, None));
+                            self.call_tool(&myself, state, tool_id, call)?;
                         }
                     }
                 }
@@ -357,6 +388,48 @@ impl Actor for SubAgentChatActor {
                 state.current_request = None;
             }
             
+            ChatMessage::Cancel => {
+                if state.current_request.take().is_none() {
+                    return Ok(());
+                }
+                tracing::info!("SubAgentChat cancelling its request");
+                
+                if let Some(ref client_ref) = self.client_ref {
+                    client_ref.send_message(ClientMessage::Cancel)?;
+                }
+                for (id, (tool_name, _)) in state.pending_tool_calls.drain() {
+                    if let Some(tool_ref) = self.tool_actors.get(&tool_name) {
+                        tool_ref.send_message(ToolMessage::Cancel { id })?;
+                    }
+                }
+                // Keep the results that arrived; function-calling history needs one for every call
+                for (id, message) in state.pending_tool_results.drain(..) {
+                    match message {
+                        Some(message) => state.messages.push(message),
+                        None => {
+                            if let Some(tool_call_id) = state.tool_call_ids.remove(&id) {
+                                state.messages.push(OpenAIMessage::Tool {
+                                    content: "Cancelled".to_string(),
+                                    tool_call_id,
+                                });
+                            }
+                        }
+                    }
+                }
+                state.pending_tool_images.clear();
+                state.tool_call_ids.clear();
+            }
+            
+            ChatMessage::Usage { id, model, usage, sub_agent } => {
+                // Report against the delegated request so the sub-agent can total it
+                let request_id = state.current_request.unwrap_or(id);
+                if let Some(context) = &state.current_context
+                    && let Some(display_ref) = state.display_refs.get(context)
+                {
+                    let _ = display_ref.send_message(ChatMessage::Usage { id: request_id, model, usage, sub_agent });
+                }
+            }
+            
             ChatMessage::RegisterDisplay { context, display_ref } => {
                 tracing::debug!("Registering display actor for context: {:?}", context);
                 state.display_refs.insert(context, display_ref);
@@ -372,6 +445,15 @@ impl Actor for SubAgentChatActor {
             ChatMessage::SwitchSession { .. } => {
                 tracing::debug!("SubAgentChat ignoring SwitchSession - not applicable to sub-agents");
             }
+            ChatMessage::Compacted { .. } => {
+                tracing::debug!("SubAgentChat ignoring Compacted - sub-agents don't compact");
+            }
+            ChatMessage::UIUpdate(_) => {
+                tracing::debug!("SubAgentChat ignoring UIUpdate - no UI to update");
+            }
+            ChatMessage::ConfirmationResponse { .. } => {
+                tracing::debug!("SubAgentChat ignoring ConfirmationResponse - sub-agents don't ask for confirmation");
+            }
         }
         
         Ok(())
@@ -381,6 +463,7 @@ impl Actor for SubAgentChatActor {
 impl SubAgentChatActor {
     pub fn new(config: Config, tool_actors: HashMap<String, ActorRef<ToolMessage>>, enable_tool_api: bool) -> Self {
         Self {
+            policy: PermissionPolicy::new(config.permissions.clone()),
             config,
             client_ref: None,
             tool_actors,
@@ -393,11 +476,52 @@ impl SubAgentChatActor {
         self
     }
     
+    /// Run a tool call the permission policy allows, answering any other with an error result
+    fn call_tool(
+        &self,
+        myself: &ActorRef<ChatMessage>,
+        state: &mut SubAgentChatState,
+        tool_id: Uuid,
+        call: ToolCall,
+    ) -> Result<(), ActorProcessingErr> {
+        state.pending_tool_calls.insert(tool_id, (call.tool_name.clone(), myself.clone()));
+        let Some(tool_ref) = self.tool_actors.get(&call.tool_name) else {
+            tracing::error!("Tool actor not found: {}", call.tool_name);
+            myself.send_message(ChatMessage::ToolResult {
+                id: tool_id,
+                result: format!("Error: Tool '{}' not available", call.tool_name),
+            })?;
+            return Ok(());
+        };
+        
+        let refusal = match self.policy.check(&call) {
+            PermissionAction::Allow => {
+                tool_ref.send_message(ToolMessage::Execute {
+                    id: tool_id,
+                    params: call.parameters,
+                    chat_ref: myself.clone(),
+                })?;
+                return Ok(());
+            }
+            PermissionAction::Deny => format!("the permission policy blocks this {} call", call.tool_name),
+            PermissionAction::Ask => format!(
+                "this {} call needs the user's confirmation, which a sub-agent can't ask for; \
+                 a permissions rule can allow it",
+                call.tool_name
+            ),
+        };
+        tracing::info!("Permission policy refused sub-agent {} call {}", call.tool_name, tool_id);
+        myself.send_message(ChatMessage::ToolResult {
+            id: tool_id,
+            result: format!("Error: Permission denied: {}", refusal),
+        })?;
+        Ok(())
+    }
+    
     fn build_tool_catalogue_xml(&self) -> String {
         let mut parts = Vec::new();
         parts.push("<tools>".to_string());
-        for tool_name in self.tool_actors.keys() {
-            let def = self.create_tool_definition(tool_name);
+        for def in self.tool_definitions() {
             let json_def = serde_json::to_string(&def).unwrap_or_default();
             parts.push(format!("  <tool>{}</tool>", json_def));
         }
@@ -411,160 +535,18 @@ impl SubAgentChatActor {
             return Vec::new();
         }
 
-        let mut tools = Vec::new();
-        // Only include tools we have actors for
-        for tool_name in self.tool_actors.keys() {
-            let tool = self.create_tool_definition(tool_name);
-            tools.push(tool);
-        }
-        tools
+        self.tool_definitions()
     }
     
-    fn create_tool_definition(&self, tool_name: &str) -> Tool {
-        // Create tool definitions for the tools available to sub-agents
-        let (description, parameters) = match tool_name {
-            "screenshot" => (
-                "Take a screenshot of the screen or a region",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "action": {
-                            "type": "string",
-                            "enum": ["capture_screen", "capture_window", "capture_region", "capture_interactive"],
-                            "description": "Type of screenshot to take"
-                        },
-                        "display": {
-                            "type": "integer",
-                            "description": "Display number (for capture_screen)"
-                        },
-                        "x": {
-                            "type": "integer",
-                            "description": "X coordinate for region capture"
-                        },
-                        "y": {
-                            "type": "integer", 
-                            "description": "Y coordinate for region capture"
-                        },
-                        "width": {
-                            "type": "integer",
-                            "description": "Width for region capture"
-                        },
-                        "height": {
-                            "type": "integer",
-                            "description": "Height for region capture"
-                        }
-                    },
-                    "required": ["action"]
-                })
-            ),
-            "desktop_control" => (
-                "Control mouse and keyboard on the desktop",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "action": {
-                            "type": "string",
-                            "enum": ["mouse_move", "mouse_click", "mouse_drag", "keyboard_type", "keyboard_key", "get_mouse_position", "check_installation"],
-                            "description": "Type of desktop control action"
-                        },
-                        "x": {
-                            "type": "integer",
-                            "description": "X coordinate for mouse actions"
-                        },
-                        "y": {
-                            "type": "integer",
-                            "description": "Y coordinate for mouse actions"
-                        },
-                        "from_x": {
-                            "type": "integer",
-                            "description": "Starting X coordinate for drag"
-                        },
-                        "from_y": {
-                            "type": "integer",
-                            "description": "Starting Y coordinate for drag"
-                        },
-                        "to_x": {
-                            "type": "integer",
-                            "description": "Ending X coordinate for drag"
-                        },
-                        "to_y": {
-                            "type": "integer",
-                            "description": "Ending Y coordinate for drag"
-                        },
-                        "button": {
-                            "type": "string",
-                            "enum": ["left", "right", "middle"],
-                            "description": "Mouse button to use"
-                        },
-                        "count": {
-                            "type": "integer",
-                            "description": "Number of clicks"
-                        },
-                        "text": {
-                            "type": "string",
-                            "description": "Text to type"
-                        },
-                        "key": {
-                            "type": "string",
-                            "description": "Key or key combination to press"
-                        },
-                        "duration": {
-                            "type": "integer",
-                            "description": "Duration in milliseconds"
-                        },
-                        "delay_ms": {
-                            "type": "integer",
-                            "description": "Delay between keystrokes in milliseconds"
-                        }
-                    },
-                    "required": ["action"]
-                })
-            ),
-            "web_search" => (
-                "Search the web for information",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "query": {
-                            "type": "string",
-                            "description": "The search query"
-                        }
-                    },
-                    "required": ["query"]
-                })
-            ),
-            "web_fetch" => (
-                "Fetch content from a URL",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "url": {
-                            "type": "string",
-                            "description": "The URL to fetch"
-                        }
-                    },
-                    "required": ["url"]
-                })
-            ),
-            _ => (
-                "Unknown tool",
-                serde_json::json!({
-                    "type": "object",
-                    "properties": {}
-                })
-            )
-        };
-        
-        Tool {
-            tool_type: "function".to_string(),
-            function: FunctionDef {
-                name: tool_name.to_string(),
-                description: description.to_string(),
-                parameters,
-            },
-        }
+    /// Definitions of the tools this sub-agent has actors for
+    fn tool_definitions(&self) -> Vec<Tool> {
+        ToolRegistry::specs()
+            .iter()
+            .filter(|spec| self.tool_actors.contains_key(spec.name))
+            .map(ToolSpec::definition)
+            .collect()
     }
-
+    
     /// Extract <tool_call>JSON</tool_call> blocks from the model output.
     /// The JSON should contain a "name" field with the tool name.
     /// Returns (calls, parse_failures)
@@ -38,7 +38,7 @@ impl Actor for SubAgentDelegator {
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
         match msg {
-            DelegatorMessage::RouteToolCall { id, call, chat_ref } => {
+            DelegatorMessage::RouteToolCall { id, call, chat_ref, .. } => {
                 tracing::info!("Sub-agent delegator routing tool call: {} back to sub-agent", call.tool_name);
                 
                 // Store the chat reference
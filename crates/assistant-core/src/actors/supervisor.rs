@@ -69,63 +69,10 @@ impl Actor for SupervisorActor {
             SupervisorMessage::StartSession { session_id } => {
                 tracing::info!("Starting session {}", session_id);
                 
-                // Create client actor
-                let client_actor = ClientActor::new(self.config.clone());
-                let (client_ref, _) = Actor::spawn(
-                    Some(format!("client-{}", session_id)),
-                    client_actor,
-                    self.config.clone()
-                ).await?;
-                
-                // Create persistence actor
-                let persistence_actor = ChatPersistenceActor::new(self.config.clone()).await
-                    .map_err(|e| format!("Failed to create persistence actor: {}", e))?
-                    .with_client_ref(client_ref.clone());
-                let (persistence_ref, _) = Actor::spawn(
-                    Some(format!("persistence-{}", session_id)),
-                    persistence_actor,
-                    ()
-                ).await?;
-                
-                // Create chat actor with session ID
-                let chat_actor = ChatActor::new(self.config.clone(), session_id.to_string())
-                    .with_client_ref(client_ref.clone())
-                    .with_persistence_ref(persistence_ref.clone());
-                let (chat_ref, _) = Actor::spawn(
-                    Some(format!("chat-{}", session_id)),
-                    chat_actor,
-                    self.config.clone()
-                ).await?;
-                
-                // Create delegator actor
-                let delegator_actor = DelegatorActor::new(self.config.clone());
-                let (delegator_ref, _) = Actor::spawn(
-                    Some(format!("delegator-{}", session_id)),
-                    delegator_actor,
-                    self.config.clone()
-                ).await?;
-                
-                // Update chat actor with delegator reference
-                chat_ref.send_message(ChatMessage::SetDelegatorRef(delegator_ref.clone()))?;
-                
-                // Update client actor with chat reference
-                client_ref.send_message(ClientMessage::SetChatRef(chat_ref.clone()))?;
-                
-                // Update delegator with tool actors
-                for (tool_name, tool_ref) in &state.tool_actors {
-                    delegator_ref.send_message(DelegatorMessage::RegisterTool {
-                        name: tool_name.clone(),
-                        actor_ref: tool_ref.clone(),
-                    })?;
-                }
-                
-                // Store session actors
-                state.sessions.insert(session_id, SessionActors {
-                    chat: chat_ref,
-                    client: client_ref,
-                    delegator: delegator_ref,
-                    persistence: persistence_ref,
-                });
+                // Boxed, so the futures spawning the session's actors live on the
+                // heap instead of in every call of `handle`
+                let session = Box::pin(self.start_session(session_id, &state.tool_actors)).await?;
+                state.sessions.insert(session_id, session);
             }
             
             SupervisorMessage::EndSession { session_id } => {
@@ -135,10 +82,13 @@ impl Actor for SupervisorActor {
                     // Send cancel to client
                     let _ = session.client.send_message(ClientMessage::Cancel);
                     
-                    // Stop actors
+                    // Stop actors, the delegator once it has dropped the session's state
                     let _ = session.chat.stop(None);
                     let _ = session.client.stop(None);
-                    let _ = session.delegator.stop(None);
+                    let _ = session.delegator.send_message(DelegatorMessage::EndSession {
+                        session_id: session_id.to_string(),
+                    });
+                    let _ = session.delegator.drain();
                     let _ = session.persistence.stop(None);
                 }
             }
@@ -173,4 +123,68 @@ impl SupervisorActor {
             config,
         }
     }
+    
+    /// Spawn and wire up the actors for a new session
+    async fn start_session(
+        &self,
+        session_id: Uuid,
+        tool_actors: &HashMap<String, ActorRef<ToolMessage>>,
+    ) -> Result<SessionActors, ActorProcessingErr> {
+        // Create client actor
+        let client_actor = ClientActor::new(self.config.clone());
+        let (client_ref, _) = Actor::spawn(
+            Some(format!("client-{}", session_id)),
+            client_actor,
+            self.config.clone()
+        ).await?;
+        
+        // Create persistence actor
+        let persistence_actor = ChatPersistenceActor::new(self.config.clone()).await
+            .map_err(|e| format!("Failed to create persistence actor: {}", e))?
+            .with_client_ref(client_ref.clone());
+        let (persistence_ref, _) = Actor::spawn(
+            Some(format!("persistence-{}", session_id)),
+            persistence_actor,
+            ()
+        ).await?;
+        
+        // Create chat actor with session ID
+        let chat_actor = ChatActor::new(self.config.clone(), session_id.to_string())
+            .with_client_ref(client_ref.clone())
+            .with_persistence_ref(persistence_ref.clone());
+        let (chat_ref, _) = Actor::spawn(
+            Some(format!("chat-{}", session_id)),
+            chat_actor,
+            self.config.clone()
+        ).await?;
+        
+        // Create delegator actor
+        let delegator_actor = DelegatorActor::new(self.config.clone());
+        let (delegator_ref, _) = Actor::spawn(
+            Some(format!("delegator-{}", session_id)),
+            delegator_actor,
+            self.config.clone()
+        ).await?;
+        
+        // Update chat actor with delegator reference
+        chat_ref.send_message(ChatMessage::SetDelegatorRef(delegator_ref.clone()))?;
+        
+        // Update client actor with chat reference
+        client_ref.send_message(ClientMessage::SetChatRef(chat_ref.clone()))?;
+        
+        // Update delegator with tool actors
+        for (tool_name, tool_ref) in tool_actors {
+            delegator_ref.send_message(DelegatorMessage::RegisterTool {
+                name: tool_name.clone(),
+                actor_ref: tool_ref.clone(),
+            })?;
+        }
+        
+        Ok(SessionActors {
+            chat: chat_ref,
+            client: client_ref,
+            delegator: delegator_ref,
+            persistence: persistence_ref,
+        })
+    }
 }
\ No newline at end of file
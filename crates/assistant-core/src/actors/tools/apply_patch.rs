@@ -0,0 +1,606 @@
+use ractor::{Actor, ActorRef, ActorProcessingErr};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::file_tracker::FileTracker;
+use crate::messages::{ToolMessage, ChatMessage};
+use crate::sandbox::Sandbox;
+use crate::utils::path::{PathAccess, normalize_path, validate_path_access};
+use super::base::ToolDescription;
+
+/// Lines of surrounding file content shown when a hunk doesn't apply
+const CONTEXT_LINES: usize = 3;
+
+/// Actor for applying unified diffs that may span several files
+pub struct ApplyPatchActor {
+    #[allow(dead_code)]
+    config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
+    /// Limits where files may be written when the sandbox is enabled
+    sandbox: Option<Sandbox>,
+}
+
+/// Apply patch actor state
+pub struct ApplyPatchState;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ApplyPatchParams {
+    patch: String,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
+}
+
+impl Actor for ApplyPatchActor {
+    type Msg = ToolMessage;
+    type State = ApplyPatchState;
+    type Arguments = Config;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        _config: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        tracing::debug!("Apply patch actor starting");
+        Ok(ApplyPatchState)
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match msg {
+            ToolMessage::Execute { id, params, chat_ref } => {
+                tracing::info!("Executing apply_patch tool");
+
+                let result = match serde_json::from_value::<ApplyPatchParams>(params) {
+                    Ok(params) => self.apply(&params.session_id, &params.patch).unwrap_or_else(|e| format!("Error: {}", e)),
+                    Err(e) => format!("Error: Invalid parameters - {}", e),
+                };
+
+                chat_ref.send_message(ChatMessage::ToolResult {
+                    id,
+                    result,
+                })?;
+            }
+
+            ToolMessage::Cancel { id } => {
+                tracing::debug!("Cancelling apply_patch operation {}", id);
+                // Patches are applied synchronously, nothing to cancel
+            }
+
+            ToolMessage::StreamUpdate { .. } => {
+                // Apply patch doesn't stream updates
+            }
+            
+            ToolMessage::EndSession { .. } => {
+                // The delegator forgets the files the session saw
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ToolDescription for ApplyPatchActor {
+    const NAME: &'static str = "apply_patch";
+    const DESCRIPTION: &'static str = "Apply a unified diff to one or more files. Supports creating (--- /dev/null), deleting (+++ /dev/null) and renaming files (git's rename from/rename to). Every hunk is checked before anything is written, so the patch applies completely or not at all";
+    const DESTRUCTIVE: bool = true;
+
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "patch": {
+                    "type": "string",
+                    "description": "The patch in unified diff format, as produced by `diff -u` or `git diff`"
+                }
+            },
+            "required": ["patch"]
+        })
+    }
+}
+
+impl ApplyPatchActor {
+    pub fn new(config: Config) -> Self {
+        let sandbox = Sandbox::from_config(&config);
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access, sandbox }
+    }
+
+    /// Check the whole patch, then write it
+    fn apply(&self, session_id: &str, patch: &str) -> Result<String, String> {
+        let patches = parse_patch(patch).map_err(|e| format!("Invalid patch - {}", e))?;
+        if patches.is_empty() {
+            return Err("Invalid patch - no file changes found".to_string());
+        }
+
+        let mut changes = Vec::new();
+        let mut summaries = Vec::new();
+        let mut problems = Vec::new();
+        for file in &patches {
+            match self.plan(session_id, file) {
+                Ok((file_changes, summary)) => {
+                    changes.extend(file_changes);
+                    summaries.push(summary);
+                }
+                Err(file_problems) => problems.extend(file_problems),
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(format!(
+                "Patch not applied; no files were changed. {} problem{} found:\n\n{}",
+                problems.len(),
+                if problems.len() == 1 { "" } else { "s" },
+                problems.join("\n\n")
+            ));
+        }
+
+        write_changes(&changes)?;
+        let tracker = FileTracker::global();
+        for change in &changes {
+            match change {
+                Change::Write { path, content } => tracker.record(session_id, path, content.as_bytes()),
+                Change::Delete { path } => tracker.forget(session_id, path),
+            }
+        }
+        Ok(format!(
+            "Successfully applied patch to {} file{}:\n{}",
+            summaries.len(),
+            if summaries.len() == 1 { "" } else { "s" },
+            summaries.iter().map(|summary| format!("- {}", summary)).collect::<Vec<_>>().join("\n")
+        ))
+    }
+
+    /// Work out what one file's patch writes, or every reason it can't be applied
+    fn plan(&self, session_id: &str, file: &FilePatch) -> Result<(Vec<Change>, String), Vec<String>> {
+        let old_path = file.old_path.as_deref().map(|path| self.check_path(path)).transpose().map_err(|e| vec![e])?;
+        if let Some(old_path) = &old_path {
+            FileTracker::global().check(session_id, old_path).map_err(|stale| vec![stale.describe("patched")])?;
+        }
+        let new_path = file.new_path.as_deref().map(|path| self.check_path(path)).transpose().map_err(|e| vec![e])?;
+        let hunks = match file.hunks.len() {
+            1 => " (1 hunk)".to_string(),
+            n => format!(" ({} hunks)", n),
+        };
+
+        match (old_path, new_path) {
+            (None, Some(new_path)) => {
+                let name = file.new_path.as_deref().unwrap_or_default();
+                if new_path.exists() {
+                    return Err(vec![format!("{}: cannot create the file, it already exists", name)]);
+                }
+                let content = apply_hunks(name, &FileContent::default(), &file.hunks)?;
+                Ok((vec![Change::Write { path: new_path, content: content.to_string() }], format!("{}: created", name)))
+            }
+            (Some(old_path), None) => {
+                let name = file.old_path.as_deref().unwrap_or_default();
+                let content = read_file(name, &old_path).map_err(|e| vec![e])?;
+                // Removed lines must match what's there, but needn't cover the whole file
+                apply_hunks(name, &content, &file.hunks)?;
+                Ok((vec![Change::Delete { path: old_path }], format!("{}: deleted", name)))
+            }
+            (Some(old_path), Some(new_path)) => {
+                let old_name = file.old_path.as_deref().unwrap_or_default();
+                let new_name = file.new_path.as_deref().unwrap_or_default();
+                let content = read_file(old_name, &old_path).map_err(|e| vec![e])?;
+                let patched = apply_hunks(old_name, &content, &file.hunks)?.to_string();
+
+                if old_path == new_path {
+                    return Ok((vec![Change::Write { path: new_path, content: patched }], format!("{}: modified{}", new_name, hunks)));
+                }
+                if new_path.exists() {
+                    return Err(vec![format!("{}: cannot rename to {}, it already exists", old_name, new_name)]);
+                }
+                let hunks = if file.hunks.is_empty() { String::new() } else { hunks };
+                Ok((
+                    vec![Change::Write { path: new_path, content: patched }, Change::Delete { path: old_path }],
+                    format!("{} -> {}: renamed{}", old_name, new_name, hunks),
+                ))
+            }
+            (None, None) => Err(vec!["A file change names neither an old nor a new path".to_string()]),
+        }
+    }
+
+    /// Resolve a path named in the patch, without `..` or symlinks, and check this
+    /// tool may write it
+    fn check_path(&self, name: &str) -> Result<PathBuf, String> {
+        let path = normalize_path(Path::new(name)).map_err(|e| format!("{}: {}", name, e))?;
+
+        validate_path_access(&path, &self.path_access).map_err(|e| format!("{}: {}", name, e))?;
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.check_write(&path).map_err(|e| format!("{}: {}", name, e))?;
+        }
+        Ok(path)
+    }
+}
+
+/// Every path a patch reads, writes or removes, as named in the patch
+pub fn patch_paths(patch: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for file in parse_patch(patch).unwrap_or_default() {
+        for path in [file.old_path, file.new_path].into_iter().flatten() {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+/// The changes to one file
+#[derive(Debug, Default)]
+struct FilePatch {
+    /// `None` when the file is created
+    old_path: Option<String>,
+    /// `None` when the file is deleted
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+#[derive(Debug)]
+struct Hunk {
+    /// The `@@ -l,s +l,s @@` line
+    header: String,
+    /// First line of the old file the hunk covers, counting from 1
+    old_start: usize,
+    lines: Vec<HunkLine>,
+    /// The old file's last line, if the hunk reaches it, has no newline
+    old_missing_newline: bool,
+    /// The new file's last line, if the hunk reaches it, has no newline
+    new_missing_newline: bool,
+}
+
+#[derive(Debug)]
+enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl Hunk {
+    /// Lines the hunk expects in the file
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Removed(text) => Some(text.as_str()),
+            HunkLine::Added(_) => None,
+        }).collect()
+    }
+
+    /// Lines the hunk leaves in their place
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Added(text) => Some(text.as_str()),
+            HunkLine::Removed(_) => None,
+        }).collect()
+    }
+}
+
+/// Parse a unified diff, with or without git's extended headers
+fn parse_patch(patch: &str) -> Result<Vec<FilePatch>, String> {
+    let lines: Vec<&str> = patch.split_terminator('\n').collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    // Whether the current file still waits for its `---`/`+++` lines
+    let mut git_header = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old, new) = parse_git_paths(rest);
+            files.push(FilePatch { old_path: old, new_path: new, hunks: Vec::new() });
+            git_header = true;
+        } else if let Some(rest) = line.strip_prefix("--- ") {
+            let Some(next) = lines.get(i + 1).and_then(|next| next.strip_prefix("+++ ")) else {
+                return Err(format!("line {}: '---' is not followed by a '+++' line", i + 1));
+            };
+            if !git_header {
+                files.push(FilePatch::default());
+            }
+            let file = files.last_mut().unwrap();
+            file.old_path = parse_path(rest, "a/");
+            file.new_path = parse_path(next, "b/");
+            git_header = false;
+            i += 1;
+        } else if line.starts_with("@@") {
+            let Some(file) = files.last_mut() else {
+                return Err(format!("line {}: hunk before any file header", i + 1));
+            };
+            let (hunk, next) = parse_hunk(&lines, i)?;
+            file.hunks.push(hunk);
+            git_header = false;
+            i = next;
+            continue;
+        } else if git_header {
+            let file = files.last_mut().unwrap();
+            if let Some(path) = line.strip_prefix("rename from ") {
+                file.old_path = Some(path.to_string());
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.new_path = Some(path.to_string());
+            } else if line.starts_with("new file mode") {
+                file.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                file.new_path = None;
+            }
+        }
+        // Anything else (`index` lines, commit messages, ...) is ignored
+        i += 1;
+    }
+
+    Ok(files)
+}
+
+/// The paths of a `diff --git a/old b/new` line
+fn parse_git_paths(rest: &str) -> (Option<String>, Option<String>) {
+    match rest.split_once(" b/") {
+        Some((old, new)) => (
+            Some(old.strip_prefix("a/").unwrap_or(old).to_string()),
+            Some(new.to_string()),
+        ),
+        None => (None, None),
+    }
+}
+
+/// The path of a `---` or `+++` line, `None` for `/dev/null`
+fn parse_path(rest: &str, prefix: &str) -> Option<String> {
+    // A timestamp may follow the path after a tab
+    let path = rest.split('\t').next().unwrap_or(rest).trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// Parse the hunk starting at `lines[start]`, returning it and the index of the line after it
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(Hunk, usize), String> {
+    let header = lines[start];
+    let invalid = || format!("line {}: invalid hunk header '{}'", start + 1, header);
+    let ranges = header.strip_prefix("@@ ")
+        .and_then(|rest| rest.split_once(" @@"))
+        .map(|(ranges, _)| ranges)
+        .ok_or_else(invalid)?;
+    let (old, new) = ranges.split_once(' ').ok_or_else(invalid)?;
+    let (old_start, mut old_remaining) = old.strip_prefix('-').and_then(parse_range).ok_or_else(invalid)?;
+    let (_, mut new_remaining) = new.strip_prefix('+').and_then(parse_range).ok_or_else(invalid)?;
+
+    let mut hunk = Hunk {
+        header: header.to_string(),
+        old_start,
+        lines: Vec::new(),
+        old_missing_newline: false,
+        new_missing_newline: false,
+    };
+    let mut i = start + 1;
+    while old_remaining > 0 || new_remaining > 0 {
+        let Some(line) = lines.get(i) else {
+            return Err(format!("line {}: hunk '{}' ends early", i, header));
+        };
+        // An empty line is a context line whose leading space was lost
+        let (marker, text) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        match marker {
+            " " | "" if old_remaining > 0 && new_remaining > 0 => {
+                hunk.lines.push(HunkLine::Context(text.to_string()));
+                old_remaining -= 1;
+                new_remaining -= 1;
+            }
+            "-" if old_remaining > 0 => {
+                hunk.lines.push(HunkLine::Removed(text.to_string()));
+                old_remaining -= 1;
+            }
+            "+" if new_remaining > 0 => {
+                hunk.lines.push(HunkLine::Added(text.to_string()));
+                new_remaining -= 1;
+            }
+            "\\" => mark_missing_newline(&mut hunk),
+            _ => return Err(format!(
+                "line {}: unexpected line '{}' in hunk '{}'; check the hunk's line counts",
+                i + 1, line, header
+            )),
+        }
+        i += 1;
+    }
+    // A "\ No newline at end of file" may follow the hunk's last line
+    if lines.get(i).is_some_and(|line| line.starts_with('\\')) {
+        mark_missing_newline(&mut hunk);
+        i += 1;
+    }
+
+    Ok((hunk, i))
+}
+
+/// Parse `l,s` or `l` (a range of one line)
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Record a "\ No newline at end of file" for the line before it
+fn mark_missing_newline(hunk: &mut Hunk) {
+    match hunk.lines.last() {
+        Some(HunkLine::Removed(_)) => hunk.old_missing_newline = true,
+        Some(HunkLine::Added(_)) => hunk.new_missing_newline = true,
+        Some(HunkLine::Context(_)) => {
+            hunk.old_missing_newline = true;
+            hunk.new_missing_newline = true;
+        }
+        None => {}
+    }
+}
+
+/// A file's lines and whether it ends with a newline
+#[derive(Debug, Clone, Default)]
+struct FileContent {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+impl FileContent {
+    fn parse(text: &str) -> Self {
+        Self {
+            lines: text.split_terminator('\n').map(str::to_string).collect(),
+            trailing_newline: text.ends_with('\n'),
+        }
+    }
+}
+
+impl std::fmt::Display for FileContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))?;
+        if self.trailing_newline && !self.lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_file(name: &str, path: &Path) -> Result<FileContent, String> {
+    fs::read_to_string(path).map(|text| FileContent::parse(&text)).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            format!("{}: file not found", name)
+        } else {
+            format!("{}: cannot read file: {}", name, e)
+        }
+    })
+}
+
+/// Apply hunks in order, each located where its old lines match nearest to the
+/// line it names, reporting every hunk that can't be placed
+fn apply_hunks(name: &str, content: &FileContent, hunks: &[Hunk]) -> Result<FileContent, Vec<String>> {
+    let mut patched = FileContent { lines: Vec::new(), trailing_newline: content.trailing_newline };
+    let mut problems = Vec::new();
+    // Lines of `content` already copied to `patched`
+    let mut copied = 0;
+    // How far hunks were found from where they said they'd be
+    let mut offset: isize = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let old_lines = hunk.old_lines();
+        // `-l,0` names the line the hunk goes after
+        let expected = if old_lines.is_empty() { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+        let expected = expected.saturating_add_signed(offset).max(copied);
+
+        let Some(position) = find_lines(&content.lines, &old_lines, expected, copied) else {
+            problems.push(describe_failure(name, index, hunks.len(), hunk, content, expected));
+            continue;
+        };
+        offset += position as isize - expected as isize;
+
+        patched.lines.extend_from_slice(&content.lines[copied..position]);
+        patched.lines.extend(hunk.new_lines().into_iter().map(str::to_string));
+        copied = position + old_lines.len();
+        if copied == content.lines.len() {
+            if hunk.new_missing_newline {
+                patched.trailing_newline = false;
+            } else if hunk.old_missing_newline || content.lines.is_empty() {
+                patched.trailing_newline = true;
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    patched.lines.extend_from_slice(&content.lines[copied..]);
+    Ok(patched)
+}
+
+/// Find `needle` in `lines` at or after `min`, nearest to `expected`
+fn find_lines(lines: &[String], needle: &[&str], expected: usize, min: usize) -> Option<usize> {
+    let last = lines.len().checked_sub(needle.len())?;
+    if min > last {
+        return None;
+    }
+    let expected = expected.clamp(min, last);
+    let matches = |position: usize| {
+        lines[position..position + needle.len()].iter().zip(needle).all(|(line, wanted)| line == wanted)
+    };
+
+    (0..=last - min).find_map(|distance| {
+        [expected.checked_add(distance), expected.checked_sub(distance)]
+            .into_iter()
+            .flatten()
+            .find(|&position| position >= min && position <= last && matches(position))
+    })
+}
+
+/// Explain why a hunk doesn't apply, showing what it expected next to what the file has
+fn describe_failure(name: &str, index: usize, count: usize, hunk: &Hunk, content: &FileContent, expected: usize) -> String {
+    let old_lines = hunk.old_lines();
+    let wanted = old_lines.iter().map(|line| format!("    {}", line)).collect::<Vec<_>>().join("\n");
+
+    let start = expected.saturating_sub(CONTEXT_LINES).min(content.lines.len());
+    let end = (expected + old_lines.len() + CONTEXT_LINES).min(content.lines.len());
+    let found = if start == end {
+        "    (no lines)".to_string()
+    } else {
+        content.lines[start..end].iter()
+            .enumerate()
+            .map(|(i, line)| format!("    {:>4} | {}", start + i + 1, line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    format!(
+        "{}: hunk {} of {} ({}) does not match the file.\nExpected these lines near line {}:\n{}\nThe file has:\n{}",
+        name,
+        index + 1,
+        count,
+        hunk.header,
+        hunk.old_start,
+        wanted,
+        found
+    )
+}
+
+/// A file write the patch makes
+#[derive(Debug)]
+enum Change {
+    Write { path: PathBuf, content: String },
+    Delete { path: PathBuf },
+}
+
+impl Change {
+    fn path(&self) -> &Path {
+        match self {
+            Change::Write { path, .. } | Change::Delete { path } => path,
+        }
+    }
+}
+
+/// Make every change, restoring the files already changed if one fails
+fn write_changes(changes: &[Change]) -> Result<(), String> {
+    let mut originals: Vec<(&Path, Option<Vec<u8>>)> = Vec::new();
+
+    for change in changes {
+        let path = change.path();
+        originals.push((path, fs::read(path).ok()));
+
+        let result = match change {
+            Change::Write { path, content } => path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, content)),
+            Change::Delete { path } => fs::remove_file(path),
+        };
+        if let Err(e) = result {
+            for (path, original) in originals.iter().rev() {
+                let _ = match original {
+                    Some(bytes) => fs::write(path, bytes),
+                    None => fs::remove_file(path),
+                };
+            }
+            return Err(format!(
+                "Cannot write '{}': {}; the files already changed were restored",
+                path.display(), e
+            ));
+        }
+    }
+
+    Ok(())
+}
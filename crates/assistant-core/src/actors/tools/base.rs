@@ -1,6 +1,120 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use tokio::task::AbortHandle;
+use uuid::Uuid;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
+use crate::openai_compat::{FunctionDef, Tool};
+
+/// Describes a tool actor to the model and to users.
+///
+/// Every tool actor implements this so that tool definitions come from the
+/// tool itself; `ToolRegistry` collects them for the chat actors and the CLI.
+pub trait ToolDescription {
+    /// Name the tool is registered and called by
+    const NAME: &'static str;
+    
+    /// What the tool does, as offered to the model
+    const DESCRIPTION: &'static str;
+    
+    /// Whether the tool only reads state and never changes anything
+    const READ_ONLY: bool = false;
+    
+    /// Whether the tool can modify or delete data outside the conversation
+    const DESTRUCTIVE: bool = false;
+    
+    /// JSON schema for the tool's parameters
+    fn parameters() -> Value;
+    
+    /// Collect the description into a `ToolSpec`
+    fn spec() -> ToolSpec {
+        ToolSpec {
+            name: Self::NAME,
+            description: Self::DESCRIPTION,
+            parameters: Self::parameters(),
+            read_only: Self::READ_ONLY,
+            destructive: Self::DESTRUCTIVE,
+        }
+    }
+}
+
+/// A tool's description, as collected by `ToolRegistry`
+#[derive(Debug, Clone)]
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Value,
+    pub read_only: bool,
+    pub destructive: bool,
+}
+
+impl ToolSpec {
+    /// Function-calling definition offered to the model
+    pub fn definition(&self) -> Tool {
+        Tool {
+            tool_type: "function".to_string(),
+            function: FunctionDef {
+                name: self.name.to_string(),
+                description: self.description.to_string(),
+                parameters: self.parameters.clone(),
+            },
+        }
+    }
+}
+
+/// Split the image out of a tool result that carries one, as `screenshot` and
+/// `read` do with `{"image": "data:image/...;base64,..."}`. Returns the result
+/// with a placeholder where the image was, and the image's data URL.
+pub fn take_image(result: &str) -> Option<(String, String)> {
+    if !result.starts_with('{') {
+        return None;
+    }
+    let mut json: Value = serde_json::from_str(result).ok()?;
+    let object = json.as_object_mut()?;
+    let image = object.get("image")?.as_str()?.to_string();
+    if !image.starts_with("data:image/") {
+        return None;
+    }
+    
+    object.insert("image".to_string(), Value::from("<image_data_removed>"));
+    object.insert("note".to_string(), Value::from("The image is attached after the tool results"));
+    Some((json.to_string(), image))
+}
+
+/// Tool executions running in the background, so that `ToolMessage::Cancel` can
+/// reach them while the actor keeps handling messages.
+///
+/// Aborting an execution drops its future, so whatever it owns (a child process,
+/// an HTTP request) is dropped with it.
+#[derive(Default)]
+pub struct RunningExecutions {
+    tasks: HashMap<Uuid, AbortHandle>,
+}
+
+impl RunningExecutions {
+    /// Run `execution` in the background under `id`
+    pub fn spawn<F>(&mut self, id: Uuid, execution: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.retain(|_, task| !task.is_finished());
+        let task = tokio::spawn(execution);
+        self.tasks.insert(id, task.abort_handle());
+    }
+    
+    /// Abort the execution running under `id`. Returns whether one was still running.
+    pub fn cancel(&mut self, id: &Uuid) -> bool {
+        match self.tasks.remove(id) {
+            Some(task) if !task.is_finished() => {
+                task.abort();
+                true
+            }
+            _ => false,
+        }
+    }
+}
 
 /// Base implementation for tool actors with common functionality
 /// 
@@ -57,6 +171,11 @@ impl Actor for BaseToolActor {
                 tracing::debug!("Stream update for '{}' ({}): {}", self.name, id, output);
                 // Tools that support streaming can override this
             }
+            
+            ToolMessage::EndSession { session_id } => {
+                tracing::debug!("Session {} ended for '{}'", session_id, self.name);
+                // Tools that keep state per session drop it here
+            }
         }
         
         Ok(())
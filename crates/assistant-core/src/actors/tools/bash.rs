@@ -1,33 +1,112 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::process::Stdio;
-use std::time::Duration;
-use tokio::process::Command;
-use tokio::time::timeout;
-use tokio::io::BufReader;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::task::JoinHandle;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
+use crate::sandbox::Sandbox;
+use super::base::{RunningExecutions, ToolDescription};
 
 /// Actor for executing bash commands
 pub struct BashActor {
     #[allow(dead_code)]
     config: Config,
+    /// Run commands in one long-lived shell instead of a new one each time
+    persistent_shell: bool,
+    /// Confines commands when the sandbox is enabled
+    sandbox: Option<Sandbox>,
 }
 
 /// Bash actor state
 pub struct BashState {
+    /// Where sessions start, restored by the reset action
+    start_directory: String,
+    /// Commands still running, by execution id
+    running: RunningExecutions,
+    /// What each session keeps, by session id, from its first command on
+    sessions: HashMap<String, BashSession>,
+}
+
+/// What the bash tool keeps for one chat session, until the session ends
+struct BashSession {
     working_directory: String,
+    /// The persistent shell, when enabled
+    shell: Option<ShellSession>,
+    /// Commands started with `run_in_background`, by job id. They live until
+    /// killed or until the session ends.
+    jobs: BTreeMap<u32, BackgroundJob>,
+    last_job_id: u32,
+}
+
+/// A command left running in the background
+struct BackgroundJob {
+    command: String,
+    started: Instant,
+    pgid: Option<u32>,
+    output: Arc<Mutex<JobOutput>>,
+    task: JoinHandle<()>,
+}
+
+/// What a background job printed since it was last polled, and how it ended
+#[derive(Default)]
+struct JobOutput {
+    unread: String,
+    /// Bytes dropped from the front of `unread` because it grew past `MAX_OUTPUT_BYTES`
+    dropped: usize,
+    /// How the job ended, once it has
+    status: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BashAction {
+    /// Run a command
+    #[default]
+    Run,
+    /// Read a background job's output since the last poll
+    Output,
+    /// List background jobs
+    List,
+    /// Kill a background job
+    Kill,
+    /// Start the shell afresh: clear its environment and return to the starting directory
+    Reset,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct BashParams {
+    #[serde(default)]
+    action: BashAction,
+    #[serde(default)]
     command: String,
     #[serde(default)]
     description: Option<String>,
     #[serde(default = "default_timeout")]
     timeout: u64,
+    #[serde(default)]
+    run_in_background: bool,
+    #[serde(default)]
+    job_id: Option<u32>,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
 }
 
+/// Longest command output, in bytes, returned to the model
+const MAX_OUTPUT_BYTES: usize = 30_000;
+
+/// Streamed output, in bytes, held back at most before it is sent to the chat
+const STREAM_BATCH_BYTES: usize = 8 * 1024;
+
+/// How long streamed output is held back at most, so a command printing lines
+/// in quick succession sends a message per batch rather than per line
+const STREAM_INTERVAL: Duration = Duration::from_millis(100);
+
 fn default_timeout() -> u64 {
     120000 // 2 minutes in milliseconds
 }
@@ -45,16 +124,32 @@ impl Actor for BashActor {
         tracing::debug!("Bash actor starting");
         
         // Get current working directory
-        let working_directory = std::env::current_dir()
+        let start_directory = std::env::current_dir()
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|_| String::from("/"));
-            
-        Ok(BashState { working_directory })
+        
+        Ok(BashState {
+            start_directory,
+            running: RunningExecutions::default(),
+            sessions: HashMap::new(),
+        })
+    }
+    
+    async fn post_stop(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        // Background jobs and shells don't outlive the session that started them
+        for (_, session) in std::mem::take(&mut state.sessions) {
+            Self::end_session(session);
+        }
+        Ok(())
     }
     
     async fn handle(
         &self,
-        myself: ActorRef<Self::Msg>,
+        _myself: ActorRef<Self::Msg>,
         msg: Self::Msg,
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
@@ -74,6 +169,46 @@ impl Actor for BashActor {
                     }
                 };
                 
+                let start_directory = &state.start_directory;
+                let session = state.sessions
+                    .entry(bash_params.session_id.clone())
+                    .or_insert_with(|| self.new_session(start_directory));
+                
+                // Background jobs are managed without running anything
+                let result = match bash_params.action {
+                    BashAction::Run => None,
+                    BashAction::Output => Some(self.job_output(bash_params.job_id, session)),
+                    BashAction::List => Some(self.list_jobs(session)),
+                    BashAction::Kill => Some(self.kill(bash_params.job_id, session)),
+                    BashAction::Reset => match session.shell.clone() {
+                        // Wait for any command still using the shell without blocking the actor
+                        Some(shell) => {
+                            let start_directory = start_directory.clone();
+                            state.running.spawn(id, async move {
+                                let result = shell.reset(start_directory).await;
+                                let _ = chat_ref.send_message(ChatMessage::ToolResult { id, result });
+                            });
+                            return Ok(());
+                        }
+                        None => {
+                            session.working_directory = start_directory.clone();
+                            Some(format!("Working directory reset to {}", session.working_directory))
+                        }
+                    },
+                };
+                if let Some(result) = result {
+                    chat_ref.send_message(ChatMessage::ToolResult { id, result })?;
+                    return Ok(());
+                }
+                
+                if bash_params.command.trim().is_empty() {
+                    chat_ref.send_message(ChatMessage::ToolResult {
+                        id,
+                        result: "Error: Invalid parameters - `command` is required to run a command".to_string(),
+                    })?;
+                    return Ok(());
+                }
+                
                 // Validate timeout
                 if bash_params.timeout > 600000 {
                     chat_ref.send_message(ChatMessage::ToolResult {
@@ -83,24 +218,72 @@ impl Actor for BashActor {
                     return Ok(());
                 }
                 
-                // Execute command
-                let result = self.execute_command(&bash_params, state, myself.clone(), id).await;
+                // TUI applications and `cd` are answered right away
+                if let Some(result) = self.check_command(&bash_params, session) {
+                    chat_ref.send_message(ChatMessage::ToolResult { id, result })?;
+                    return Ok(());
+                }
+                
+                if bash_params.run_in_background {
+                    // Background jobs get a shell of their own, started where the session's shell is
+                    let working_directory = match &session.shell {
+                        Some(shell) => shell.working_directory(),
+                        None => session.working_directory.clone(),
+                    };
+                    let result = match self.build_command(&bash_params, &working_directory) {
+                        Ok(cmd) => self.start_job(cmd, &bash_params, session),
+                        Err(e) => e,
+                    };
+                    chat_ref.send_message(ChatMessage::ToolResult { id, result })?;
+                    return Ok(());
+                }
+                
+                // Run the command in its own task so it can be cancelled
+                let timeout_duration = Duration::from_millis(bash_params.timeout);
+                let sandbox = self.sandbox.clone();
+                if let Some(shell) = session.shell.clone() {
+                    state.running.spawn(id, async move {
+                        let result = match shell.run(&bash_params.command, timeout_duration, &chat_ref, id).await {
+                            Ok(output) => Self::annotate(sandbox.as_ref(), output),
+                            Err(e) => format!("Error executing command: {}", e),
+                        };
+                        let _ = chat_ref.send_message(ChatMessage::ToolResult { id, result });
+                    });
+                    return Ok(());
+                }
                 
-                // Send result back to chat
-                chat_ref.send_message(ChatMessage::ToolResult {
-                    id,
-                    result,
-                })?;
+                let cmd = match self.build_command(&bash_params, &session.working_directory) {
+                    Ok(cmd) => cmd,
+                    Err(result) => {
+                        chat_ref.send_message(ChatMessage::ToolResult { id, result })?;
+                        return Ok(());
+                    }
+                };
+                state.running.spawn(id, async move {
+                    let result = match Self::run_command(cmd, timeout_duration, &chat_ref, id).await {
+                        Ok(output) => Self::annotate(sandbox.as_ref(), output),
+                        Err(e) => format!("Error executing command: {}", e),
+                    };
+                    let _ = chat_ref.send_message(ChatMessage::ToolResult { id, result });
+                });
             }
             
             ToolMessage::Cancel { id } => {
-                tracing::debug!("Cancelling bash operation {}", id);
-                // TODO: Implement command cancellation
+                if state.running.cancel(&id) {
+                    tracing::info!("Cancelled bash command {}", id);
+                }
             }
             
             ToolMessage::StreamUpdate { id, output } => {
                 tracing::debug!("Bash stream update for {}: {}", id, output);
-                // This is used internally for streaming output
+                // Running commands stream their output to the chat as ChatMessage::ToolOutput
+            }
+            
+            ToolMessage::EndSession { session_id } => {
+                if let Some(session) = state.sessions.remove(&session_id) {
+                    tracing::debug!("Ending bash session {}", session_id);
+                    Self::end_session(session);
+                }
             }
         }
         
@@ -108,9 +291,53 @@ impl Actor for BashActor {
     }
 }
 
+impl ToolDescription for BashActor {
+    const NAME: &'static str = "bash";
+    const DESCRIPTION: &'static str = "Execute a bash command. Long-running commands such as dev servers and watchers can run in the background; poll their output, list them or kill them with the other actions. The working directory, and with a persistent shell the environment, carries over between commands until reset. Cannot run interactive TUI applications like vim, nano, or htop; use tui_control for those";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["run", "output", "list", "kill", "reset"],
+                    "description": "run a command (default), read a background job's new output, list background jobs, kill one, or reset the shell's environment and working directory"
+                },
+                "command": {
+                    "type": "string",
+                    "description": "The command to execute (for run)"
+                },
+                "description": {
+                    "type": "string",
+                    "description": "Short description of what the command does"
+                },
+                "timeout": {
+                    "type": "integer",
+                    "description": "Timeout in milliseconds (default: 120000, max: 600000); background jobs have none"
+                },
+                "run_in_background": {
+                    "type": "boolean",
+                    "description": "Start the command as a background job and return its job id right away (for run)"
+                },
+                "job_id": {
+                    "type": "integer",
+                    "description": "Background job to act on (for output and kill)"
+                }
+            },
+            "required": []
+        })
+    }
+}
+
 impl BashActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let persistent_shell = config.tools.configs.get(Self::NAME)
+            .and_then(|tool| tool.get_setting::<bool>("persistent_shell"))
+            .unwrap_or(false);
+        let sandbox = Sandbox::from_config(&config);
+        Self { config, persistent_shell, sandbox }
     }
     
     /// Check if a command is a TUI application
@@ -161,40 +388,42 @@ impl BashActor {
         None
     }
     
-    async fn execute_command(
-        &self,
-        params: &BashParams,
-        state: &mut BashState,
-        myself: ActorRef<ToolMessage>,
-        execution_id: uuid::Uuid,
-    ) -> String {
+    /// Answer commands that don't need a shell: TUI applications are refused and,
+    /// without a persistent shell, `cd` changes the working directory used by later commands
+    fn check_command(&self, params: &BashParams, session: &mut BashSession) -> Option<String> {
         // Check if this is a TUI command
         if let Some(tui_cmd) = self.is_tui_command(&params.command) {
-            return format!(
+            return Some(format!(
                 "Error: '{}' is a TUI (Terminal User Interface) application that takes over the terminal.\n\
                 Please use the 'tui_control' tool instead of 'bash' to interact with TUI applications.\n\
                 Example: Use tui_control to start a session with '{}'",
                 tui_cmd, params.command.trim()
-            );
+            ));
         }
-        // Update working directory if cd command
-        if let Some(new_dir) = self.extract_cd_path(&params.command) {
-            match self.change_directory(&new_dir, &state.working_directory) {
+        // Update working directory if cd command; a persistent shell keeps track itself
+        if session.shell.is_none()
+            && let Some(new_dir) = self.extract_cd_path(&params.command)
+        {
+            match self.change_directory(&new_dir, &session.working_directory) {
                 Ok(absolute_path) => {
-                    state.working_directory = absolute_path.clone();
-                    return format!("Changed directory to: {}", absolute_path);
+                    session.working_directory = absolute_path.clone();
+                    return Some(format!("Changed directory to: {}", absolute_path));
                 }
                 Err(e) => {
-                    return format!("Error changing directory: {}", e);
+                    return Some(format!("Error changing directory: {}", e));
                 }
             }
         }
         
+        None
+    }
+    
+    fn build_command(&self, params: &BashParams, working_directory: &str) -> Result<Command, String> {
         // Prepare command with shell
         let mut cmd = Command::new("bash");
         cmd.arg("-c")
             .arg(&params.command)
-            .current_dir(&state.working_directory)
+            .current_dir(working_directory)
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
@@ -203,75 +432,347 @@ impl BashActor {
         cmd.env("NO_COLOR", "1");
         cmd.env("TERM", "dumb");
         
-        // Execute command with timeout
-        let timeout_duration = Duration::from_millis(params.timeout);
+        // Kill the command if it outlives its timeout or is cancelled
+        cmd.kill_on_drop(true);
+        
+        // Run the command in its own process group, so that everything it starts can be
+        // killed with it, and so that a Ctrl-C meant for the assistant doesn't reach it
+        #[cfg(unix)]
+        cmd.process_group(0);
+        
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.apply(&mut cmd).map_err(|e| format!("Error: Cannot sandbox the command - {}", e))?;
+        }
         
-        match timeout(timeout_duration, self.run_command(cmd, myself, execution_id)).await {
-            Ok(Ok(output)) => output,
-            Ok(Err(e)) => format!("Error executing command: {}", e),
-            Err(_) => format!("Command timed out after {}ms", params.timeout),
+        Ok(cmd)
+    }
+    
+    /// Point out in a command's output where the sandbox may have stopped it
+    fn annotate(sandbox: Option<&Sandbox>, output: String) -> String {
+        match sandbox {
+            Some(sandbox) => sandbox.annotate(output),
+            None => output,
         }
     }
     
+    /// Run the command, streaming its output to `chat_ref` in batches as it arrives.
+    /// Returns the combined output, stdout first, truncated to `MAX_OUTPUT_BYTES`.
+    ///
+    /// If this future is dropped before the command finishes, because it timed out
+    /// or was cancelled, the command's whole process group is killed.
     async fn run_command(
-        &self,
         mut cmd: Command,
-        _myself: ActorRef<ToolMessage>,
-        _execution_id: uuid::Uuid,
+        timeout_duration: Duration,
+        chat_ref: &ActorRef<ChatMessage>,
+        execution_id: uuid::Uuid,
     ) -> Result<String, String> {
         let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn command: {}", e))?;
+        let mut process_group = ProcessGroupGuard::new(child.id());
         
-        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
-        let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
-        
-        let stdout_reader = BufReader::new(stdout);
-        let stderr_reader = BufReader::new(stderr);
-        
-        let mut output = String::new();
-        
-        // Read output from both stdout and stderr
-        use tokio::io::AsyncReadExt;
+        let mut stdout = BufReader::new(child.stdout.take().ok_or("Failed to capture stdout")?);
+        let mut stderr = BufReader::new(child.stderr.take().ok_or("Failed to capture stderr")?);
         
         let mut stdout_buf = Vec::new();
         let mut stderr_buf = Vec::new();
+        // Partially read lines survive a cancelled read_until, so these live outside the loop
+        let mut stdout_line = Vec::new();
+        let mut stderr_line = Vec::new();
+        let (mut stdout_open, mut stderr_open) = (true, true);
+        let mut stdout_stream = OutputStream::new(chat_ref, execution_id);
+        let mut stderr_stream = OutputStream::new(chat_ref, execution_id);
         
-        // Read all stdout
-        let mut stdout_reader = stdout_reader.into_inner();
-        stdout_reader.read_to_end(&mut stdout_buf).await.map_err(|e| format!("Failed to read stdout: {}", e))?;
+        let deadline = tokio::time::sleep(timeout_duration);
+        tokio::pin!(deadline);
+        let mut timed_out = false;
         
-        // Read all stderr
-        let mut stderr_reader = stderr_reader.into_inner();
-        stderr_reader.read_to_end(&mut stderr_buf).await.map_err(|e| format!("Failed to read stderr: {}", e))?;
+        while stdout_open || stderr_open {
+            tokio::select! {
+                read = stdout.read_until(b'\n', &mut stdout_line), if stdout_open => {
+                    stdout_open = Self::forward_line(read, &mut stdout_line, &mut stdout_buf, &mut stdout_stream)
+                        .map_err(|e| format!("Failed to read stdout: {}", e))?;
+                }
+                read = stderr.read_until(b'\n', &mut stderr_line), if stderr_open => {
+                    stderr_open = Self::forward_line(read, &mut stderr_line, &mut stderr_buf, &mut stderr_stream)
+                        .map_err(|e| format!("Failed to read stderr: {}", e))?;
+                }
+                _ = stdout_stream.due() => stdout_stream.flush(),
+                _ = stderr_stream.due() => stderr_stream.flush(),
+                _ = &mut deadline => {
+                    timed_out = true;
+                    break;
+                }
+            }
+        }
+        stdout_stream.flush();
+        stderr_stream.flush();
         
-        // Convert to strings
-        let stdout_str = String::from_utf8_lossy(&stdout_buf);
-        let stderr_str = String::from_utf8_lossy(&stderr_buf);
+        // Wait for process to complete; it may outlive its pipes
+        let status = if timed_out {
+            None
+        } else {
+            tokio::select! {
+                status = child.wait() => Some(status.map_err(|e| format!("Failed to wait for command: {}", e))?),
+                _ = &mut deadline => None,
+            }
+        };
+        
+        let note = match status {
+            None => {
+                process_group.kill();
+                let _ = child.kill().await;
+                Some(format!("Command timed out after {}ms", timeout_duration.as_millis()))
+            }
+            Some(status) if !status.success() => match status.code() {
+                Some(code) => Some(format!("Command exited with code: {}", code)),
+                None => Some("Command terminated by signal".to_string()),
+            },
+            Some(_) => None,
+        };
+        
+        // Leave alone what the command started in the background and detached from its output
+        if status.is_some() {
+            process_group.disarm();
+        }
         
-        // Combine output
-        output.push_str(&stdout_str);
+        Ok(Self::format_output(&stdout_buf, &stderr_buf, note))
+    }
+    
+    /// Combine a command's output, stdout first, with a note on how it ended,
+    /// truncated to `MAX_OUTPUT_BYTES`
+    fn format_output(stdout_buf: &[u8], stderr_buf: &[u8], note: Option<String>) -> String {
+        let stdout_str = String::from_utf8_lossy(stdout_buf);
+        let stderr_str = String::from_utf8_lossy(stderr_buf);
+        let mut output = stdout_str.to_string();
         if !stdout_str.is_empty() && !stderr_str.is_empty() {
             output.push('\n');
         }
         output.push_str(&stderr_str);
         
-        // Wait for process to complete
-        let status = child.wait().await.map_err(|e| format!("Failed to wait for command: {}", e))?;
-        
-        if !status.success() {
-            if let Some(code) = status.code() {
-                output.push_str(&format!("\n\nCommand exited with code: {}", code));
-            } else {
-                output.push_str("\n\nCommand terminated by signal");
-            }
+        if let Some(note) = note {
+            output.push_str("\n\n");
+            output.push_str(&note);
         }
         
         // Truncate output if too large
-        if output.len() > 30000 {
-            output.truncate(30000);
+        if output.len() > MAX_OUTPUT_BYTES {
+            let mut end = MAX_OUTPUT_BYTES;
+            while !output.is_char_boundary(end) {
+                end -= 1;
+            }
+            output.truncate(end);
             output.push_str("\n\n... (output truncated)");
         }
         
-        Ok(output)
+        output
+    }
+    
+    /// Start a background job and describe how to follow it
+    fn start_job(&self, mut cmd: Command, params: &BashParams, session: &mut BashSession) -> String {
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => return format!("Error executing command: Failed to spawn command: {}", e),
+        };
+        let pgid = child.id();
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return "Error executing command: Failed to capture output".to_string();
+        };
+        
+        session.last_job_id += 1;
+        let job_id = session.last_job_id;
+        
+        let output = Arc::new(Mutex::new(JobOutput::default()));
+        let task = tokio::spawn(Self::run_job(
+            child,
+            BufReader::new(stdout),
+            BufReader::new(stderr),
+            ProcessGroupGuard::new(pgid),
+            output.clone(),
+        ));
+        
+        tracing::info!("Started background job {}: {}", job_id, params.command);
+        session.jobs.insert(job_id, BackgroundJob {
+            command: params.command.clone(),
+            started: Instant::now(),
+            pgid,
+            output,
+            task,
+        });
+        
+        format!(
+            "Started background job {}. Use action \"output\" with job_id {} to read what it prints, and action \"kill\" to stop it.",
+            job_id, job_id
+        )
+    }
+    
+    /// Collect a background job's output until it exits
+    async fn run_job(
+        mut child: Child,
+        mut stdout: BufReader<tokio::process::ChildStdout>,
+        mut stderr: BufReader<tokio::process::ChildStderr>,
+        mut process_group: ProcessGroupGuard,
+        output: Arc<Mutex<JobOutput>>,
+    ) {
+        let mut stdout_line = Vec::new();
+        let mut stderr_line = Vec::new();
+        let (mut stdout_open, mut stderr_open) = (true, true);
+        
+        while stdout_open || stderr_open {
+            tokio::select! {
+                read = stdout.read_until(b'\n', &mut stdout_line), if stdout_open => {
+                    stdout_open = matches!(read, Ok(n) if n > 0);
+                    Self::append_job_output(&output, &mut stdout_line);
+                }
+                read = stderr.read_until(b'\n', &mut stderr_line), if stderr_open => {
+                    stderr_open = matches!(read, Ok(n) if n > 0);
+                    Self::append_job_output(&output, &mut stderr_line);
+                }
+            }
+        }
+        
+        let status = match child.wait().await {
+            Ok(status) => match status.code() {
+                Some(code) => format!("exited with code {}", code),
+                None => "terminated by signal".to_string(),
+            },
+            Err(e) => format!("failed: {}", e),
+        };
+        process_group.disarm();
+        output.lock().unwrap().status = Some(status);
+    }
+    
+    fn append_job_output(output: &Mutex<JobOutput>, line: &mut Vec<u8>) {
+        if line.is_empty() {
+            return;
+        }
+        let mut output = output.lock().unwrap();
+        output.unread.push_str(&String::from_utf8_lossy(line));
+        line.clear();
+        
+        // Keep only the newest output of a job nobody polls
+        if output.unread.len() > MAX_OUTPUT_BYTES {
+            let mut start = output.unread.len() - MAX_OUTPUT_BYTES;
+            while !output.unread.is_char_boundary(start) {
+                start += 1;
+            }
+            output.unread.drain(..start);
+            output.dropped += start;
+        }
+    }
+    
+    /// Describe a job's state and take the output it printed since the last poll
+    fn take_job_output(job_id: u32, job: &BackgroundJob) -> String {
+        let mut output = job.output.lock().unwrap();
+        let status = output.status.clone().unwrap_or_else(|| "running".to_string());
+        let mut result = format!("Job {} ({}):\n", job_id, status);
+        
+        if output.dropped > 0 {
+            result.push_str(&format!("... ({} earlier bytes dropped)\n", output.dropped));
+            output.dropped = 0;
+        }
+        if output.unread.is_empty() {
+            result.push_str("(no new output)");
+        } else {
+            result.push_str(&std::mem::take(&mut output.unread));
+        }
+        result
+    }
+    
+    fn job_output(&self, job_id: Option<u32>, session: &mut BashSession) -> String {
+        let Some(job_id) = job_id else {
+            return "Error: Invalid parameters - `job_id` is required to read a job's output".to_string();
+        };
+        match session.jobs.get(&job_id) {
+            Some(job) => Self::take_job_output(job_id, job),
+            None => format!("Error: No background job {}", job_id),
+        }
+    }
+    
+    fn list_jobs(&self, session: &BashSession) -> String {
+        if session.jobs.is_empty() {
+            return "No background jobs".to_string();
+        }
+        
+        session.jobs
+            .iter()
+            .map(|(job_id, job)| {
+                let status = job.output.lock().unwrap().status.clone().unwrap_or_else(|| "running".to_string());
+                format!("{}  {}  {}s  {}", job_id, status, job.started.elapsed().as_secs(), job.command)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
+    fn kill(&self, job_id: Option<u32>, session: &mut BashSession) -> String {
+        let Some(job_id) = job_id else {
+            return "Error: Invalid parameters - `job_id` is required to kill a job".to_string();
+        };
+        let Some(job) = session.jobs.remove(&job_id) else {
+            return format!("Error: No background job {}", job_id);
+        };
+        
+        let output = Self::take_job_output(job_id, &job);
+        let finished = job.output.lock().unwrap().status.is_some();
+        Self::kill_job(job);
+        if finished {
+            output
+        } else {
+            format!("{}\n\nKilled job {}", output, job_id)
+        }
+    }
+    
+    /// State for a session's first command, without a shell until it runs one
+    fn new_session(&self, start_directory: &str) -> BashSession {
+        BashSession {
+            working_directory: start_directory.to_string(),
+            shell: self.persistent_shell
+                .then(|| ShellSession::new(start_directory.to_string(), self.sandbox.clone())),
+            jobs: BTreeMap::new(),
+            last_job_id: 0,
+        }
+    }
+    
+    /// Kill what a session left running: its background jobs and its shell,
+    /// unless a command still running holds the shell
+    fn end_session(session: BashSession) {
+        for (job_id, job) in session.jobs {
+            tracing::debug!("Killing background job {}: {}", job_id, job.command);
+            Self::kill_job(job);
+        }
+        if let Some(shell) = session.shell
+            && let Ok(mut shell) = shell.shell.try_lock()
+        {
+            shell.take();
+        }
+    }
+    
+    /// Stop a job and, if it is still running, everything it started
+    fn kill_job(job: BackgroundJob) {
+        if job.output.lock().unwrap().status.is_none()
+            && let Some(pgid) = job.pgid
+        {
+            kill_process_group(pgid);
+        }
+        job.task.abort();
+    }
+    
+    /// Handle one `read_until` result: stream a completed line to the chat and
+    /// keep it for the final result. Returns whether the stream is still open.
+    fn forward_line(
+        read: std::io::Result<usize>,
+        line: &mut Vec<u8>,
+        buf: &mut Vec<u8>,
+        stream: &mut OutputStream,
+    ) -> std::io::Result<bool> {
+        let open = read? > 0;
+        Self::emit_line(line, buf, stream);
+        line.clear();
+        Ok(open)
+    }
+    
+    /// Stream a line of output to the chat and keep it for the final result
+    fn emit_line(line: &[u8], buf: &mut Vec<u8>, stream: &mut OutputStream) {
+        stream.push(line);
+        buf.extend_from_slice(line);
     }
     
     fn extract_cd_path(&self, command: &str) -> Option<String> {
@@ -321,4 +822,338 @@ impl BashActor {
         
         Ok(absolute_path.to_string_lossy().to_string())
     }
-}
\ No newline at end of file
+}
+
+/// The long-lived shell a chat session's commands share when `persistent_shell` is on.
+/// It is started on first use and restarted in the last known working directory
+/// after it exits, times out or is cancelled.
+#[derive(Clone)]
+struct ShellSession {
+    shell: Arc<tokio::sync::Mutex<Option<PersistentShell>>>,
+    /// The shell's working directory after the last command that finished
+    working_directory: Arc<Mutex<String>>,
+    sandbox: Option<Sandbox>,
+}
+
+impl ShellSession {
+    fn new(working_directory: String, sandbox: Option<Sandbox>) -> Self {
+        Self {
+            shell: Arc::new(tokio::sync::Mutex::new(None)),
+            working_directory: Arc::new(Mutex::new(working_directory)),
+            sandbox,
+        }
+    }
+    
+    fn working_directory(&self) -> String {
+        self.working_directory.lock().unwrap().clone()
+    }
+    
+    /// Run a command in the shell, waiting for any command before it. Output streams
+    /// to `chat_ref` and is combined and truncated like that of any other command.
+    ///
+    /// The shell is only kept once the command finished; if this future is dropped
+    /// first, the shell is killed along with the command.
+    async fn run(
+        &self,
+        command: &str,
+        timeout_duration: Duration,
+        chat_ref: &ActorRef<ChatMessage>,
+        execution_id: uuid::Uuid,
+    ) -> Result<String, String> {
+        let mut slot = self.shell.lock().await;
+        let mut shell = match slot.take() {
+            Some(shell) => shell,
+            None => PersistentShell::spawn(&self.working_directory(), self.sandbox.as_ref())
+                .map_err(|e| format!("Failed to start shell: {}", e))?,
+        };
+        
+        let mut stdout_buf = Vec::new();
+        let mut stderr_buf = Vec::new();
+        let outcome = shell
+            .run(command, timeout_duration, chat_ref, execution_id, &mut stdout_buf, &mut stderr_buf)
+            .await
+            .map_err(|e| format!("Failed to run command in the shell: {}", e))?;
+        
+        let note = match outcome {
+            ShellOutcome::Finished { code, working_directory } => {
+                if !working_directory.is_empty() {
+                    *self.working_directory.lock().unwrap() = working_directory;
+                }
+                *slot = Some(shell);
+                (code != 0).then(|| format!("Command exited with code: {}", code))
+            }
+            ShellOutcome::TimedOut => Some(format!(
+                "Command timed out after {}ms; the shell was restarted and its environment lost",
+                timeout_duration.as_millis()
+            )),
+            ShellOutcome::Exited(code) => Some(match code {
+                Some(code) => format!("The shell exited with code {}; the next command starts a new one", code),
+                None => "The shell exited; the next command starts a new one".to_string(),
+            }),
+        };
+        
+        Ok(BashActor::format_output(&stdout_buf, &stderr_buf, note))
+    }
+    
+    /// Kill the shell, once no command is using it, and go back to `start_directory`
+    async fn reset(&self, start_directory: String) -> String {
+        self.shell.lock().await.take();
+        *self.working_directory.lock().unwrap() = start_directory.clone();
+        format!("Shell reset: environment cleared and working directory set to {}", start_directory)
+    }
+}
+
+/// A bash process reading commands from its stdin. Each command is followed by a
+/// unique marker on stdout and stderr, so its output can be told from the next one's.
+struct PersistentShell {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    stderr: BufReader<ChildStderr>,
+    /// Kills the shell and everything it started when the shell is dropped
+    _process_group: ProcessGroupGuard,
+}
+
+/// How a command run in the persistent shell ended
+enum ShellOutcome {
+    Finished { code: i32, working_directory: String },
+    TimedOut,
+    /// The shell itself exited, e.g. because the command ran `exit`
+    Exited(Option<i32>),
+}
+
+impl PersistentShell {
+    fn spawn(working_directory: &str, sandbox: Option<&Sandbox>) -> std::io::Result<Self> {
+        let mut cmd = Command::new("bash");
+        cmd.args(["--noprofile", "--norc"])
+            .current_dir(working_directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .env("NO_COLOR", "1")
+            .env("TERM", "dumb")
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+        if let Some(sandbox) = sandbox {
+            sandbox.apply(&mut cmd)?;
+        }
+        
+        let mut child = cmd.spawn()?;
+        let process_group = ProcessGroupGuard::new(child.id());
+        let missing = |stream| std::io::Error::other(format!("Failed to capture {}", stream));
+        let stdin = child.stdin.take().ok_or_else(|| missing("stdin"))?;
+        let stdout = BufReader::new(child.stdout.take().ok_or_else(|| missing("stdout"))?);
+        let stderr = BufReader::new(child.stderr.take().ok_or_else(|| missing("stderr"))?);
+        
+        Ok(Self { child, stdin, stdout, stderr, _process_group: process_group })
+    }
+    
+    /// Run one command, streaming its output and collecting it into the buffers
+    async fn run(
+        &mut self,
+        command: &str,
+        timeout_duration: Duration,
+        chat_ref: &ActorRef<ChatMessage>,
+        execution_id: uuid::Uuid,
+        stdout_buf: &mut Vec<u8>,
+        stderr_buf: &mut Vec<u8>,
+    ) -> std::io::Result<ShellOutcome> {
+        let marker = format!("__ASSISTANT_DONE_{}__", uuid::Uuid::new_v4().simple());
+        // The command can't read the shell's own input, and the markers start on a line of their own
+        let script = format!(
+            "{{ eval '{}'; }} < /dev/null\n\
+            __assistant_status=$?\n\
+            printf '\\n%s %d %s\\n' '{marker}' \"$__assistant_status\" \"$PWD\"\n\
+            printf '\\n%s\\n' '{marker}' >&2\n",
+            command.replace('\'', r"'\''"),
+        );
+        self.stdin.write_all(script.as_bytes()).await?;
+        self.stdin.flush().await?;
+        
+        let mut stdout = MarkedLines::default();
+        let mut stderr = MarkedLines::default();
+        let (mut stdout_open, mut stderr_open) = (true, true);
+        let mut stdout_stream = OutputStream::new(chat_ref, execution_id);
+        let mut stderr_stream = OutputStream::new(chat_ref, execution_id);
+        let mut status: Option<String> = None;
+        let mut stderr_done = false;
+        
+        let deadline = tokio::time::sleep(timeout_duration);
+        tokio::pin!(deadline);
+        
+        while (status.is_none() && stdout_open) || (!stderr_done && stderr_open) {
+            tokio::select! {
+                read = self.stdout.read_until(b'\n', &mut stdout.line), if status.is_none() && stdout_open => {
+                    if read? == 0 {
+                        stdout_open = false;
+                        stdout.flush(stdout_buf, &mut stdout_stream);
+                    } else {
+                        status = stdout.take_line(&marker, stdout_buf, &mut stdout_stream);
+                    }
+                }
+                read = self.stderr.read_until(b'\n', &mut stderr.line), if !stderr_done && stderr_open => {
+                    if read? == 0 {
+                        stderr_open = false;
+                        stderr.flush(stderr_buf, &mut stderr_stream);
+                    } else {
+                        stderr_done = stderr.take_line(&marker, stderr_buf, &mut stderr_stream).is_some();
+                    }
+                }
+                _ = stdout_stream.due() => stdout_stream.flush(),
+                _ = stderr_stream.due() => stderr_stream.flush(),
+                _ = &mut deadline => {
+                    stdout.flush(stdout_buf, &mut stdout_stream);
+                    stderr.flush(stderr_buf, &mut stderr_stream);
+                    return Ok(ShellOutcome::TimedOut);
+                }
+            }
+        }
+        stdout_stream.flush();
+        stderr_stream.flush();
+        
+        match status {
+            Some(status) if stderr_done => {
+                let (code, working_directory) = status.split_once(' ').unwrap_or((status.as_str(), ""));
+                Ok(ShellOutcome::Finished {
+                    code: code.parse().unwrap_or(-1),
+                    working_directory: working_directory.to_string(),
+                })
+            }
+            _ => {
+                let code = tokio::select! {
+                    status = self.child.wait() => status.ok().and_then(|status| status.code()),
+                    _ = &mut deadline => None,
+                };
+                Ok(ShellOutcome::Exited(code))
+            }
+        }
+    }
+}
+
+/// Lines read from one of the persistent shell's streams. The last complete line is
+/// held back until the next one arrives, because the newline printed before the
+/// marker belongs to it rather than to the command's output.
+#[derive(Default)]
+struct MarkedLines {
+    line: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+impl MarkedLines {
+    /// Handle a line just read. Returns what follows the marker if the line is the marker.
+    fn take_line(&mut self, marker: &str, buf: &mut Vec<u8>, stream: &mut OutputStream) -> Option<String> {
+        if let Some(rest) = self.line.strip_prefix(marker.as_bytes()) {
+            let rest = String::from_utf8_lossy(rest).trim_matches(|c| c == ' ' || c == '\n').to_string();
+            self.line.clear();
+            if self.pending.last() == Some(&b'\n') {
+                self.pending.pop();
+            }
+            BashActor::emit_line(&self.pending, buf, stream);
+            self.pending.clear();
+            return Some(rest);
+        }
+        BashActor::emit_line(&self.pending, buf, stream);
+        self.pending = std::mem::take(&mut self.line);
+        None
+    }
+    
+    /// Forward whatever is left when the marker won't come
+    fn flush(&mut self, buf: &mut Vec<u8>, stream: &mut OutputStream) {
+        BashActor::emit_line(&self.pending, buf, stream);
+        BashActor::emit_line(&self.line, buf, stream);
+        self.pending.clear();
+        self.line.clear();
+    }
+}
+
+/// Streams one of a running command's outputs to the chat. Lines are collected
+/// and sent together once `STREAM_BATCH_BYTES` of them are waiting or the oldest
+/// has waited `STREAM_INTERVAL`; whatever is left is sent when the stream is dropped.
+struct OutputStream<'a> {
+    chat_ref: &'a ActorRef<ChatMessage>,
+    execution_id: uuid::Uuid,
+    pending: Vec<u8>,
+    /// When the pending output is due to be sent
+    due: Option<tokio::time::Instant>,
+}
+
+impl<'a> OutputStream<'a> {
+    fn new(chat_ref: &'a ActorRef<ChatMessage>, execution_id: uuid::Uuid) -> Self {
+        Self { chat_ref, execution_id, pending: Vec::new(), due: None }
+    }
+    
+    fn push(&mut self, line: &[u8]) {
+        if line.is_empty() {
+            return;
+        }
+        self.pending.extend_from_slice(line);
+        self.due.get_or_insert_with(|| tokio::time::Instant::now() + STREAM_INTERVAL);
+        if self.pending.len() >= STREAM_BATCH_BYTES {
+            self.flush();
+        }
+    }
+    
+    /// Completes once the pending output is due; never while nothing is pending
+    async fn due(&self) {
+        match self.due {
+            Some(due) => tokio::time::sleep_until(due).await,
+            None => std::future::pending().await,
+        }
+    }
+    
+    fn flush(&mut self) {
+        self.due = None;
+        if self.pending.is_empty() {
+            return;
+        }
+        let _ = self.chat_ref.send_message(ChatMessage::ToolOutput {
+            id: self.execution_id,
+            output: String::from_utf8_lossy(&std::mem::take(&mut self.pending)).to_string(),
+        });
+    }
+}
+
+impl Drop for OutputStream<'_> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Kills a command's process group when dropped, unless disarmed once the command exited
+struct ProcessGroupGuard {
+    pgid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    fn new(pid: Option<u32>) -> Self {
+        Self { pgid: pid }
+    }
+    
+    fn kill(&mut self) {
+        if let Some(pgid) = self.pgid.take() {
+            kill_process_group(pgid);
+        }
+    }
+    
+    fn disarm(&mut self) {
+        self.pgid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Kill every process in a group created for a command
+fn kill_process_group(pgid: u32) {
+    #[cfg(unix)]
+    // SAFETY: killpg only sends a signal; the group was created for the command
+    unsafe {
+        libc::killpg(pgid as libc::pid_t, libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
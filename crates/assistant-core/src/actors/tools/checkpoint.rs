@@ -0,0 +1,140 @@
+use ractor::{Actor, ActorRef, ActorProcessingErr};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use crate::config::Config;
+use crate::messages::{ToolMessage, ChatMessage};
+use crate::persistence::checkpoints::{self, CheckpointManager};
+use super::base::ToolDescription;
+
+/// Actor for listing and restoring the files changed in earlier turns
+pub struct CheckpointActor {
+    #[allow(dead_code)]
+    config: Config,
+    checkpoints: CheckpointManager,
+}
+
+/// Checkpoint actor state
+pub struct CheckpointState;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckpointAction {
+    /// Show the turns that changed files
+    #[default]
+    List,
+    /// Undo a turn and every later one
+    Restore,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CheckpointParams {
+    #[serde(default)]
+    action: CheckpointAction,
+    /// Turn to restore, as numbered by `list`
+    turn: Option<usize>,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
+}
+
+impl Actor for CheckpointActor {
+    type Msg = ToolMessage;
+    type State = CheckpointState;
+    type Arguments = Config;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        _config: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        tracing::debug!("Checkpoint actor starting");
+        Ok(CheckpointState)
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match msg {
+            ToolMessage::Execute { id, params, chat_ref } => {
+                tracing::info!("Executing checkpoint tool with params: {:?}", params);
+
+                let result = match serde_json::from_value::<CheckpointParams>(params) {
+                    Ok(params) => self.execute(params).await.unwrap_or_else(|e| format!("Error: {}", e)),
+                    Err(e) => format!("Error: Invalid parameters - {}", e),
+                };
+
+                chat_ref.send_message(ChatMessage::ToolResult {
+                    id,
+                    result,
+                })?;
+            }
+
+            ToolMessage::Cancel { id } => {
+                tracing::debug!("Cancelling checkpoint operation {}", id);
+                // Checkpoint operations are short, nothing to cancel
+            }
+
+            ToolMessage::StreamUpdate { .. } => {
+                // Checkpoint doesn't stream updates
+            }
+            
+            ToolMessage::EndSession { .. } => {
+                // Checkpoints are persisted, so /undo still works on resumed sessions
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ToolDescription for CheckpointActor {
+    const NAME: &'static str = "checkpoint";
+    const DESCRIPTION: &'static str = "List the files changed by write, edit and apply_patch in each turn of this session, or restore them to how they were before a turn. Restoring undoes that turn and every later one";
+    const DESTRUCTIVE: bool = true;
+
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list", "restore"],
+                    "description": "list shows the turns that changed files (default); restore undoes `turn` and later turns"
+                },
+                "turn": {
+                    "type": "integer",
+                    "description": "The turn to restore to the state before, as numbered by list"
+                }
+            },
+            "required": []
+        })
+    }
+}
+
+impl CheckpointActor {
+    pub async fn new(config: Config) -> Result<Self> {
+        let checkpoints = CheckpointManager::open(&config).await?;
+        Ok(Self { config, checkpoints })
+    }
+
+    async fn execute(&self, params: CheckpointParams) -> Result<String> {
+        if params.session_id.is_empty() {
+            anyhow::bail!("Checkpoints are only available within a session");
+        }
+
+        match params.action {
+            CheckpointAction::List => {
+                let turns = self.checkpoints.list(&params.session_id).await?;
+                Ok(checkpoints::describe_turns(&turns))
+            }
+            CheckpointAction::Restore => {
+                let turn = params.turn.ok_or_else(|| anyhow::anyhow!("Invalid parameters - restore needs a `turn`"))?;
+                let restored = self.checkpoints.restore(&params.session_id, turn).await?;
+                Ok(checkpoints::describe_restore(turn, &restored))
+            }
+        }
+    }
+}
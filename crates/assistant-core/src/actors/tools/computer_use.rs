@@ -4,6 +4,7 @@ use serde_json::json;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
 use anyhow::Result;
+use super::base::ToolDescription;
 
 /// Actor for computer use - a delegated tool that uses VLM for visual tasks
 /// This is primarily a placeholder that gets delegated to a subagent with vision capabilities
@@ -60,6 +61,52 @@ pub struct ScreenRegion {
     pub height: u32,
 }
 
+impl ToolDescription for ComputerUseActor {
+    const NAME: &'static str = "computer_use";
+    const DESCRIPTION: &'static str = "Visual desktop automation agent. This tool uses a dedicated sub-agent with vision capabilities to interact with the desktop through screenshots and control actions";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["describe_screen", "navigate_to", "perform_task", "type_text", "read_text", "wait_and_observe"],
+                    "description": "The computer use action to perform"
+                },
+                "description": {
+                    "type": "string",
+                    "description": "Natural language description of what to click/find (for navigate_to)"
+                },
+                "task": {
+                    "type": "string",
+                    "description": "Natural language description of the task to perform"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to type in the current focused element"
+                },
+                "region": {
+                    "type": "object",
+                    "properties": {
+                        "x": {"type": "integer"},
+                        "y": {"type": "integer"},
+                        "width": {"type": "integer"},
+                        "height": {"type": "integer"}
+                    },
+                    "description": "Screen region for describe_screen or read_text actions"
+                },
+                "duration_ms": {
+                    "type": "integer",
+                    "description": "Duration to wait in milliseconds (for wait_and_observe)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl ComputerUseActor {
     pub fn new(config: Config) -> Self {
         Self { config }
@@ -151,6 +198,10 @@ impl Actor for ComputerUseActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Streaming handled by subagent
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Computer use keeps nothing per session
+            }
         }
         
         Ok(())
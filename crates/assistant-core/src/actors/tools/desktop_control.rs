@@ -4,6 +4,7 @@ use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
 use anyhow::Result;
 use std::process::Command;
+use super::base::ToolDescription;
 
 /// Actor for controlling desktop (mouse and keyboard) on macOS
 pub struct DesktopControlActor {
@@ -82,6 +83,75 @@ fn default_click_count() -> u32 {
     1
 }
 
+impl ToolDescription for DesktopControlActor {
+    const NAME: &'static str = "desktop_control";
+    const DESCRIPTION: &'static str = "Control mouse and keyboard on macOS using cliclick";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["mouse_move", "mouse_click", "mouse_drag", "keyboard_type", "keyboard_key", "get_mouse_position", "check_installation"],
+                    "description": "The desktop control action to perform"
+                },
+                "x": {
+                    "type": "integer",
+                    "description": "X coordinate (for mouse actions)"
+                },
+                "y": {
+                    "type": "integer",
+                    "description": "Y coordinate (for mouse actions)"
+                },
+                "from_x": {
+                    "type": "integer",
+                    "description": "Starting X coordinate (for mouse_drag)"
+                },
+                "from_y": {
+                    "type": "integer",
+                    "description": "Starting Y coordinate (for mouse_drag)"
+                },
+                "to_x": {
+                    "type": "integer",
+                    "description": "Ending X coordinate (for mouse_drag)"
+                },
+                "to_y": {
+                    "type": "integer",
+                    "description": "Ending Y coordinate (for mouse_drag)"
+                },
+                "button": {
+                    "type": "string",
+                    "enum": ["left", "right", "middle"],
+                    "description": "Mouse button (default: left)"
+                },
+                "count": {
+                    "type": "integer",
+                    "description": "Click count for mouse_click (1=single, 2=double, 3=triple)"
+                },
+                "duration": {
+                    "type": "integer",
+                    "description": "Duration in milliseconds for smooth mouse movement"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to type (for keyboard_type)"
+                },
+                "key": {
+                    "type": "string",
+                    "description": "Key or key combination to press (e.g., 'cmd+c', 'escape', 'return')"
+                },
+                "delay_ms": {
+                    "type": "integer",
+                    "description": "Delay between keystrokes in milliseconds"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl DesktopControlActor {
     pub fn new(config: Config) -> Self {
         Self { config }
@@ -327,6 +397,10 @@ impl Actor for DesktopControlActor {
             ToolMessage::StreamUpdate { .. } => {
                 // No streaming for desktop control
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Desktop control keeps nothing per session
+            }
         }
         
         Ok(())
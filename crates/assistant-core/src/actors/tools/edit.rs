@@ -3,21 +3,78 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 use crate::config::Config;
+use crate::file_tracker::FileTracker;
 use crate::messages::{ToolMessage, ChatMessage};
-use crate::utils::path::{resolve_path, validate_path_access};
+use crate::sandbox::Sandbox;
+use crate::utils::path::{PathAccess, resolve_path, validate_path_access};
+use super::base::ToolDescription;
 
 /// Actor for editing files by replacing text
 pub struct EditActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
+    /// Limits where files may be written when the sandbox is enabled
+    sandbox: Option<Sandbox>,
 }
 
 /// Edit actor state
 pub struct EditState;
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EditAction {
+    /// Replace one string
+    #[default]
+    Edit,
+    /// Apply a list of replacements, all or none
+    MultiEdit,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct EditParams {
+    #[serde(default)]
+    action: EditAction,
     file_path: String,
+    #[serde(default)]
+    old_string: Option<String>,
+    #[serde(default)]
+    new_string: Option<String>,
+    #[serde(default = "default_expected_replacements")]
+    expected_replacements: usize,
+    /// Replacements for `multi_edit`, applied in order
+    #[serde(default)]
+    edits: Vec<Replacement>,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
+}
+
+impl EditParams {
+    /// Split into the file, the action and the replacements it makes
+    fn into_replacements(self) -> Result<(String, EditAction, Vec<Replacement>), String> {
+        let edits = match self.action {
+            EditAction::Edit => match (self.old_string, self.new_string) {
+                (Some(old_string), Some(new_string)) => vec![Replacement {
+                    old_string,
+                    new_string,
+                    expected_replacements: self.expected_replacements,
+                }],
+                _ => return Err("`old_string` and `new_string` are required".to_string()),
+            },
+            EditAction::MultiEdit if self.edits.is_empty() => {
+                return Err("`edits` must list at least one replacement".to_string());
+            }
+            EditAction::MultiEdit => self.edits,
+        };
+        Ok((self.file_path, self.action, edits))
+    }
+}
+
+/// One replacement of a `multi_edit`
+#[derive(Debug, Serialize, Deserialize)]
+struct Replacement {
     old_string: String,
     new_string: String,
     #[serde(default = "default_expected_replacements")]
@@ -53,7 +110,13 @@ impl Actor for EditActor {
                 tracing::info!("Executing edit tool with params: {:?}", params);
                 
                 // Parse parameters
-                let edit_params: EditParams = match serde_json::from_value(params) {
+                let edit_params = serde_json::from_value::<EditParams>(params)
+                    .map_err(|e| e.to_string())
+                    .and_then(|params| {
+                        let session_id = params.session_id.clone();
+                        params.into_replacements().map(|replacements| (session_id, replacements))
+                    });
+                let (session_id, (file_path, action, edits)) = match edit_params {
                     Ok(p) => p,
                     Err(e) => {
                         chat_ref.send_message(ChatMessage::ToolResult {
@@ -65,7 +128,7 @@ impl Actor for EditActor {
                 };
                 
                 // Resolve path (handle both absolute and relative paths)
-                let canonical_path = match resolve_path(&edit_params.file_path) {
+                let canonical_path = match resolve_path(&file_path) {
                     Ok(p) => p,
                     Err(e) => {
                         chat_ref.send_message(ChatMessage::ToolResult {
@@ -77,7 +140,16 @@ impl Actor for EditActor {
                 };
                 
                 // Validate path access
-                if let Err(e) = validate_path_access(&canonical_path) {
+                if let Err(e) = validate_path_access(&canonical_path, &self.path_access) {
+                    chat_ref.send_message(ChatMessage::ToolResult {
+                        id,
+                        result: format!("Error: {}", e),
+                    })?;
+                    return Ok(());
+                }
+                if let Some(sandbox) = &self.sandbox
+                    && let Err(e) = sandbox.check_write(&canonical_path)
+                {
                     chat_ref.send_message(ChatMessage::ToolResult {
                         id,
                         result: format!("Error: {}", e),
@@ -85,19 +157,22 @@ impl Actor for EditActor {
                     return Ok(());
                 }
                 
-                // Update edit_params with the canonical path
-                let canonical_params = EditParams {
-                    file_path: canonical_path.to_string_lossy().to_string(),
-                    old_string: edit_params.old_string,
-                    new_string: edit_params.new_string,
-                    expected_replacements: edit_params.expected_replacements,
-                };
+                if let Err(stale) = FileTracker::global().check(&session_id, &canonical_path) {
+                    chat_ref.send_message(ChatMessage::ToolResult {
+                        id,
+                        result: format!("Error: {}", stale.describe("edited")),
+                    })?;
+                    return Ok(());
+                }
+                
+                let file_path = canonical_path.to_string_lossy().to_string();
                 
                 // Execute edit operation
-                let result = match self.edit_file(&canonical_params) {
-                    Ok(info) => info,
-                    Err(e) => format!("Error: {}", e),
+                let result = match action {
+                    EditAction::Edit => self.edit_file(&session_id, &file_path, &edits[0]),
+                    EditAction::MultiEdit => self.multi_edit_file(&session_id, &file_path, &edits),
                 };
+                let result = result.unwrap_or_else(|e| format!("Error: {}", e));
                 
                 // Send result back to chat
                 chat_ref.send_message(ChatMessage::ToolResult {
@@ -114,99 +189,184 @@ impl Actor for EditActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Edit doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // The delegator forgets the files the session saw
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for EditActor {
+    const NAME: &'static str = "edit";
+    const DESCRIPTION: &'static str = "Edit a file by replacing content. Use multi_edit to make several replacements in one file at once; if any of them fails, the file is left unchanged";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["edit", "multi_edit"],
+                    "description": "edit makes one replacement (default); multi_edit applies `edits` in order, all or none"
+                },
+                "file_path": {
+                    "type": "string",
+                    "description": "The path to the file to edit"
+                },
+                "old_string": {
+                    "type": "string",
+                    "description": "The exact string to replace; empty to create a new file (for edit)"
+                },
+                "new_string": {
+                    "type": "string",
+                    "description": "The string to replace it with (for edit)"
+                },
+                "expected_replacements": {
+                    "type": "integer",
+                    "description": "Number of occurrences expected to be replaced (default: 1)"
+                },
+                "edits": {
+                    "type": "array",
+                    "description": "Replacements to make, each applied to the result of the previous one (for multi_edit)",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "old_string": { "type": "string" },
+                            "new_string": { "type": "string" },
+                            "expected_replacements": { "type": "integer" }
+                        },
+                        "required": ["old_string", "new_string"]
+                    }
+                }
+            },
+            "required": ["file_path"]
+        })
+    }
+}
+
 impl EditActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let sandbox = Sandbox::from_config(&config);
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access, sandbox }
     }
     
-    fn edit_file(&self, params: &EditParams) -> Result<String, String> {
-        let path = Path::new(&params.file_path);
+    fn edit_file(&self, session_id: &str, file_path: &str, replacement: &Replacement) -> Result<String, String> {
+        let path = Path::new(file_path);
         
         // Check if it's a new file creation (empty old_string and file doesn't exist)
         let file_exists = path.exists();
-        let is_new_file = params.old_string.is_empty() && !file_exists;
+        let is_new_file = replacement.old_string.is_empty() && !file_exists;
         
         if is_new_file {
             // Create new file
             if let Some(parent) = path.parent() {
                 if !parent.exists() {
                     fs::create_dir_all(parent)
-                        .map_err(|e| format!("Cannot create parent directories for '{}': {}", params.file_path, e))?;
+                        .map_err(|e| format!("Cannot create parent directories for '{}': {}", file_path, e))?;
                 }
             }
             
-            fs::write(&path, &params.new_string)
-                .map_err(|e| format!("Cannot create file '{}': {}", params.file_path, e))?;
+            fs::write(&path, &replacement.new_string)
+                .map_err(|e| format!("Cannot create file '{}': {}", file_path, e))?;
+            FileTracker::global().record(session_id, path, replacement.new_string.as_bytes());
             
-            let lines = params.new_string.lines().count();
+            let lines = replacement.new_string.lines().count();
             return Ok(format!(
                 "Successfully created new file: {}\n\nFile details:\n- Size: {} bytes\n- Lines: {}\n- Path: {}",
-                params.file_path,
-                params.new_string.len(),
+                file_path,
+                replacement.new_string.len(),
                 lines,
-                params.file_path
+                file_path
             ));
         }
         
-        // Read existing file
-        let content = match fs::read_to_string(&path) {
-            Ok(content) => content,
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    return Err(format!("File not found: {}", params.file_path));
-                } else {
-                    return Err(format!("Cannot read file '{}': {}", params.file_path, e));
-                }
+        let content = Self::read_file(file_path)?;
+        let new_content = Self::replace(&content, replacement, file_path)?;
+        
+        // Write the file
+        fs::write(path, &new_content)
+            .map_err(|e| format!("Cannot write to file '{}': {}", file_path, e))?;
+        FileTracker::global().record(session_id, path, new_content.as_bytes());
+        
+        Ok(Self::describe_changes(file_path, &content, &new_content, replacement.expected_replacements))
+    }
+    
+    /// Apply every replacement to the file's content before writing anything, so
+    /// the file either gets all of them or is left as it was
+    fn multi_edit_file(&self, session_id: &str, file_path: &str, edits: &[Replacement]) -> Result<String, String> {
+        let content = Self::read_file(file_path)?;
+        let mut new_content = content.clone();
+        for (index, replacement) in edits.iter().enumerate() {
+            if replacement.old_string.is_empty() {
+                return Err(format!("Edit {} of {}: old_string must not be empty; the file was not changed", index + 1, edits.len()));
             }
-        };
+            new_content = Self::replace(&new_content, replacement, file_path)
+                .map_err(|e| format!("Edit {} of {}: {}; the file was not changed", index + 1, edits.len(), e))?;
+        }
         
+        fs::write(file_path, &new_content)
+            .map_err(|e| format!("Cannot write to file '{}': {}", file_path, e))?;
+        FileTracker::global().record(session_id, Path::new(file_path), new_content.as_bytes());
+        
+        let replacements = edits.iter().map(|replacement| replacement.expected_replacements).sum();
+        Ok(Self::describe_changes(file_path, &content, &new_content, replacements))
+    }
+    
+    fn read_file(file_path: &str) -> Result<String, String> {
+        fs::read_to_string(file_path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                format!("File not found: {}", file_path)
+            } else {
+                format!("Cannot read file '{}': {}", file_path, e)
+            }
+        })
+    }
+    
+    /// Replace `old_string` in `content`, checking it occurs as often as expected
+    fn replace(content: &str, replacement: &Replacement, file_path: &str) -> Result<String, String> {
         // Count occurrences
-        let occurrences = content.matches(&params.old_string).count();
+        let occurrences = content.matches(&replacement.old_string).count();
         
         if occurrences == 0 {
             return Err(format!(
                 "No matches found for the specified old_string in file: {}",
-                params.file_path
+                file_path
             ));
         }
         
-        if occurrences != params.expected_replacements {
+        if occurrences != replacement.expected_replacements {
             return Err(format!(
                 "Expected {} replacements but found {} occurrences of old_string in file: {}",
-                params.expected_replacements, occurrences, params.file_path
+                replacement.expected_replacements, occurrences, file_path
             ));
         }
         
-        // Perform replacement
-        let new_content = content.replace(&params.old_string, &params.new_string);
-        
-        // Write the file
-        fs::write(&path, &new_content)
-            .map_err(|e| format!("Cannot write to file '{}': {}", params.file_path, e))?;
-        
+        Ok(content.replace(&replacement.old_string, &replacement.new_string))
+    }
+    
+    fn describe_changes(file_path: &str, content: &str, new_content: &str, replacements: usize) -> String {
         // Calculate changes
         let old_lines = content.lines().count();
         let new_lines = new_content.lines().count();
         let line_diff = new_lines as i32 - old_lines as i32;
         let size_diff = new_content.len() as i64 - content.len() as i64;
         
-        Ok(format!(
+        format!(
             "Successfully edited file: {}\n\nChanges:\n- Replacements made: {}\n- Lines: {} → {} ({:+})\n- Size: {} → {} bytes ({:+} bytes)\n- Path: {}",
-            params.file_path,
-            occurrences,
+            file_path,
+            replacements,
             old_lines,
             new_lines,
             line_diff,
             content.len(),
             new_content.len(),
             size_diff,
-            params.file_path
-        ))
+            file_path
+        )
     }
 }
\ No newline at end of file
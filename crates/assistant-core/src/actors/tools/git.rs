@@ -0,0 +1,715 @@
+use ractor::{Actor, ActorRef, ActorProcessingErr};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, FixedOffset};
+use git2::{
+    BlameOptions, BranchType, Commit, Delta, Diff, DiffFindOptions, DiffOptions, IndexAddOption, Oid,
+    Patch, Repository, Sort, Status, StatusOptions, Time,
+};
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::messages::{ToolMessage, ChatMessage};
+use crate::sandbox::Sandbox;
+use crate::utils::path::{PathAccess, normalize_path, validate_path_access};
+use super::base::ToolDescription;
+
+/// Characters of patch text returned by one diff or show before the rest is left out
+const MAX_PATCH_CHARS: usize = 40_000;
+
+/// Actor for inspecting and committing to git repositories
+pub struct GitActor {
+    config: Config,
+    /// Where this tool may work
+    path_access: PathAccess,
+    /// Limits where commits and branches may be written when the sandbox is enabled
+    sandbox: Option<Sandbox>,
+}
+
+/// Git actor state
+pub struct GitState;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitAction {
+    /// Branch, upstream and changed files
+    #[default]
+    Status,
+    /// Changes in the working tree, the index or between refs
+    Diff,
+    /// Commit history
+    Log,
+    /// Who last changed each line of a file
+    Blame,
+    /// One commit with its changes
+    Show,
+    /// List branches, or create one
+    Branch,
+    /// Commit the index
+    Commit,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitParams {
+    #[serde(default)]
+    action: GitAction,
+    /// A directory in the repository; the workspace by default
+    #[serde(default)]
+    repository: Option<String>,
+    /// Limit status, diff and log to these paths
+    #[serde(default)]
+    paths: Vec<String>,
+    /// Diff the index against HEAD instead of the working tree against the index
+    #[serde(default)]
+    staged: bool,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    /// Leave patches out of diffs, keeping the per-file counts
+    #[serde(default)]
+    stat_only: bool,
+    /// Commit for log to start from or show to show
+    #[serde(default)]
+    rev: Option<String>,
+    #[serde(default = "default_max_count")]
+    max_count: usize,
+    /// File to blame
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    start_line: Option<usize>,
+    #[serde(default)]
+    end_line: Option<usize>,
+    /// Branch to create
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    start_point: Option<String>,
+    /// Switch to the created branch
+    #[serde(default)]
+    checkout: bool,
+    #[serde(default)]
+    message: Option<String>,
+    /// Stage every change to tracked files before committing, like `git commit -a`
+    #[serde(default)]
+    all: bool,
+}
+
+fn default_max_count() -> usize {
+    20
+}
+
+impl Actor for GitActor {
+    type Msg = ToolMessage;
+    type State = GitState;
+    type Arguments = Config;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        _config: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        tracing::debug!("Git actor starting");
+        Ok(GitState)
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match msg {
+            ToolMessage::Execute { id, params, chat_ref } => {
+                tracing::info!("Executing git tool with params: {:?}", params);
+
+                let result = match serde_json::from_value::<GitParams>(params) {
+                    Ok(params) => match self.execute(&params) {
+                        Ok(result) => result.to_string(),
+                        // git2's own messages, without its class and code
+                        Err(e) => match e.downcast_ref::<git2::Error>() {
+                            Some(e) => format!("Error: {}", e.message()),
+                            None => format!("Error: {}", e),
+                        },
+                    },
+                    Err(e) => format!("Error: Invalid parameters - {}", e),
+                };
+
+                chat_ref.send_message(ChatMessage::ToolResult {
+                    id,
+                    result,
+                })?;
+            }
+
+            ToolMessage::Cancel { id } => {
+                tracing::debug!("Cancelling git operation {}", id);
+                // Git operations run synchronously, nothing to cancel
+            }
+
+            ToolMessage::StreamUpdate { .. } => {
+                // Git doesn't stream updates
+            }
+            
+            ToolMessage::EndSession { .. } => {
+                // Git keeps nothing per session
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ToolDescription for GitActor {
+    const NAME: &'static str = "git";
+    const DESCRIPTION: &'static str = "Work with the git repository without the shell: status, diff (unstaged, staged or between refs), log (optionally for some paths), blame for a line range, show a commit, list or create branches, and commit. Results are JSON";
+    const DESTRUCTIVE: bool = true;
+
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["status", "diff", "log", "blame", "show", "branch", "commit"],
+                    "description": "What to do (default: status)"
+                },
+                "repository": {
+                    "type": "string",
+                    "description": "A directory inside the repository (default: the workspace)"
+                },
+                "paths": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Limit status, diff and log to these files or directories; for commit, stage them first"
+                },
+                "staged": {
+                    "type": "boolean",
+                    "description": "diff: compare the index with HEAD instead of the working tree with the index"
+                },
+                "from": {
+                    "type": "string",
+                    "description": "diff: ref to compare from; with no `to`, it is compared with the working tree"
+                },
+                "to": {
+                    "type": "string",
+                    "description": "diff: ref to compare to"
+                },
+                "stat_only": {
+                    "type": "boolean",
+                    "description": "diff, show: list changed files and line counts without patches"
+                },
+                "rev": {
+                    "type": "string",
+                    "description": "log: where to start (default HEAD); show: the commit to show (default HEAD)"
+                },
+                "max_count": {
+                    "type": "integer",
+                    "description": "log: most commits to list (default 20)"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "blame: the file to blame"
+                },
+                "start_line": {
+                    "type": "integer",
+                    "description": "blame: first line (default 1)"
+                },
+                "end_line": {
+                    "type": "integer",
+                    "description": "blame: last line (default: the end of the file)"
+                },
+                "name": {
+                    "type": "string",
+                    "description": "branch: name of a branch to create; without it, branches are listed"
+                },
+                "start_point": {
+                    "type": "string",
+                    "description": "branch: where the new branch starts (default HEAD)"
+                },
+                "checkout": {
+                    "type": "boolean",
+                    "description": "branch: switch to the new branch"
+                },
+                "message": {
+                    "type": "string",
+                    "description": "commit: the commit message"
+                },
+                "all": {
+                    "type": "boolean",
+                    "description": "commit: stage all changes to tracked files first, like `git commit -a`"
+                }
+            },
+            "required": []
+        })
+    }
+}
+
+impl GitActor {
+    pub fn new(config: Config) -> Self {
+        let sandbox = Sandbox::from_config(&config);
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access, sandbox }
+    }
+
+    fn execute(&self, params: &GitParams) -> Result<Value> {
+        let repo = self.open(params.repository.as_deref())?;
+        let creates_branch = params.name.as_deref().is_some_and(|name| !name.is_empty());
+        let writes = params.action == GitAction::Commit || (params.action == GitAction::Branch && creates_branch);
+        if writes && let Some(sandbox) = &self.sandbox {
+            sandbox.check_write(repo.path())?;
+        }
+
+        match params.action {
+            GitAction::Status => status(&repo, &params.paths),
+            GitAction::Diff => diff(&repo, params),
+            GitAction::Log => log(&repo, params),
+            GitAction::Blame => blame(&repo, params),
+            GitAction::Show => show(&repo, params),
+            GitAction::Branch if creates_branch => create_branch(&repo, params.name.as_deref().unwrap_or_default(), params),
+            GitAction::Branch => branches(&repo),
+            GitAction::Commit => commit(&repo, params),
+        }
+    }
+
+    /// Find the repository containing `directory`, or the workspace
+    fn open(&self, directory: Option<&str>) -> Result<Repository> {
+        let directory = match directory {
+            Some(directory) => PathBuf::from(directory),
+            None => match &self.config.session.workspace_path {
+                Some(workspace) => workspace.clone(),
+                None => std::env::current_dir()?,
+            },
+        };
+        let directory = normalize_path(&directory)?;
+        validate_path_access(&directory, &self.path_access)?;
+
+        Repository::discover(&directory)
+            .map_err(|_| anyhow!("'{}' is not in a git repository", directory.display()))
+    }
+}
+
+/// The working tree of a repository, which paths are given relative to
+fn workdir(repo: &Repository) -> Result<&Path> {
+    repo.workdir().ok_or_else(|| anyhow!("The repository is bare; it has no working tree"))
+}
+
+/// Make a path given by the model relative to the working tree
+fn relative_path(repo: &Repository, path: &str) -> Result<String> {
+    let workdir = workdir(repo)?;
+    let candidate = Path::new(path);
+    if !candidate.is_absolute() {
+        return Ok(path.trim_start_matches("./").to_string());
+    }
+
+    let resolved = normalize_path(candidate)?;
+    let workdir = normalize_path(workdir)?;
+    resolved.strip_prefix(&workdir)
+        .map(|relative| relative.to_string_lossy().into_owned())
+        .map_err(|_| anyhow!("'{}' is outside the repository at {}", path, workdir.display()))
+}
+
+fn relative_paths(repo: &Repository, paths: &[String]) -> Result<Vec<String>> {
+    paths.iter().map(|path| relative_path(repo, path)).collect()
+}
+
+fn status(repo: &Repository, paths: &[String]) -> Result<Value> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+    for path in relative_paths(repo, paths)? {
+        options.pathspec(path);
+    }
+
+    let (mut staged, mut unstaged, mut untracked, mut conflicted) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+        let path = entry.path().unwrap_or_default().to_string();
+        if status.is_conflicted() {
+            conflicted.push(json!(path));
+            continue;
+        }
+        if status.is_wt_new() {
+            untracked.push(json!(path));
+        }
+
+        let index_change = [
+            (Status::INDEX_NEW, "added"),
+            (Status::INDEX_MODIFIED, "modified"),
+            (Status::INDEX_DELETED, "deleted"),
+            (Status::INDEX_RENAMED, "renamed"),
+            (Status::INDEX_TYPECHANGE, "typechange"),
+        ].into_iter().find(|(flag, _)| status.contains(*flag));
+        if let Some((_, change)) = index_change {
+            let old_path = entry.head_to_index().and_then(|delta| delta.old_file().path().map(Path::to_path_buf));
+            staged.push(file_change(&path, change, old_path.as_deref()));
+        }
+
+        let workdir_change = [
+            (Status::WT_MODIFIED, "modified"),
+            (Status::WT_DELETED, "deleted"),
+            (Status::WT_RENAMED, "renamed"),
+            (Status::WT_TYPECHANGE, "typechange"),
+        ].into_iter().find(|(flag, _)| status.contains(*flag));
+        if let Some((_, change)) = workdir_change {
+            let old_path = entry.index_to_workdir().and_then(|delta| delta.old_file().path().map(Path::to_path_buf));
+            unstaged.push(file_change(&path, change, old_path.as_deref()));
+        }
+    }
+
+    let mut result = json!({
+        "branch": current_branch(repo),
+        "head": repo.head().ok().and_then(|head| head.target()).map(|oid| short_id(repo, oid)),
+        "staged": staged,
+        "unstaged": unstaged,
+        "untracked": untracked,
+    });
+    if !conflicted.is_empty() {
+        result["conflicted"] = json!(conflicted);
+    }
+    if let Some(upstream) = upstream(repo) {
+        result["upstream"] = upstream;
+    }
+    Ok(result)
+}
+
+fn file_change(path: &str, change: &str, old_path: Option<&Path>) -> Value {
+    match old_path.map(|old| old.to_string_lossy()) {
+        Some(old_path) if change == "renamed" && old_path != path => json!({ "path": path, "status": change, "old_path": old_path }),
+        _ => json!({ "path": path, "status": change }),
+    }
+}
+
+/// The checked-out branch, or None with a detached or unborn HEAD
+fn current_branch(repo: &Repository) -> Option<String> {
+    match repo.head() {
+        Ok(head) if head.is_branch() => head.shorthand().map(str::to_string),
+        Ok(_) => None,
+        // A new repository's branch has no commits yet
+        Err(_) => repo.find_reference("HEAD").ok()
+            .and_then(|head| head.symbolic_target().map(|target| target.trim_start_matches("refs/heads/").to_string())),
+    }
+}
+
+/// The current branch's upstream and how far the two have diverged
+fn upstream(repo: &Repository) -> Option<Value> {
+    let branch = repo.find_branch(&current_branch(repo)?, BranchType::Local).ok()?;
+    let upstream = branch.upstream().ok()?;
+    let (local, remote) = (branch.get().target()?, upstream.get().target()?);
+    let (ahead, behind) = repo.graph_ahead_behind(local, remote).ok()?;
+    Some(json!({ "name": upstream.name().ok().flatten(), "ahead": ahead, "behind": behind }))
+}
+
+fn diff(repo: &Repository, params: &GitParams) -> Result<Value> {
+    let mut options = DiffOptions::new();
+    for path in relative_paths(repo, &params.paths)? {
+        options.pathspec(path);
+    }
+    let tree = |rev: &str| -> Result<git2::Tree<'_>> {
+        repo.revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|_| anyhow!("'{}' does not name a commit or tree", rev))
+    };
+
+    let mut diff = match (params.from.as_deref(), params.to.as_deref()) {
+        (Some(from), Some(to)) => repo.diff_tree_to_tree(Some(&tree(from)?), Some(&tree(to)?), Some(&mut options))?,
+        (Some(from), None) => repo.diff_tree_to_workdir_with_index(Some(&tree(from)?), Some(&mut options))?,
+        (None, Some(_)) => bail!("`to` needs a `from`"),
+        (None, None) if params.staged => {
+            // Before the first commit everything in the index is new
+            let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+            repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))?
+        }
+        (None, None) => repo.diff_index_to_workdir(None, Some(&mut options))?,
+    };
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    describe_diff(&diff, params.stat_only)
+}
+
+/// Files a diff changes with their line counts and, within the budget, patches
+fn describe_diff(diff: &Diff, stat_only: bool) -> Result<Value> {
+    let mut files = Vec::new();
+    let (mut additions, mut deletions) = (0, 0);
+    let mut budget = MAX_PATCH_CHARS;
+    let mut omitted = 0;
+
+    for index in 0..diff.deltas().len() {
+        let Some(mut patch) = Patch::from_diff(diff, index)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let path = delta.new_file().path().or_else(|| delta.old_file().path())
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let status = match delta.status() {
+            Delta::Added | Delta::Untracked => "added",
+            Delta::Deleted => "deleted",
+            Delta::Renamed => "renamed",
+            Delta::Copied => "copied",
+            Delta::Typechange => "typechange",
+            _ => "modified",
+        };
+        let binary = delta.flags().is_binary();
+        let old_path = delta.old_file().path().map(Path::to_path_buf);
+
+        let mut file = file_change(&path, status, old_path.as_deref());
+        let (_, added, deleted) = patch.line_stats()?;
+        additions += added;
+        deletions += deleted;
+        file["additions"] = json!(added);
+        file["deletions"] = json!(deleted);
+        if binary {
+            file["binary"] = json!(true);
+        } else if !stat_only {
+            let text = patch.to_buf()?.as_str().unwrap_or_default().to_string();
+            if text.len() <= budget {
+                budget -= text.len();
+                file["patch"] = json!(text);
+            } else {
+                omitted += 1;
+            }
+        }
+        files.push(file);
+    }
+
+    let mut result = json!({
+        "files": files,
+        "total": { "files": files.len(), "additions": additions, "deletions": deletions },
+    });
+    if omitted > 0 {
+        result["note"] = json!(format!(
+            "Patches of {} files were left out to keep the result short; diff them on their own with `paths`",
+            omitted
+        ));
+    }
+    Ok(result)
+}
+
+fn log(repo: &Repository, params: &GitParams) -> Result<Value> {
+    let paths = relative_paths(repo, &params.paths)?;
+    let start = repo.revparse_single(params.rev.as_deref().unwrap_or("HEAD"))
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| match &params.rev {
+            Some(rev) => anyhow!("'{}' does not name a commit", rev),
+            None => anyhow!("The repository has no commits yet"),
+        })?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(start.id())?;
+
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        if commits.len() >= params.max_count {
+            break;
+        }
+        let commit = repo.find_commit(oid?)?;
+        if !paths.is_empty() && !touches(repo, &commit, &paths)? {
+            continue;
+        }
+        commits.push(commit_summary(repo, &commit));
+    }
+
+    Ok(json!({ "commits": commits }))
+}
+
+/// Whether a commit changed any of `paths` relative to its first parent
+fn touches(repo: &Repository, commit: &Commit, paths: &[String]) -> Result<bool> {
+    let mut options = DiffOptions::new();
+    for path in paths {
+        options.pathspec(path);
+    }
+    let parent = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+    let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), Some(&mut options))?;
+    Ok(diff.deltas().len() > 0)
+}
+
+fn commit_summary(repo: &Repository, commit: &Commit) -> Value {
+    let author = commit.author();
+    json!({
+        "id": short_id(repo, commit.id()),
+        "author": author.name().unwrap_or_default(),
+        "date": format_time(commit.time()),
+        "summary": commit.summary().unwrap_or_default(),
+    })
+}
+
+fn blame(repo: &Repository, params: &GitParams) -> Result<Value> {
+    let path = params.path.as_deref().ok_or_else(|| anyhow!("Invalid parameters - blame needs a `path`"))?;
+    let path = relative_path(repo, path)?;
+    let content = std::fs::read_to_string(workdir(repo)?.join(&path))
+        .map_err(|e| anyhow!("Cannot read '{}': {}", path, e))?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    let start = params.start_line.unwrap_or(1).max(1);
+    let end = params.end_line.unwrap_or(lines.len()).min(lines.len());
+    if start > end {
+        bail!("Lines {} to {} are outside the file, which has {} lines", start, end, lines.len());
+    }
+
+    // Blame the working tree's contents, so line numbers match the file on disk
+    let mut options = BlameOptions::new();
+    options.min_line(start).max_line(end);
+    let committed = repo.blame_file(Path::new(&path), Some(&mut options))?;
+    let blame = committed.blame_buffer(content.as_bytes())?;
+
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let first = hunk.final_start_line().max(start);
+        let last = (hunk.final_start_line() + hunk.lines_in_hunk() - 1).min(end);
+        if first > last {
+            continue;
+        }
+
+        let text: Vec<&str> = lines[first - 1..last].to_vec();
+        let mut entry = if hunk.final_commit_id().is_zero() {
+            json!({ "commit": null, "note": "not committed yet" })
+        } else {
+            let signature = hunk.final_signature();
+            let summary = repo.find_commit(hunk.final_commit_id()).ok()
+                .and_then(|commit| commit.summary().map(str::to_string));
+            json!({
+                "commit": short_id(repo, hunk.final_commit_id()),
+                "author": signature.name().unwrap_or_default(),
+                "date": format_time(signature.when()),
+                "summary": summary,
+            })
+        };
+        entry["lines"] = json!([first, last]);
+        entry["text"] = json!(text);
+        hunks.push(entry);
+    }
+
+    Ok(json!({ "path": path, "hunks": hunks }))
+}
+
+fn show(repo: &Repository, params: &GitParams) -> Result<Value> {
+    let rev = params.rev.as_deref().unwrap_or("HEAD");
+    let commit = repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| anyhow!("'{}' does not name a commit", rev))?;
+
+    let parent = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+    let mut diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let author = commit.author();
+    let mut result = json!({
+        "id": commit.id().to_string(),
+        "author": format!("{} <{}>", author.name().unwrap_or_default(), author.email().unwrap_or_default()),
+        "date": format_time(commit.time()),
+        "parents": commit.parent_ids().map(|oid| short_id(repo, oid)).collect::<Vec<_>>(),
+        "message": commit.message().unwrap_or_default().trim_end(),
+    });
+    let changes = describe_diff(&diff, params.stat_only)?;
+    for key in ["files", "total", "note"] {
+        if let Some(value) = changes.get(key) {
+            result[key] = value.clone();
+        }
+    }
+    Ok(result)
+}
+
+fn branches(repo: &Repository) -> Result<Value> {
+    let mut list = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let mut entry = json!({
+            "name": branch.name()?.unwrap_or_default(),
+            "head": branch.get().target().map(|oid| short_id(repo, oid)),
+        });
+        if branch.is_head() {
+            entry["current"] = json!(true);
+        }
+        if let Ok(upstream) = branch.upstream() {
+            entry["upstream"] = json!(upstream.name()?.unwrap_or_default());
+        }
+        list.push(entry);
+    }
+
+    Ok(json!({ "current": current_branch(repo), "branches": list }))
+}
+
+fn create_branch(repo: &Repository, name: &str, params: &GitParams) -> Result<Value> {
+    let start_point = params.start_point.as_deref().unwrap_or("HEAD");
+    let target = repo.revparse_single(start_point)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| anyhow!("'{}' does not name a commit", start_point))?;
+    let branch = repo.branch(name, &target, false)?;
+
+    if params.checkout {
+        // A safe checkout refuses to overwrite local changes
+        repo.checkout_tree(target.as_object(), Some(git2::build::CheckoutBuilder::new().safe()))?;
+        let reference = branch.get().name().ok_or_else(|| anyhow!("The branch name is not valid UTF-8"))?;
+        repo.set_head(reference)?;
+    }
+
+    Ok(json!({
+        "created": name,
+        "at": short_id(repo, target.id()),
+        "checked_out": params.checkout,
+    }))
+}
+
+fn commit(repo: &Repository, params: &GitParams) -> Result<Value> {
+    let message = params.message.as_deref()
+        .filter(|message| !message.trim().is_empty())
+        .ok_or_else(|| anyhow!("Invalid parameters - commit needs a `message`"))?;
+
+    let mut index = repo.index()?;
+    if params.all {
+        index.update_all(["*"], None)?;
+    }
+    let paths = relative_paths(repo, &params.paths)?;
+    if !paths.is_empty() {
+        // Stages new and modified files, then deletions
+        index.add_all(paths.iter(), IndexAddOption::DEFAULT, None)?;
+        index.update_all(paths.iter(), None)?;
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    if parent.as_ref().is_some_and(|parent| parent.tree_id() == tree.id()) {
+        bail!("Nothing to commit; stage changes with `paths` or `all` first");
+    }
+    let signature = repo.signature()
+        .map_err(|_| anyhow!("Git has no author to commit as; set user.name and user.email in the git config"))?;
+
+    let parents: Vec<&Commit> = parent.iter().collect();
+    let oid = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)?;
+
+    let mut options = DiffOptions::new();
+    let changed = repo.diff_tree_to_tree(
+        parent.as_ref().map(|parent| parent.tree()).transpose()?.as_ref(),
+        Some(&tree),
+        Some(&mut options),
+    )?;
+    let stats = changed.stats()?;
+    Ok(json!({
+        "committed": short_id(repo, oid),
+        "branch": current_branch(repo),
+        "summary": message.lines().next().unwrap_or_default(),
+        "files_changed": stats.files_changed(),
+        "additions": stats.insertions(),
+        "deletions": stats.deletions(),
+    }))
+}
+
+/// The shortest unambiguous form of an object id
+fn short_id(repo: &Repository, oid: Oid) -> String {
+    repo.find_object(oid, None).ok()
+        .and_then(|object| object.short_id().ok())
+        .and_then(|id| id.as_str().map(str::to_string))
+        .unwrap_or_else(|| oid.to_string()[..7].to_string())
+}
+
+fn format_time(time: Time) -> String {
+    let offset = FixedOffset::east_opt(time.offset_minutes() * 60).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    DateTime::from_timestamp(time.seconds(), 0)
+        .map(|date| date.with_timezone(&offset).format("%Y-%m-%d %H:%M:%S %z").to_string())
+        .unwrap_or_default()
+}
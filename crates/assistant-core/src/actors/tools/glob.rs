@@ -4,12 +4,15 @@ use glob::glob_with;
 use glob::MatchOptions;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
-use crate::utils::path::{resolve_path, validate_path_access};
+use crate::utils::path::{PathAccess, resolve_path, validate_path_access, validate_pattern_access};
+use super::base::ToolDescription;
 
 /// Actor for finding files using glob patterns
 pub struct GlobActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
 }
 
 /// Glob actor state
@@ -87,15 +90,51 @@ impl Actor for GlobActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Glob doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Glob keeps nothing per session
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for GlobActor {
+    const NAME: &'static str = "glob";
+    const DESCRIPTION: &'static str = "Search for files matching a pattern";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "The glob pattern to match files"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "The base directory to search in"
+                },
+                "case_sensitive": {
+                    "type": "boolean",
+                    "description": "Match the pattern case-sensitively (default: false)"
+                },
+                "respect_git_ignore": {
+                    "type": "boolean",
+                    "description": "Skip files ignored by .gitignore (default: true)"
+                }
+            },
+            "required": ["pattern"]
+        })
+    }
+}
+
 impl GlobActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access }
     }
     
     fn find_files(&self, params: &GlobParams) -> Result<String, String> {
@@ -109,7 +148,7 @@ impl GlobActor {
                 };
                 
                 // Validate path access
-                if let Err(e) = validate_path_access(&resolved) {
+                if let Err(e) = validate_path_access(&resolved, &self.path_access) {
                     return Err(format!("{}", e));
                 }
                 
@@ -121,14 +160,15 @@ impl GlobActor {
             }
             None => {
                 // Use current working directory
-                std::env::current_dir()
-                    .map_err(|e| format!("Cannot get current directory: {}", e))?
-                    .to_string_lossy()
-                    .to_string()
+                let cwd = std::env::current_dir()
+                    .map_err(|e| format!("Cannot get current directory: {}", e))?;
+                validate_path_access(&cwd, &self.path_access).map_err(|e| format!("{}", e))?;
+                cwd.to_string_lossy().to_string()
             }
         };
         
         // Build the full pattern
+        validate_pattern_access(&params.pattern, &self.path_access).map_err(|e| format!("{}", e))?;
         let full_pattern = if params.pattern.starts_with('/') {
             // Absolute pattern
             params.pattern.clone()
@@ -152,8 +192,9 @@ impl GlobActor {
         for entry in glob_result {
             match entry {
                 Ok(path) => {
-                    // Filter out directories unless explicitly included in pattern
-                    if path.is_file() {
+                    // Filter out directories unless explicitly included in pattern, and
+                    // files a symlink leads to outside the allowed locations
+                    if path.is_file() && validate_path_access(&path, &self.path_access).is_ok() {
                         matches.push(path);
                     }
                 }
@@ -5,12 +5,15 @@ use grep::regex::RegexMatcher;
 use grep::searcher::{Searcher, SearcherBuilder, Sink, SinkMatch};
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
-use crate::utils::path::{resolve_path, validate_path_access};
+use crate::utils::path::{PathAccess, resolve_path, validate_path_access, validate_pattern_access};
+use super::base::ToolDescription;
 
 /// Actor for searching file contents using regular expressions
 pub struct GrepActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
 }
 
 /// Grep actor state
@@ -112,6 +115,10 @@ impl Actor for GrepActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Grep doesn't stream updates currently
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Grep keeps nothing per session
+            }
         }
         
         Ok(())
@@ -170,9 +177,74 @@ impl Sink for ResultCollector {
     }
 }
 
+impl ToolDescription for GrepActor {
+    const NAME: &'static str = "grep";
+    const DESCRIPTION: &'static str = "Search file contents using regex";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "The regex pattern to search for"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "The path to search in"
+                },
+                "glob": {
+                    "type": "string",
+                    "description": "Only search files matching this glob"
+                },
+                "type": {
+                    "type": "string",
+                    "description": "Only search files of this type (e.g. rust, py)"
+                },
+                "output_mode": {
+                    "type": "string",
+                    "enum": ["content", "files_with_matches", "count"],
+                    "description": "What to return (default: files_with_matches)"
+                },
+                "-i": {
+                    "type": "boolean",
+                    "description": "Case insensitive search"
+                },
+                "-n": {
+                    "type": "boolean",
+                    "description": "Show line numbers (content mode)"
+                },
+                "-A": {
+                    "type": "integer",
+                    "description": "Lines of context after each match (content mode)"
+                },
+                "-B": {
+                    "type": "integer",
+                    "description": "Lines of context before each match (content mode)"
+                },
+                "-C": {
+                    "type": "integer",
+                    "description": "Lines of context around each match (content mode)"
+                },
+                "multiline": {
+                    "type": "boolean",
+                    "description": "Let patterns span lines"
+                },
+                "head_limit": {
+                    "type": "integer",
+                    "description": "Return at most this many results"
+                }
+            },
+            "required": ["pattern"]
+        })
+    }
+}
+
 impl GrepActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access }
     }
     
     async fn search_files(&self, params: &GrepParams) -> Result<String, String> {
@@ -186,7 +258,7 @@ impl GrepActor {
                 };
                 
                 // Validate path access
-                if let Err(e) = validate_path_access(&resolved) {
+                if let Err(e) = validate_path_access(&resolved, &self.path_access) {
                     return Err(format!("{}", e));
                 }
                 
@@ -194,10 +266,10 @@ impl GrepActor {
             }
             None => {
                 // Use current working directory
-                std::env::current_dir()
-                    .map_err(|e| format!("Cannot get current directory: {}", e))?
-                    .to_string_lossy()
-                    .to_string()
+                let cwd = std::env::current_dir()
+                    .map_err(|e| format!("Cannot get current directory: {}", e))?;
+                validate_path_access(&cwd, &self.path_access).map_err(|e| format!("{}", e))?;
+                cwd.to_string_lossy().to_string()
             }
         };
         
@@ -240,6 +312,7 @@ impl GrepActor {
         
         if let Some(glob_pattern) = &params.glob {
             // Use glob pattern
+            validate_pattern_access(glob_pattern, &self.path_access).map_err(|e| format!("{}", e))?;
             let pattern = if glob_pattern.starts_with('/') {
                 glob_pattern.clone()
             } else {
@@ -264,6 +337,8 @@ impl GrepActor {
             files = self.find_all_files(base_path)?;
         }
         
+        // Symlinks inside the directory may lead outside the allowed locations
+        files.retain(|file| validate_path_access(Path::new(file), &self.path_access).is_ok());
         Ok(files)
     }
     
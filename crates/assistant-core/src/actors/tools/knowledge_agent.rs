@@ -17,8 +17,10 @@ use sqlx::Row;
 use std::sync::Arc;
 use std::collections::HashMap;
 use uuid::Uuid;
+use super::base::{RunningExecutions, ToolDescription};
 
 /// Actor for knowledge synthesis and intelligent information retrieval
+#[derive(Clone)]
 pub struct KnowledgeAgentActor {
     #[allow(dead_code)]
     config: Config,
@@ -30,6 +32,8 @@ pub struct KnowledgeAgentActor {
 pub struct KnowledgeAgentState {
     embedding_client: Option<Arc<dyn EmbeddingClient + Send + Sync>>,
     db: Arc<Database>,
+    /// Actions still running, by execution id
+    running: RunningExecutions,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -112,6 +116,75 @@ pub enum AnalysisDepth {
 fn default_limit() -> usize { 20 }
 fn default_analysis_depth() -> AnalysisDepth { AnalysisDepth::Standard }
 
+impl ToolDescription for KnowledgeAgentActor {
+    const NAME: &'static str = "knowledge_agent";
+    const DESCRIPTION: &'static str = "Search and synthesize knowledge from memories, chat history, todos, and sessions. This tool uses a dedicated sub-agent that can search across all stored information and provide comprehensive analysis";
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["search", "store", "get_details", "analyze", "synthesize"],
+                    "description": "The action to perform"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Search query (for search action)"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Content to store (for store action)"
+                },
+                "key": {
+                    "type": "string",
+                    "description": "Optional key for the stored memory (for store action)"
+                },
+                "metadata": {
+                    "type": "object",
+                    "description": "Optional metadata to attach to the memory (for store action)"
+                },
+                "topic": {
+                    "type": "string",
+                    "description": "Topic to analyze or synthesize (for analyze/synthesize actions)"
+                },
+                "source": {
+                    "type": "string",
+                    "enum": ["memory", "chat_history", "todo", "session", "all"],
+                    "description": "Knowledge source (for get_details action)"
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Item ID (for get_details action)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum results to return (for search, default: 20)"
+                },
+                "source_filter": {
+                    "type": "array",
+                    "items": {
+                        "type": "string",
+                        "enum": ["memory", "chat_history", "todo", "session"]
+                    },
+                    "description": "Filter by specific sources (for search)"
+                },
+                "depth": {
+                    "type": "string",
+                    "enum": ["quick", "standard", "deep"],
+                    "description": "Analysis depth (for analyze action, default: standard)"
+                },
+                "include_examples": {
+                    "type": "boolean",
+                    "description": "Include examples in synthesis (for synthesize action)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl KnowledgeAgentActor {
     pub async fn new(config: Config) -> Result<Self> {
         let db_path = config.session.database_path.as_ref()
@@ -630,6 +703,52 @@ impl KnowledgeAgentActor {
             },
         }
     }
+    
+    /// Carry out one action and describe its outcome for the model
+    async fn run_action(&self, action: KnowledgeAction, state: &KnowledgeAgentState) -> String {
+        match action {
+            KnowledgeAction::Search { query, limit, source_filter, time_filter } => {
+                tracing::info!("KnowledgeAgent searching for: {}", query);
+                match self.search_knowledge(&query, limit, source_filter, time_filter, state).await {
+                    Ok(results) => serde_json::to_string_pretty(&results)
+                        .unwrap_or_else(|_| "Failed to format results".to_string()),
+                    Err(e) => format!("Error searching knowledge: {}", e),
+                }
+            }
+            KnowledgeAction::GetDetails { source, id: item_id } => {
+                match self.get_details(source, &item_id, state).await {
+                    Ok(details) => serde_json::to_string_pretty(&details)
+                        .unwrap_or_else(|_| "Failed to format details".to_string()),
+                    Err(e) => format!("Error getting details: {}", e),
+                }
+            }
+            KnowledgeAction::Analyze { topic, depth: _ } => {
+                // For now, perform a comprehensive search and return results
+                match self.search_knowledge(&topic, 30, None, None, state).await {
+                    Ok(results) => {
+                        format!("Analysis of '{}': {}", topic, serde_json::to_string_pretty(&results)
+                            .unwrap_or_else(|_| "Failed to format analysis".to_string()))
+                    }
+                    Err(e) => format!("Error analyzing topic: {}", e),
+                }
+            }
+            KnowledgeAction::Synthesize { topic, include_examples: _ } => {
+                // For now, perform a search and return a summary
+                match self.search_knowledge(&topic, 20, None, None, state).await {
+                    Ok(results) => {
+                        format!("Knowledge synthesis for '{}': {}", topic, serde_json::to_string_pretty(&results)
+                            .unwrap_or_else(|_| "Failed to format synthesis".to_string()))
+                    }
+                    Err(e) => format!("Error synthesizing knowledge: {}", e),
+                }
+            }
+            KnowledgeAction::Store { content, key, metadata } => {
+                tracing::info!("KnowledgeAgent storing memory: content={}, key={:?}", content, key);
+                // Use the memory tool to store the information
+                self.store_in_memory(content, key, metadata, state).await
+            }
+        }
+    }
 }
 
 impl Actor for KnowledgeAgentActor {
@@ -645,6 +764,7 @@ impl Actor for KnowledgeAgentActor {
         Ok(KnowledgeAgentState {
             embedding_client: self.embedding_client.clone(),
             db: self.db.clone(),
+            running: RunningExecutions::default(),
         })
     }
     
@@ -669,61 +789,32 @@ impl Actor for KnowledgeAgentActor {
                 
                 tracing::info!("KnowledgeAgent processing action: {:?}", action);
                 
-                let result = match action {
-                    KnowledgeAction::Search { query, limit, source_filter, time_filter } => {
-                        tracing::info!("KnowledgeAgent searching for: {}", query);
-                        match self.search_knowledge(&query, limit, source_filter, time_filter, state).await {
-                            Ok(results) => serde_json::to_string_pretty(&results)
-                                .unwrap_or_else(|_| "Failed to format results".to_string()),
-                            Err(e) => format!("Error searching knowledge: {}", e),
-                        }
-                    }
-                    KnowledgeAction::GetDetails { source, id: item_id } => {
-                        match self.get_details(source, &item_id, state).await {
-                            Ok(details) => serde_json::to_string_pretty(&details)
-                                .unwrap_or_else(|_| "Failed to format details".to_string()),
-                            Err(e) => format!("Error getting details: {}", e),
-                        }
-                    }
-                    KnowledgeAction::Analyze { topic, depth: _ } => {
-                        // For now, perform a comprehensive search and return results
-                        match self.search_knowledge(&topic, 30, None, None, state).await {
-                            Ok(results) => {
-                                format!("Analysis of '{}': {}", topic, serde_json::to_string_pretty(&results)
-                                    .unwrap_or_else(|_| "Failed to format analysis".to_string()))
-                            }
-                            Err(e) => format!("Error analyzing topic: {}", e),
-                        }
-                    }
-                    KnowledgeAction::Synthesize { topic, include_examples: _ } => {
-                        // For now, perform a search and return a summary
-                        match self.search_knowledge(&topic, 20, None, None, state).await {
-                            Ok(results) => {
-                                format!("Knowledge synthesis for '{}': {}", topic, serde_json::to_string_pretty(&results)
-                                    .unwrap_or_else(|_| "Failed to format synthesis".to_string()))
-                            }
-                            Err(e) => format!("Error synthesizing knowledge: {}", e),
-                        }
-                    }
-                    KnowledgeAction::Store { content, key, metadata } => {
-                        tracing::info!("KnowledgeAgent storing memory: content={}, key={:?}", content, key);
-                        // Use the memory tool to store the information
-                        self.store_in_memory(content, key, metadata, state).await
-                    }
+                // Run the action in the background so it can be cancelled
+                let agent = self.clone();
+                let task_state = KnowledgeAgentState {
+                    embedding_client: state.embedding_client.clone(),
+                    db: state.db.clone(),
+                    running: RunningExecutions::default(),
                 };
-                
-                chat_ref.send_message(ChatMessage::ToolResult { id, result })?;
+                state.running.spawn(id, async move {
+                    let result = agent.run_action(action, &task_state).await;
+                    let _ = chat_ref.send_message(ChatMessage::ToolResult { id, result });
+                });
             }
-            ToolMessage::Cancel { .. } => {
-                // Knowledge agent doesn't support cancellation yet
-                tracing::debug!("Cancel request received but not implemented");
+            ToolMessage::Cancel { id } => {
+                if state.running.cancel(&id) {
+                    tracing::info!("Cancelled knowledge agent action {}", id);
+                }
             }
             ToolMessage::StreamUpdate { .. } => {
                 // Knowledge agent doesn't support streaming yet
                 tracing::debug!("Stream update received but not implemented");
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Knowledge agent keeps nothing per session
+            }
         }
         Ok(())
     }
 }
-
@@ -3,13 +3,16 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
-use crate::utils::path::{resolve_path, validate_path_access};
+use crate::utils::path::{PathAccess, resolve_path, validate_path_access};
 use chrono::{DateTime, Local};
+use super::base::ToolDescription;
 
 /// Actor for listing directory contents
 pub struct LsActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
 }
 
 pub struct LsState;
@@ -83,7 +86,7 @@ impl Actor for LsActor {
                 };
                 
                 // Validate path access
-                if let Err(e) = validate_path_access(&canonical_path) {
+                if let Err(e) = validate_path_access(&canonical_path, &self.path_access) {
                     chat_ref.send_message(ChatMessage::ToolResult {
                         id,
                         result: format!("Error: {}", e),
@@ -126,15 +129,48 @@ impl Actor for LsActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Ls doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Ls keeps nothing per session
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for LsActor {
+    const NAME: &'static str = "ls";
+    const DESCRIPTION: &'static str = "List files in a directory";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The directory path to list"
+                },
+                "ignore": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Glob patterns of entries to leave out"
+                },
+                "respect_git_ignore": {
+                    "type": "boolean",
+                    "description": "Skip entries ignored by .gitignore (default: true)"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+}
+
 impl LsActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access }
     }
     
     fn list_directory(&self, params: &LsParams) -> Result<Vec<FileEntry>, String> {
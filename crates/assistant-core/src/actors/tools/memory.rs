@@ -14,6 +14,7 @@ use chrono::Utc;
 use sqlx::Row;
 use std::sync::Arc;
 use uuid::Uuid;
+use super::base::ToolDescription;
 
 /// Actor for memory/context management with semantic search
 pub struct MemoryActor {
@@ -248,12 +249,77 @@ impl Actor for MemoryActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Memory doesn't stream updates currently
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Memory keeps nothing per session
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for MemoryActor {
+    const NAME: &'static str = "memory";
+    const DESCRIPTION: &'static str = "Store and retrieve information in persistent memory with keyword and semantic search";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["store", "store_with_key", "retrieve", "update", "search", "list", "delete", "clear", "stats"],
+                    "description": "The memory operation to perform"
+                },
+                "key": {
+                    "type": "string",
+                    "description": "Memory key (for store_with_key, retrieve, update, delete)"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Content to store (for store, store_with_key, update)"
+                },
+                "metadata": {
+                    "type": "object",
+                    "description": "Metadata to attach (for store, store_with_key, update)"
+                },
+                "merge_metadata": {
+                    "type": "boolean",
+                    "description": "Merge into existing metadata instead of replacing it (for update)"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Search query (for search)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum results (for search, default: 10)"
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["hybrid", "semantic", "keyword", "exact"],
+                    "description": "Search mode (for search, default: hybrid)"
+                },
+                "metadata_filter": {
+                    "type": "object",
+                    "description": "Only match memories with this metadata (for search)"
+                },
+                "prefix": {
+                    "type": "string",
+                    "description": "Key prefix (for list)"
+                },
+                "session_only": {
+                    "type": "boolean",
+                    "description": "Only clear memories of the current session (for clear)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl MemoryActor {
     pub async fn new(config: Config) -> Result<Self> {
         // Initialize database
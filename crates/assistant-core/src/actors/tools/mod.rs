@@ -4,14 +4,17 @@ pub mod tool_registry;
 // Individual tool actors
 pub mod read;
 pub mod edit;
+pub mod apply_patch;
 pub mod write;
 pub mod ls;
 pub mod glob;
 pub mod grep;
 pub mod bash;
+pub mod git;
 pub mod web_search;
 pub mod web_fetch;
 pub mod todo;
+pub mod checkpoint;
 pub mod memory;
 pub mod read_many_files;
 pub mod knowledge_agent;
@@ -21,21 +24,25 @@ pub mod computer_use;
 pub mod tui_control;
 pub mod tui_agent;
 
-// Re-export message type and registry
+// Re-export message type, descriptions and registry
 pub use crate::messages::ToolMessage;
+pub use base::{RunningExecutions, ToolDescription, ToolSpec, take_image};
 pub use tool_registry::ToolRegistry;
 
 // Re-export all tool actors
 pub use read::ReadActor;
 pub use edit::EditActor;
+pub use apply_patch::ApplyPatchActor;
 pub use write::WriteActor;
 pub use ls::LsActor;
 pub use glob::GlobActor;
 pub use grep::GrepActor;
 pub use bash::BashActor;
+pub use git::GitActor;
 pub use web_search::WebSearchActor;
 pub use web_fetch::WebFetchActor;
 pub use todo::TodoActor;
+pub use checkpoint::CheckpointActor;
 pub use memory::MemoryActor;
 pub use read_many_files::ReadManyFilesActor;
 pub use knowledge_agent::KnowledgeAgentActor;
@@ -1,19 +1,45 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fs;
+use std::path::Path;
 use crate::config::Config;
+use crate::file_tracker::FileTracker;
 use crate::messages::{ToolMessage, ChatMessage};
-use crate::utils::path::{resolve_path, validate_path_access};
+use crate::utils::path::{PathAccess, resolve_path, validate_path_access};
+use super::base::ToolDescription;
 
 /// Actor for reading files
 pub struct ReadActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
 }
 
 /// Read actor state
 pub struct ReadState;
 
+/// Largest image sent to the model, the limit most providers accept
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Pages of a PDF shown when no `pages` are asked for
+const DEFAULT_PDF_PAGES: usize = 20;
+
+/// Characters of a notebook cell output shown before it is cut short
+const MAX_CELL_OUTPUT: usize = 2000;
+
+/// How a file is shown to the model
+#[derive(Debug, PartialEq)]
+enum FileKind {
+    /// An image the model can see, with its MIME type
+    Image(&'static str),
+    Pdf,
+    Notebook,
+    Text,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ReadParams {
     path: String,
@@ -21,6 +47,12 @@ struct ReadParams {
     offset: Option<usize>,
     #[serde(default)]
     limit: Option<usize>,
+    /// Pages of a PDF to read, e.g. "3" or "1-5,8"
+    #[serde(default)]
+    pages: Option<String>,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
 }
 
 impl Actor for ReadActor {
@@ -72,7 +104,7 @@ impl Actor for ReadActor {
                 };
                 
                 // Validate path access
-                if let Err(e) = validate_path_access(&canonical_path) {
+                if let Err(e) = validate_path_access(&canonical_path, &self.path_access) {
                     chat_ref.send_message(ChatMessage::ToolResult {
                         id,
                         result: format!("Error: {}", e),
@@ -81,30 +113,8 @@ impl Actor for ReadActor {
                 }
                 
                 // Execute read operation
-                let result = match fs::read_to_string(&canonical_path) {
-                    Ok(content) => {
-                        let mut output = content;
-                        
-                        // Apply offset and limit if specified
-                        if let Some(offset) = read_params.offset {
-                            let lines: Vec<&str> = output.lines().collect();
-                            let remaining_lines = lines.len().saturating_sub(offset);
-                            let limit = read_params.limit.unwrap_or(remaining_lines);
-                            output = lines.iter()
-                                .skip(offset)
-                                .take(limit)
-                                .enumerate()
-                                .map(|(i, line)| format!("{:>5}│{}", offset + i + 1, line))
-                                .collect::<Vec<_>>()
-                                .join("\n");
-                        }
-                        
-                        output
-                    }
-                    Err(e) => {
-                        format!("Error reading file '{}': {}", read_params.path, e)
-                    }
-                };
+                let result = self.read_file(&canonical_path, &read_params).await
+                    .unwrap_or_else(|e| e);
                 
                 // Send result back to chat
                 chat_ref.send_message(ChatMessage::ToolResult {
@@ -121,16 +131,286 @@ impl Actor for ReadActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Read doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // The delegator forgets the files the session saw
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for ReadActor {
+    const NAME: &'static str = "read";
+    const DESCRIPTION: &'static str = "Read the contents of a file. Images (PNG, JPEG, GIF, WebP) are shown to you as images, PDFs as their text page by page, and Jupyter notebooks as their cells with outputs. Other binary files can't be read";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The path to the file to read"
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Line to start reading from; lines are numbered when set"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Number of lines to read from the offset"
+                },
+                "pages": {
+                    "type": "string",
+                    "description": "Pages of a PDF to read, e.g. \"3\", \"1-5\" or \"1-3,8\" (default: the first 20)"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+}
+
 impl ReadActor {
     pub fn new(config: Config) -> Self {
         Self {
+            path_access: PathAccess::for_tool(&config, Self::NAME),
             config,
         }
     }
+    
+    /// Read a file the way its kind is best shown; errors are ready to send
+    async fn read_file(&self, path: &Path, params: &ReadParams) -> Result<String, String> {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Error reading file '{}': {}", params.path, e))?;
+        FileTracker::global().record(&params.session_id, path, &bytes);
+        
+        let text = match FileKind::of(path, &bytes) {
+            FileKind::Image(mime) => return read_image(params, mime, &bytes),
+            FileKind::Pdf => return read_pdf(params, bytes).await,
+            FileKind::Notebook => render_notebook(&bytes)
+                .map_err(|e| format!("Error: '{}' is not a valid Jupyter notebook: {}", params.path, e))?,
+            // NUL bytes early on mark binary files even when they are valid UTF-8
+            FileKind::Text if bytes[..bytes.len().min(8000)].contains(&0) => return Err(binary_file(params, bytes.len())),
+            FileKind::Text => String::from_utf8(bytes).map_err(|e| binary_file(params, e.as_bytes().len()))?,
+        };
+        
+        // Apply offset and limit if specified
+        match params.offset {
+            Some(offset) => {
+                let lines: Vec<&str> = text.lines().collect();
+                let remaining_lines = lines.len().saturating_sub(offset);
+                let limit = params.limit.unwrap_or(remaining_lines);
+                Ok(lines.iter()
+                    .skip(offset)
+                    .take(limit)
+                    .enumerate()
+                    .map(|(i, line)| format!("{:>5}│{}", offset + i + 1, line))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            None => Ok(text),
+        }
+    }
+}
+
+impl FileKind {
+    /// Tell a file's kind from its first bytes, or for notebooks its extension
+    fn of(path: &Path, bytes: &[u8]) -> Self {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            FileKind::Image("image/png")
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            FileKind::Image("image/jpeg")
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            FileKind::Image("image/gif")
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            FileKind::Image("image/webp")
+        } else if bytes.starts_with(b"%PDF-") {
+            FileKind::Pdf
+        } else if path.extension().is_some_and(|ext| ext == "ipynb") {
+            FileKind::Notebook
+        } else {
+            FileKind::Text
+        }
+    }
+}
+
+/// Return an image in the form tool results carry images to the model
+fn read_image(params: &ReadParams, mime: &str, bytes: &[u8]) -> Result<String, String> {
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "Error: '{}' is an image of {:.1} MB; images over {} MB can't be shown to the model",
+            params.path,
+            bytes.len() as f64 / (1024.0 * 1024.0),
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+    
+    Ok(serde_json::json!({
+        "success": true,
+        "path": params.path,
+        "image": format!("data:{};base64,{}", mime, STANDARD.encode(bytes)),
+        "format": mime.trim_start_matches("image/"),
+        "encoding": "base64",
+        "size_bytes": bytes.len()
+    }).to_string())
+}
+
+/// Extract the text of the requested pages of a PDF
+async fn read_pdf(params: &ReadParams, bytes: Vec<u8>) -> Result<String, String> {
+    // The extractor panics on some malformed files; on a blocking thread the panic
+    // comes back as an error instead of stopping the actor
+    let pages = tokio::task::spawn_blocking(move || pdf_extract::extract_text_from_mem_by_pages(&bytes))
+        .await
+        .map_err(|_| format!("Error: Cannot extract text from PDF '{}': the file is malformed or unsupported", params.path))?
+        .map_err(|e| format!("Error: Cannot extract text from PDF '{}': {}", params.path, e))?;
+    if pages.is_empty() {
+        return Err(format!("Error: PDF '{}' has no pages", params.path));
+    }
+    
+    let (wanted, note) = match &params.pages {
+        Some(spec) => (
+            parse_pages(spec, pages.len()).map_err(|e| format!("Error: Invalid parameters - {}", e))?,
+            String::new(),
+        ),
+        None if pages.len() > DEFAULT_PDF_PAGES => (
+            (1..=DEFAULT_PDF_PAGES).collect(),
+            format!(
+                "\n\n(Showing pages 1-{} of {}; ask for others with `pages`)",
+                DEFAULT_PDF_PAGES,
+                pages.len()
+            ),
+        ),
+        None => ((1..=pages.len()).collect(), String::new()),
+    };
+    
+    let text = wanted.iter()
+        .map(|&number| format!("--- Page {} of {} ---\n{}", number, pages.len(), pages[number - 1].trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(text + &note)
+}
+
+/// Parse page numbers and ranges like "1-3,8" into page numbers, in order
+fn parse_pages(spec: &str, count: usize) -> Result<Vec<usize>, String> {
+    let mut numbers = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let parse = |number: &str, default: usize| -> Result<usize, String> {
+            if number.is_empty() {
+                return Ok(default);
+            }
+            number.parse().map_err(|_| format!("'{}' is not a page number or range", part))
+        };
+        let (start, end) = (parse(start, 1)?, parse(end, count)?);
+        if start == 0 || start > end || end > count {
+            return Err(format!("pages '{}' are out of range; the PDF has {} pages", part, count));
+        }
+        for number in start..=end {
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+    }
+    
+    if numbers.is_empty() {
+        return Err("`pages` names no pages".to_string());
+    }
+    Ok(numbers)
+}
+
+/// Render a Jupyter notebook as its cells, each followed by its outputs
+fn render_notebook(bytes: &[u8]) -> Result<String, String> {
+    let notebook: Value = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    let cells = notebook.get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "it has no cells".to_string())?;
+    let language = notebook.pointer("/metadata/language_info/name")
+        .or_else(|| notebook.pointer("/metadata/kernelspec/language"))
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    
+    let mut rendered = Vec::new();
+    for (index, cell) in cells.iter().enumerate() {
+        let cell_type = cell.get("cell_type").and_then(Value::as_str).unwrap_or("unknown");
+        let header = match cell.get("execution_count").and_then(Value::as_u64) {
+            Some(count) if cell_type == "code" => format!("--- Cell {} ({}, In [{}]) ---", index + 1, cell_type, count),
+            _ => format!("--- Cell {} ({}) ---", index + 1, cell_type),
+        };
+        let source = notebook_text(cell.get("source"));
+        let mut text = if cell_type == "code" {
+            format!("{}\n```{}\n{}\n```", header, language, source.trim_end())
+        } else {
+            format!("{}\n{}", header, source.trim_end())
+        };
+        
+        let outputs = cell.get("outputs").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        for output in outputs.iter().filter_map(render_output) {
+            text.push_str("\nOutput:\n");
+            text.push_str(&output);
+        }
+        rendered.push(text);
+    }
+    
+    Ok(rendered.join("\n\n"))
+}
+
+/// Render one cell output as text; rich outputs without a text form are named
+fn render_output(output: &Value) -> Option<String> {
+    let text = match output.get("output_type").and_then(Value::as_str)? {
+        "stream" => notebook_text(output.get("text")),
+        "execute_result" | "display_data" => {
+            let data = output.get("data")?;
+            match data.get("text/plain") {
+                Some(text) => notebook_text(Some(text)),
+                None => {
+                    let kinds = data.as_object()?.keys().cloned().collect::<Vec<_>>().join(", ");
+                    format!("[{} output]", kinds)
+                }
+            }
+        }
+        "error" => {
+            let traceback = output.get("traceback")
+                .and_then(Value::as_array)
+                .map(|lines| lines.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("\n"))
+                .unwrap_or_default();
+            let traceback = String::from_utf8_lossy(&strip_ansi_escapes::strip(traceback)).into_owned();
+            if traceback.is_empty() {
+                format!(
+                    "{}: {}",
+                    output.get("ename").and_then(Value::as_str).unwrap_or("Error"),
+                    output.get("evalue").and_then(Value::as_str).unwrap_or("")
+                )
+            } else {
+                traceback
+            }
+        }
+        _ => return None,
+    };
+    
+    let text = text.trim_end();
+    Some(match text.char_indices().nth(MAX_CELL_OUTPUT) {
+        Some((cut, _)) => format!("{}\n... (output truncated, {} characters in all)", &text[..cut], text.chars().count()),
+        None => text.to_string(),
+    })
+}
+
+/// Notebook text fields are a string or a list of lines
+fn notebook_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn binary_file(params: &ReadParams, size: usize) -> String {
+    format!(
+        "Error: '{}' is a binary file ({} bytes) and can't be shown as text. The read tool reads text files, images (PNG, JPEG, GIF, WebP), PDFs and Jupyter notebooks",
+        params.path, size
+    )
 }
\ No newline at end of file
@@ -3,11 +3,16 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::fs;
 use crate::config::Config;
+use crate::file_tracker::FileTracker;
 use crate::messages::{ToolMessage, ChatMessage};
+use crate::utils::path::{PathAccess, validate_path_access};
+use super::base::ToolDescription;
 
 pub struct ReadManyFilesActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read
+    path_access: PathAccess,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,6 +20,9 @@ struct ReadManyFilesParams {
     paths: Vec<String>,
     #[serde(default = "default_max_lines_per_file")]
     max_lines_per_file: usize,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
 }
 
 fn default_max_lines_per_file() -> usize {
@@ -79,7 +87,7 @@ impl Actor for ReadManyFilesActor {
                 let mut read_futures = Vec::new();
                 for path_str in read_params.paths {
                     let max_lines = read_params.max_lines_per_file;
-                    read_futures.push(self.read_file_with_result(path_str, max_lines));
+                    read_futures.push(self.read_file_with_result(&read_params.session_id, path_str, max_lines));
                 }
                 
                 // Wait for all reads to complete
@@ -103,18 +111,47 @@ impl Actor for ReadManyFilesActor {
             ToolMessage::StreamUpdate { .. } => {
                 // ReadManyFiles doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // The delegator forgets the files the session saw
+            }
         }
         Ok(())
     }
 }
 
+impl ToolDescription for ReadManyFilesActor {
+    const NAME: &'static str = "read_many_files";
+    const DESCRIPTION: &'static str = "Read multiple files at once";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "paths": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Paths of the files to read"
+                },
+                "max_lines_per_file": {
+                    "type": "integer",
+                    "description": "Maximum lines to read from each file (default: 2000)"
+                }
+            },
+            "required": ["paths"]
+        })
+    }
+}
+
 impl ReadManyFilesActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access }
     }
     
-    async fn read_file_with_result(&self, path_str: String, max_lines: usize) -> FileContent {
-        match self.read_file(&path_str, max_lines).await {
+    async fn read_file_with_result(&self, session_id: &str, path_str: String, max_lines: usize) -> FileContent {
+        match self.read_file(session_id, &path_str, max_lines).await {
             Ok(content) => FileContent {
                 path: path_str,
                 content,
@@ -128,9 +165,12 @@ impl ReadManyFilesActor {
         }
     }
     
-    async fn read_file(&self, path_str: &str, max_lines: usize) -> Result<String, String> {
+    async fn read_file(&self, session_id: &str, path_str: &str, max_lines: usize) -> Result<String, String> {
         let path = Path::new(path_str);
         
+        // Validate path access
+        validate_path_access(path, &self.path_access).map_err(|e| format!("{}", e))?;
+        
         // Check if file exists
         if !path.exists() {
             return Err(format!("File not found: {}", path_str));
@@ -144,6 +184,8 @@ impl ReadManyFilesActor {
         // Read the file
         match fs::read_to_string(path).await {
             Ok(content) => {
+                FileTracker::global().record(session_id, path, content.as_bytes());
+                
                 // Limit the number of lines
                 let lines: Vec<&str> = content.lines().collect();
                 let truncated = if lines.len() > max_lines {
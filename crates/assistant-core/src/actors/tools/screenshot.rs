@@ -6,6 +6,7 @@ use anyhow::Result;
 use std::process::Command;
 use std::fs;
 use base64::{Engine as _, engine::general_purpose::STANDARD};
+use super::base::ToolDescription;
 
 /// Actor for taking screenshots on macOS
 pub struct ScreenshotActor {
@@ -40,6 +41,46 @@ pub enum ScreenshotAction {
     CaptureInteractive,
 }
 
+impl ToolDescription for ScreenshotActor {
+    const NAME: &'static str = "screenshot";
+    const DESCRIPTION: &'static str = "Take a screenshot on macOS. Returns a base64 data URL of the screenshot image";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["capture_screen", "capture_window", "capture_region", "capture_interactive"],
+                    "description": "Type of screenshot to take"
+                },
+                "display": {
+                    "type": "integer",
+                    "description": "Display number (for capture_screen)"
+                },
+                "x": {
+                    "type": "integer",
+                    "description": "X coordinate for region capture"
+                },
+                "y": {
+                    "type": "integer",
+                    "description": "Y coordinate for region capture"
+                },
+                "width": {
+                    "type": "integer",
+                    "description": "Width for region capture"
+                },
+                "height": {
+                    "type": "integer",
+                    "description": "Height for region capture"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl ScreenshotActor {
     pub fn new(config: Config) -> Self {
         Self { config }
@@ -183,6 +224,10 @@ impl Actor for ScreenshotActor {
             ToolMessage::StreamUpdate { .. } => {
                 // No streaming for screenshots
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Screenshot keeps nothing per session
+            }
         }
         
         Ok(())
@@ -8,6 +8,7 @@ use sqlx::Row;
 use uuid::Uuid;
 use chrono::Utc;
 use crate::persistence::database::Database;
+use super::base::ToolDescription;
 
 pub struct TodoActor {
     #[allow(dead_code)]
@@ -281,11 +282,56 @@ impl Actor for TodoActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Todo doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // Todos are persisted, and outlive the session
+            }
         }
         Ok(())
     }
 }
 
+impl ToolDescription for TodoActor {
+    const NAME: &'static str = "todo";
+    const DESCRIPTION: &'static str = "Manage todo list with various operations";
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "operation": {
+                    "type": "string",
+                    "enum": ["list", "add", "update", "remove", "clear", "stats"],
+                    "description": "The operation to perform"
+                },
+                "session_id": {
+                    "type": "string",
+                    "description": "Session ID for the todo list (defaults to 'default')"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Content for add operation"
+                },
+                "priority": {
+                    "type": "string",
+                    "enum": ["low", "medium", "high"],
+                    "description": "Priority for add/update/list operations"
+                },
+                "status": {
+                    "type": "string",
+                    "enum": ["pending", "in_progress", "completed"],
+                    "description": "Status for update/list/clear operations"
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Todo ID for update/remove operations"
+                }
+            },
+            "required": ["operation"]
+        })
+    }
+}
+
 impl TodoActor {
     pub async fn new(config: Config) -> Result<Self> {
         // Get database path from config
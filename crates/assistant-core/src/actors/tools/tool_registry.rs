@@ -2,8 +2,62 @@ use std::collections::HashMap;
 use ractor::{Actor, ActorRef};
 use crate::config::Config;
 use crate::messages::ToolMessage;
+use crate::openai_compat::Tool;
+use super::base::ToolSpec;
 use anyhow::Result;
 
+/// Every tool, in registration order. Invokes `$sync` with the actors created by
+/// a plain `new` and `$async` with those whose `new` is async; spawning the tools
+/// and describing them both go through this list.
+macro_rules! for_each_tool {
+    ($sync:ident, $async:ident) => {
+        // File system tools
+        $sync!(LsActor);
+        $sync!(ReadActor);
+        $sync!(WriteActor);
+        $sync!(EditActor);
+        $sync!(ApplyPatchActor);
+        $sync!(GlobActor);
+        $sync!(GrepActor);
+        $sync!(ReadManyFilesActor);
+        
+        // Shell tool
+        $sync!(BashActor);
+        
+        // Version control
+        $sync!(GitActor);
+        
+        // Web tools
+        $sync!(WebSearchActor);
+        $sync!(WebFetchActor);
+        
+        // Utility tools
+        $async!(MemoryActor);
+        
+        // Todo tool (session-aware)
+        $async!(TodoActor);
+        
+        // File checkpoints (session-aware)
+        $async!(CheckpointActor);
+        
+        // Knowledge agent (delegated)
+        $async!(KnowledgeAgentActor);
+        
+        // Desktop automation tools (macOS only for now)
+        $sync!(ScreenshotActor);
+        $sync!(DesktopControlActor);
+        
+        // Computer use agent (delegated)
+        $sync!(ComputerUseActor);
+        
+        // TUI control tool
+        $sync!(TuiControlActor);
+        
+        // TUI agent (delegated)
+        $async!(TuiAgentActor);
+    };
+}
+
 /// Registry for managing available tools
 pub struct ToolRegistry {
     config: Config,
@@ -20,151 +74,102 @@ impl ToolRegistry {
         use crate::actors::tools::*;
         
         let mut tool_actors = HashMap::new();
-        let exclude_list = &self.config.tools.exclude;
         let tool_config = self.config.clone();
         
         // Helper closure to check if a tool is enabled
         let is_tool_enabled = |name: &str| -> bool {
-            if exclude_list.contains(&name.to_string()) {
-                tracing::info!("Tool '{}' is excluded by configuration", name);
-                return false;
-            }
-            
-            let enabled = self.config.tools.configs
-                .get(name)
-                .map(|tc| tc.enabled)
-                .unwrap_or(true);
-                
+            let enabled = self.is_enabled(name);
             if !enabled {
-                tracing::info!("Tool '{}' is disabled by configuration", name);
+                tracing::info!("Tool '{}' is excluded or disabled by configuration", name);
             }
-            
             enabled
         };
         
         // Helper macro to register a tool
         macro_rules! register_tool {
-            ($name:expr, $actor_type:ty) => {
-                if is_tool_enabled($name) {
+            ($actor_type:ty) => {
+                let name = <$actor_type>::NAME;
+                if is_tool_enabled(name) {
                     let actor = <$actor_type>::new(tool_config.clone());
                     let (actor_ref, _) = Actor::spawn(
-                        Some($name.to_string()), 
+                        Some(name.to_string()), 
                         actor, 
                         tool_config.clone()
                     ).await?;
-                    tool_actors.insert($name.to_string(), actor_ref);
-                    tracing::info!("Tool '{}' initialized", $name);
+                    tool_actors.insert(name.to_string(), actor_ref);
+                    tracing::info!("Tool '{}' initialized", name);
                 }
             };
         }
         
         // Helper macro for async tool creation
         macro_rules! register_async_tool {
-            ($name:expr, $actor_type:ty) => {
-                if is_tool_enabled($name) {
+            ($actor_type:ty) => {
+                let name = <$actor_type>::NAME;
+                if is_tool_enabled(name) {
                     let actor = <$actor_type>::new(tool_config.clone()).await?;
                     let (actor_ref, _) = Actor::spawn(
-                        Some($name.to_string()), 
+                        Some(name.to_string()), 
                         actor, 
                         tool_config.clone()
                     ).await?;
-                    tool_actors.insert($name.to_string(), actor_ref);
-                    tracing::info!("Tool '{}' initialized", $name);
+                    tool_actors.insert(name.to_string(), actor_ref);
+                    tracing::info!("Tool '{}' initialized", name);
                 }
             };
         }
         
-        // File system tools
-        register_tool!("ls", LsActor);
-        register_tool!("read", ReadActor);
-        register_tool!("write", WriteActor);
-        register_tool!("edit", EditActor);
-        register_tool!("glob", GlobActor);
-        register_tool!("grep", GrepActor);
-        register_tool!("read_many_files", ReadManyFilesActor);
-        
-        // Shell tool
-        register_tool!("bash", BashActor);
-        
-        // Web tools
-        register_tool!("web_search", WebSearchActor);
-        register_tool!("web_fetch", WebFetchActor);
-        
-        // Utility tools
-        register_async_tool!("memory", MemoryActor);
-        
-        // Todo tool (session-aware)
-        register_async_tool!("todo", TodoActor);
-        
-        // Knowledge agent (delegated)
-        register_async_tool!("knowledge_agent", KnowledgeAgentActor);
-        
-        // Desktop automation tools (macOS only for now)
-        register_tool!("screenshot", ScreenshotActor);
-        register_tool!("desktop_control", DesktopControlActor);
-        
-        // Computer use agent (delegated)
-        register_tool!("computer_use", ComputerUseActor);
-        
-        // TUI control tool
-        register_tool!("tui_control", TuiControlActor);
-        
-        // TUI agent (delegated)
-        register_async_tool!("tui_agent", TuiAgentActor);
+        for_each_tool!(register_tool, register_async_tool);
         
         tracing::info!("Initialized {} tools", tool_actors.len());
         Ok(tool_actors)
     }
     
+    /// Descriptions of every tool, in registration order
+    pub fn specs() -> Vec<ToolSpec> {
+        use crate::actors::tools::*;
+        
+        let mut specs = Vec::new();
+        macro_rules! add_spec {
+            ($actor_type:ty) => {
+                specs.push(<$actor_type>::spec());
+            };
+        }
+        for_each_tool!(add_spec, add_spec);
+        specs
+    }
+    
     /// Get list of all available tool names
     pub fn available_tools() -> Vec<&'static str> {
-        vec![
-            "ls", "read", "write", "edit", "glob", "grep", "read_many_files",
-            "bash", "web_search", "web_fetch", "memory", "todo", "knowledge_agent",
-            "screenshot", "desktop_control", "computer_use", "tui_control", "tui_agent"
-        ]
+        Self::specs().into_iter().map(|spec| spec.name).collect()
     }
     
-    /// Get list of enabled tools based on current configuration
-    pub fn enabled_tools(&self) -> Vec<&str> {
-        let exclude_list = &self.config.tools.exclude;
+    /// Whether a tool is neither excluded nor disabled by configuration
+    pub fn is_enabled(&self, name: &str) -> bool {
+        if self.config.tools.exclude.iter().any(|excluded| excluded == name) {
+            return false;
+        }
         
+        self.config.tools.configs
+            .get(name)
+            .map(|tc| tc.enabled)
+            .unwrap_or(true)
+    }
+    
+    /// Get list of enabled tools based on current configuration
+    pub fn enabled_tools(&self) -> Vec<&'static str> {
         Self::available_tools()
             .into_iter()
-            .filter(|name| !exclude_list.contains(&name.to_string()))
-            .filter(|name| {
-                self.config.tools.configs
-                    .get(*name)
-                    .map(|tc| tc.enabled)
-                    .unwrap_or(true)
-            })
+            .filter(|name| self.is_enabled(name))
             .collect()
     }
     
-    /// Get tool descriptions (for help/documentation)
-    pub fn tool_descriptions() -> HashMap<&'static str, (&'static str, &'static str)> {
-        let mut descriptions = HashMap::new();
-        
-        // (display_name, description)
-        descriptions.insert("ls", ("List Directory", "List files and directories"));
-        descriptions.insert("read", ("Read File", "Read the contents of a file"));
-        descriptions.insert("write", ("Write File", "Write content to a file"));
-        descriptions.insert("edit", ("Edit File", "Edit a file by replacing content"));
-        descriptions.insert("glob", ("Glob Search", "Search for files matching a pattern"));
-        descriptions.insert("grep", ("Grep Search", "Search file contents using regex"));
-        descriptions.insert("read_many_files", ("Read Many Files", "Read multiple files at once"));
-        descriptions.insert("bash", ("Shell Command", "Execute shell commands. Cannot run interactive TUI applications like vim, nano, or htop. Use tui_control for those."));
-        descriptions.insert("web_search", ("Web Search", "Search the web for information"));
-        descriptions.insert("web_fetch", ("Web Fetch", "Fetch content from a URL"));
-        descriptions.insert("memory", ("Memory", "Store and retrieve information"));
-        descriptions.insert("todo_write", ("Todo List", "Manage a todo list"));
-        descriptions.insert("knowledge_agent", ("Knowledge Agent", "Search and synthesize knowledge from memories, chat history, todos, and sessions"));
-        descriptions.insert("screenshot", ("Screenshot", "Take screenshots on macOS"));
-        descriptions.insert("desktop_control", ("Desktop Control", "Control mouse and keyboard on macOS"));
-        descriptions.insert("computer_use", ("Computer Use", "Visual desktop automation agent"));
-        descriptions.insert("tui_control", ("TUI Control", "Control interactive terminal applications (vim, htop, etc.) through tmux. Use this instead of bash for TUI apps."));
-        descriptions.insert("tui_agent", ("TUI Agent", "High-level intelligent agent for complex TUI interactions. Delegates to tui_control for execution."));
-        
-        descriptions
+    /// Function-calling definitions of the enabled tools, to offer to the model
+    pub fn tool_definitions(&self) -> Vec<Tool> {
+        Self::specs()
+            .iter()
+            .filter(|spec| self.is_enabled(spec.name))
+            .map(ToolSpec::definition)
+            .collect()
     }
-}
\ No newline at end of file
+}
@@ -4,6 +4,7 @@ use serde_json::json;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
 use anyhow::Result;
+use super::base::ToolDescription;
 
 /// TUI agent tool - high-level interface for TUI interaction
 pub struct TuiAgentActor {
@@ -46,6 +47,43 @@ pub enum TuiAgentAction {
     ExitApp,
 }
 
+impl ToolDescription for TuiAgentActor {
+    const NAME: &'static str = "tui_agent";
+    const DESCRIPTION: &'static str = "High-level intelligent agent for complex TUI interactions. Delegates to tui_control for execution";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["start_app", "navigate_to", "perform_task", "analyze_screen", "execute_steps", "exit_app"],
+                    "description": "The TUI agent action to perform"
+                },
+                "command": {
+                    "type": "string",
+                    "description": "Application to start (for start_app)"
+                },
+                "task": {
+                    "type": "string",
+                    "description": "Natural language description of the task (for start_app, perform_task)"
+                },
+                "target": {
+                    "type": "string",
+                    "description": "What to navigate to (for navigate_to)"
+                },
+                "steps": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Steps to execute in order (for execute_steps)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl TuiAgentActor {
     pub async fn new(config: Config) -> Result<Self> {
         Ok(Self {
@@ -166,6 +204,10 @@ impl Actor for TuiAgentActor {
             ToolMessage::StreamUpdate { .. } => {
                 // No streaming for TUI agent
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // TUI agent keeps nothing per session
+            }
         }
         
         Ok(())
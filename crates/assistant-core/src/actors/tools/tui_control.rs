@@ -8,6 +8,7 @@ use anyhow::Result;
 use std::process::Command;
 use std::collections::HashMap;
 use uuid::Uuid;
+use super::base::ToolDescription;
 
 /// Actor for controlling TUI applications through tmux
 pub struct TuiControlActor {
@@ -96,6 +97,54 @@ pub struct TuiScreen {
     pub grid: Option<Vec<Vec<char>>>,
 }
 
+impl ToolDescription for TuiControlActor {
+    const NAME: &'static str = "tui_control";
+    const DESCRIPTION: &'static str = "Control interactive terminal applications (vim, htop, etc.) through tmux. Use this instead of bash for TUI apps";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["start_session", "capture_screen", "send_keys", "send_text", "end_session", "list_sessions", "get_dimensions"],
+                    "description": "The TUI action to perform"
+                },
+                "command": {
+                    "type": "string",
+                    "description": "Command to run (for start_session)"
+                },
+                "width": {
+                    "type": "integer",
+                    "description": "Terminal width in columns (for start_session)"
+                },
+                "height": {
+                    "type": "integer",
+                    "description": "Terminal height in rows (for start_session)"
+                },
+                "session_id": {
+                    "type": "string",
+                    "description": "TUI session to act on (defaults to an active session)"
+                },
+                "include_ansi": {
+                    "type": "boolean",
+                    "description": "Keep ANSI escape codes in the capture (for capture_screen)"
+                },
+                "keys": {
+                    "type": "string",
+                    "description": "Keys to send in tmux send-keys notation, e.g. 'Escape', 'C-c', ':wq Enter' (for send_keys)"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to type (for send_text)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+}
+
 impl TuiControlActor {
     pub fn new(config: Config) -> Self {
         Self { 
@@ -602,6 +651,10 @@ impl Actor for TuiControlActor {
             ToolMessage::StreamUpdate { .. } => {
                 // No streaming for TUI control
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // TUI control keeps nothing per session
+            }
         }
         
         Ok(())
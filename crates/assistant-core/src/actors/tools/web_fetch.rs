@@ -1,25 +1,100 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use serde::{Deserialize, Serialize};
-use reqwest::{Client, Url};
-use html2text;
+use reqwest::{Client, StatusCode, Url};
+use chrono::{DateTime, Utc};
 use std::time::Duration;
+use uuid::Uuid;
+use crate::actors::client::{ClientActor, ClientMessage};
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
+use crate::openai_compat::{ChatMessage as OpenAIMessage, UserContent};
+use crate::persistence::HttpCache;
+use crate::persistence::http_cache::{self, CachedResponse};
+use crate::url_policy::UrlPolicy;
+use crate::utils::html;
+use super::base::{RunningExecutions, ToolDescription};
+
+/// Characters of content returned per page when no model reads it
+const PAGE_CHARS: usize = 20_000;
+
+/// Characters of page text the analysis model reads at a time
+const CHUNK_CHARS: usize = 12_000;
+
+/// Most chunks of one page the model reads; the rest of the page is left out
+const MAX_CHUNKS: usize = 10;
+
+/// How long one analysis request may take
+const ANALYSIS_TIMEOUT: Duration = Duration::from_secs(90);
+
+const ANSWER_PROMPT: &str = "You answer questions about a web page using only its content. \
+    Give a focused answer to the request, backing each point with short verbatim quotes from the page \
+    as Markdown blockquotes (lines starting with \"> \"). If the page doesn't answer the request, say so plainly \
+    and mention what it covers instead. Don't add knowledge that isn't on the page.";
+
+const EXTRACT_PROMPT: &str = "You read one part of a long web page for someone with a request about it. \
+    List what this part says that bears on the request, each point with a short verbatim quote from the text \
+    as a Markdown blockquote. Reply with just NONE if nothing in this part is relevant.";
+
+const COMBINE_PROMPT: &str = "You answer a request about a web page from notes taken on its parts, in page order. \
+    Give one focused answer, keeping the notes' verbatim quotes (Markdown blockquotes) that support it. \
+    If the notes don't answer the request, say so plainly. Don't add knowledge that isn't in the notes.";
 
 /// Actor for fetching and processing web content
+#[derive(Clone)]
 pub struct WebFetchActor {
     #[allow(dead_code)]
     config: Config,
-    client: Client,
+    /// The client enforcing `policy`, or why it couldn't be built; pages are then
+    /// not fetched at all rather than without the policy
+    client: Result<Client, String>,
+    /// Which URLs may be fetched; the client enforces it on redirects and addresses
+    policy: UrlPolicy,
 }
 
 /// WebFetch actor state
-pub struct WebFetchState;
+pub struct WebFetchState {
+    /// Requests still running, by execution id
+    running: RunningExecutions,
+    /// Client for the model that reads pages, when one is configured
+    analyzer: Option<ActorRef<ClientMessage>>,
+    /// Pages fetched before, unless caching is turned off
+    cache: Option<HttpCache>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct WebFetchParams {
     url: String,
     prompt: String,
+    /// Character offset to read from
+    #[serde(default)]
+    offset: Option<usize>,
+    /// Page of `PAGE_CHARS` characters to read, from 1; `offset` takes precedence
+    #[serde(default)]
+    page: Option<usize>,
+    /// Fetch from the server even when a cached copy is fresh
+    #[serde(default)]
+    no_cache: bool,
+}
+
+/// A page as fetched, or as kept in the cache
+struct FetchedPage {
+    /// Where the page was served from, after redirects
+    url: Url,
+    content_type: String,
+    content: String,
+    /// When the page was fetched, if it came from the cache
+    cached_at: Option<DateTime<Utc>>,
+}
+
+impl FetchedPage {
+    fn from_cache(cached: &CachedResponse, requested: &Url) -> Self {
+        Self {
+            url: Url::parse(&cached.url).unwrap_or_else(|_| requested.clone()),
+            content_type: cached.content_type.clone().unwrap_or_else(|| "text/html".to_string()),
+            content: String::from_utf8_lossy(&cached.body).into_owned(),
+            cached_at: Some(cached.stored_at),
+        }
+    }
 }
 
 impl Actor for WebFetchActor {
@@ -33,14 +108,35 @@ impl Actor for WebFetchActor {
         _config: Self::Arguments,
     ) -> Result<Self::State, ActorProcessingErr> {
         tracing::debug!("WebFetch actor starting");
-        Ok(WebFetchState)
+        
+        let analyzer = match Self::analysis_config(&self.config) {
+            Some(config) => {
+                tracing::info!("web_fetch will analyze pages with {}", config.model);
+                let (client_ref, _) = Actor::spawn(None, ClientActor::new(config.clone()), config).await?;
+                Some(client_ref)
+            }
+            None => None,
+        };
+        
+        let cache = match self.config.http_cache.enabled {
+            true => HttpCache::open(&self.config).await
+                .inspect_err(|e| tracing::warn!("web_fetch will not cache pages: {}", e))
+                .ok(),
+            false => None,
+        };
+        
+        Ok(WebFetchState {
+            running: RunningExecutions::default(),
+            analyzer,
+            cache,
+        })
     }
     
     async fn handle(
         &self,
         _myself: ActorRef<Self::Msg>,
         msg: Self::Msg,
-        _state: &mut Self::State,
+        state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
         match msg {
             ToolMessage::Execute { id, params, chat_ref } => {
@@ -58,42 +154,112 @@ impl Actor for WebFetchActor {
                     }
                 };
                 
-                // Execute web fetch
-                let result = self.fetch_and_process(&fetch_params).await;
-                
-                // Send result back to chat
-                chat_ref.send_message(ChatMessage::ToolResult {
-                    id,
-                    result,
-                })?;
+                // Fetch in the background so the request can be cancelled
+                let fetcher = self.clone();
+                let analyzer = state.analyzer.clone();
+                let cache = state.cache.clone();
+                state.running.spawn(id, async move {
+                    let analysis = analyzer.as_ref().map(|client| Analysis { client, chat_ref: &chat_ref, id });
+                    let result = fetcher.fetch_and_process(&fetch_params, analysis.as_ref(), cache.as_ref()).await;
+                    let _ = chat_ref.send_message(ChatMessage::ToolResult { id, result });
+                });
             }
             
             ToolMessage::Cancel { id } => {
-                tracing::debug!("Cancelling web fetch operation {}", id);
-                // Web fetch operations are not cancellable once started
+                if state.running.cancel(&id) {
+                    tracing::info!("Cancelled web fetch {}", id);
+                }
             }
             
             ToolMessage::StreamUpdate { .. } => {
                 // WebFetch doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // WebFetch keeps nothing per session
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for WebFetchActor {
+    const NAME: &'static str = "web_fetch";
+    const DESCRIPTION: &'static str = "Fetch content from a URL and answer a prompt about it, quoting the relevant parts of the page. \
+        HTML pages are reduced to their main content and a list of its links. Long content is split into pages; \
+        fetch again with `page` or `offset` to read on";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "The URL to fetch"
+                },
+                "prompt": {
+                    "type": "string",
+                    "description": "What to extract or look for in the fetched content"
+                },
+                "page": {
+                    "type": "integer",
+                    "description": format!("Page of the content to read, {} characters each, starting at 1 (default 1)", PAGE_CHARS)
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Character offset to read the content from; overrides page"
+                },
+                "no_cache": {
+                    "type": "boolean",
+                    "description": "Fetch the page again even if a cached copy is still fresh (default false)"
+                }
+            },
+            "required": ["url", "prompt"]
+        })
+    }
+}
+
 impl WebFetchActor {
     pub fn new(config: Config) -> Self {
-        let client = Client::builder()
+        let policy = UrlPolicy::new(&config.url_policy);
+        let client = policy.client_builder()
             .timeout(Duration::from_secs(30))
             .user_agent("Mozilla/5.0 (compatible; assistant-core/0.1)")
             .build()
-            .unwrap_or_default();
+            .map_err(|e| format!("Error: Cannot create the HTTP client: {}", e));
             
-        Self { config, client }
+        Self { config, client, policy }
     }
     
-    async fn fetch_and_process(&self, params: &WebFetchParams) -> String {
+    /// The base config with the model settings from `tools.web_fetch`, or `None`
+    /// when no model is configured there and pages are searched by keyword instead
+    fn analysis_config(config: &Config) -> Option<Config> {
+        let tool_config = config.tools.configs.get(Self::NAME)?;
+        let mut analysis = config.clone();
+        analysis.model = tool_config.model.clone()?;
+        if let Some(api_key) = &tool_config.api_key {
+            analysis.api_key = api_key.clone();
+        }
+        if let Some(provider) = &tool_config.provider {
+            analysis.provider = provider.clone();
+        }
+        if let Some(base_url) = &tool_config.base_url {
+            analysis.base_url = base_url.clone();
+        }
+        if let Some(temperature) = tool_config.temperature {
+            analysis.temperature = temperature;
+        }
+        Some(analysis)
+    }
+    
+    async fn fetch_and_process(
+        &self,
+        params: &WebFetchParams,
+        analysis: Option<&Analysis<'_>>,
+        cache: Option<&HttpCache>,
+    ) -> String {
         // Validate URL
         let url = match Url::parse(&params.url) {
             Ok(url) => url,
@@ -101,58 +267,26 @@ impl WebFetchActor {
                 return format!("Error: Invalid URL '{}' - {}", params.url, e);
             }
         };
-        
-        // Fetch the content
-        let response = match self.client.get(url.clone()).send().await {
-            Ok(resp) => resp,
-            Err(e) => {
-                return format!("Error fetching URL '{}': {}", url, e);
-            }
-        };
-        
-        // Check status
-        if !response.status().is_success() {
-            return format!("Error: HTTP {} when fetching '{}'", response.status(), url);
-        }
-        
-        // Check for redirects
-        if let Some(final_url) = response.url().host_str() {
-            if let Some(original_host) = url.host_str() {
-                if final_url != original_host {
-                    return format!(
-                        "Redirect detected: The URL redirected to a different host.\n\
-                        Original: {}\n\
-                        Redirected to: {}\n\n\
-                        Please make a new WebFetch request with the redirect URL if you want to fetch its content.",
-                        url, response.url()
-                    );
-                }
-            }
+        // Before the cache, so pages the policy now refuses aren't served from it either
+        if let Err(violation) = self.policy.check_url(&url) {
+            return format!("Error: {}", violation);
         }
         
-        // Get content type
-        let content_type = response
-            .headers()
-            .get("content-type")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("text/html")
-            .to_string();
-        
-        // Get the content
-        let content = match response.text().await {
-            Ok(text) => text,
-            Err(e) => {
-                return format!("Error reading response from '{}': {}", url, e);
-            }
+        let FetchedPage { url: final_url, content_type, content, cached_at } = match self.fetch(&url, cache, params.no_cache).await {
+            Ok(page) => page,
+            Err(message) => return message,
         };
+        let cached = cached_at
+            .map(|time| format!("Cached copy from: {}\n", time.format("%Y-%m-%d %H:%M:%S UTC")))
+            .unwrap_or_default();
         
         // Process based on content type
+        let mut title = None;
         let processed_content = if content_type.contains("text/html") {
-            // Convert HTML to markdown
-            match html2text::from_read(content.as_bytes(), 80) {
-                Ok(markdown) => markdown,
-                Err(_) => content,
-            }
+            // Keep the main content and its links, without navigation and other furniture
+            let page = html::extract_page(&content, &final_url);
+            title = page.title.clone();
+            page.to_text()
         } else if content_type.contains("application/json") {
             // Pretty print JSON
             match serde_json::from_str::<serde_json::Value>(&content) {
@@ -164,36 +298,225 @@ impl WebFetchActor {
             content
         };
         
-        // Truncate if too long
-        let max_length = 50000;
-        let content_length = processed_content.len();
-        let truncated = if content_length > max_length {
+        let content_length = processed_content.chars().count();
+        let start = match (params.offset, params.page) {
+            (Some(offset), _) => offset,
+            (None, Some(page)) => match page.saturating_sub(1).checked_mul(PAGE_CHARS) {
+                Some(start) => start,
+                None => {
+                    return format!(
+                        "Error: Page {} is out of range for the content from '{}' ({} characters)",
+                        page, url, content_length
+                    );
+                }
+            },
+            (None, None) => 0,
+        };
+        if start > 0 && start >= content_length {
+            return format!(
+                "Error: Offset {} is past the end of the content from '{}' ({} characters)",
+                start, url, content_length
+            );
+        }
+        let rest = &processed_content[byte_offset(&processed_content, start)..];
+        let title = title.map(|title| format!("Title: {}\n", title)).unwrap_or_default();
+        
+        if let Some(analysis) = analysis {
+            match Self::analyze_with_model(analysis, rest, &params.prompt).await {
+                Ok((mut answer, parts, read)) => {
+                    let analyzed = match parts {
+                        1 => String::new(),
+                        parts => format!(", read in {} parts", parts),
+                    };
+                    if start > 0 || start + read < content_length {
+                        answer.push_str(&format!("\n\n[Read characters {}-{} of {}", start + 1, start + read, content_length));
+                        if start + read < content_length {
+                            answer.push_str(&format!("; fetch again with \"offset\": {} to read on", start + read));
+                        }
+                        answer.push(']');
+                    }
+                    return format!(
+                        "Fetched content from: {}\n\
+                        {}{}\
+                        Content-Type: {}\n\
+                        Length: {} characters{}\n\n\
+                        {}",
+                        url,
+                        cached,
+                        title,
+                        content_type,
+                        content_length,
+                        analyzed,
+                        answer
+                    );
+                }
+                Err(e) => tracing::warn!("Analyzing {} with the model failed, matching keywords instead: {}", url, e),
+            }
+        }
+        
+        // One page of the content, with directions to the next
+        let end = (start + PAGE_CHARS).min(content_length);
+        let window = &rest[..byte_offset(rest, end - start)];
+        let range = match start > 0 || end < content_length {
+            true => format!("Showing characters {}-{} of {}\n", start + 1, end, content_length),
+            false => String::new(),
+        };
+        let truncated = if end < content_length {
+            let next = match start % PAGE_CHARS {
+                0 => format!("\"page\": {} or \"offset\": {}", end / PAGE_CHARS + 1, end),
+                _ => format!("\"offset\": {}", end),
+            };
             format!(
-                "{}...\n\n[Content truncated - {} characters omitted]",
-                &processed_content[..max_length],
-                content_length - max_length
+                "{}...\n\n[Content truncated - {} characters omitted; fetch again with {} to read on]",
+                window,
+                content_length - end,
+                next
             )
         } else {
-            processed_content
+            window.to_string()
         };
         
         // Format the result with the prompt context
         format!(
             "Fetched content from: {}\n\
+            {}{}\
             Content-Type: {}\n\
-            Length: {} characters\n\n\
+            Length: {} characters\n\
+            {}\n\
             Content:\n{}\n\n\
             Analysis based on prompt: \"{}\"\n\n\
             The content above shows the fetched web page. {}",
             url,
+            cached,
+            title,
             content_type,
             content_length,
+            range,
             truncated,
             params.prompt,
             self.analyze_content(&truncated, &params.prompt)
         )
     }
     
+    /// Fetch `url`, answering from the cache while its copy is fresh or the server
+    /// confirms it unchanged. With `no_cache` the cached copy is ignored, though the
+    /// response still replaces it. Errors are the message to return to the caller.
+    async fn fetch(&self, url: &Url, cache: Option<&HttpCache>, no_cache: bool) -> Result<FetchedPage, String> {
+        let key = url.as_str();
+        let cached = match cache {
+            Some(cache) if !no_cache => cache.get(key).await
+                .inspect_err(|e| tracing::warn!("Reading the HTTP cache failed: {}", e))
+                .ok()
+                .flatten(),
+            _ => None,
+        };
+        if let Some(cached) = &cached && cached.is_fresh() {
+            return Ok(FetchedPage::from_cache(cached, url));
+        }
+        
+        // Fetch the content, or just confirm the cached copy is current
+        let mut request = self.client.as_ref().map_err(Clone::clone)?.get(url.clone());
+        if let Some(cached) = &cached {
+            request = request.headers(cached.conditional_headers());
+        }
+        let response = request.send().await
+            .map_err(|e| match UrlPolicy::violation(&e) {
+                Some(violation) => format!("Error: {}", violation),
+                None => format!("Error fetching URL '{}': {}", url, e),
+            })?;
+        let ttl = Duration::from_secs(self.config.http_cache.fetch_ttl_secs);
+        
+        if response.status() == StatusCode::NOT_MODIFIED && let (Some(cache), Some(cached)) = (cache, &cached) {
+            let expires_at = http_cache::expires_at(response.headers(), ttl).unwrap_or_else(Utc::now);
+            if let Err(e) = cache.revalidated(key, expires_at).await {
+                tracing::warn!("Updating the HTTP cache failed: {}", e);
+            }
+            return Ok(FetchedPage::from_cache(cached, url));
+        }
+        
+        // Check status
+        if !response.status().is_success() {
+            return Err(format!("Error: HTTP {} when fetching '{}'", response.status(), url));
+        }
+        
+        // Check for redirects
+        if let Some(final_url) = response.url().host_str() {
+            if let Some(original_host) = url.host_str() {
+                if final_url != original_host {
+                    return Err(format!(
+                        "Redirect detected: The URL redirected to a different host.\n\
+                        Original: {}\n\
+                        Redirected to: {}\n\n\
+                        Please make a new WebFetch request with the redirect URL if you want to fetch its content.",
+                        url, response.url()
+                    ));
+                }
+            }
+        }
+        
+        // Get content type
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("text/html")
+            .to_string();
+        
+        // Relative links resolve against where the page was actually served from
+        let final_url = response.url().clone();
+        let headers = response.headers().clone();
+        
+        // Get the content
+        let content = response.text().await
+            .map_err(|e| format!("Error reading response from '{}': {}", url, e))?;
+        
+        if let Some(cache) = cache && let Some(expires_at) = http_cache::expires_at(&headers, ttl) {
+            let response = CachedResponse::new(final_url.as_str(), &headers, content.clone().into_bytes(), expires_at);
+            // A copy that is stale at once is only worth keeping if it can be revalidated
+            if (response.is_fresh() || response.can_revalidate()) && let Err(e) = cache.put(key, &response).await {
+                tracing::warn!("Writing to the HTTP cache failed: {}", e);
+            }
+        }
+        
+        Ok(FetchedPage { url: final_url, content_type, content, cached_at: None })
+    }
+    
+    /// Answer `prompt` from the page with the analysis model. Long pages are read
+    /// in chunks whose notes are then combined. Returns the answer, the number
+    /// of chunks and the number of characters read.
+    async fn analyze_with_model(
+        analysis: &Analysis<'_>,
+        content: &str,
+        prompt: &str,
+    ) -> anyhow::Result<(String, usize, usize)> {
+        let mut chunks = split_chunks(content, CHUNK_CHARS);
+        chunks.truncate(MAX_CHUNKS);
+        let read = chunks.iter().map(|chunk| chunk.chars().count()).sum();
+        
+        if let [page] = chunks.as_slice() {
+            let answer = analysis.complete(ANSWER_PROMPT, format!("Request: {}\n\nPage content:\n{}", prompt, page)).await?;
+            return Ok((answer, 1, read));
+        }
+        
+        let mut notes = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let note = analysis.complete(
+                EXTRACT_PROMPT,
+                format!("Request: {}\n\nPart {} of {}:\n{}", prompt, i + 1, chunks.len(), chunk),
+            ).await?;
+            if note.trim() != "NONE" {
+                notes.push(format!("Notes on part {}:\n{}", i + 1, note.trim()));
+            }
+        }
+        
+        let answer = if notes.is_empty() {
+            "None of the page's content bears on the request.".to_string()
+        } else {
+            analysis.complete(COMBINE_PROMPT, format!("Request: {}\n\n{}", prompt, notes.join("\n\n"))).await?
+        };
+        Ok((answer, chunks.len(), read))
+    }
+    
     fn analyze_content(&self, content: &str, prompt: &str) -> String {
         // Simple analysis based on the prompt
         // In a real implementation, this would use an AI model
@@ -226,4 +549,59 @@ impl WebFetchActor {
             )
         }
     }
-}
\ No newline at end of file
+}
+
+/// The analysis model, and the chat and tool call its usage is charged to
+struct Analysis<'a> {
+    client: &'a ActorRef<ClientMessage>,
+    chat_ref: &'a ActorRef<ChatMessage>,
+    id: Uuid,
+}
+
+impl Analysis<'_> {
+    /// One request to the analysis model
+    async fn complete(&self, instructions: &str, input: String) -> anyhow::Result<String> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.client.send_message(ClientMessage::Complete {
+            messages: vec![
+                OpenAIMessage::System { content: instructions.to_string(), name: None },
+                OpenAIMessage::User { content: UserContent::Text(input), name: None },
+            ],
+            reply_to: tx,
+        })?;
+        let reply = tokio::time::timeout(ANALYSIS_TIMEOUT, rx).await
+            .map_err(|_| anyhow::anyhow!("Timed out waiting for the analysis model"))?
+            .map_err(|_| anyhow::anyhow!("The analysis client dropped the request"))??;
+        let _ = self.chat_ref.send_message(ChatMessage::Usage {
+            id: self.id,
+            model: reply.model,
+            usage: reply.usage,
+            sub_agent: Some(WebFetchActor::NAME.to_string()),
+        });
+        Ok(reply.text.trim().to_string())
+    }
+}
+
+/// The byte index of the character at `chars`, or the end of the text
+fn byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices().nth(chars).map(|(i, _)| i).unwrap_or(text.len())
+}
+
+/// Split text into chunks of at most `size` bytes, at line breaks where possible
+fn split_chunks(text: &str, size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > size {
+        let mut end = size;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = rest[..end].rfind('\n').map(|i| i + 1).filter(|&i| i > size / 2).unwrap_or(end);
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    if !rest.trim().is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
@@ -1,33 +1,44 @@
 use ractor::{Actor, ActorRef, ActorProcessingErr};
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
-use scraper::{Html, Selector};
+use reqwest::Url;
+use std::sync::Arc;
 use std::time::Duration;
 use crate::config::Config;
 use crate::messages::{ToolMessage, ChatMessage};
+use crate::persistence::HttpCache;
+use crate::persistence::http_cache::{self, CachedResponse};
+use crate::search::{self, SearchBackend, SearchResult};
+use crate::url_policy::UrlPolicy;
+use super::base::{RunningExecutions, ToolDescription};
 
-/// Actor for performing web searches using DuckDuckGo
+/// How long one backend may take to answer
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Actor for performing web searches with the configured backends
+#[derive(Clone)]
 pub struct WebSearchActor {
-    #[allow(dead_code)]
     config: Config,
-    client: Client,
+    /// Search engines to try, in order, until one returns results, or why the
+    /// HTTP client they share couldn't be built
+    backends: Result<Arc<Vec<Box<dyn SearchBackend>>>, String>,
 }
 
 /// WebSearch actor state
-pub struct WebSearchState;
+pub struct WebSearchState {
+    /// Requests still running, by execution id
+    running: RunningExecutions,
+    /// Results of earlier searches, unless caching is turned off
+    cache: Option<HttpCache>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct WebSearchParams {
     query: String,
     #[serde(default = "default_limit")]
     limit: usize,
-}
-
-#[derive(Debug)]
-struct SearchResult {
-    title: String,
-    url: String,
-    description: String,
+    /// Search again even when the results of the same query are cached
+    #[serde(default)]
+    no_cache: bool,
 }
 
 fn default_limit() -> usize {
@@ -45,14 +56,23 @@ impl Actor for WebSearchActor {
         _config: Self::Arguments,
     ) -> Result<Self::State, ActorProcessingErr> {
         tracing::debug!("WebSearch actor starting");
-        Ok(WebSearchState)
+        let cache = match self.config.http_cache.enabled {
+            true => HttpCache::open(&self.config).await
+                .inspect_err(|e| tracing::warn!("web_search will not cache results: {}", e))
+                .ok(),
+            false => None,
+        };
+        Ok(WebSearchState {
+            running: RunningExecutions::default(),
+            cache,
+        })
     }
     
     async fn handle(
         &self,
         _myself: ActorRef<Self::Msg>,
         msg: Self::Msg,
-        _state: &mut Self::State,
+        state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
         match msg {
             ToolMessage::Execute { id, params, chat_ref } => {
@@ -70,147 +90,178 @@ impl Actor for WebSearchActor {
                     }
                 };
                 
-                // Execute search
-                let result = self.search(&search_params).await;
-                
-                // Send result back to chat
-                tracing::info!("Sending web search result back to chat actor");
-                chat_ref.send_message(ChatMessage::ToolResult {
-                    id,
-                    result,
-                })?;
+                // Search in the background so the request can be cancelled
+                let searcher = self.clone();
+                let cache = state.cache.clone();
+                state.running.spawn(id, async move {
+                    let result = searcher.search(&search_params, cache.as_ref()).await;
+                    tracing::info!("Sending web search result back to chat actor");
+                    let _ = chat_ref.send_message(ChatMessage::ToolResult { id, result });
+                });
             }
             
             ToolMessage::Cancel { id } => {
-                tracing::debug!("Cancelling web search operation {}", id);
-                // Web search operations are typically quick and not cancellable
+                if state.running.cancel(&id) {
+                    tracing::info!("Cancelled web search {}", id);
+                }
             }
             
             ToolMessage::StreamUpdate { .. } => {
                 // WebSearch doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // WebSearch keeps nothing per session
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for WebSearchActor {
+    const NAME: &'static str = "web_search";
+    const DESCRIPTION: &'static str = "Search the web for information. Supports complex natural language queries for comprehensive research. When delegated, a dedicated sub-agent can perform multiple searches and fetch pages to gather detailed information";
+    const READ_ONLY: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "The search query. Can be a simple search term or a detailed natural language request for comprehensive information gathering"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of results (default: 5)"
+                },
+                "no_cache": {
+                    "type": "boolean",
+                    "description": "Search again even if results for this query are cached (default: false)"
+                }
+            },
+            "required": ["query"]
+        })
+    }
+}
+
 impl WebSearchActor {
     pub fn new(config: Config) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
+        // Search engines named in the configuration may be on the local network
+        let configured = search::configured_backends(&config);
+        let hosts = configured.iter()
+            .filter_map(|backend| Url::parse(backend.base_url()?).ok())
+            .filter_map(|url| Some(url.host()?.to_string().trim_matches(['[', ']']).to_string()))
+            .collect::<Vec<_>>();
+        let client = UrlPolicy::new(&config.url_policy)
+            .trusting(hosts)
+            .client_builder()
+            .timeout(SEARCH_TIMEOUT)
             .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-            .build()
-            .unwrap_or_default();
+            .build();
+        let backends = client
+            .map(|client| Arc::new(search::create_backends(configured, &client)))
+            .map_err(|e| format!("Error: Cannot create the HTTP client: {}", e));
             
-        Self { config, client }
+        Self { config, backends }
     }
     
-    async fn search(&self, params: &WebSearchParams) -> String {
+    async fn search(&self, params: &WebSearchParams, cache: Option<&HttpCache>) -> String {
         // Validate query
-        if params.query.trim().is_empty() {
+        let query = params.query.trim();
+        if query.is_empty() {
             return String::from("Error: Search query cannot be empty");
         }
+        if params.limit == 0 {
+            return self.format_results(&params.query, "", Vec::new());
+        }
+        let backends = match &self.backends {
+            Ok(backends) if backends.is_empty() => {
+                return String::from("Error: No search backend is available; check tools.web_search.backends");
+            }
+            Ok(backends) => backends,
+            Err(e) => return e.clone(),
+        };
         
-        // Build search URL
-        let encoded_query = urlencoding::encode(&params.query);
-        let url = format!("https://html.duckduckgo.com/html/?q={}", encoded_query);
-        
-        tracing::info!("Performing web search: {}", url);
-        
-        // Perform search with timeout
-        let timeout_duration = std::time::Duration::from_secs(10);
-        match tokio::time::timeout(timeout_duration, self.client.get(&url).send()).await {
-            Ok(Ok(response)) => {
-                match response.text().await {
-                    Ok(html) => {
-                        tracing::info!("Got search response, parsing results");
-                        let results = self.parse_search_results(&html, params.limit);
-                        let formatted = self.format_results(&params.query, results);
-                        tracing::info!("Search completed successfully");
-                        formatted
-                    }
-                    Err(e) => {
-                        format!("Error reading response: {}", e)
-                    }
+        // Reuse the results of an earlier search for the same query, from any backend
+        if let Some(cache) = cache && !params.no_cache {
+            for backend in backends.iter() {
+                if let Some(results) = Self::cached_results(cache, &cache_key(backend.name(), query, params.limit)).await {
+                    tracing::info!("Using cached {} results for {:?}", backend.name(), query);
+                    return self.format_results(&params.query, backend.name(), results);
                 }
             }
-            Ok(Err(e)) => {
-                format!("Error performing search: {}", e)
-            }
-            Err(_) => {
-                format!("Error: Search request timed out after 10 seconds")
-            }
         }
-    }
-    
-    fn parse_search_results(&self, html: &str, limit: usize) -> Vec<SearchResult> {
-        let document = Html::parse_document(html);
-        let mut results = Vec::new();
-        
-        // DuckDuckGo HTML results structure:
-        // Results are in div with class "result results_links results_links_deep web-result"
-        // Title is in h2 > a.result__a
-        // URL is in a.result__a href attribute (needs extraction from redirect URL)
-        // Description is in a.result__snippet
         
-        let result_selector = Selector::parse("div.result.results_links.results_links_deep.web-result").unwrap();
-        let title_selector = Selector::parse("h2 > a.result__a").unwrap();
-        let snippet_selector = Selector::parse("a.result__snippet").unwrap();
-        
-        for result_element in document.select(&result_selector).take(limit) {
-            let title_element = result_element.select(&title_selector).next();
-            
-            let title = title_element
-                .as_ref()
-                .map(|el| el.text().collect::<String>())
-                .unwrap_or_default()
-                .trim()
-                .to_string();
-            
-            // Extract URL from the href attribute of the title link
-            let url = title_element
-                .and_then(|el| el.value().attr("href"))
-                .and_then(|href| {
-                    // DuckDuckGo wraps URLs in a redirect, extract the actual URL
-                    if href.contains("uddg=") {
-                        href.split("uddg=")
-                            .nth(1)
-                            .and_then(|u| u.split('&').next())
-                            .and_then(|u| urlencoding::decode(u).ok())
-                            .map(|u| u.into_owned())
-                    } else {
-                        Some(href.to_string())
+        // Fall back to the next backend when one fails or finds nothing
+        let mut failures = Vec::new();
+        let mut answered = false;
+        for backend in backends.iter() {
+            tracing::info!("Searching {} for {:?}", backend.name(), query);
+            let failure = match tokio::time::timeout(SEARCH_TIMEOUT, backend.search(query, params.limit)).await {
+                Ok(Ok(results)) if !results.is_empty() => {
+                    if let Some(cache) = cache {
+                        self.store_results(cache, &cache_key(backend.name(), query, params.limit), &results).await;
                     }
-                })
-                .unwrap_or_default();
-            
-            let description = result_element
-                .select(&snippet_selector)
-                .next()
-                .map(|el| el.text().collect::<String>())
-                .unwrap_or_default()
-                .trim()
-                .to_string();
-            
-            if !title.is_empty() && !url.is_empty() {
-                results.push(SearchResult {
-                    title,
-                    url,
-                    description,
-                });
-            }
+                    return self.format_results(&params.query, backend.name(), results);
+                }
+                Ok(Ok(_)) => {
+                    answered = true;
+                    "no results".to_string()
+                }
+                Ok(Err(e)) => match e.downcast_ref::<reqwest::Error>().and_then(UrlPolicy::violation) {
+                    Some(violation) => violation.to_string(),
+                    None => e.to_string(),
+                },
+                Err(_) => format!("timed out after {} seconds", SEARCH_TIMEOUT.as_secs()),
+            };
+            tracing::warn!("Searching {} failed: {}", backend.name(), failure);
+            failures.push(format!("{}: {}", backend.name(), failure));
         }
         
-        results
+        match answered {
+            true => self.format_results(&params.query, "", Vec::new()),
+            false => format!("Error: Every search backend failed:\n- {}", failures.join("\n- ")),
+        }
+    }
+    
+    /// Results stored under `key` that are still fresh
+    async fn cached_results(cache: &HttpCache, key: &str) -> Option<Vec<SearchResult>> {
+        let cached = cache.get(key).await
+            .inspect_err(|e| tracing::warn!("Reading the HTTP cache failed: {}", e))
+            .ok()??;
+        if !cached.is_fresh() {
+            return None;
+        }
+        serde_json::from_slice(&cached.body).ok()
     }
     
-    fn format_results(&self, query: &str, results: Vec<SearchResult>) -> String {
+    /// Keep results for `search_ttl_secs`, whatever the search engine's own
+    /// cache headers say
+    async fn store_results(&self, cache: &HttpCache, key: &str, results: &[SearchResult]) {
+        let ttl = Duration::from_secs(self.config.http_cache.search_ttl_secs);
+        let expires_at = http_cache::expires_at(&Default::default(), ttl).unwrap_or_else(chrono::Utc::now);
+        let response = CachedResponse {
+            url: key.to_string(),
+            content_type: Some("application/json".to_string()),
+            body: serde_json::to_vec(results).unwrap_or_default(),
+            etag: None,
+            last_modified: None,
+            stored_at: chrono::Utc::now(),
+            expires_at,
+        };
+        if let Err(e) = cache.put(key, &response).await {
+            tracing::warn!("Writing to the HTTP cache failed: {}", e);
+        }
+    }
+    
+    fn format_results(&self, query: &str, backend: &str, results: Vec<SearchResult>) -> String {
         if results.is_empty() {
             return format!("No results found for query: '{}'", query);
         }
         
-        let mut output = format!("Search results for '{}':\n\n", query);
+        let mut output = format!("Search results for '{}' from {}:\n\n", query, backend);
         
         for (i, result) in results.iter().enumerate() {
             output.push_str(&format!("{}. {}\n", i + 1, result.title));
@@ -224,4 +275,9 @@ impl WebSearchActor {
         output.push_str(&format!("Total results shown: {}", results.len()));
         output
     }
-}
\ No newline at end of file
+}
+
+/// Where a backend's results for a query are cached
+fn cache_key(backend: &str, query: &str, limit: usize) -> String {
+    format!("search:{}:{}:{}", backend, limit, query)
+}
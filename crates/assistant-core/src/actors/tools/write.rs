@@ -3,13 +3,20 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 use crate::config::Config;
+use crate::file_tracker::FileTracker;
 use crate::messages::{ToolMessage, ChatMessage};
-use crate::utils::path::validate_path_access;
+use crate::sandbox::Sandbox;
+use crate::utils::path::{PathAccess, validate_path_access};
+use super::base::ToolDescription;
 
 /// Actor for writing files
 pub struct WriteActor {
     #[allow(dead_code)]
     config: Config,
+    /// Where this tool may read or write
+    path_access: PathAccess,
+    /// Limits where files may be written when the sandbox is enabled
+    sandbox: Option<Sandbox>,
 }
 
 /// Write actor state
@@ -19,6 +26,9 @@ pub struct WriteState;
 struct WriteParams {
     file_path: String,
     content: String,
+    /// Filled in by the delegator with the calling session
+    #[serde(default)]
+    session_id: String,
 }
 
 impl Actor for WriteActor {
@@ -111,7 +121,16 @@ impl Actor for WriteActor {
                 };
                 
                 // Validate path access
-                if let Err(e) = validate_path_access(&canonical_path) {
+                if let Err(e) = validate_path_access(&canonical_path, &self.path_access) {
+                    chat_ref.send_message(ChatMessage::ToolResult {
+                        id,
+                        result: format!("Error: {}", e),
+                    })?;
+                    return Ok(());
+                }
+                if let Some(sandbox) = &self.sandbox
+                    && let Err(e) = sandbox.check_write(&canonical_path)
+                {
                     chat_ref.send_message(ChatMessage::ToolResult {
                         id,
                         result: format!("Error: {}", e),
@@ -119,10 +138,19 @@ impl Actor for WriteActor {
                     return Ok(());
                 }
                 
+                if let Err(stale) = FileTracker::global().check(&write_params.session_id, &canonical_path) {
+                    chat_ref.send_message(ChatMessage::ToolResult {
+                        id,
+                        result: format!("Error: {}", stale.describe("overwritten")),
+                    })?;
+                    return Ok(());
+                }
+                
                 // Update write_params with the canonical path
                 let canonical_params = WriteParams {
                     file_path: canonical_path.to_string_lossy().to_string(),
                     content: write_params.content,
+                    session_id: write_params.session_id,
                 };
                 
                 // Execute write operation
@@ -146,15 +174,44 @@ impl Actor for WriteActor {
             ToolMessage::StreamUpdate { .. } => {
                 // Write doesn't stream updates
             }
+            
+            ToolMessage::EndSession { .. } => {
+                // The delegator forgets the files the session saw
+            }
         }
         
         Ok(())
     }
 }
 
+impl ToolDescription for WriteActor {
+    const NAME: &'static str = "write";
+    const DESCRIPTION: &'static str = "Write content to a file";
+    const DESTRUCTIVE: bool = true;
+    
+    fn parameters() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "file_path": {
+                    "type": "string",
+                    "description": "The path to the file to write"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "The content to write to the file"
+                }
+            },
+            "required": ["file_path", "content"]
+        })
+    }
+}
+
 impl WriteActor {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let sandbox = Sandbox::from_config(&config);
+        let path_access = PathAccess::for_tool(&config, Self::NAME);
+        Self { config, path_access, sandbox }
     }
     
     fn write_file(&self, params: &WriteParams) -> Result<String, String> {
@@ -189,6 +246,7 @@ impl WriteActor {
         // Write the file
         fs::write(&path, &params.content)
             .map_err(|e| format!("Cannot write to file '{}': {}", params.file_path, e))?;
+        FileTracker::global().record(&params.session_id, path, params.content.as_bytes());
         
         // Get file info after write
         let metadata = fs::metadata(&path)
@@ -0,0 +1,207 @@
+//! Commits each turn's file changes to git, so the history shows what the agent
+//! changed and single turns can be reverted.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use git2::{DiffFormat, DiffOptions, Index, IndexEntry, IndexTime, Oid, Repository, Status};
+
+/// Trailer linking a commit to the chat session that made it
+pub const SESSION_TRAILER: &str = "Assistant-Session";
+
+/// Characters of diff shown to the model when it writes the commit message
+const MAX_DIFF_CHARS: usize = 20_000;
+
+pub const COMMIT_MESSAGE_PROMPT: &str = "You write git commit messages. Given a diff, reply with a commit message \
+    and nothing else: a summary line of at most 72 characters in the imperative mood (\"Add ...\", \"Fix ...\"), \
+    then, only if the change needs explaining, a blank line and a short body wrapped at 72 characters. \
+    Describe what changed and why, not how the diff looks.";
+
+/// Files a turn changed that differ from HEAD, ready to commit
+pub struct TurnChanges {
+    repo: Repository,
+    /// Paths relative to the working tree
+    paths: Vec<String>,
+    /// The files' contents when collected, `None` for deleted ones. Committed in
+    /// place of the working tree, which later turns may have changed since.
+    contents: Vec<Option<Vec<u8>>>,
+    /// The changes as a patch, shortened for the model
+    pub diff: String,
+}
+
+impl TurnChanges {
+    /// The changes to `paths` in the repository containing `workspace`, or `None`
+    /// if there are none. Paths outside the repository and ignored files are left out.
+    pub fn collect(workspace: &Path, paths: &[PathBuf]) -> Result<Option<Self>> {
+        let Some((repo, workdir)) = open(workspace) else {
+            return Ok(None);
+        };
+
+        let mut changed = Vec::new();
+        for path in paths {
+            if let Some(relative) = changed_path(&repo, &workdir, path)
+                && !changed.contains(&relative)
+            {
+                changed.push(relative);
+            }
+        }
+        if changed.is_empty() {
+            return Ok(None);
+        }
+
+        let diff = patch(&repo, &changed)?;
+        let contents = changed.iter().map(|path| std::fs::read(workdir.join(path)).ok()).collect();
+        Ok(Some(Self { repo, paths: changed, contents, diff }))
+    }
+
+    /// A plain message, for when none could be generated
+    pub fn fallback_message(&self) -> String {
+        match self.paths.as_slice() {
+            [path] => format!("Update {}", path),
+            paths => format!("Update {} files", paths.len()),
+        }
+    }
+
+    /// Commit the changed files as collected, and only them, on top of HEAD with `message`
+    /// and, given a session, the session trailer. Returns the commit's short id.
+    pub fn commit(&self, message: &str, session_id: &str) -> Result<String> {
+        let head = self.repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+
+        // Built from HEAD rather than the index, so whatever the user has staged stays out
+        let mut index = Index::new()?;
+        if let Some(head) = &head {
+            index.read_tree(&head.tree()?)?;
+        }
+        let mut staged = self.repo.index()?;
+        let workdir = self.repo.workdir().ok_or_else(|| anyhow!("The repository has no working tree"))?;
+        for (path, content) in self.paths.iter().zip(&self.contents) {
+            match content {
+                Some(content) => {
+                    let mode = index.get_path(Path::new(path), 0)
+                        .map(|entry| entry.mode)
+                        .unwrap_or_else(|| file_mode(&workdir.join(path)));
+                    let blob = self.repo.blob(content)?;
+                    index.add(&entry(path, mode, blob, content.len()))?;
+                    staged.add(&entry(path, mode, blob, content.len()))?;
+                }
+                None => {
+                    index.remove_path(Path::new(path))?;
+                    // Not in the index when the user already staged the deletion
+                    let _ = staged.remove_path(Path::new(path));
+                }
+            }
+        }
+        let tree = self.repo.find_tree(index.write_tree_to(&self.repo)?)?;
+
+        let signature = self.repo.signature()
+            .map_err(|_| anyhow!("Git has no author to commit as; set user.name and user.email in the git config"))?;
+        let message = match session_id {
+            "" => format!("{}\n", message.trim()),
+            session_id => format!("{}\n\n{}: {}\n", message.trim(), SESSION_TRAILER, session_id),
+        };
+        let parents: Vec<&git2::Commit> = head.iter().collect();
+        let oid = self.repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)?;
+        // Keeps `git status` from showing the committed changes as undone
+        staged.write()?;
+
+        Ok(oid.to_string()[..7].to_string())
+    }
+}
+
+/// Those of `paths` that differ from HEAD in the repository containing `workspace`.
+/// Checked before a turn changes the files, these are the user's own changes,
+/// which the turn's commit leaves alone.
+pub fn uncommitted(workspace: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let Some((repo, workdir)) = open(workspace) else {
+        return Vec::new();
+    };
+    paths.iter()
+        .filter(|path| changed_path(&repo, &workdir, path).is_some())
+        .cloned()
+        .collect()
+}
+
+/// Tidy the model's reply into a commit message: no code fences or quotes
+pub fn clean_message(reply: &str) -> String {
+    let reply = reply.trim();
+    let reply = reply.strip_prefix("```")
+        .map(|rest| rest.split_once('\n').map(|(_, body)| body).unwrap_or(rest))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .unwrap_or(reply);
+    reply.trim().trim_matches('"').trim().to_string()
+}
+
+/// The repository containing `workspace` and its working tree, if it has one
+fn open(workspace: &Path) -> Option<(Repository, PathBuf)> {
+    let repo = Repository::discover(workspace).ok()?;
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    Some((repo, workdir))
+}
+
+/// `path` relative to the working tree, if it differs from HEAD and isn't ignored
+fn changed_path(repo: &Repository, workdir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(workdir).ok()?.to_string_lossy().into_owned();
+    if repo.status_should_ignore(Path::new(&relative)).unwrap_or(true) {
+        return None;
+    }
+    match repo.status_file(Path::new(&relative)) {
+        Ok(status) if status != Status::CURRENT => Some(relative),
+        // Deleted files that were never committed have no status
+        _ => None,
+    }
+}
+
+/// The changes to `paths` against HEAD, new files included
+fn patch(repo: &Repository, paths: &[String]) -> Result<String> {
+    let mut options = DiffOptions::new();
+    options.include_untracked(true).show_untracked_content(true).recurse_untracked_dirs(true);
+    for path in paths {
+        options.pathspec(path);
+    }
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_workdir(head.as_ref(), Some(&mut options))?;
+
+    let mut patch = String::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin());
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        patch.len() < MAX_DIFF_CHARS
+    }).or_else(|e| if e.code() == git2::ErrorCode::User { Ok(()) } else { Err(e) })?;
+    if patch.len() >= MAX_DIFF_CHARS {
+        patch.push_str("\n[diff truncated]\n");
+    }
+    Ok(patch)
+}
+
+fn entry(path: &str, mode: u32, id: Oid, size: usize) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o111 != 0 => 0o100755,
+        _ => 0o100644,
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> u32 {
+    0o100644
+}
@@ -14,6 +14,10 @@ pub struct Config {
     /// Primary API key
     pub api_key: String,
     
+    /// LLM provider backend ("openai" for any OpenAI-compatible API, or "anthropic")
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    
     /// Base URL for API
     #[serde(default = "default_base_url")]
     pub base_url: String,
@@ -30,10 +34,38 @@ pub struct Config {
     #[serde(default = "default_max_tokens")]
     pub max_tokens: u32,
     
+    /// Retry policy for LLM requests
+    #[serde(default)]
+    pub retry: RetryConfig,
+    
+    /// Per-model token prices used for cost accounting
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPricing>,
+    
+    /// Context window budget and compaction settings
+    #[serde(default)]
+    pub context: ContextConfig,
+    
     /// Tool configurations
     #[serde(default)]
     pub tools: ToolsConfig,
     
+    /// Rules deciding which tool calls run, are refused or need confirmation
+    #[serde(default)]
+    pub permissions: PermissionsConfig,
+    
+    /// Restrictions on what `bash` commands and the file tools may change
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    
+    /// Cache of `web_fetch` and `web_search` responses
+    #[serde(default)]
+    pub http_cache: HttpCacheConfig,
+    
+    /// Which URLs `web_fetch` and `web_search` may request
+    #[serde(default)]
+    pub url_policy: UrlPolicyConfig,
+    
     /// Telemetry settings
     #[serde(default)]
     pub telemetry: TelemetryConfig,
@@ -58,6 +90,259 @@ pub struct ToolsConfig {
     pub configs: HashMap<String, ToolConfig>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionsConfig {
+    /// Rules checked in order; the first one matching a tool call decides it
+    #[serde(default)]
+    pub rules: Vec<PermissionRule>,
+    
+    /// Action for calls no rule matches
+    #[serde(default = "default_permission_action")]
+    pub default_action: PermissionAction,
+    
+    /// Action for unmatched calls to tools that modify files or the system
+    #[serde(default = "default_destructive_action")]
+    pub destructive_action: PermissionAction,
+}
+
+impl Default for PermissionsConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default_action: default_permission_action(),
+            destructive_action: default_destructive_action(),
+        }
+    }
+}
+
+/// A permission rule for one tool, optionally narrowed to matching arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionRule {
+    /// Tool name, `*` for every tool
+    pub tool: String,
+    
+    /// Wildcard pattern (`*`, `?`) matched against the call's main argument,
+    /// e.g. the command for `bash` or the file path for `write`
+    #[serde(default)]
+    pub pattern: Option<String>,
+    
+    pub action: PermissionAction,
+}
+
+/// Sandbox for `bash` commands (Landlock and resource limits, Linux only) and for
+/// the files `write` and `edit` change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// Restrict writes to the workspace, the temp directories and `writable_paths`
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Directories writable besides the workspace and the temp directories
+    #[serde(default)]
+    pub writable_paths: Vec<PathBuf>,
+    
+    /// Whether commands may bind or connect TCP sockets
+    #[serde(default = "default_true")]
+    pub allow_network: bool,
+    
+    /// CPU time a command may use, in seconds
+    #[serde(default)]
+    pub max_cpu_seconds: Option<u64>,
+    
+    /// Address space a command may map, in megabytes
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    
+    /// Processes the user may run at once while a command runs (not enforced for root)
+    #[serde(default)]
+    pub max_processes: Option<u64>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            writable_paths: Vec::new(),
+            allow_network: true,
+            max_cpu_seconds: None,
+            max_memory_mb: None,
+            max_processes: None,
+        }
+    }
+}
+
+/// Cache of fetched pages and search results, kept in the sessions database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpCacheConfig {
+    /// Whether responses are cached
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// How long a fetched page is reused when its response doesn't say, in seconds
+    #[serde(default = "default_fetch_ttl_secs")]
+    pub fetch_ttl_secs: u64,
+    
+    /// How long search results are reused, in seconds
+    #[serde(default = "default_search_ttl_secs")]
+    pub search_ttl_secs: u64,
+    
+    /// Most the cache may hold, in megabytes; the least recently used responses go first
+    #[serde(default = "default_http_cache_max_mb")]
+    pub max_size_mb: u64,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fetch_ttl_secs: default_fetch_ttl_secs(),
+            search_ttl_secs: default_search_ttl_secs(),
+            max_size_mb: default_http_cache_max_mb(),
+        }
+    }
+}
+
+/// Limits on the URLs the web tools request, so a model can't reach internal
+/// services through them. Every redirect is checked too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlPolicyConfig {
+    /// URL schemes that may be requested
+    #[serde(default = "default_allowed_schemes")]
+    pub allowed_schemes: Vec<String>,
+    
+    /// When not empty, only these domains and their subdomains may be requested
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    
+    /// Domains that may never be requested, nor their subdomains
+    #[serde(default)]
+    pub denied_domains: Vec<String>,
+    
+    /// Whether hosts on this machine (127.0.0.0/8, ::1) may be requested
+    #[serde(default)]
+    pub allow_loopback: bool,
+    
+    /// Whether private, shared and link-local addresses may be requested, such
+    /// as 10.0.0.0/8, 192.168.0.0/16 or the cloud metadata endpoint 169.254.169.254
+    #[serde(default)]
+    pub allow_private_networks: bool,
+}
+
+impl Default for UrlPolicyConfig {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: default_allowed_schemes(),
+            allowed_domains: Vec::new(),
+            denied_domains: Vec::new(),
+            allow_loopback: false,
+            allow_private_networks: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionAction {
+    Allow,
+    Ask,
+    Deny,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Maximum number of retries after the first attempt (0 disables retries)
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    
+    /// Delay before the first retry, doubled on each subsequent attempt
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    
+    /// Upper bound for any single delay, including server-requested ones
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    
+    /// Randomise delays so concurrent clients don't retry in lockstep
+    #[serde(default = "default_true")]
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            jitter: true,
+        }
+    }
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    /// Cost in USD of the given prompt and completion token counts
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.input_per_million
+            + completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextConfig {
+    /// Context window in tokens for models without an entry in `windows`
+    #[serde(default = "default_context_window")]
+    pub context_window: usize,
+    
+    /// Per-model context windows in tokens
+    #[serde(default)]
+    pub windows: HashMap<String, usize>,
+    
+    /// Fraction of the context window the prompt may fill before older turns are compacted
+    #[serde(default = "default_compact_threshold")]
+    pub compact_threshold: f32,
+    
+    /// Number of most recent user turns kept verbatim when compacting
+    #[serde(default = "default_keep_recent_turns")]
+    pub keep_recent_turns: usize,
+    
+    /// Paths to HuggingFace `tokenizer.json` files per model; others use a character heuristic
+    #[serde(default)]
+    pub tokenizers: HashMap<String, PathBuf>,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            context_window: default_context_window(),
+            windows: HashMap::new(),
+            compact_threshold: default_compact_threshold(),
+            keep_recent_turns: default_keep_recent_turns(),
+            tokenizers: HashMap::new(),
+        }
+    }
+}
+
+impl ContextConfig {
+    /// Context window of a model in tokens
+    pub fn window_for(&self, model: &str) -> usize {
+        lookup_model(&self.windows, model).copied().unwrap_or(self.context_window)
+    }
+    
+    /// Prompt tokens allowed before compaction, leaving room for `max_tokens` of output
+    pub fn budget_for(&self, model: &str, max_tokens: u32) -> usize {
+        let window = self.window_for(model);
+        let threshold = (window as f32 * self.compact_threshold) as usize;
+        threshold.min(window.saturating_sub(max_tokens as usize))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TelemetryConfig {
     #[serde(default)]
@@ -73,11 +358,27 @@ pub struct SessionConfig {
     /// Explicit session ID (used with SessionMode::Explicit)
     pub session_id: Option<String>,
     
-    /// Workspace path for session context
+    /// Workspace path for session context. When set, the file tools are confined to it
     pub workspace_path: Option<PathBuf>,
     
+    /// Directories outside the workspace the file tools may also use
+    #[serde(default)]
+    pub extra_roots: Vec<PathBuf>,
+    
     /// Database path (defaults to ~/.assistant/assistant.db)
     pub database_path: Option<PathBuf>,
+    
+    /// Save files' contents before the agent changes them, so turns can be undone
+    #[serde(default = "default_true")]
+    pub checkpoints: bool,
+    
+    /// Work in a new git worktree and branch instead of the workspace itself
+    #[serde(default)]
+    pub worktree: bool,
+    
+    /// Commit the files each turn changed, with a message written by the model
+    #[serde(default)]
+    pub auto_commit: bool,
 }
 
 impl Default for SessionConfig {
@@ -86,7 +387,11 @@ impl Default for SessionConfig {
             mode: SessionMode::default(),
             session_id: None,
             workspace_path: None,
+            extra_roots: Vec::new(),
             database_path: None,
+            checkpoints: true,
+            worktree: false,
+            auto_commit: false,
         }
     }
 }
@@ -156,6 +461,58 @@ impl Default for EmbeddingConfig {
     }
 }
 
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_ms() -> u64 {
+    60_000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_permission_action() -> PermissionAction {
+    PermissionAction::Allow
+}
+
+fn default_destructive_action() -> PermissionAction {
+    PermissionAction::Ask
+}
+
+fn default_context_window() -> usize {
+    128_000
+}
+
+fn default_compact_threshold() -> f32 {
+    0.8
+}
+
+fn default_keep_recent_turns() -> usize {
+    2
+}
+
+fn default_fetch_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_search_ttl_secs() -> u64 {
+    86400
+}
+
+fn default_http_cache_max_mb() -> u64 {
+    100
+}
+
+fn default_allowed_schemes() -> Vec<String> {
+    vec!["http".to_string(), "https".to_string()]
+}
+
 fn default_cache_size() -> usize {
     1000
 }
@@ -164,6 +521,10 @@ fn default_embedding_model() -> String {
     "openai-small".to_string()
 }
 
+fn default_provider() -> String {
+    "openai".to_string()
+}
+
 fn default_base_url() -> String {
     "https://api.openai.com/v1".to_string()
 }
@@ -184,11 +545,19 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             api_key: "test-api-key".to_string(),
+            provider: default_provider(),
             base_url: default_base_url(),
             model: default_model(),
             temperature: default_temperature(),
             max_tokens: default_max_tokens(),
+            retry: RetryConfig::default(),
+            pricing: HashMap::new(),
+            context: ContextConfig::default(),
             tools: ToolsConfig::default(),
+            permissions: PermissionsConfig::default(),
+            sandbox: SandboxConfig::default(),
+            http_cache: HttpCacheConfig::default(),
+            url_policy: UrlPolicyConfig::default(),
             telemetry: TelemetryConfig::default(),
             session: SessionConfig::default(),
             embeddings: EmbeddingConfig::default(),
@@ -197,6 +566,12 @@ impl Default for Config {
 }
 
 impl Config {
+    /// Look up pricing for a model, falling back to the name without a
+    /// provider prefix (e.g. `anthropic/claude-sonnet-4` -> `claude-sonnet-4`)
+    pub fn pricing_for(&self, model: &str) -> Option<ModelPricing> {
+        lookup_model(&self.pricing, model).copied()
+    }
+    
     /// Load configuration from file
     pub fn load(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
@@ -218,4 +593,10 @@ impl Config {
     pub fn get_tool_config(&self, tool_name: &str) -> Option<&ToolConfig> {
         self.tools.configs.get(tool_name)
     }
-}
\ No newline at end of file
+}
+
+/// Look up a per-model setting, falling back to the model name without its provider prefix
+fn lookup_model<'a, T>(map: &'a HashMap<String, T>, model: &str) -> Option<&'a T> {
+    map.get(model)
+        .or_else(|| model.rsplit_once('/').and_then(|(_, name)| map.get(name)))
+}
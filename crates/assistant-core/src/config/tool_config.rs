@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// Configuration for an individual tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +16,9 @@ pub struct ToolConfig {
     /// API key for delegated tool
     pub api_key: Option<String>,
     
+    /// LLM provider for delegated tool ("openai" or "anthropic")
+    pub provider: Option<String>,
+    
     /// Base URL for delegated tool
     pub base_url: Option<String>,
     
@@ -31,6 +35,10 @@ pub struct ToolConfig {
     #[serde(default = "default_true")]
     pub use_tool_api: bool,
     
+    /// Paths outside the workspace this tool may still use, e.g. `~/.cargo/registry` for `read`
+    #[serde(default)]
+    pub allowed_paths: Vec<PathBuf>,
+    
     /// Tool-specific settings
     #[serde(flatten)]
     pub settings: HashMap<String, serde_json::Value>,
@@ -81,11 +89,13 @@ impl Default for ToolConfig {
             enabled: true,
             delegate: false,
             api_key: None,
+            provider: None,
             base_url: None,
             model: None,
             temperature: None,
             system_prompt: None,
             use_tool_api: true,
+            allowed_paths: Vec::new(),
             settings: HashMap::new(),
         }
     }
@@ -104,11 +114,13 @@ impl ToolConfig {
             enabled: true,
             delegate: true,
             api_key: Some(api_key),
+            provider: None,
             base_url: None,
             model: Some(model),
             temperature: None,
             system_prompt: Some(system_prompt),
             use_tool_api: true,
+            allowed_paths: Vec::new(),
             settings: HashMap::new(),
         }
     }
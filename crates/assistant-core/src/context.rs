@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+use crate::config::ContextConfig;
+use crate::openai_compat::{ChatMessage as OpenAIMessage, ContentPart, Tool, UserContent};
+
+/// Marks the system message that replaces compacted turns
+pub const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:\n";
+
+/// Instructions for the model that writes the compaction summary
+pub const COMPACTION_PROMPT: &str = "You compact conversations between a user and an AI assistant that uses tools. \
+Summarize the transcript you are given so the assistant can continue the conversation without it. \
+Keep the user's goals and preferences, decisions made, facts learned from tool results, file paths, \
+commands and any work still in progress. Be concise. Respond with ONLY the summary.";
+
+/// Tokens charged for framing each message (role, separators)
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Rough upper bound for a high-detail image
+const IMAGE_TOKENS: usize = 1_000;
+
+/// Longest tool result, in characters, included in a compaction transcript
+const MAX_TRANSCRIPT_TOOL_RESULT: usize = 2_000;
+
+/// Counts prompt tokens with the model's tokenizer when one is configured,
+/// otherwise with a four-characters-per-token heuristic
+#[derive(Clone, Default)]
+pub struct TokenCounter {
+    tokenizer: Option<Arc<Tokenizer>>,
+}
+
+impl TokenCounter {
+    /// Create a counter for `model`, loading its tokenizer from `context.tokenizers` if listed
+    pub fn new(context: &ContextConfig, model: &str) -> Self {
+        let path = context.tokenizers.get(model)
+            .or_else(|| model.rsplit_once('/').and_then(|(_, name)| context.tokenizers.get(name)));
+
+        let tokenizer = path.and_then(|path| match Tokenizer::from_file(path) {
+            Ok(tokenizer) => Some(Arc::new(tokenizer)),
+            Err(e) => {
+                tracing::warn!("Failed to load tokenizer {:?} for {}, using heuristic: {}", path, model, e);
+                None
+            }
+        });
+
+        Self { tokenizer }
+    }
+
+    /// Count the tokens in a piece of text
+    pub fn count_text(&self, text: &str) -> usize {
+        if let Some(tokenizer) = &self.tokenizer
+            && let Ok(encoding) = tokenizer.encode(text, false)
+        {
+            return encoding.len();
+        }
+
+        text.len().div_ceil(4)
+    }
+
+    /// Count the tokens a list of messages will take up in the prompt
+    pub fn count_messages(&self, messages: &[OpenAIMessage]) -> usize {
+        messages.iter().map(|message| MESSAGE_OVERHEAD + self.count_message(message)).sum()
+    }
+
+    /// Count the tokens taken up by tool definitions
+    pub fn count_tools(&self, tools: &[Tool]) -> usize {
+        if tools.is_empty() {
+            return 0;
+        }
+        self.count_text(&serde_json::to_string(tools).unwrap_or_default())
+    }
+
+    fn count_message(&self, message: &OpenAIMessage) -> usize {
+        match message {
+            OpenAIMessage::System { content, .. } | OpenAIMessage::Tool { content, .. } => self.count_text(content),
+            OpenAIMessage::User { content: UserContent::Text(text), .. } => self.count_text(text),
+            OpenAIMessage::User { content: UserContent::Array(parts), .. } => parts
+                .iter()
+                .map(|part| match part {
+                    ContentPart::Text { text } => self.count_text(text),
+                    ContentPart::Image { .. } => IMAGE_TOKENS,
+                })
+                .sum(),
+            OpenAIMessage::Assistant { content, tool_calls, .. } => {
+                let content = content.as_deref().map(|c| self.count_text(c)).unwrap_or_default();
+                let calls: usize = tool_calls
+                    .iter()
+                    .flatten()
+                    .map(|call| self.count_text(&call.function.name) + self.count_text(&call.function.arguments))
+                    .sum();
+                content + calls
+            }
+        }
+    }
+}
+
+/// Whether a message is a compaction summary
+pub fn is_summary(message: &OpenAIMessage) -> bool {
+    matches!(message, OpenAIMessage::System { content, .. } if content.starts_with(SUMMARY_PREFIX))
+}
+
+/// The system message that stands in for compacted turns
+pub fn summary_message(summary: &str) -> OpenAIMessage {
+    OpenAIMessage::System {
+        content: format!("{}{}", SUMMARY_PREFIX, summary.trim()),
+        name: None,
+    }
+}
+
+/// Choose which messages to compact, as the range `start..end`.
+///
+/// The leading system prompt is always kept, and so are the last `keep_recent_turns`
+/// turns, where a turn starts at a user message. Splitting only at turn boundaries
+/// keeps every assistant tool call together with its results, including calls
+/// that are still waiting for a result. Returns `None` if there is nothing to compact.
+pub fn compaction_range(messages: &[OpenAIMessage], keep_recent_turns: usize) -> Option<(usize, usize)> {
+    let start = match messages.first() {
+        Some(OpenAIMessage::System { .. }) if !is_summary(&messages[0]) => 1,
+        _ => 0,
+    };
+
+    let turn_starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .skip(start)
+        .filter(|(_, message)| matches!(message, OpenAIMessage::User { .. }))
+        .map(|(index, _)| index)
+        .collect();
+
+    let keep = keep_recent_turns.max(1);
+    if turn_starts.len() <= keep {
+        return None;
+    }
+    let end = turn_starts[turn_starts.len() - keep];
+
+    // A lone previous summary is not worth summarizing again
+    if messages[start..end].iter().all(is_summary) {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+/// Render messages as a plain-text transcript for the summarizer
+pub fn render_transcript(messages: &[OpenAIMessage]) -> String {
+    let mut transcript = String::new();
+
+    for message in messages {
+        match message {
+            OpenAIMessage::System { content, .. } => {
+                transcript.push_str(&format!("[system]\n{}\n\n", content));
+            }
+            OpenAIMessage::User { content, .. } => {
+                let text = match content {
+                    UserContent::Text(text) => text.clone(),
+                    UserContent::Array(parts) => parts
+                        .iter()
+                        .map(|part| match part {
+                            ContentPart::Text { text } => text.as_str(),
+                            ContentPart::Image { .. } => "[image]",
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                transcript.push_str(&format!("[user]\n{}\n\n", text));
+            }
+            OpenAIMessage::Assistant { content, tool_calls, .. } => {
+                transcript.push_str("[assistant]\n");
+                if let Some(content) = content {
+                    transcript.push_str(content);
+                    transcript.push('\n');
+                }
+                for call in tool_calls.iter().flatten() {
+                    transcript.push_str(&format!("called {}({})\n", call.function.name, call.function.arguments));
+                }
+                transcript.push('\n');
+            }
+            OpenAIMessage::Tool { content, .. } => {
+                let mut result: String = content.chars().take(MAX_TRANSCRIPT_TOOL_RESULT).collect();
+                if result.len() < content.len() {
+                    result.push_str("... [truncated]");
+                }
+                transcript.push_str(&format!("[tool result]\n{}\n\n", result));
+            }
+        }
+    }
+
+    transcript
+}
@@ -0,0 +1,153 @@
+//! Remembers what each session last saw of the files it read or wrote, so the
+//! file tools can refuse to overwrite changes the model hasn't seen.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use similar::TextDiff;
+
+use crate::utils::path::normalize_path;
+
+/// Files larger than this are tracked by hash alone, without a diff on conflict
+const MAX_KEPT_CONTENT: usize = 256 * 1024;
+
+/// Lines of context around each change in a conflict diff
+const DIFF_CONTEXT: usize = 3;
+
+/// A file as a session last saw it
+struct SeenFile {
+    hash: u64,
+    len: u64,
+    modified: Option<SystemTime>,
+    /// The text itself, kept to show what changed since
+    content: Option<String>,
+}
+
+/// The last-seen state of files, per session
+#[derive(Default)]
+pub struct FileTracker {
+    files: Mutex<HashMap<(String, PathBuf), SeenFile>>,
+}
+
+/// A file that changed on disk after the session last read or wrote it
+#[derive(Debug)]
+pub struct StaleFile {
+    pub path: PathBuf,
+    /// When the file was last modified, if known
+    pub modified: Option<SystemTime>,
+    /// Unified diff from the contents the session saw to the current ones
+    pub diff: Option<String>,
+}
+
+impl FileTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tracker shared by the file tools
+    pub fn global() -> &'static FileTracker {
+        static TRACKER: OnceLock<FileTracker> = OnceLock::new();
+        TRACKER.get_or_init(FileTracker::new)
+    }
+
+    /// Note that a session has seen `content` as the contents of `path`, after
+    /// reading or writing it
+    pub fn record(&self, session_id: &str, path: &Path, content: &[u8]) {
+        let metadata = std::fs::metadata(path).ok();
+        let seen = SeenFile {
+            hash: hash(content),
+            len: content.len() as u64,
+            modified: metadata.and_then(|m| m.modified().ok()),
+            content: (content.len() <= MAX_KEPT_CONTENT)
+                .then(|| String::from_utf8(content.to_vec()).ok())
+                .flatten(),
+        };
+        self.lock().insert(key(session_id, path), seen);
+    }
+
+    /// Stop tracking a file, e.g. after the session deleted it
+    pub fn forget(&self, session_id: &str, path: &Path) {
+        self.lock().remove(&key(session_id, path));
+    }
+
+    /// Check that `path` is as the session last saw it. Files the session never
+    /// read, and files that no longer exist, are never stale.
+    pub fn check(&self, session_id: &str, path: &Path) -> Result<(), StaleFile> {
+        let files = self.lock();
+        let Some(seen) = files.get(&key(session_id, path)) else {
+            return Ok(());
+        };
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok(());
+        };
+        let modified = metadata.modified().ok();
+        if metadata.len() == seen.len && modified.is_some() && modified == seen.modified {
+            return Ok(());
+        }
+
+        // The modification time alone isn't proof; the contents may be the same
+        let Ok(current) = std::fs::read(path) else {
+            return Ok(());
+        };
+        if current.len() as u64 == seen.len && hash(&current) == seen.hash {
+            return Ok(());
+        }
+
+        let diff = seen.content.as_ref()
+            .zip(String::from_utf8(current).ok())
+            .map(|(before, after)| {
+                TextDiff::from_lines(before.as_str(), after.as_str())
+                    .unified_diff()
+                    .context_radius(DIFF_CONTEXT)
+                    .header("last read", "on disk now")
+                    .to_string()
+            });
+        Err(StaleFile { path: path.to_path_buf(), modified, diff })
+    }
+
+    /// Forget every file a session saw, once the session has ended
+    pub fn end_session(&self, session_id: &str) {
+        self.lock().retain(|(session, _), _| session != session_id);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, PathBuf), SeenFile>> {
+        // The map stays consistent even if a holder panicked
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StaleFile {
+    /// Explain the conflict to the model, `action` being what was refused
+    pub fn describe(&self, action: &str) -> String {
+        let when = self.modified
+            .map(|time| format!(" (modified {})", DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S")))
+            .unwrap_or_default();
+        let changes = match &self.diff {
+            Some(diff) => format!("Changes since then:\n```diff\n{}```\n", diff),
+            None => "It is too large or not text, so the changes aren't shown.\n".to_string(),
+        };
+        format!(
+            "'{}' has changed on disk since you last read it{}, so it was not {}. {}Read the file again and redo the change against its current contents.",
+            self.path.display(),
+            when,
+            action,
+            changes
+        )
+    }
+}
+
+/// Paths are compared once resolved, as the tools name them in different ways
+fn key(session_id: &str, path: &Path) -> (String, PathBuf) {
+    let path = normalize_path(path).unwrap_or_else(|_| path.to_path_buf());
+    (session_id.to_string(), path)
+}
+
+fn hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
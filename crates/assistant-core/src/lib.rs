@@ -1,11 +1,19 @@
 pub mod actors;
 pub mod actor_init;
+pub mod auto_commit;
 pub mod config;
+pub mod context;
 pub mod embeddings;
+pub mod file_tracker;
 pub mod messages;
 pub mod openai_compat;
+pub mod permissions;
 pub mod persistence;
+pub mod sandbox;
+pub mod search;
+pub mod url_policy;
 pub mod utils;
+pub mod worktree;
 
 // Re-export commonly used types
 pub use config::Config;
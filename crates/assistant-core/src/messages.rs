@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 use ractor::ActorRef;
 
@@ -47,6 +48,9 @@ pub enum ChatMessage {
     /// Tool execution result
     ToolResult { id: Uuid, result: String },
     
+    /// Output of a tool that is still running, shown by displays but not sent to the model
+    ToolOutput { id: Uuid, output: String },
+    
     /// Assistant response (may include tool calls)
     AssistantResponse { 
         id: Uuid, 
@@ -60,6 +64,39 @@ pub enum ChatMessage {
     /// Error during processing
     Error { id: Uuid, error: String },
     
+    /// Cancel the request in progress: stop generating, stop running tools and
+    /// record them as cancelled
+    Cancel,
+    
+    /// Token usage reported by the provider for one completion
+    Usage {
+        id: Uuid,
+        model: String,
+        usage: TokenUsage,
+        /// Name of the delegated sub-agent that incurred it, if any
+        sub_agent: Option<String>,
+    },
+    
+    /// The summary a compaction asked for, to replace messages `start..end`
+    /// with. The chat sends it to itself once the model has answered.
+    Compacted {
+        /// The request, or `/compact` prompt, the compaction was started for
+        id: Uuid,
+        start: usize,
+        end: usize,
+        /// Prompt tokens before compacting
+        before: usize,
+        /// Whether the user asked for it with `/compact`
+        force: bool,
+        summary: Result<crate::actors::client::Completion, String>,
+    },
+    
+    /// UI update forwarded to display actors
+    UIUpdate(UIMessage),
+    
+    /// A display's answer to a `UIMessage::ShowConfirmation`
+    ConfirmationResponse { id: Uuid, decision: ConfirmationDecision },
+    
     /// Set delegator actor reference
     SetDelegatorRef(ActorRef<DelegatorMessage>),
     
@@ -73,12 +110,17 @@ pub enum ChatMessage {
     SwitchSession { 
         session_id: String, 
         messages: Vec<crate::openai_compat::ChatMessage>,
+        /// The id each of `messages` is stored under, if it was loaded from the database
+        row_ids: Vec<Option<Uuid>>,
     },
 }
 
 /// Tool call information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
+    /// Provider-assigned tool call id, echoed back with the tool result
+    #[serde(default)]
+    pub id: String,
     pub tool_name: String,
     pub parameters: serde_json::Value,
     pub delegate: bool,
@@ -99,6 +141,9 @@ pub enum ToolMessage {
     
     /// Stream partial output
     StreamUpdate { id: Uuid, output: String },
+    
+    /// A session ended; drop what the tool kept for it
+    EndSession { session_id: String },
 }
 
 /// UI display messages
@@ -140,11 +185,53 @@ pub enum ConfirmationType {
     WebAccess { url: String },
 }
 
+/// The user's answer to a confirmation request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfirmationDecision {
+    /// Run this call
+    Allow,
+    /// Run this call and don't ask again for the same call in this session
+    AllowForSession,
+    /// Refuse this call
+    Deny,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Stats {
     pub tokens_used: usize,
     pub tools_executed: usize,
     pub session_duration: u64,
+    /// Usage of the current request, including any sub-agents it delegated to
+    #[serde(default)]
+    pub request: TokenUsage,
+    /// Usage of the whole session
+    #[serde(default)]
+    pub session: TokenUsage,
+    /// Session usage broken down by sub-agent tool name
+    #[serde(default)]
+    pub sub_agents: HashMap<String, TokenUsage>,
+}
+
+/// Prompt/completion token counts and their cost in USD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+impl TokenUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+    }
 }
 
 /// Delegator messages for tool routing
@@ -155,8 +242,21 @@ pub enum DelegatorMessage {
         id: Uuid,
         call: ToolCall,
         chat_ref: ActorRef<ChatMessage>,
+        /// Session the call belongs to, which scopes remembered approvals
+        session_id: String,
+        /// User request the call was made for; file checkpoints are grouped by it
+        turn: Uuid,
+    },
+    
+    /// The user's answer to a confirmation the delegator asked for
+    ConfirmationResponse {
+        id: Uuid,
+        decision: ConfirmationDecision,
     },
     
+    /// Stop a tool call that was routed or is waiting for confirmation
+    Cancel { id: Uuid },
+    
     /// Register a tool actor
     RegisterTool {
         name: String,
@@ -167,7 +267,19 @@ pub enum DelegatorMessage {
     SubAgentResponse {
         id: Uuid,
         result: String,
+        usage: Option<SubAgentUsage>,
     },
+    
+    /// A session ended; drop what was kept for it
+    EndSession { session_id: String },
+}
+
+/// Tokens a sub-agent spent answering one delegated request
+#[derive(Debug, Clone)]
+pub struct SubAgentUsage {
+    pub tool_name: String,
+    pub model: String,
+    pub usage: TokenUsage,
 }
 
 /// Supervisor messages for actor lifecycle
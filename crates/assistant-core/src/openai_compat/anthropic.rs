@@ -0,0 +1,556 @@
+use anyhow::Result;
+use futures::stream::StreamExt;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use eventsource_stream::Eventsource;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use crate::config::Config;
+use super::provider::ChatCompletionStream;
+use super::retry::ProviderError;
+use super::types::*;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Native Anthropic Messages API client.
+///
+/// Translates our OpenAI-shaped requests into Messages API requests (top-level
+/// system prompt, `tool_use`/`tool_result` blocks, image blocks) and maps the
+/// streamed events back into `ChatCompletionChunk`s.
+pub struct AnthropicClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+/// Messages API request body
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: String,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: ImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    /// Blocks we don't map (e.g. thinking)
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+/// Non-streaming Messages API response
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    id: String,
+    model: String,
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+/// Server-sent events emitted by a streaming Messages API call
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: MessageStart,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: BlockDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: MessageDeltaBody,
+        #[serde(default)]
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: AnthropicError,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageStart {
+    id: String,
+    model: String,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    /// Deltas we don't map (e.g. thinking, signatures)
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageDeltaBody {
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicErrorResponse {
+    error: AnthropicError,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicError {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+}
+
+impl AnthropicError {
+    /// HTTP status equivalent for errors delivered inside an event stream
+    fn status(&self) -> StatusCode {
+        match self.error_type.as_str() {
+            "overloaded_error" => StatusCode::SERVICE_UNAVAILABLE,
+            "rate_limit_error" => StatusCode::TOO_MANY_REQUESTS,
+            "api_error" => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl AnthropicClient {
+    pub fn new(config: &Config) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-api-key",
+            HeaderValue::from_str(&config.api_key).expect("Invalid API key format"),
+        );
+        headers.insert("anthropic-version", HeaderValue::from_static(ANTHROPIC_VERSION));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(std::time::Duration::from_secs(120))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Create a streaming chat completion
+    pub async fn create_chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionStream> {
+        let body = build_messages_request(request, true);
+        let response = self.send(&body).await?;
+
+        let event_stream = response.bytes_stream().eventsource();
+
+        let stream = async_stream::stream! {
+            futures::pin_mut!(event_stream);
+
+            let mut message_id = String::new();
+            let mut model = String::new();
+            let mut input_tokens = 0;
+            // Tool blocks by index: (initial input, whether any input_json_delta arrived)
+            let mut tool_blocks: HashMap<usize, (Value, bool)> = HashMap::new();
+
+            while let Some(event) = event_stream.next().await {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::error!("SSE error: {}", e);
+                        yield Err(ProviderError::Stream(e.to_string()).into());
+                        break;
+                    }
+                };
+
+                if event.data.trim().is_empty() {
+                    continue;
+                }
+
+                let parsed = match serde_json::from_str::<StreamEvent>(&event.data) {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        // New event types are added over time; skip rather than fail the turn
+                        tracing::warn!("Skipping unrecognised event: {} - Data: {}", e, event.data);
+                        continue;
+                    }
+                };
+
+                match parsed {
+                    StreamEvent::MessageStart { message } => {
+                        message_id = message.id;
+                        model = message.model;
+                        input_tokens = message.usage.input_tokens;
+                    }
+                    StreamEvent::ContentBlockStart { index, content_block } => match content_block {
+                        ContentBlock::Text { text } if !text.is_empty() => {
+                            yield Ok(chunk(&message_id, &model, text_delta(text), None, None));
+                        }
+                        ContentBlock::ToolUse { id, name, input } => {
+                            tool_blocks.insert(index, (input, false));
+                            let delta = tool_call_delta(index, Some(id), Some(name), None);
+                            yield Ok(chunk(&message_id, &model, delta, None, None));
+                        }
+                        _ => {}
+                    },
+                    StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                        BlockDelta::TextDelta { text } => {
+                            yield Ok(chunk(&message_id, &model, text_delta(text), None, None));
+                        }
+                        BlockDelta::InputJsonDelta { partial_json } => {
+                            if let Some(block) = tool_blocks.get_mut(&index) {
+                                block.1 = true;
+                            }
+                            let delta = tool_call_delta(index, None, None, Some(partial_json));
+                            yield Ok(chunk(&message_id, &model, delta, None, None));
+                        }
+                        BlockDelta::Unsupported => {}
+                    },
+                    StreamEvent::ContentBlockStop { index } => {
+                        // Tools without parameters may never stream any input JSON
+                        if let Some((input, false)) = tool_blocks.remove(&index) {
+                            let input = if input.is_null() { json!({}) } else { input };
+                            let delta = tool_call_delta(index, None, None, Some(input.to_string()));
+                            yield Ok(chunk(&message_id, &model, delta, None, None));
+                        }
+                    }
+                    StreamEvent::MessageDelta { delta, usage } => {
+                        let usage = usage.map(|u| {
+                            let input = if u.input_tokens > 0 { u.input_tokens } else { input_tokens };
+                            usage_value(input, u.output_tokens)
+                        });
+                        let finish_reason = delta.stop_reason.as_deref().map(map_stop_reason);
+                        yield Ok(chunk(&message_id, &model, Delta {
+                            role: None,
+                            content: None,
+                            tool_calls: None,
+                        }, finish_reason, usage));
+                    }
+                    StreamEvent::MessageStop => {
+                        tracing::debug!("Stream complete");
+                        break;
+                    }
+                    StreamEvent::Ping => {}
+                    StreamEvent::Error { error } => {
+                        tracing::error!("Anthropic stream error: {} - {}", error.error_type, error.message);
+                        yield Err(ProviderError::Api {
+                            status: error.status(),
+                            message: format!("{}: {}", error.error_type, error.message),
+                            retry_after: None,
+                        }.into());
+                        break;
+                    }
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Create a non-streaming chat completion
+    pub async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        let body = build_messages_request(request, false);
+        let response = self.send(&body).await?;
+        let response = response.json::<MessagesResponse>().await?;
+
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block {
+                ContentBlock::Text { text: t } => text.push_str(&t),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id,
+                    tool_type: "function".to_string(),
+                    function: FunctionCall {
+                        name,
+                        arguments: input.to_string(),
+                    },
+                }),
+                _ => {}
+            }
+        }
+
+        let usage = &response.usage;
+        Ok(ChatCompletionResponse {
+            id: response.id,
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: response.model,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage::Assistant {
+                    content: if text.is_empty() { None } else { Some(text) },
+                    name: None,
+                    tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                },
+                finish_reason: map_stop_reason(response.stop_reason.as_deref().unwrap_or("end_turn")),
+            }],
+            usage: Usage {
+                prompt_tokens: usage.input_tokens,
+                completion_tokens: usage.output_tokens,
+                total_tokens: usage.input_tokens + usage.output_tokens,
+            },
+        })
+    }
+
+    async fn send(&self, body: &MessagesRequest) -> Result<reqwest::Response> {
+        let url = format!("{}/messages", self.base_url);
+
+        let response = self.client
+            .post(&url)
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let headers = response.headers().clone();
+            let text = response.text().await?;
+
+            let message = match serde_json::from_str::<AnthropicErrorResponse>(&text) {
+                Ok(error_resp) => error_resp.error.message,
+                Err(_) => text,
+            };
+
+            return Err(ProviderError::api(status, &headers, message).into());
+        }
+
+        Ok(response)
+    }
+}
+
+/// Convert an OpenAI-shaped request into a Messages API request
+fn build_messages_request(request: ChatCompletionRequest, stream: bool) -> MessagesRequest {
+    let mut system_parts = Vec::new();
+    let mut messages: Vec<AnthropicMessage> = Vec::new();
+
+    for message in request.messages {
+        let (role, content) = match message {
+            ChatMessage::System { content, .. } => {
+                system_parts.push(content);
+                continue;
+            }
+            ChatMessage::User { content, .. } => ("user", convert_user_content(content)),
+            ChatMessage::Assistant { content, tool_calls, .. } => {
+                let mut blocks = Vec::new();
+                if let Some(text) = content.filter(|t| !t.is_empty()) {
+                    blocks.push(ContentBlock::Text { text });
+                }
+                for call in tool_calls.unwrap_or_default() {
+                    let input = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| json!({}));
+                    blocks.push(ContentBlock::ToolUse {
+                        id: call.id,
+                        name: call.function.name,
+                        input,
+                    });
+                }
+                ("assistant", blocks)
+            }
+            ChatMessage::Tool { content, tool_call_id } => (
+                "user",
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: tool_call_id,
+                    content,
+                }],
+            ),
+        };
+
+        if content.is_empty() {
+            continue;
+        }
+
+        // The Messages API requires alternating roles, so merge consecutive
+        // messages (e.g. several tool results) into one
+        match messages.last_mut() {
+            Some(last) if last.role == role => last.content.extend(content),
+            _ => messages.push(AnthropicMessage {
+                role: role.to_string(),
+                content,
+            }),
+        }
+    }
+
+    let tools = request.tools.unwrap_or_default()
+        .into_iter()
+        .map(|tool| AnthropicTool {
+            name: tool.function.name,
+            description: tool.function.description,
+            input_schema: tool.function.parameters,
+        })
+        .collect();
+
+    MessagesRequest {
+        model: request.model,
+        max_tokens: request.max_tokens.unwrap_or(4096),
+        system: if system_parts.is_empty() { None } else { Some(system_parts.join("\n\n")) },
+        messages,
+        tools,
+        temperature: request.temperature,
+        stream,
+    }
+}
+
+fn convert_user_content(content: UserContent) -> Vec<ContentBlock> {
+    match content {
+        UserContent::Text(text) => vec![ContentBlock::Text { text }],
+        UserContent::Array(parts) => parts.into_iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => ContentBlock::Text { text },
+                ContentPart::Image { image_url } => ContentBlock::Image {
+                    source: convert_image_url(image_url.url),
+                },
+            })
+            .collect(),
+    }
+}
+
+/// Data URLs become base64 sources, anything else is passed by URL
+fn convert_image_url(url: String) -> ImageSource {
+    if let Some(rest) = url.strip_prefix("data:")
+        && let Some((media_type, data)) = rest.split_once(";base64,")
+    {
+        return ImageSource::Base64 {
+            media_type: media_type.to_string(),
+            data: data.to_string(),
+        };
+    }
+    ImageSource::Url { url }
+}
+
+fn map_stop_reason(reason: &str) -> String {
+    match reason {
+        "end_turn" | "stop_sequence" => "stop",
+        "tool_use" => "tool_calls",
+        "max_tokens" => "length",
+        other => other,
+    }.to_string()
+}
+
+fn usage_value(input_tokens: u32, output_tokens: u32) -> Value {
+    json!({
+        "prompt_tokens": input_tokens,
+        "completion_tokens": output_tokens,
+        "total_tokens": input_tokens + output_tokens,
+    })
+}
+
+fn text_delta(text: String) -> Delta {
+    Delta {
+        role: None,
+        content: Some(text),
+        tool_calls: None,
+    }
+}
+
+fn tool_call_delta(
+    index: usize,
+    id: Option<String>,
+    name: Option<String>,
+    arguments: Option<String>,
+) -> Delta {
+    Delta {
+        role: None,
+        content: None,
+        tool_calls: Some(vec![ToolCallChunk {
+            index: index as i32,
+            tool_type: id.as_ref().map(|_| "function".to_string()),
+            id,
+            function: Some(FunctionCallChunk { name, arguments }),
+        }]),
+    }
+}
+
+fn chunk(
+    id: &str,
+    model: &str,
+    delta: Delta,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        id: id.to_string(),
+        object: "chat.completion.chunk".to_string(),
+        created: chrono::Utc::now().timestamp() as u64,
+        model: model.to_string(),
+        choices: vec![ChunkChoice {
+            index: 0,
+            delta,
+            finish_reason,
+        }],
+        usage,
+    }
+}
@@ -4,6 +4,7 @@ use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use eventsource_stream::Eventsource;
 use std::pin::Pin;
 use crate::config::Config;
+use super::retry::ProviderError;
 use super::types::*;
 
 /// OpenAI-compatible API client with streaming support
@@ -43,6 +44,7 @@ impl OpenAICompatClient {
         mut request: ChatCompletionRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<ChatCompletionChunk>> + Send>>> {
         request.stream = true;
+        request.stream_options = Some(StreamOptions { include_usage: true });
         
         let url = format!("{}/chat/completions", self.base_url);
         
@@ -54,14 +56,16 @@ impl OpenAICompatClient {
         
         if !response.status().is_success() {
             let status = response.status();
+            let headers = response.headers().clone();
             let text = response.text().await?;
             
             // Try to parse as error response
-            if let Ok(error_resp) = serde_json::from_str::<ErrorResponse>(&text) {
-                return Err(anyhow!("API error {}: {}", status, error_resp.error.message));
-            }
+            let message = match serde_json::from_str::<ErrorResponse>(&text) {
+                Ok(error_resp) => error_resp.error.message,
+                Err(_) => text,
+            };
             
-            return Err(anyhow!("API error {}: {}", status, text));
+            return Err(ProviderError::api(status, &headers, message).into());
         }
         
         // Convert response bytes stream to SSE events
@@ -100,7 +104,7 @@ impl OpenAICompatClient {
                     }
                     Err(e) => {
                         tracing::error!("SSE error: {}", e);
-                        yield Err(anyhow!("SSE error: {}", e));
+                        yield Err(ProviderError::Stream(e.to_string()).into());
                         break;
                     }
                 }
@@ -116,6 +120,7 @@ impl OpenAICompatClient {
         mut request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
         request.stream = false;
+        request.stream_options = None;
         
         let url = format!("{}/chat/completions", self.base_url);
         
@@ -127,14 +132,16 @@ impl OpenAICompatClient {
         
         if !response.status().is_success() {
             let status = response.status();
+            let headers = response.headers().clone();
             let text = response.text().await?;
             
             // Try to parse as error response
-            if let Ok(error_resp) = serde_json::from_str::<ErrorResponse>(&text) {
-                return Err(anyhow!("API error {}: {}", status, error_resp.error.message));
-            }
+            let message = match serde_json::from_str::<ErrorResponse>(&text) {
+                Ok(error_resp) => error_resp.error.message,
+                Err(_) => text,
+            };
             
-            return Err(anyhow!("API error {}: {}", status, text));
+            return Err(ProviderError::api(status, &headers, message).into());
         }
         
         let response = response.json::<ChatCompletionResponse>().await?;
@@ -1,5 +1,11 @@
 pub mod types;
 pub mod client;
+pub mod anthropic;
+pub mod provider;
+pub mod retry;
 
 pub use client::OpenAICompatClient;
-pub use types::*;
\ No newline at end of file
+pub use anthropic::AnthropicClient;
+pub use provider::{ChatProvider, ChatCompletionStream, create_provider};
+pub use retry::{ProviderError, RetryingProvider};
+pub use types::*;
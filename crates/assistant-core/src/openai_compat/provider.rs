@@ -0,0 +1,81 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::config::Config;
+use super::anthropic::AnthropicClient;
+use super::client::OpenAICompatClient;
+use super::retry::RetryingProvider;
+use super::types::*;
+
+/// Stream of completion chunks produced by a provider
+pub type ChatCompletionStream = Pin<Box<dyn Stream<Item = Result<ChatCompletionChunk>> + Send>>;
+
+/// An LLM backend that speaks our OpenAI-shaped request/response types.
+///
+/// Providers with a different wire format translate to and from these types,
+/// so `ClientActor` can stay provider agnostic.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    /// Create a streaming chat completion
+    async fn create_chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionStream>;
+
+    /// Create a non-streaming chat completion
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse>;
+}
+
+/// Create the provider configured by `config.provider`, wrapped with the
+/// retry policy from `config.retry`
+pub fn create_provider(config: &Config) -> Box<dyn ChatProvider> {
+    let inner: Arc<dyn ChatProvider> = match config.provider.as_str() {
+        "anthropic" => Arc::new(AnthropicClient::new(config)),
+        "openai" => Arc::new(OpenAICompatClient::new(config)),
+        other => {
+            tracing::warn!("Unknown provider '{}', falling back to OpenAI-compatible client", other);
+            Arc::new(OpenAICompatClient::new(config))
+        }
+    };
+
+    Box::new(RetryingProvider::new(inner, config.retry.clone()))
+}
+
+#[async_trait]
+impl ChatProvider for OpenAICompatClient {
+    async fn create_chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionStream> {
+        OpenAICompatClient::create_chat_completion_stream(self, request).await
+    }
+
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        OpenAICompatClient::create_chat_completion(self, request).await
+    }
+}
+
+#[async_trait]
+impl ChatProvider for AnthropicClient {
+    async fn create_chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionStream> {
+        AnthropicClient::create_chat_completion_stream(self, request).await
+    }
+
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        AnthropicClient::create_chat_completion(self, request).await
+    }
+}
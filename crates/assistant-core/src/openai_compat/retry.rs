@@ -0,0 +1,280 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::config::RetryConfig;
+use super::provider::{ChatCompletionStream, ChatProvider};
+use super::types::*;
+
+/// Errors raised by providers that the retry layer knows how to classify
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    #[error("API error {status}: {message}")]
+    Api {
+        status: StatusCode,
+        message: String,
+        /// Delay requested by the server via `Retry-After` / `x-ratelimit-reset`
+        retry_after: Option<Duration>,
+    },
+
+    #[error("SSE error: {0}")]
+    Stream(String),
+}
+
+impl ProviderError {
+    /// Build an API error from a failed response's status and headers
+    pub fn api(status: StatusCode, headers: &HeaderMap, message: String) -> Self {
+        ProviderError::Api {
+            status,
+            message,
+            retry_after: retry_after_from_headers(headers),
+        }
+    }
+}
+
+/// Wraps a provider with retries, exponential backoff and jitter.
+///
+/// Failed requests are retried on 408/409/425/429/5xx and connection errors.
+/// A stream that fails before yielding any content or tool call is reopened;
+/// once tokens have been streamed, errors are passed through unchanged.
+pub struct RetryingProvider {
+    inner: Arc<dyn ChatProvider>,
+    policy: RetryConfig,
+}
+
+impl RetryingProvider {
+    pub fn new(inner: Arc<dyn ChatProvider>, policy: RetryConfig) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl ChatProvider for RetryingProvider {
+    async fn create_chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionStream> {
+        let mut attempt = 0;
+        let first = open_stream(&self.inner, &self.policy, &request, &mut attempt).await?;
+
+        let inner = self.inner.clone();
+        let policy = self.policy.clone();
+
+        let stream = async_stream::stream! {
+            let mut stream = first;
+            let mut streamed = false;
+
+            loop {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        if chunk.choices.iter().any(|c| c.delta.content.is_some() || c.delta.tool_calls.is_some()) {
+                            streamed = true;
+                        }
+                        yield Ok(chunk);
+                    }
+                    Some(Err(e)) => {
+                        if streamed || attempt >= policy.max_retries || !is_retryable(&e) {
+                            yield Err(e);
+                            break;
+                        }
+
+                        let delay = backoff_delay(&policy, attempt, &e);
+                        attempt += 1;
+                        tracing::warn!(
+                            "Stream failed before any tokens ({}), retrying in {:?} (attempt {}/{})",
+                            e, delay, attempt, policy.max_retries
+                        );
+                        tokio::time::sleep(delay).await;
+
+                        match open_stream(&inner, &policy, &request, &mut attempt).await {
+                            Ok(reopened) => stream = reopened,
+                            Err(e) => {
+                                yield Err(e);
+                                break;
+                            }
+                        }
+                    }
+                    None => break,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.create_chat_completion(request.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < self.policy.max_retries && is_retryable(&e) => {
+                    let delay = backoff_delay(&self.policy, attempt, &e);
+                    attempt += 1;
+                    tracing::warn!(
+                        "Request failed ({}), retrying in {:?} (attempt {}/{})",
+                        e, delay, attempt, self.policy.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Open a stream, retrying failed requests. `attempt` counts retries across the whole turn.
+async fn open_stream(
+    inner: &Arc<dyn ChatProvider>,
+    policy: &RetryConfig,
+    request: &ChatCompletionRequest,
+    attempt: &mut u32,
+) -> Result<ChatCompletionStream> {
+    loop {
+        match inner.create_chat_completion_stream(request.clone()).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) if *attempt < policy.max_retries && is_retryable(&e) => {
+                let delay = backoff_delay(policy, *attempt, &e);
+                *attempt += 1;
+                tracing::warn!(
+                    "Request failed ({}), retrying in {:?} (attempt {}/{})",
+                    e, delay, attempt, policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether an error is worth retrying
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    if let Some(provider_error) = error.downcast_ref::<ProviderError>() {
+        return match provider_error {
+            ProviderError::Api { status, .. } => matches!(status.as_u16(), 408 | 409 | 425 | 429) || status.is_server_error(),
+            ProviderError::Stream(_) => true,
+        };
+    }
+
+    if let Some(reqwest_error) = error.downcast_ref::<reqwest::Error>() {
+        return reqwest_error.is_connect()
+            || reqwest_error.is_timeout()
+            || reqwest_error.is_request()
+            || reqwest_error.is_body();
+    }
+
+    false
+}
+
+/// Delay before the next attempt: the server's requested delay if it sent one,
+/// otherwise exponential backoff. Both are capped at `max_backoff_ms`.
+pub fn backoff_delay(policy: &RetryConfig, attempt: u32, error: &anyhow::Error) -> Duration {
+    let max = Duration::from_millis(policy.max_backoff_ms);
+
+    if let Some(ProviderError::Api { retry_after: Some(delay), .. }) = error.downcast_ref::<ProviderError>() {
+        return (*delay).min(max);
+    }
+
+    let exponential = policy.initial_backoff_ms
+        .saturating_mul(1u64 << attempt.min(20))
+        .min(policy.max_backoff_ms);
+
+    let millis = if policy.jitter {
+        // Equal jitter: keep half the delay, randomise the other half
+        let half = exponential / 2;
+        half + (uuid::Uuid::new_v4().as_u128() % (half as u128 + 1)) as u64
+    } else {
+        exponential
+    };
+
+    Duration::from_millis(millis)
+}
+
+/// Parse `Retry-After` (seconds or HTTP date) or `x-ratelimit-reset*` headers.
+///
+/// Rate limit resets come in several shapes depending on the provider: epoch
+/// milliseconds (OpenRouter), epoch seconds, plain seconds, or durations like
+/// `1s` / `6m0s` / `250ms` (OpenAI).
+pub fn retry_after_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(seconds) = value.parse::<f64>() {
+            return positive(from_secs(seconds));
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            let millis = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_milliseconds();
+            return positive(Duration::from_millis(millis.max(0) as u64));
+        }
+    }
+
+    ["x-ratelimit-reset", "x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .iter()
+        .filter_map(|name| header(name))
+        .filter_map(parse_reset)
+        .max()
+}
+
+fn parse_reset(value: &str) -> Option<Duration> {
+    if let Ok(number) = value.parse::<f64>() {
+        let now = chrono::Utc::now();
+        let delay = if number > 1e12 {
+            // Epoch milliseconds
+            Duration::from_millis((number as i64 - now.timestamp_millis()).max(0) as u64)
+        } else if number > 1e9 {
+            // Epoch seconds
+            Duration::from_millis((number * 1000.0) as u64)
+                .saturating_sub(Duration::from_millis(now.timestamp_millis() as u64))
+        } else {
+            from_secs(number)
+        };
+        return positive(delay);
+    }
+
+    positive(parse_duration(value)?)
+}
+
+/// Parse Go-style durations such as `1s`, `6m0s`, `1h2m` or `250ms`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+
+        let unit_len = tail.find(|c: char| c.is_ascii_digit()).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let seconds = match unit {
+            "ms" => number / 1000.0,
+            "s" => number,
+            "m" => number * 60.0,
+            "h" => number * 3600.0,
+            _ => return None,
+        };
+
+        total = total.saturating_add(from_secs(seconds));
+        rest = tail;
+    }
+
+    Some(total)
+}
+
+/// `seconds` as a duration, negative ones as zero and ones too long to represent,
+/// `inf` included, as the longest duration
+fn from_secs(seconds: f64) -> Duration {
+    if seconds.is_nan() {
+        return Duration::ZERO;
+    }
+    Duration::try_from_secs_f64(seconds.max(0.0)).unwrap_or(Duration::MAX)
+}
+
+fn positive(delay: Duration) -> Option<Duration> {
+    if delay.is_zero() { None } else { Some(delay) }
+}
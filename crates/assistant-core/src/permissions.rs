@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use uuid::Uuid;
+use crate::actors::tools::ToolRegistry;
+use crate::config::{PermissionAction, PermissionRule, PermissionsConfig};
+use crate::messages::{ConfirmationRequest, ConfirmationType, ToolCall};
+
+/// Parameters holding a call's main argument, in order of preference
+const SUBJECT_PARAMETERS: &[&str] = &["command", "file_path", "path", "url", "query", "pattern"];
+
+/// Longest content preview, in characters, shown when confirming a file change
+const MAX_PREVIEW: usize = 2_000;
+
+/// Decides whether a tool call runs, is refused or needs the user's confirmation
+#[derive(Debug, Clone)]
+pub struct PermissionPolicy {
+    config: PermissionsConfig,
+    destructive: HashSet<&'static str>,
+}
+
+impl PermissionPolicy {
+    pub fn new(config: PermissionsConfig) -> Self {
+        let destructive = ToolRegistry::specs()
+            .into_iter()
+            .filter(|spec| spec.destructive)
+            .map(|spec| spec.name)
+            .collect();
+
+        Self { config, destructive }
+    }
+
+    /// Decide a tool call.
+    ///
+    /// `bash` commands are split at `;`, `&`, `&&`, `|`, `||` and newlines and every
+    /// part is checked on its own, the strictest decision winning, so an allowed
+    /// `git status*` can't carry a denied command along with it. A rule never allows
+    /// a part that uses command substitution; the user is asked instead. Managing
+    /// background jobs runs nothing new, and neither does listing checkpoints or
+    /// reading a git repository, so these fall back to the default action.
+    pub fn check(&self, call: &ToolCall) -> PermissionAction {
+        let subject = subject(call);
+        if (call.tool_name == "bash" && !runs_command(call))
+            || (call.tool_name == "checkpoint" && !restores_files(call))
+            || (call.tool_name == "git" && !changes_repository(call))
+        {
+            return self.matching_rule(&call.tool_name, &subject)
+                .map(|rule| rule.action)
+                .unwrap_or(self.config.default_action);
+        }
+        if call.tool_name != "bash" {
+            return self.matching_rule(&call.tool_name, &subject)
+                .map(|rule| rule.action)
+                .unwrap_or_else(|| self.fallback(&call.tool_name));
+        }
+
+        split_command(&subject)
+            .into_iter()
+            .map(|part| match self.matching_rule("bash", part) {
+                Some(rule) if rule.action == PermissionAction::Allow && has_substitution(part) => PermissionAction::Ask,
+                Some(rule) => rule.action,
+                None => self.fallback("bash"),
+            })
+            .max()
+            .unwrap_or_else(|| self.fallback("bash"))
+    }
+
+    fn matching_rule(&self, tool: &str, subject: &str) -> Option<&PermissionRule> {
+        self.config.rules.iter().find(|rule| {
+            wildcard_match(&rule.tool, tool)
+                && rule.pattern.as_deref().is_none_or(|pattern| wildcard_match(pattern, subject))
+        })
+    }
+
+    fn fallback(&self, tool: &str) -> PermissionAction {
+        if self.destructive.contains(tool) {
+            self.config.destructive_action
+        } else {
+            self.config.default_action
+        }
+    }
+}
+
+/// The argument permission patterns are matched against: the first of `command`,
+/// `file_path`, `path`, `url`, `query` and `pattern` present, otherwise all parameters as JSON
+pub fn subject(call: &ToolCall) -> String {
+    SUBJECT_PARAMETERS
+        .iter()
+        .find_map(|key| call.parameters.get(key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| call.parameters.to_string())
+}
+
+/// Describe a tool call for the user to confirm
+pub fn confirmation_request(id: Uuid, call: &ToolCall) -> ConfirmationRequest {
+    let param = |key: &str| {
+        call.parameters.get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let (description, confirm_type) = match call.tool_name.as_str() {
+        "bash" => {
+            let description = param("description");
+            (
+                if description.is_empty() { "Run a shell command".to_string() } else { description },
+                ConfirmationType::Execute { command: param("command") },
+            )
+        }
+        "write" => (
+            "Write a file".to_string(),
+            ConfirmationType::Edit { file: param("file_path"), diff: preview_lines("+", &param("content")) },
+        ),
+        "edit" if param("action") == "multi_edit" => {
+            let edits = call.parameters.get("edits").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            let text = |edit: &serde_json::Value, key: &str| edit.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            (
+                format!("Make {} edits to a file", edits.len()),
+                ConfirmationType::Edit {
+                    file: param("file_path"),
+                    diff: edits.iter()
+                        .map(|edit| format!("{}\n{}", preview_lines("-", &text(edit, "old_string")), preview_lines("+", &text(edit, "new_string"))))
+                        .collect::<Vec<_>>()
+                        .join("\n\n"),
+                },
+            )
+        }
+        "edit" => (
+            "Edit a file".to_string(),
+            ConfirmationType::Edit {
+                file: param("file_path"),
+                diff: format!("{}\n{}", preview_lines("-", &param("old_string")), preview_lines("+", &param("new_string"))),
+            },
+        ),
+        "apply_patch" => (
+            "Apply a patch".to_string(),
+            ConfirmationType::Edit { file: patched_files(&param("patch")).join(", "), diff: preview(&param("patch")) },
+        ),
+        "checkpoint" => (
+            "Restore files changed by the agent".to_string(),
+            ConfirmationType::Execute {
+                command: match call.parameters.get("turn").and_then(|v| v.as_u64()) {
+                    Some(turn) => format!("Undo turn {} and every later turn", turn),
+                    None => "Undo file changes".to_string(),
+                },
+            },
+        ),
+        "git" if param("action") == "commit" => (
+            "Commit to the git repository".to_string(),
+            ConfirmationType::Execute { command: format!("git commit -m {:?}", param("message")) },
+        ),
+        "git" => (
+            "Create a git branch".to_string(),
+            ConfirmationType::Execute {
+                command: match param("start_point").as_str() {
+                    "" => format!("git branch {}", param("name")),
+                    start_point => format!("git branch {} {}", param("name"), start_point),
+                },
+            },
+        ),
+        "web_fetch" => (
+            "Fetch a web page".to_string(),
+            ConfirmationType::WebAccess { url: param("url") },
+        ),
+        name => (
+            format!("Use the {} tool", name),
+            ConfirmationType::Execute { command: subject(call) },
+        ),
+    };
+
+    ConfirmationRequest {
+        id,
+        tool_name: call.tool_name.clone(),
+        description,
+        confirm_type,
+    }
+}
+
+/// Match `text` against a pattern in which `*` matches any run of characters and `?` any one
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it was tried against, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Split a shell command into the commands it chains, keeping `2>&1`-style redirections intact.
+/// Quoting is ignored, which can only split a command further than the shell would.
+fn split_command(command: &str) -> Vec<&str> {
+    let bytes = command.as_bytes();
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in command.char_indices() {
+        let separator = match c {
+            ';' | '|' | '\n' => true,
+            '&' => !(i > 0 && matches!(bytes[i - 1], b'>' | b'<')) && bytes.get(i + 1) != Some(&b'>'),
+            _ => false,
+        };
+        if separator {
+            parts.push(&command[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&command[start..]);
+
+    parts.into_iter().map(str::trim).filter(|part| !part.is_empty()).collect()
+}
+
+/// Whether a `bash` call runs a command rather than managing background jobs
+fn runs_command(call: &ToolCall) -> bool {
+    call.parameters.get("action").and_then(|v| v.as_str()).is_none_or(|action| action == "run")
+}
+
+/// Whether a `checkpoint` call restores files rather than listing checkpoints
+fn restores_files(call: &ToolCall) -> bool {
+    call.parameters.get("action").and_then(|v| v.as_str()) == Some("restore")
+}
+
+/// Whether a `git` call commits or creates a branch rather than only reading the repository
+fn changes_repository(call: &ToolCall) -> bool {
+    match call.parameters.get("action").and_then(|v| v.as_str()) {
+        Some("commit") => true,
+        Some("branch") => call.parameters.get("name").and_then(|v| v.as_str()).is_some_and(|name| !name.is_empty()),
+        _ => false,
+    }
+}
+
+fn has_substitution(command: &str) -> bool {
+    command.contains("$(") || command.contains('`') || command.contains("<(") || command.contains(">(")
+}
+
+fn preview_lines(prefix: &str, text: &str) -> String {
+    let mut preview: String = text.chars().take(MAX_PREVIEW).collect();
+    let truncated = preview.len() < text.len();
+    preview = preview.lines().map(|line| format!("{} {}", prefix, line)).collect::<Vec<_>>().join("\n");
+    if truncated {
+        preview.push_str("\n... [truncated]");
+    }
+    preview
+}
+
+/// The start of a diff, which already marks its own lines
+fn preview(diff: &str) -> String {
+    let mut preview: String = diff.chars().take(MAX_PREVIEW).collect();
+    if preview.len() < diff.len() {
+        preview.push_str("\n... [truncated]");
+    }
+    preview
+}
+
+/// The files a patch's `+++` lines name, or its `---` lines for deletions
+fn patched_files(patch: &str) -> Vec<&str> {
+    let lines: Vec<&str> = patch.lines().collect();
+    lines.windows(2)
+        .filter_map(|pair| {
+            let old = pair[0].strip_prefix("--- ")?;
+            let new = pair[1].strip_prefix("+++ ")?;
+            let path = if new.starts_with("/dev/null") { old.strip_prefix("a/").unwrap_or(old) } else { new.strip_prefix("b/").unwrap_or(new) };
+            Some(path.split('\t').next().unwrap_or(path))
+        })
+        .collect()
+}
@@ -0,0 +1,247 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use std::path::{Path, PathBuf};
+
+use super::database::Database;
+use super::schema::FileCheckpointRecord;
+use crate::actors::tools::apply_patch;
+use crate::config::Config;
+use crate::utils::path::normalize_path;
+
+/// The files the agent changed in one turn, numbered from 1 within the session
+#[derive(Debug, Clone)]
+pub struct TurnCheckpoint {
+    pub number: usize,
+    pub turn_id: String,
+    pub created_at: DateTime<Utc>,
+    pub paths: Vec<String>,
+}
+
+/// A file put back by a restore
+#[derive(Debug, Clone)]
+pub struct RestoredFile {
+    pub path: String,
+    /// Whether the file existed before; if not, restoring removed it
+    pub existed: bool,
+}
+
+/// Keeps the contents files had before the agent changed them, so a session's
+/// later turns can be undone
+pub struct CheckpointManager {
+    db: Database,
+}
+
+impl CheckpointManager {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Open the sessions database named in the configuration
+    pub async fn open(config: &Config) -> Result<Self> {
+        let db_path = match &config.session.database_path {
+            Some(path) => path.clone(),
+            None => Database::default_path()?,
+        };
+        Ok(Self::new(Database::new(&db_path).await?))
+    }
+
+    /// Record the current contents of `paths` for a turn. Only the first snapshot
+    /// of a file in a turn is kept, since that is the state undoing the turn returns to.
+    pub async fn snapshot(&self, session_id: &str, turn_id: &str, paths: &[PathBuf]) -> Result<()> {
+        for path in paths {
+            let content = match std::fs::read(path) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    // The tool will fail on it too, leaving nothing to restore
+                    tracing::debug!("Not checkpointing {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO file_checkpoints (session_id, turn_id, path, content, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (session_id, turn_id, path) DO NOTHING
+                "#,
+            )
+            .bind(session_id)
+            .bind(turn_id)
+            .bind(path.display().to_string())
+            .bind(content)
+            .bind(Utc::now())
+            .execute(self.db.pool())
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// The session's turns that changed files, oldest first
+    pub async fn list(&self, session_id: &str) -> Result<Vec<TurnCheckpoint>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT turn_id, path, created_at
+            FROM file_checkpoints
+            WHERE session_id = ?1
+            ORDER BY id
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut turns: Vec<TurnCheckpoint> = Vec::new();
+        for row in rows {
+            let turn_id: String = row.try_get("turn_id")?;
+            let path: String = row.try_get("path")?;
+            match turns.iter_mut().find(|turn| turn.turn_id == turn_id) {
+                Some(turn) => turn.paths.push(path),
+                None => turns.push(TurnCheckpoint {
+                    number: turns.len() + 1,
+                    turn_id,
+                    created_at: row.try_get("created_at")?,
+                    paths: vec![path],
+                }),
+            }
+        }
+
+        Ok(turns)
+    }
+
+    /// Put every file changed in turn `number` or later back the way it was before
+    /// that turn, and forget those turns' checkpoints
+    pub async fn restore(&self, session_id: &str, number: usize) -> Result<Vec<RestoredFile>> {
+        let turns = self.list(session_id).await?;
+        if turns.is_empty() {
+            bail!("No file changes have been checkpointed in this session");
+        }
+        if number == 0 || number > turns.len() {
+            bail!("There is no turn {}; this session has checkpoints for turns 1 to {}", number, turns.len());
+        }
+        let undone = &turns[number - 1..];
+
+        // The earliest snapshot of each file is its state before turn `number`
+        let mut snapshots: Vec<FileCheckpointRecord> = Vec::new();
+        for turn in undone {
+            let records = sqlx::query_as::<_, FileCheckpointRecord>(
+                r#"
+                SELECT id, session_id, turn_id, path, content, created_at
+                FROM file_checkpoints
+                WHERE session_id = ?1 AND turn_id = ?2
+                ORDER BY id
+                "#,
+            )
+            .bind(session_id)
+            .bind(&turn.turn_id)
+            .fetch_all(self.db.pool())
+            .await?;
+
+            for record in records {
+                if !snapshots.iter().any(|snapshot| snapshot.path == record.path) {
+                    snapshots.push(record);
+                }
+            }
+        }
+
+        let mut restored = Vec::new();
+        for snapshot in snapshots {
+            restore_file(Path::new(&snapshot.path), snapshot.content.as_deref())?;
+            restored.push(RestoredFile { path: snapshot.path, existed: snapshot.content.is_some() });
+        }
+
+        let mut tx = self.db.pool().begin().await?;
+        for turn in undone {
+            sqlx::query("DELETE FROM file_checkpoints WHERE session_id = ?1 AND turn_id = ?2")
+                .bind(session_id)
+                .bind(&turn.turn_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(restored)
+    }
+
+    /// Undo the session's most recent turn that changed files, returning its number
+    pub async fn undo_last(&self, session_id: &str) -> Result<(usize, Vec<RestoredFile>)> {
+        let last = self.list(session_id).await?.len();
+        let restored = self.restore(session_id, last).await?;
+        Ok((last, restored))
+    }
+}
+
+fn restore_file(path: &Path, content: Option<&[u8]>) -> Result<()> {
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)
+                .map_err(|e| anyhow::anyhow!("Cannot restore '{}': {}", path.display(), e))
+        }
+        None => match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(anyhow::anyhow!("Cannot remove '{}': {}", path.display(), e))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+/// The files a tool call may change, for the tools whose changes are checkpointed
+pub fn changed_paths(tool_name: &str, parameters: &serde_json::Value) -> Vec<PathBuf> {
+    let names = match tool_name {
+        "write" | "edit" => parameters.get("file_path")
+            .and_then(|v| v.as_str())
+            .map(|path| vec![path.to_string()])
+            .unwrap_or_default(),
+        "apply_patch" => parameters.get("patch")
+            .and_then(|v| v.as_str())
+            .map(apply_patch::patch_paths)
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    names.iter().filter_map(|name| normalize_path(Path::new(name)).ok()).collect()
+}
+
+/// List a session's checkpoints for the user or the model
+pub fn describe_turns(turns: &[TurnCheckpoint]) -> String {
+    if turns.is_empty() {
+        return "No file changes have been checkpointed in this session".to_string();
+    }
+
+    turns.iter()
+        .map(|turn| format!(
+            "Turn {} ({}): {}\n{}",
+            turn.number,
+            turn.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            match turn.paths.len() {
+                1 => "1 file".to_string(),
+                n => format!("{} files", n),
+            },
+            turn.paths.iter().map(|path| format!("  {}", path)).collect::<Vec<_>>().join("\n")
+        ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Summarize a restore
+pub fn describe_restore(number: usize, restored: &[RestoredFile]) -> String {
+    let files = restored.iter()
+        .map(|file| if file.existed {
+            format!("- {}", file.path)
+        } else {
+            format!("- {} (removed, it didn't exist before)", file.path)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let count = match restored.len() {
+        1 => "1 file to its".to_string(),
+        n => format!("{} files to their", n),
+    };
+    format!("Restored {} state before turn {}:\n{}", count, number, files)
+}
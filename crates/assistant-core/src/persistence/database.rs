@@ -8,7 +8,8 @@ use std::str::FromStr;
 use std::sync::Arc;
 use tracing::log::LevelFilter;
 
-use super::schema::{SCHEMA_SQL, SessionSummary, SessionRecord, ChatMessageRecord};
+use crate::messages::TokenUsage;
+use super::schema::{SCHEMA_SQL, SessionSummary, SessionRecord, ChatMessageRecord, SessionUsageRecord};
 
 #[derive(Clone)]
 pub struct Database {
@@ -44,6 +45,7 @@ impl Database {
 
         // Initialize schema
         db.initialize_schema().await?;
+        db.migrate().await?;
 
         Ok(db)
     }
@@ -73,9 +75,17 @@ impl Database {
         &self.path
     }
 
-    /// Execute migrations (placeholder for future use)
+    /// Bring a database created by an older version up to the current schema
     pub async fn migrate(&self) -> Result<()> {
-        // TODO: Implement migrations when schema changes
+        // `kept_from` marks which prompt a compaction summary comes before
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('chat_messages')")
+            .fetch_all(&*self.pool)
+            .await?;
+        if !columns.iter().any(|column| column == "kept_from") {
+            sqlx::query("ALTER TABLE chat_messages ADD COLUMN kept_from TEXT")
+                .execute(&*self.pool)
+                .await?;
+        }
         Ok(())
     }
 
@@ -186,6 +196,18 @@ impl Database {
             .execute(&mut *tx)
             .await?;
 
+        // Delete usage totals
+        sqlx::query("DELETE FROM session_usage WHERE session_id = ?1")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Delete file checkpoints
+        sqlx::query("DELETE FROM file_checkpoints WHERE session_id = ?1")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
         // Delete the session
         sqlx::query("DELETE FROM sessions WHERE id = ?1")
             .bind(session_id)
@@ -208,7 +230,7 @@ impl Database {
         let query = if let (Some(limit), Some(offset)) = (limit, offset) {
             sqlx::query_as::<_, ChatMessageRecord>(
                 r#"
-                SELECT id, session_id, role, content, tool_calls, embedding, created_at
+                SELECT id, session_id, role, content, tool_calls, embedding, kept_from, created_at
                 FROM chat_messages
                 WHERE session_id = ?1
                 ORDER BY created_at DESC
@@ -221,7 +243,7 @@ impl Database {
         } else {
             sqlx::query_as::<_, ChatMessageRecord>(
                 r#"
-                SELECT id, session_id, role, content, tool_calls, embedding, created_at
+                SELECT id, session_id, role, content, tool_calls, embedding, kept_from, created_at
                 FROM chat_messages
                 WHERE session_id = ?1
                 ORDER BY created_at ASC
@@ -291,4 +313,53 @@ impl Database {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Add token usage to a session's running totals
+    pub async fn record_usage(
+        &self,
+        session_id: &str,
+        source: &str,
+        model: &str,
+        usage: &TokenUsage,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_usage (session_id, source, model, prompt_tokens, completion_tokens, cost, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT (session_id, source, model) DO UPDATE SET
+                prompt_tokens = prompt_tokens + excluded.prompt_tokens,
+                completion_tokens = completion_tokens + excluded.completion_tokens,
+                cost = cost + excluded.cost,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(session_id)
+        .bind(source)
+        .bind(model)
+        .bind(usage.prompt_tokens as i64)
+        .bind(usage.completion_tokens as i64)
+        .bind(usage.cost)
+        .bind(chrono::Utc::now())
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get token usage totals for a session, one row per source and model
+    pub async fn get_session_usage(&self, session_id: &str) -> Result<Vec<SessionUsageRecord>> {
+        let rows = sqlx::query_as::<_, SessionUsageRecord>(
+            r#"
+            SELECT session_id, source, model, prompt_tokens, completion_tokens, cost, updated_at
+            FROM session_usage
+            WHERE session_id = ?1
+            ORDER BY source, model
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&*self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
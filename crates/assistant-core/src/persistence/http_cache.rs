@@ -0,0 +1,215 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    CACHE_CONTROL, CONTENT_TYPE, ETAG, EXPIRES, HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED,
+};
+use sqlx::FromRow;
+use std::time::Duration;
+
+use super::database::Database;
+use crate::config::Config;
+
+/// A response kept in the cache
+#[derive(Debug, Clone, FromRow)]
+pub struct CachedResponse {
+    /// Where the response came from, after redirects
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub stored_at: DateTime<Utc>,
+    /// Until when the response is used without asking the server again
+    pub expires_at: DateTime<Utc>,
+}
+
+impl CachedResponse {
+    /// A response to store, with its validators taken from `headers`
+    pub fn new(url: &str, headers: &HeaderMap, body: Vec<u8>, expires_at: DateTime<Utc>) -> Self {
+        let header = |name: HeaderName| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        Self {
+            url: url.to_string(),
+            content_type: header(CONTENT_TYPE),
+            body,
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            stored_at: Utc::now(),
+            expires_at,
+        }
+    }
+
+    /// Whether the server can be asked if the response changed, rather than
+    /// fetching it again
+    pub fn can_revalidate(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        Utc::now() < self.expires_at
+    }
+
+    /// Headers asking the server to answer 304 Not Modified if the response
+    /// hasn't changed; empty when there is nothing to compare against
+    pub fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = self.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(date) = self.last_modified.as_deref().and_then(|date| HeaderValue::from_str(date).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, date);
+        }
+        headers
+    }
+}
+
+/// Fetched pages and search results in the sessions database, so repeated
+/// requests don't go to the network
+#[derive(Clone)]
+pub struct HttpCache {
+    db: Database,
+    /// Most bytes of responses kept
+    max_bytes: u64,
+}
+
+impl HttpCache {
+    pub fn new(db: Database, max_bytes: u64) -> Self {
+        Self { db, max_bytes }
+    }
+
+    /// Open the cache in the sessions database named in the configuration
+    pub async fn open(config: &Config) -> Result<Self> {
+        let db_path = match &config.session.database_path {
+            Some(path) => path.clone(),
+            None => Database::default_path()?,
+        };
+        let max_bytes = config.http_cache.max_size_mb.saturating_mul(1024 * 1024);
+        Ok(Self::new(Database::new(&db_path).await?, max_bytes))
+    }
+
+    /// The response stored under `key`, fresh or not
+    pub async fn get(&self, key: &str) -> Result<Option<CachedResponse>> {
+        let response = sqlx::query_as::<_, CachedResponse>(
+            r#"
+            SELECT url, content_type, body, etag, last_modified, stored_at, expires_at
+            FROM http_cache
+            WHERE key = ?1
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        if response.is_some() {
+            sqlx::query("UPDATE http_cache SET accessed_at = ?1 WHERE key = ?2")
+                .bind(Utc::now())
+                .bind(key)
+                .execute(self.db.pool())
+                .await?;
+        }
+        Ok(response)
+    }
+
+    /// Store `response` under `key`, replacing what was there, then shrink the
+    /// cache back under its size limit
+    pub async fn put(&self, key: &str, response: &CachedResponse) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO http_cache (key, url, content_type, body, etag, last_modified, size, stored_at, expires_at, accessed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?8)
+            ON CONFLICT (key) DO UPDATE SET
+                url = excluded.url,
+                content_type = excluded.content_type,
+                body = excluded.body,
+                etag = excluded.etag,
+                last_modified = excluded.last_modified,
+                size = excluded.size,
+                stored_at = excluded.stored_at,
+                expires_at = excluded.expires_at,
+                accessed_at = excluded.accessed_at
+            "#,
+        )
+        .bind(key)
+        .bind(&response.url)
+        .bind(&response.content_type)
+        .bind(&response.body)
+        .bind(&response.etag)
+        .bind(&response.last_modified)
+        .bind(response.body.len() as i64)
+        .bind(response.stored_at)
+        .bind(response.expires_at)
+        .execute(self.db.pool())
+        .await?;
+
+        // Keeps the most recently used responses that fit
+        sqlx::query(
+            r#"
+            DELETE FROM http_cache WHERE key IN (
+                SELECT key FROM (
+                    SELECT key, SUM(size) OVER (ORDER BY accessed_at DESC, key) AS total
+                    FROM http_cache
+                )
+                WHERE total > ?1
+            )
+            "#,
+        )
+        .bind(self.max_bytes.min(i64::MAX as u64) as i64)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Keep using the response under `key` until `expires_at`, after the server
+    /// confirmed it hasn't changed
+    pub async fn revalidated(&self, key: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE http_cache SET expires_at = ?1, accessed_at = ?2 WHERE key = ?3")
+            .bind(expires_at)
+            .bind(Utc::now())
+            .bind(key)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Until when a response may be used without asking the server again, from its
+/// `Cache-Control` and `Expires` headers, or `default_ttl` from now when they say
+/// nothing. `None` when the response must not be stored at all.
+pub fn expires_at(headers: &HeaderMap, default_ttl: Duration) -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+    let directives = headers.get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase());
+    let mut max_age = None;
+    for directive in directives {
+        match directive.split_once('=') {
+            _ if directive == "no-store" => return None,
+            // Stored, but checked with the server before every use
+            _ if directive == "no-cache" => return Some(now),
+            Some(("max-age", seconds)) => max_age = seconds.trim_matches('"').parse::<i64>().ok(),
+            _ => {}
+        }
+    }
+    if let Some(seconds) = max_age {
+        return Some(after(now, seconds.max(0) as u64));
+    }
+
+    if let Some(expires) = headers.get(EXPIRES).and_then(|value| value.to_str().ok()) {
+        // An unparseable date, such as "0", means already expired
+        return Some(match DateTime::parse_from_rfc2822(expires) {
+            Ok(expires) => expires.with_timezone(&Utc).max(now),
+            Err(_) => now,
+        });
+    }
+
+    Some(after(now, default_ttl.as_secs()))
+}
+
+/// `seconds` after `now`, with lifetimes capped at ten years
+fn after(now: DateTime<Utc>, seconds: u64) -> DateTime<Utc> {
+    const MAX_LIFETIME: u64 = 10 * 365 * 24 * 60 * 60;
+    now + chrono::Duration::seconds(seconds.min(MAX_LIFETIME) as i64)
+}
@@ -1,9 +1,13 @@
+pub mod checkpoints;
 pub mod database;
+pub mod http_cache;
 pub mod migrations;
 pub mod schema;
 pub mod session;
 pub mod tui_session;
 
+pub use checkpoints::CheckpointManager;
 pub use database::Database;
+pub use http_cache::HttpCache;
 pub use session::{Session, SessionManager, SessionMode};
 pub use tui_session::TuiSessionManager;
\ No newline at end of file
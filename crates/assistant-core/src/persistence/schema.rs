@@ -35,6 +35,33 @@ pub struct ChatMessageRecord {
     pub content: Option<String>,
     pub tool_calls: Option<serde_json::Value>,
     pub embedding: Option<Vec<u8>>,
+    /// On a compaction summary, the id of the first message it kept
+    pub kept_from: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Accumulated token usage for one session, source ("main" or a sub-agent tool name) and model
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SessionUsageRecord {
+    pub session_id: String,
+    pub source: String,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A file's contents from before the agent first changed it in a turn
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FileCheckpointRecord {
+    pub id: i64,
+    pub session_id: String,
+    /// The user request the change was made for
+    pub turn_id: String,
+    pub path: String,
+    /// `None` when the file didn't exist yet
+    pub content: Option<Vec<u8>>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -95,6 +122,7 @@ CREATE TABLE IF NOT EXISTS chat_messages (
     content TEXT,
     tool_calls TEXT,
     embedding BLOB,
+    kept_from TEXT,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     FOREIGN KEY (session_id) REFERENCES sessions(id)
 );
@@ -203,6 +231,45 @@ CREATE TABLE IF NOT EXISTS tui_sessions (
     FOREIGN KEY (chat_session_id) REFERENCES sessions(id)
 );
 
+-- Token usage totals per session, source and model
+CREATE TABLE IF NOT EXISTS session_usage (
+    session_id TEXT NOT NULL,
+    source TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    completion_tokens INTEGER NOT NULL DEFAULT 0,
+    cost REAL NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, source, model),
+    FOREIGN KEY (session_id) REFERENCES sessions(id)
+);
+
+-- Contents of files before the agent changed them, one row per session, turn and file
+CREATE TABLE IF NOT EXISTS file_checkpoints (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    turn_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    content BLOB,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (session_id, turn_id, path),
+    FOREIGN KEY (session_id) REFERENCES sessions(id)
+);
+
+-- Responses of web_fetch and web_search, keyed by URL or search query
+CREATE TABLE IF NOT EXISTS http_cache (
+    key TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    content_type TEXT,
+    body BLOB NOT NULL,
+    etag TEXT,
+    last_modified TEXT,
+    size INTEGER NOT NULL,
+    stored_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    accessed_at TIMESTAMP NOT NULL
+);
+
 -- Indexes for better performance
 CREATE INDEX IF NOT EXISTS idx_chat_messages_session_id ON chat_messages(session_id);
 CREATE INDEX IF NOT EXISTS idx_chat_messages_created_at ON chat_messages(created_at);
@@ -214,4 +281,6 @@ CREATE INDEX IF NOT EXISTS idx_memories_created_at ON memories(created_at);
 CREATE INDEX IF NOT EXISTS idx_memories_accessed_at ON memories(accessed_at);
 CREATE INDEX IF NOT EXISTS idx_tui_sessions_status ON tui_sessions(status);
 CREATE INDEX IF NOT EXISTS idx_tui_sessions_chat_session ON tui_sessions(chat_session_id);
+CREATE INDEX IF NOT EXISTS idx_file_checkpoints_session ON file_checkpoints(session_id);
+CREATE INDEX IF NOT EXISTS idx_http_cache_accessed_at ON http_cache(accessed_at);
 "#;
\ No newline at end of file
@@ -0,0 +1,302 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use tokio::process::Command;
+use crate::config::{Config, SandboxConfig};
+use crate::utils::path::expand_home;
+
+/// Temp directories writable inside the sandbox, besides `std::env::temp_dir()`
+const TEMP_DIRECTORIES: &[&str] = &["/tmp", "/var/tmp", "/dev/shm"];
+
+/// Device files commands may still write to, so redirections like `> /dev/null` keep working
+#[cfg(target_os = "linux")]
+const WRITABLE_DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/full", "/dev/tty", "/dev/random", "/dev/urandom", "/dev/ptmx", "/dev/pts"];
+
+/// Messages commands print when a write or a connection is refused
+const DENIED_MESSAGES: &[&str] = &["Permission denied", "Operation not permitted", "Read-only file system"];
+
+/// Messages commands print when they run out of address space
+const OUT_OF_MEMORY_MESSAGES: &[&str] = &["Cannot allocate memory", "cannot allocate memory", "memory exhausted", "MemoryError", "out of memory"];
+
+/// Restricts what `bash` commands and the file tools may change: writes are only
+/// allowed under the workspace, the temp directories and the configured
+/// `writable_paths`, and commands may also be cut off from the network and
+/// limited in CPU time, memory and processes.
+///
+/// Commands are confined by Landlock, which the kernel enforces on them and
+/// everything they start; the file tools check their paths with `check_write`.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    /// Canonical directories writes are allowed under
+    writable: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// The configured sandbox, or `None` when it is disabled. The workspace is
+    /// `session.workspace_path`, or the current directory when that isn't set.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if !config.sandbox.enabled {
+            return None;
+        }
+
+        let workspace = config.session.workspace_path.clone()
+            .or_else(|| std::env::current_dir().ok());
+        let candidates = workspace.into_iter()
+            .chain(std::iter::once(std::env::temp_dir()))
+            .chain(TEMP_DIRECTORIES.iter().map(PathBuf::from))
+            .chain(config.sandbox.writable_paths.iter().map(|path| expand_home(path)));
+
+        // Paths that don't exist can't be written to through the sandbox either
+        let mut writable: Vec<PathBuf> = Vec::new();
+        for path in candidates {
+            if let Ok(path) = path.canonicalize()
+                && !writable.contains(&path)
+            {
+                writable.push(path);
+            }
+        }
+
+        Some(Self { config: config.sandbox.clone(), writable })
+    }
+
+    /// Directories writes are allowed under
+    pub fn writable_roots(&self) -> &[PathBuf] {
+        &self.writable
+    }
+
+    /// Check that a file tool may write `path`, given with its symlinks resolved
+    pub fn check_write(&self, path: &Path) -> Result<()> {
+        if self.writable.iter().any(|root| path.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Sandbox: writing to '{}' is not allowed; writable locations are {}",
+                path.display(),
+                self.describe_roots()
+            ))
+        }
+    }
+
+    /// Confine a command before it is spawned: Landlock rules limit where it may
+    /// write and, without network access, refuse TCP binds and connections, and
+    /// the configured resource limits are set. Fails when the kernel can't
+    /// enforce the rules, rather than running the command unconfined.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cmd: &mut Command) -> std::io::Result<()> {
+        let ruleset = landlock::Ruleset::new(&self.writable, self.config.allow_network)?;
+        let limits = [
+            (libc::RLIMIT_CPU, self.config.max_cpu_seconds),
+            (libc::RLIMIT_AS, self.config.max_memory_mb.map(|mb| mb.saturating_mul(1024 * 1024))),
+            (libc::RLIMIT_NPROC, self.config.max_processes),
+        ];
+
+        // SAFETY: the hook runs between fork and exec, where it only makes system
+        // calls on values prepared beforehand
+        unsafe {
+            cmd.pre_exec(move || {
+                for (resource, value) in limits {
+                    if let Some(value) = value {
+                        let limit = libc::rlimit { rlim_cur: value as libc::rlim_t, rlim_max: value as libc::rlim_t };
+                        if libc::setrlimit(resource, &limit) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    }
+                }
+                ruleset.restrict_self()
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _cmd: &mut Command) -> std::io::Result<()> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "the sandbox is only available on Linux"))
+    }
+
+    /// Add a note to a command's output when it looks like the sandbox stopped it
+    pub fn annotate(&self, mut output: String) -> String {
+        let mut notes = Vec::new();
+        if DENIED_MESSAGES.iter().any(|message| output.contains(message)) {
+            let network = if self.config.allow_network { "" } else { ", and TCP connections are refused" };
+            notes.push(format!(
+                "The sandbox may have blocked this: commands can only write under {}{}.",
+                self.describe_roots(),
+                network
+            ));
+        }
+        if let Some(mb) = self.config.max_memory_mb
+            && OUT_OF_MEMORY_MESSAGES.iter().any(|message| output.contains(message))
+        {
+            notes.push(format!("The sandbox limits commands to {} MB of memory.", mb));
+        }
+        if let Some(seconds) = self.config.max_cpu_seconds
+            && (output.contains("terminated by signal") || output.contains("CPU time limit exceeded"))
+        {
+            notes.push(format!("The sandbox limits commands to {} seconds of CPU time.", seconds));
+        }
+
+        if !notes.is_empty() {
+            output.push_str("\n\n");
+            output.push_str(&notes.join("\n"));
+        }
+        output
+    }
+
+    fn describe_roots(&self) -> String {
+        self.writable.iter().map(|root| root.display().to_string()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// The Landlock ABI version the kernel supports, or `None` without Landlock
+pub fn landlock_abi() -> Option<i32> {
+    #[cfg(target_os = "linux")]
+    return landlock::abi();
+    #[cfg(not(target_os = "linux"))]
+    return None;
+}
+
+/// The few Landlock system calls the sandbox needs; see `landlock(7)`
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::fs::OpenOptions;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use super::WRITABLE_DEVICES;
+
+    const CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    /// ABI 2
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    /// ABI 3
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    /// ABI 4
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+    /// Every way of changing the filesystem known to ABI 1
+    const ACCESS_FS_WRITE: u64 = ACCESS_FS_WRITE_FILE | ACCESS_FS_REMOVE_DIR | ACCESS_FS_REMOVE_FILE
+        | ACCESS_FS_MAKE_CHAR | ACCESS_FS_MAKE_DIR | ACCESS_FS_MAKE_REG | ACCESS_FS_MAKE_SOCK
+        | ACCESS_FS_MAKE_FIFO | ACCESS_FS_MAKE_BLOCK | ACCESS_FS_MAKE_SYM;
+
+    /// The only rights a rule for a file, rather than a directory, may grant
+    const ACCESS_FILE: u64 = ACCESS_FS_WRITE_FILE | ACCESS_FS_TRUNCATE;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    pub fn abi() -> Option<i32> {
+        // SAFETY: asking for the version passes no attributes
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        (abi > 0).then_some(abi as i32)
+    }
+
+    /// A ruleset that only allows writes under the given paths, to be enforced on a
+    /// command between fork and exec
+    pub struct Ruleset {
+        fd: Arc<OwnedFd>,
+    }
+
+    impl Ruleset {
+        pub fn new(writable: &[PathBuf], allow_network: bool) -> io::Result<Self> {
+            let abi = abi().ok_or_else(|| io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the sandbox needs Landlock, which this kernel doesn't support or has disabled",
+            ))?;
+
+            let mut handled_fs = ACCESS_FS_WRITE;
+            if abi >= 2 {
+                handled_fs |= ACCESS_FS_REFER;
+            }
+            if abi >= 3 {
+                handled_fs |= ACCESS_FS_TRUNCATE;
+            }
+            let handled_net = match (allow_network, abi >= 4) {
+                (true, _) => 0,
+                (false, true) => ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP,
+                (false, false) => return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "denying network access needs Landlock ABI 4 (Linux 6.7 or later)",
+                )),
+            };
+
+            let attr = RulesetAttr { handled_access_fs: handled_fs, handled_access_net: handled_net };
+            // Older kernels reject the network field, even when zero
+            let size = if abi >= 4 { size_of::<RulesetAttr>() } else { size_of::<u64>() };
+            // SAFETY: `attr` outlives the call and `size` doesn't exceed it
+            let fd = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, &attr as *const RulesetAttr, size, 0u32) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: the call returned a new file descriptor, owned by nothing else
+            let fd = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+            let devices = WRITABLE_DEVICES.iter().map(Path::new);
+            for path in writable.iter().map(PathBuf::as_path).chain(devices) {
+                let Ok(file) = OpenOptions::new().read(true).custom_flags(libc::O_PATH | libc::O_CLOEXEC).open(path) else {
+                    continue;
+                };
+                let allowed = if path.is_dir() { handled_fs } else { handled_fs & ACCESS_FILE };
+                let rule = PathBeneathAttr { allowed_access: allowed, parent_fd: file.as_raw_fd() };
+                // SAFETY: both descriptors are open and `rule` outlives the call
+                let result = unsafe {
+                    libc::syscall(
+                        libc::SYS_landlock_add_rule,
+                        fd.as_raw_fd(),
+                        RULE_PATH_BENEATH,
+                        &rule as *const PathBeneathAttr,
+                        0u32,
+                    )
+                };
+                if result < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+
+            Ok(Self { fd: Arc::new(fd) })
+        }
+
+        /// Enforce the ruleset on the calling process and everything it starts
+        pub fn restrict_self(&self) -> io::Result<()> {
+            // SAFETY: plain system calls on an open descriptor
+            unsafe {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::syscall(libc::SYS_landlock_restrict_self, self.fd.as_raw_fd(), 0u32) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+    }
+}
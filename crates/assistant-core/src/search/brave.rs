@@ -0,0 +1,74 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use super::{SearchBackend, SearchResult, check_status, plain_text};
+
+const DEFAULT_URL: &str = "https://api.search.brave.com/res/v1";
+
+/// Most results the Brave API returns for one request
+const MAX_COUNT: usize = 20;
+
+/// The Brave Search API
+pub struct BraveBackend {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    web: Option<WebResults>,
+}
+
+#[derive(Deserialize)]
+struct WebResults {
+    #[serde(default)]
+    results: Vec<Hit>,
+}
+
+#[derive(Deserialize)]
+struct Hit {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    description: String,
+}
+
+impl BraveBackend {
+    pub fn new(client: Client, api_key: String, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| DEFAULT_URL.to_string()).trim_end_matches('/').to_string();
+        Self { client, api_key, base_url }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for BraveBackend {
+    fn name(&self) -> &'static str {
+        "brave"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let count = limit.clamp(1, MAX_COUNT).to_string();
+        let response = self.client
+            .get(format!("{}/web/search", self.base_url))
+            .query(&[("q", query), ("count", &count)])
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .send()
+            .await?;
+        let response: Response = check_status(response).await?.json().await?;
+        // Titles and descriptions come with the matches in <strong>
+        Ok(response.web.map(|web| web.results).unwrap_or_default()
+            .into_iter()
+            .take(limit)
+            .map(|hit| SearchResult {
+                title: plain_text(&hit.title),
+                url: hit.url,
+                description: plain_text(&hit.description),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use scraper::{Html, Selector};
+use super::{SearchBackend, SearchResult, check_status};
+
+const DEFAULT_URL: &str = "https://html.duckduckgo.com/html/";
+
+/// DuckDuckGo's HTML results page, scraped
+pub struct DuckDuckGoBackend {
+    client: Client,
+    url: String,
+}
+
+impl DuckDuckGoBackend {
+    pub fn new(client: Client, base_url: Option<String>) -> Self {
+        Self { client, url: base_url.unwrap_or_else(|| DEFAULT_URL.to_string()) }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for DuckDuckGoBackend {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let response = self.client.get(&self.url).query(&[("q", query)]).send().await?;
+        let html = check_status(response).await?.text().await?;
+        Ok(parse_results(&html, limit))
+    }
+}
+
+/// The results on a DuckDuckGo HTML page
+pub fn parse_results(html: &str, limit: usize) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let mut results = Vec::new();
+    
+    // DuckDuckGo HTML results structure:
+    // Results are in div with class "result results_links results_links_deep web-result"
+    // Title is in h2 > a.result__a
+    // URL is in a.result__a href attribute (needs extraction from redirect URL)
+    // Description is in a.result__snippet
+    
+    let result_selector = Selector::parse("div.result.results_links.results_links_deep.web-result").unwrap();
+    let title_selector = Selector::parse("h2 > a.result__a").unwrap();
+    let snippet_selector = Selector::parse("a.result__snippet").unwrap();
+    
+    for result_element in document.select(&result_selector).take(limit) {
+        let title_element = result_element.select(&title_selector).next();
+        
+        let title = title_element
+            .as_ref()
+            .map(|el| el.text().collect::<String>())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        
+        // Extract URL from the href attribute of the title link
+        let url = title_element
+            .and_then(|el| el.value().attr("href"))
+            .and_then(|href| {
+                // DuckDuckGo wraps URLs in a redirect, extract the actual URL
+                if href.contains("uddg=") {
+                    href.split("uddg=")
+                        .nth(1)
+                        .and_then(|u| u.split('&').next())
+                        .and_then(|u| urlencoding::decode(u).ok())
+                        .map(|u| u.into_owned())
+                } else {
+                    Some(href.to_string())
+                }
+            })
+            .unwrap_or_default();
+        
+        let description = result_element
+            .select(&snippet_selector)
+            .next()
+            .map(|el| el.text().collect::<String>())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        
+        if !title.is_empty() && !url.is_empty() {
+            results.push(SearchResult {
+                title,
+                url,
+                description,
+            });
+        }
+    }
+    
+    results
+}
@@ -0,0 +1,140 @@
+//! Web search engines `web_search` can query, tried in the configured order
+
+pub mod brave;
+pub mod duckduckgo;
+pub mod searxng;
+pub mod tavily;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+
+pub use brave::BraveBackend;
+pub use duckduckgo::DuckDuckGoBackend;
+pub use searxng::SearxngBackend;
+pub use tavily::TavilyBackend;
+
+/// One search hit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+}
+
+/// A search engine. Implementations turn the engine's response into
+/// `SearchResult`s and fail on anything they can't read, so `web_search` can
+/// move on to the next backend.
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Name of the backend, as used in the configuration
+    fn name(&self) -> &'static str;
+
+    /// Search for `query`, returning at most `limit` results
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>>;
+}
+
+/// One entry of `tools.web_search.backends`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SearchBackendConfig {
+    /// DuckDuckGo's HTML results page; needs no key
+    Duckduckgo {
+        #[serde(default)]
+        base_url: Option<String>,
+    },
+    /// A SearXNG instance with the JSON format enabled
+    Searxng {
+        base_url: String,
+    },
+    /// The Brave Search API; the key falls back to `BRAVE_API_KEY`
+    Brave {
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        base_url: Option<String>,
+    },
+    /// The Tavily search API; the key falls back to `TAVILY_API_KEY`
+    Tavily {
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        base_url: Option<String>,
+    },
+}
+
+impl SearchBackendConfig {
+    /// The URL configured for the backend, if not its default
+    pub fn base_url(&self) -> Option<&str> {
+        match self {
+            Self::Duckduckgo { base_url } | Self::Brave { base_url, .. } | Self::Tavily { base_url, .. } => base_url.as_deref(),
+            Self::Searxng { base_url } => Some(base_url),
+        }
+    }
+}
+
+/// The backends configured under `tools.web_search.backends`, in order, or
+/// DuckDuckGo alone when none are
+pub fn configured_backends(config: &Config) -> Vec<SearchBackendConfig> {
+    config.get_tool_config("web_search")
+        .and_then(|tool| tool.settings.get("backends"))
+        .and_then(|backends| {
+            serde_json::from_value::<Vec<SearchBackendConfig>>(backends.clone())
+                .inspect_err(|e| tracing::warn!("Ignoring tools.web_search.backends: {}", e))
+                .ok()
+        })
+        .unwrap_or_else(|| vec![SearchBackendConfig::Duckduckgo { base_url: None }])
+}
+
+/// Create the `backends` that can be used, leaving out those missing an API key
+pub fn create_backends(backends: Vec<SearchBackendConfig>, client: &Client) -> Vec<Box<dyn SearchBackend>> {
+    backends.into_iter()
+        .filter_map(|backend| {
+            let created = create_backend(backend, client);
+            if let Err(e) = &created {
+                tracing::warn!("Not searching with a backend: {}", e);
+            }
+            created.ok()
+        })
+        .collect()
+}
+
+fn create_backend(config: SearchBackendConfig, client: &Client) -> Result<Box<dyn SearchBackend>> {
+    let client = client.clone();
+    Ok(match config {
+        SearchBackendConfig::Duckduckgo { base_url } => Box::new(DuckDuckGoBackend::new(client, base_url)),
+        SearchBackendConfig::Searxng { base_url } => Box::new(SearxngBackend::new(client, base_url)),
+        SearchBackendConfig::Brave { api_key, base_url } => {
+            Box::new(BraveBackend::new(client, api_key_or_env(api_key, "brave", "BRAVE_API_KEY")?, base_url))
+        }
+        SearchBackendConfig::Tavily { api_key, base_url } => {
+            Box::new(TavilyBackend::new(client, api_key_or_env(api_key, "tavily", "TAVILY_API_KEY")?, base_url))
+        }
+    })
+}
+
+fn api_key_or_env(api_key: Option<String>, backend: &str, variable: &str) -> Result<String> {
+    api_key
+        .or_else(|| std::env::var(variable).ok())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("{} needs an api_key or the {} environment variable", backend, variable))
+}
+
+/// Fail with the engine's status and the start of its response when it didn't answer 2xx
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    anyhow::bail!("HTTP {}: {}", status, body.chars().take(200).collect::<String>().trim())
+}
+
+/// The text of an HTML snippet, for engines that mark up matches in their results
+fn plain_text(html: &str) -> String {
+    let fragment = scraper::Html::parse_fragment(html);
+    let text: String = fragment.root_element().text().collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
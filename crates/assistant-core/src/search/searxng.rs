@@ -0,0 +1,57 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use super::{SearchBackend, SearchResult, check_status};
+
+/// A SearXNG instance's JSON API. The instance must list `json` under
+/// `search.formats` in its settings.
+pub struct SearxngBackend {
+    client: Client,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    results: Vec<Hit>,
+}
+
+#[derive(Deserialize)]
+struct Hit {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+impl SearxngBackend {
+    pub fn new(client: Client, base_url: String) -> Self {
+        Self { client, base_url: base_url.trim_end_matches('/').to_string() }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for SearxngBackend {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let response = self.client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await?;
+        let response: Response = check_status(response).await?.json().await?;
+        Ok(response.results.into_iter()
+            .take(limit)
+            .map(|hit| SearchResult {
+                title: hit.title.trim().to_string(),
+                url: hit.url,
+                description: hit.content.unwrap_or_default().trim().to_string(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use super::{SearchBackend, SearchResult, check_status};
+
+const DEFAULT_URL: &str = "https://api.tavily.com";
+
+/// Most results the Tavily API returns for one request
+const MAX_RESULTS: usize = 20;
+
+/// The Tavily search API
+pub struct TavilyBackend {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    results: Vec<Hit>,
+}
+
+#[derive(Deserialize)]
+struct Hit {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl TavilyBackend {
+    pub fn new(client: Client, api_key: String, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| DEFAULT_URL.to_string()).trim_end_matches('/').to_string();
+        Self { client, api_key, base_url }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for TavilyBackend {
+    fn name(&self) -> &'static str {
+        "tavily"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let response = self.client
+            .post(format!("{}/search", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "query": query,
+                "max_results": limit.clamp(1, MAX_RESULTS),
+            }))
+            .send()
+            .await?;
+        let response: Response = check_status(response).await?.json().await?;
+        Ok(response.results.into_iter()
+            .take(limit)
+            .map(|hit| SearchResult {
+                title: hit.title.trim().to_string(),
+                url: hit.url,
+                description: hit.content.trim().to_string(),
+            })
+            .collect())
+    }
+}
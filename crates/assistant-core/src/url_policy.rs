@@ -0,0 +1,251 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect;
+use url::{Host, Url};
+use crate::config::UrlPolicyConfig;
+
+/// Most redirects followed for one request, as reqwest does by default
+const MAX_REDIRECTS: usize = 10;
+
+/// Decides which URLs the web tools may request, so a model can't use them to
+/// reach this machine, the local network or a cloud metadata endpoint.
+///
+/// The scheme and domain lists are checked on the URL and on every redirect.
+/// Addresses are checked when a host name is resolved, in the client's DNS
+/// resolver, so the address checked is the one connected to. For that reason
+/// clients ignore proxies, which would resolve the host themselves. Hosts the
+/// configuration itself names, such as a local search engine, can be trusted
+/// with `trusting`.
+#[derive(Debug, Clone)]
+pub struct UrlPolicy {
+    config: Arc<UrlPolicyConfig>,
+    /// Hosts exempt from the policy
+    trusted_hosts: Arc<[String]>,
+}
+
+/// A request the policy refused
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyViolation {
+    url: String,
+    reason: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "URL policy violation: '{}' {}", self.url, self.reason)
+    }
+}
+
+impl Error for PolicyViolation {}
+
+impl UrlPolicy {
+    pub fn new(config: &UrlPolicyConfig) -> Self {
+        Self { config: Arc::new(config.clone()), trusted_hosts: Arc::new([]) }
+    }
+
+    /// Exempt `hosts` from the policy
+    pub fn trusting(self, hosts: impl IntoIterator<Item = String>) -> Self {
+        let trusted = self.trusted_hosts.iter()
+            .cloned()
+            .chain(hosts.into_iter().map(|host| host.to_ascii_lowercase()))
+            .collect();
+        Self { trusted_hosts: trusted, ..self }
+    }
+
+    /// Check the scheme and host of `url`. Host names are only checked against
+    /// the domain lists here; their addresses are checked once resolved.
+    pub fn check_url(&self, url: &Url) -> Result<(), PolicyViolation> {
+        let violation = |reason: String| PolicyViolation { url: url.to_string(), reason };
+
+        if !self.config.allowed_schemes.iter().any(|scheme| scheme.eq_ignore_ascii_case(url.scheme())) {
+            return Err(violation(format!(
+                "uses the scheme '{}'; allowed schemes are: {}",
+                url.scheme(),
+                self.config.allowed_schemes.join(", ")
+            )));
+        }
+        let Some(host) = url.host() else {
+            return Err(violation("has no host".to_string()));
+        };
+        let name = host.to_string().trim_matches(['[', ']']).to_ascii_lowercase();
+        if self.is_trusted(&name) {
+            return Ok(());
+        }
+
+        if let Some(domain) = self.config.denied_domains.iter().find(|domain| in_domain(&name, domain)) {
+            return Err(violation(format!("is in the denied domain '{}'", domain)));
+        }
+        if !self.config.allowed_domains.is_empty()
+            && !self.config.allowed_domains.iter().any(|domain| in_domain(&name, domain))
+        {
+            return Err(violation(format!(
+                "is not in an allowed domain; allowed domains are: {}",
+                self.config.allowed_domains.join(", ")
+            )));
+        }
+
+        // Addresses written into the URL are never resolved, so they are checked now
+        let address = match host {
+            Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+            Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+            Host::Domain(_) => None,
+        };
+        match address {
+            Some(ip) => self.check_address(&ip).map_err(violation),
+            None => Ok(()),
+        }
+    }
+
+    /// A client builder that enforces the policy on every connection and
+    /// redirect. It connects directly, ignoring `HTTP_PROXY` and the like.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        let policy = self.clone();
+        let redirects = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error(format!("too many redirects (more than {})", MAX_REDIRECTS));
+            }
+            match policy.check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(violation) => attempt.error(violation),
+            }
+        });
+        reqwest::Client::builder()
+            .no_proxy()
+            .redirect(redirects)
+            .dns_resolver(Arc::new(PolicyResolver { policy: self.clone() }))
+    }
+
+    /// The policy violation that made a request fail, if that is why it failed
+    pub fn violation(error: &reqwest::Error) -> Option<&PolicyViolation> {
+        let mut source = error.source();
+        while let Some(error) = source {
+            if let Some(violation) = error.downcast_ref::<PolicyViolation>() {
+                return Some(violation);
+            }
+            source = error.source();
+        }
+        None
+    }
+
+    fn is_trusted(&self, host: &str) -> bool {
+        self.trusted_hosts.iter().any(|trusted| trusted == host)
+    }
+
+    /// Why requests to `ip` are refused, if they are
+    fn check_address(&self, ip: &IpAddr) -> Result<(), String> {
+        let range = match classify(ip) {
+            Range::Public => return Ok(()),
+            Range::Loopback if self.config.allow_loopback => return Ok(()),
+            Range::Private(_) if self.config.allow_private_networks => return Ok(()),
+            Range::Loopback => "a loopback address; set url_policy.allow_loopback to allow it".to_string(),
+            Range::Private(kind) => format!("a {} address; set url_policy.allow_private_networks to allow it", kind),
+            Range::Reserved(kind) => format!("a {} address", kind),
+        };
+        Err(format!("resolves to {}, {}", ip, range))
+    }
+}
+
+/// Resolves host names with the system resolver, refusing those with an
+/// address the policy blocks
+struct PolicyResolver {
+    policy: UrlPolicy,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        let host = name.as_str().to_ascii_lowercase();
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if !policy.is_trusted(&host) {
+                // One blocked address refuses the host, so it can't be reached by retrying
+                for address in &addresses {
+                    if let Err(reason) = policy.check_address(&address.ip()) {
+                        return Err(Box::new(PolicyViolation { url: host, reason }) as Box<dyn Error + Send + Sync>);
+                    }
+                }
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn in_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches("*.").trim_end_matches('.').to_ascii_lowercase();
+    let host = host.trim_end_matches('.');
+    host == domain || host.strip_suffix(domain.as_str()).is_some_and(|rest| rest.ends_with('.'))
+}
+
+enum Range {
+    Public,
+    Loopback,
+    /// Addresses of local networks, named
+    Private(&'static str),
+    /// Addresses nothing should be requested from, named
+    Reserved(&'static str),
+}
+
+fn classify(ip: &IpAddr) -> Range {
+    match ip {
+        IpAddr::V4(ip) => classify_v4(ip),
+        IpAddr::V6(ip) => classify_v6(ip),
+    }
+}
+
+fn classify_v4(ip: &Ipv4Addr) -> Range {
+    let [a, b, ..] = ip.octets();
+    if ip.is_loopback() || ip.is_unspecified() || a == 0 {
+        // 0.0.0.0 reaches this machine on most systems
+        Range::Loopback
+    } else if ip.is_private() {
+        Range::Private("private")
+    } else if ip.is_link_local() {
+        Range::Private("link-local")
+    } else if a == 100 && (64..128).contains(&b) {
+        Range::Private("shared (carrier-grade NAT)")
+    } else if ip.is_broadcast() || ip.is_multicast() || a >= 240 {
+        Range::Reserved("broadcast, multicast or reserved")
+    } else if ip.is_documentation() || (a == 198 && (18..20).contains(&b)) || (a == 192 && b == 0 && ip.octets()[2] == 0) {
+        Range::Reserved("documentation, benchmarking or protocol assignment")
+    } else {
+        Range::Public
+    }
+}
+
+fn classify_v6(ip: &Ipv6Addr) -> Range {
+    if ip.is_loopback() || ip.is_unspecified() {
+        return Range::Loopback;
+    }
+
+    // IPv4 addresses in IPv6 clothing: mapped, IPv4-compatible, translated by
+    // NAT64, or tunnelled by 6to4
+    let embedded = |high: u16, low: u16| Ipv4Addr::from(((high as u32) << 16) | low as u32);
+    if let Some(ipv4) = ip.to_ipv4_mapped() {
+        return classify_v4(&ipv4);
+    }
+    let segments = ip.segments();
+    if segments[..6] == [0, 0, 0, 0, 0, 0] || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        return classify_v4(&embedded(segments[6], segments[7]));
+    }
+    if segments[0] == 0x2002 {
+        return classify_v4(&embedded(segments[1], segments[2]));
+    }
+
+    if segments[0] & 0xfe00 == 0xfc00 {
+        Range::Private("unique local")
+    } else if segments[0] & 0xffc0 == 0xfe80 {
+        Range::Private("link-local")
+    } else if segments[0] & 0xffc0 == 0xfec0 {
+        Range::Private("site-local")
+    } else if ip.is_multicast() {
+        Range::Reserved("multicast")
+    } else if segments[0] == 0x2001 && segments[1] == 0x0db8 {
+        Range::Reserved("documentation")
+    } else {
+        Range::Public
+    }
+}
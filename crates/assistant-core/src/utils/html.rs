@@ -0,0 +1,237 @@
+//! Reading the main content of a web page, without its navigation, banners and
+//! footers, in the spirit of Readability.
+
+use std::collections::HashMap;
+
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+/// Width the page text is wrapped to
+const TEXT_WIDTH: usize = 80;
+
+/// Most links listed for one page
+const MAX_LINKS: usize = 200;
+
+/// Elements that are never part of a page's content
+const BOILERPLATE: &str = "script, style, noscript, template, svg, canvas, iframe, form, button, dialog, nav, aside, footer, \
+    [role=navigation], [role=banner], [role=contentinfo], [role=complementary], [role=dialog], \
+    [aria-hidden=true], [hidden]";
+
+/// Words in a class or id that mark menus, banners and other page furniture
+const BOILERPLATE_HINTS: &[&str] = &[
+    "cookie", "consent", "gdpr", "banner", "newsletter", "subscribe", "share", "social",
+    "breadcrumb", "sidebar", "related", "comment", "advert", "promo", "popup", "modal", "menu",
+];
+
+/// Elements that hold the content itself on well-marked pages, most specific first
+const CONTENT_ROOTS: &[&str] = &["[itemprop=articleBody]", "article", "main, [role=main]"];
+
+/// The text a content root needs to be trusted over paragraph scoring
+const MIN_CONTENT_CHARS: usize = 140;
+
+/// The readable part of a web page
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub title: Option<String>,
+    /// The main content as plain text
+    pub text: String,
+    /// Links in the main content, with absolute URLs
+    pub links: Vec<Link>,
+    /// Links left out of `links` beyond `MAX_LINKS`
+    pub more_links: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub text: String,
+    pub url: String,
+}
+
+impl Page {
+    /// The page as one document: the text, then the link list
+    pub fn to_text(&self) -> String {
+        let mut text = self.text.trim_end().to_string();
+        if !self.links.is_empty() {
+            text.push_str("\n\nLinks:\n");
+            for link in &self.links {
+                text.push_str(&format!("- [{}]({})\n", link.text, link.url));
+            }
+            if self.more_links > 0 {
+                text.push_str(&format!("- ... and {} more\n", self.more_links));
+            }
+        }
+        text
+    }
+}
+
+/// Extract the main content of an HTML page fetched from `url`
+pub fn extract_page(html: &str, url: &Url) -> Page {
+    let mut document = Html::parse_document(html);
+    let title = title(&document);
+    let base = base_url(&document, url);
+
+    // Detached first, so they count neither for finding the content nor in it
+    let boilerplate = selector(BOILERPLATE);
+    let furniture: Vec<_> = document.root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| is_boilerplate(*element, &boilerplate))
+        .map(|element| element.id())
+        .collect();
+    for id in furniture {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let root = main_content(&document);
+    let text = html2text::config::plain()
+        .link_footnotes(false)
+        .string_from_read(root.html().as_bytes(), TEXT_WIDTH)
+        .unwrap_or_else(|_| root.text().collect::<Vec<_>>().join(" "));
+    let (links, more_links) = links(root, &base);
+
+    Page { title, text: tidy(&text), links, more_links }
+}
+
+fn selector(selectors: &str) -> Selector {
+    Selector::parse(selectors).expect("valid selector")
+}
+
+fn title(document: &Html) -> Option<String> {
+    ["title", "h1"].iter()
+        .filter_map(|name| document.select(&selector(name)).next())
+        .map(|element| normalize(&element.text().collect::<String>()))
+        .find(|title| !title.is_empty())
+}
+
+/// The page's `<base href>`, which relative links are resolved against, or its URL
+fn base_url(document: &Html, url: &Url) -> Url {
+    document.select(&selector("base[href]"))
+        .next()
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| url.join(href).ok())
+        .unwrap_or_else(|| url.clone())
+}
+
+fn is_boilerplate(element: ElementRef, boilerplate: &Selector) -> bool {
+    let name = element.value().name();
+    if matches!(name, "html" | "head" | "body" | "main" | "article") {
+        return false;
+    }
+    if boilerplate.matches(&element) {
+        return true;
+    }
+    // Page headers go, but an article's own header holds its title and byline
+    if name == "header" {
+        return !element.ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| matches!(ancestor.value().name(), "article" | "main"));
+    }
+    let marks = format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or_default(),
+        element.value().attr("id").unwrap_or_default()
+    ).to_lowercase();
+    BOILERPLATE_HINTS.iter().any(|hint| marks.contains(hint))
+}
+
+/// The element holding the page's content: a marked content root when there is
+/// a substantial one, else the element with the most paragraph text, else the body
+fn main_content(document: &Html) -> ElementRef<'_> {
+    let body = document.select(&selector("body")).next().unwrap_or_else(|| document.root_element());
+    let total = text_len(body);
+
+    for roots in CONTENT_ROOTS {
+        let best = document.select(&selector(roots)).max_by_key(|element| text_len(*element));
+        if let Some(best) = best {
+            let len = text_len(best);
+            if len >= MIN_CONTENT_CHARS && len * 3 >= total {
+                return best;
+            }
+        }
+    }
+
+    // Paragraphs score for their parent, and half as much for their grandparent
+    let mut scores: HashMap<_, (ElementRef, f64)> = HashMap::new();
+    for paragraph in document.select(&selector("p, pre, blockquote, td")) {
+        let len = text_len(paragraph);
+        if len < 25 {
+            continue;
+        }
+        let commas = paragraph.text().map(|text| text.matches(',').count()).sum::<usize>();
+        let score = 1.0 + commas as f64 + (len / 100).min(3) as f64;
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (ancestor, share) in ancestors.zip([1.0, 0.5]) {
+            scores.entry(ancestor.id()).or_insert((ancestor, 0.0)).1 += score * share;
+        }
+    }
+    scores.into_values()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)
+        .filter(|element| text_len(*element) * 2 >= total)
+        .unwrap_or(body)
+}
+
+fn text_len(element: ElementRef) -> usize {
+    element.text().map(|text| text.trim().chars().count()).sum()
+}
+
+/// The content's links, resolved against `base`, without duplicates or
+/// in-page anchors, and how many were left out past `MAX_LINKS`
+fn links(root: ElementRef, base: &Url) -> (Vec<Link>, usize) {
+    let mut links: Vec<Link> = Vec::new();
+    let mut more = 0;
+    for anchor in root.select(&selector("a[href]")) {
+        let href = anchor.value().attr("href").unwrap_or_default().trim();
+        if href.is_empty() || href.starts_with('#') {
+            continue;
+        }
+        let Ok(mut url) = base.join(href) else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            continue;
+        }
+        url.set_fragment(None);
+        let url = url.to_string();
+        if links.iter().any(|link| link.url == url) {
+            continue;
+        }
+        if links.len() == MAX_LINKS {
+            more += 1;
+            continue;
+        }
+        let text = normalize(&anchor.text().collect::<String>());
+        let text = match text.is_empty() {
+            true => anchor.value().attr("title").map(normalize).unwrap_or_else(|| url.clone()),
+            false => text,
+        };
+        links.push(Link { text, url });
+    }
+    (links, more)
+}
+
+/// Collapse runs of whitespace to single spaces
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trim trailing spaces and runs of blank lines left where furniture was removed
+fn tidy(text: &str) -> String {
+    let mut tidy = String::new();
+    let mut blank = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank += 1;
+            if blank > 1 || tidy.is_empty() {
+                continue;
+            }
+        } else {
+            blank = 0;
+        }
+        tidy.push_str(line);
+        tidy.push('\n');
+    }
+    tidy
+}
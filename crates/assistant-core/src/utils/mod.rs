@@ -1 +1,2 @@
+pub mod html;
 pub mod path;
\ No newline at end of file
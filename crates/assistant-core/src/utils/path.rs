@@ -1,5 +1,10 @@
-use std::path::{Path, PathBuf};
-use anyhow::{Result, anyhow};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{Result, anyhow, bail};
+use crate::config::Config;
+
+/// Most symlinks followed while normalizing one path, as in Linux's `MAXSYMLINKS`
+const MAX_SYMLINKS: usize = 40;
 
 /// Resolve a path (relative or absolute) to a canonical absolute path
 pub fn resolve_path(path: &str) -> Result<PathBuf> {
@@ -19,10 +24,146 @@ pub fn resolve_path(path: &str) -> Result<PathBuf> {
         .map_err(|e| anyhow!("Cannot access path '{}': {}", path.display(), e))
 }
 
-/// Check if a path is within the allowed workspace
-/// For now, this is a placeholder - we'll implement proper sandboxing later
-pub fn validate_path_access(_path: &Path) -> Result<()> {
-    // TODO: Implement workspace/sandbox validation
-    // For now, allow all paths
+/// Resolve a path to an absolute one without symlinks, `.` or `..`, like
+/// `canonicalize` but also for paths whose last components don't exist yet.
+/// Symlinks are followed even when they dangle, so a link to a file still to be
+/// created resolves to where that file would be.
+pub fn normalize_path(path: &Path) -> Result<PathBuf> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|e| anyhow!("Failed to get current directory: {}", e))?
+            .join(path)
+    };
+    
+    let mut resolved = PathBuf::new();
+    // Components still to resolve, last one first
+    let mut pending: Vec<OsString> = Vec::new();
+    push_components(&mut pending, &mut resolved, &absolute);
+    let mut symlinks = 0;
+    
+    while let Some(component) = pending.pop() {
+        if component == ".." {
+            resolved.pop();
+            continue;
+        }
+        let candidate = resolved.join(&component);
+        match std::fs::symlink_metadata(&candidate) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                symlinks += 1;
+                if symlinks > MAX_SYMLINKS {
+                    bail!("Too many levels of symbolic links in '{}'", path.display());
+                }
+                let target = std::fs::read_link(&candidate)
+                    .map_err(|e| anyhow!("Cannot read link '{}': {}", candidate.display(), e))?;
+                // An absolute target starts over from its root, a relative one from the link's directory
+                push_components(&mut pending, &mut resolved, &target);
+            }
+            _ => resolved = candidate,
+        }
+    }
+    
+    Ok(resolved)
+}
+
+/// Queue a path's components for `normalize_path`, restarting from its root if it has one
+fn push_components(pending: &mut Vec<OsString>, resolved: &mut PathBuf, path: &Path) {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => {
+                components.clear();
+                resolved.clear();
+                resolved.push(component.as_os_str());
+            }
+            Component::CurDir => {}
+            Component::ParentDir => components.push(OsString::from("..")),
+            Component::Normal(name) => components.push(name.to_os_string()),
+        }
+    }
+    pending.extend(components.into_iter().rev());
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Where a file tool may go: the session's workspace, the extra roots configured
+/// for the session and the tool's own `allowed_paths`. Without a workspace every
+/// path is allowed.
+#[derive(Debug, Clone, Default)]
+pub struct PathAccess {
+    /// Normalized roots, or `None` when access is unrestricted
+    roots: Option<Vec<PathBuf>>,
+}
+
+impl PathAccess {
+    /// Access allowed to the tool named `tool`
+    pub fn for_tool(config: &Config, tool: &str) -> Self {
+        let Some(workspace) = &config.session.workspace_path else {
+            return Self::default();
+        };
+        let exceptions = config.tools.configs.get(tool)
+            .map(|tool_config| tool_config.allowed_paths.as_slice())
+            .unwrap_or_default();
+        
+        let roots = std::iter::once(workspace)
+            .chain(&config.session.extra_roots)
+            .chain(exceptions)
+            .filter_map(|root| normalize_path(&expand_home(root)).ok())
+            .collect();
+        Self { roots: Some(roots) }
+    }
+    
+    /// The directories access is limited to, or `None` when it isn't
+    pub fn roots(&self) -> Option<&[PathBuf]> {
+        self.roots.as_deref()
+    }
+}
+
+/// Check that a tool may access `path`. The path is normalized first, so neither
+/// `..` nor a symlink can lead outside the allowed roots.
+pub fn validate_path_access(path: &Path, access: &PathAccess) -> Result<()> {
+    let Some(roots) = &access.roots else {
+        return Ok(());
+    };
+    
+    let resolved = normalize_path(path)?;
+    if roots.iter().any(|root| resolved.starts_with(root)) {
+        return Ok(());
+    }
+    
+    let allowed = roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>().join(", ");
+    if resolved == path {
+        Err(anyhow!("Access denied: '{}' is outside the workspace; allowed locations are {}", path.display(), allowed))
+    } else {
+        Err(anyhow!(
+            "Access denied: '{}' resolves to '{}', outside the workspace; allowed locations are {}",
+            path.display(),
+            resolved.display(),
+            allowed
+        ))
+    }
+}
+
+/// Check that a glob `pattern` stays under the directory it is matched in. With
+/// access restricted it may be neither absolute nor climb out with `..`; matches
+/// still need `validate_path_access`, as a symlink can lead elsewhere.
+pub fn validate_pattern_access(pattern: &str, access: &PathAccess) -> Result<()> {
+    if access.roots.is_none() {
+        return Ok(());
+    }
+    let path = Path::new(pattern);
+    if path.is_absolute() || path.components().any(|component| component == Component::ParentDir) {
+        bail!(
+            "Access denied: pattern '{}' must be relative to the search directory and may not contain '..'",
+            pattern
+        );
+    }
     Ok(())
-}
\ No newline at end of file
+}
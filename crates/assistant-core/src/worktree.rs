@@ -0,0 +1,335 @@
+//! Git worktrees the agent works in instead of the user's checkout, so its
+//! changes stay on their own branch until they are reviewed and merged back
+//! or discarded.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use git2::{
+    BranchType, DiffFormat, DiffOptions, IndexAddOption, MergeOptions, Repository, StatusOptions,
+    WorktreeAddOptions, WorktreePruneOptions,
+    build::CheckoutBuilder,
+};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::utils::path::normalize_path;
+
+/// Git's name for the agent's worktrees starts with this
+const WORKTREE_PREFIX: &str = "assistant-";
+
+/// The agent's branches start with this
+const BRANCH_PREFIX: &str = "assistant/";
+
+/// A worktree the agent works in, on a branch of its own
+#[derive(Debug, Clone)]
+pub struct AgentWorktree {
+    /// The worktree's name in git, e.g. `assistant-1a2b3c4d`
+    pub name: String,
+    pub branch: String,
+    pub path: PathBuf,
+}
+
+/// What an agent worktree changed since it branched off
+#[derive(Debug)]
+pub struct WorktreeDiff {
+    pub patch: String,
+    pub files: usize,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+impl WorktreeDiff {
+    /// One line counting the changes
+    pub fn summary(&self) -> String {
+        match self.files {
+            0 => "No changes".to_string(),
+            1 => format!("1 file changed, +{} -{}", self.additions, self.deletions),
+            files => format!("{} files changed, +{} -{}", files, self.additions, self.deletions),
+        }
+    }
+}
+
+/// The agent worktrees of one repository
+pub struct Worktrees {
+    repo: Repository,
+}
+
+/// Create a worktree for this run under `location` and point the workspace and
+/// the sandbox at it, so the file tools and `bash` only see the worktree. The
+/// workspace becomes its counterpart in the worktree, keeping the subdirectory
+/// it was in; the caller changes the process's working directory to it. The
+/// workspace must be in a git repository.
+pub fn isolate(config: &mut Config, location: &Path) -> Result<AgentWorktree> {
+    let workspace = match &config.session.workspace_path {
+        Some(workspace) => workspace.clone(),
+        None => std::env::current_dir()?,
+    };
+    let worktrees = Worktrees::open(&workspace)
+        .map_err(|e| anyhow!("session.worktree needs the workspace to be a git repository: {}", e))?;
+    let worktree = worktrees.create(location)?;
+
+    let subdirectory = normalize_path(&workspace).ok()
+        .zip(worktrees.workdir()?.canonicalize().ok())
+        .and_then(|(workspace, root)| workspace.strip_prefix(root).ok().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let counterpart = worktree.path.join(&subdirectory);
+    config.session.workspace_path = Some(if counterpart.is_dir() {
+        counterpart
+    } else {
+        tracing::warn!("{} is not in the worktree, working from its root", subdirectory.display());
+        worktree.path.clone()
+    });
+
+    // Commits on the branch write to the main repository's git directory: the
+    // worktree's own files there, the objects, and the branch's ref and reflog.
+    // Git locks a ref by creating a file next to it, so the agent's branch
+    // directory is granted rather than the ref alone.
+    let git_dir = worktrees.repo.path();
+    let branch_ref = Path::new("refs/heads").join(&worktree.branch);
+    let branch_dir = branch_ref.parent().unwrap_or(&branch_ref);
+    config.sandbox.writable_paths.extend([
+        Repository::open(&worktree.path)?.path().to_path_buf(),
+        git_dir.join("objects"),
+        git_dir.join(branch_dir),
+        git_dir.join("logs").join(branch_dir),
+    ]);
+
+    tracing::info!("Working in worktree {} on branch {}", worktree.path.display(), worktree.branch);
+    Ok(worktree)
+}
+
+/// Remove a run's worktree when the run is over if the agent changed nothing in
+/// it, so worktrees don't pile up. Returns whether it was removed.
+pub fn finish(worktree: &AgentWorktree) -> Result<bool> {
+    Worktrees::open(&worktree.path)?.discard_if_unchanged(&worktree.name)
+}
+
+/// Where worktrees are created: `~/.assistant/worktrees`
+pub fn default_location() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".assistant")
+        .join("worktrees")
+}
+
+impl Worktrees {
+    /// The repository containing `directory`. From inside one of its worktrees,
+    /// that is the main repository.
+    pub fn open(directory: &Path) -> Result<Self> {
+        let repo = Repository::discover(directory)
+            .map_err(|_| anyhow!("'{}' is not in a git repository", directory.display()))?;
+        let repo = if repo.is_worktree() { Repository::open(repo.commondir())? } else { repo };
+        Ok(Self { repo })
+    }
+
+    /// Create a worktree under `location` on a new branch from HEAD
+    pub fn create(&self, location: &Path) -> Result<AgentWorktree> {
+        let head = self.repo.head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(|_| anyhow!("The repository has no commits to branch from"))?;
+        let id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        let name = format!("{}{}", WORKTREE_PREFIX, id);
+        let branch_name = format!("{}{}", BRANCH_PREFIX, id);
+
+        let repo_name = self.workdir()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "repository".to_string());
+        std::fs::create_dir_all(location)?;
+        let path = location.join(format!("{}-{}", repo_name, id));
+
+        let branch = self.repo.branch(&branch_name, &head, false)?;
+        let mut options = WorktreeAddOptions::new();
+        options.reference(Some(branch.get()));
+        self.repo.worktree(&name, &path, Some(&options))?;
+
+        Ok(AgentWorktree { name, branch: branch_name, path })
+    }
+
+    /// The agent's worktrees, oldest name first
+    pub fn list(&self) -> Result<Vec<AgentWorktree>> {
+        let names = self.repo.worktrees()?;
+        let mut worktrees: Vec<AgentWorktree> = names.iter()
+            .flatten()
+            .filter(|name| name.starts_with(WORKTREE_PREFIX))
+            .filter_map(|name| self.find(name).ok())
+            .collect();
+        worktrees.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(worktrees)
+    }
+
+    /// The agent worktree `path` is in, if any
+    pub fn containing(&self, path: &Path) -> Option<AgentWorktree> {
+        let path = normalize_path(path).ok()?;
+        self.list().ok()?
+            .into_iter()
+            .find(|worktree| normalize_path(&worktree.path).is_ok_and(|root| path.starts_with(root)))
+    }
+
+    /// Look a worktree up by its name, its id or its branch
+    pub fn find(&self, name: &str) -> Result<AgentWorktree> {
+        let id = name.strip_prefix(WORKTREE_PREFIX)
+            .or_else(|| name.strip_prefix(BRANCH_PREFIX))
+            .unwrap_or(name);
+        let name = format!("{}{}", WORKTREE_PREFIX, id);
+        let worktree = self.repo.find_worktree(&name)
+            .map_err(|_| anyhow!("There is no agent worktree '{}'; `assistant worktree list` shows them", id))?;
+
+        Ok(AgentWorktree {
+            name,
+            branch: format!("{}{}", BRANCH_PREFIX, id),
+            path: worktree.path().to_path_buf(),
+        })
+    }
+
+    /// The worktree's changes since it branched off, committed or not, including new files
+    pub fn diff(&self, name: &str) -> Result<WorktreeDiff> {
+        let worktree = self.find(name)?;
+        let checkout = self.checkout(&worktree)?;
+        let base = self.base(&worktree)?;
+
+        let mut options = DiffOptions::new();
+        options.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let diff = checkout.diff_tree_to_workdir_with_index(Some(&base.tree()?), Some(&mut options))?;
+
+        let mut patch = String::new();
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+        let stats = diff.stats()?;
+        Ok(WorktreeDiff {
+            patch,
+            files: stats.files_changed(),
+            additions: stats.insertions(),
+            deletions: stats.deletions(),
+        })
+    }
+
+    /// Commit what the worktree has left uncommitted, merge its branch into the
+    /// current branch, then remove the worktree and the branch. Nothing changes
+    /// if the merge would conflict, or overwrite changes in the checkout.
+    pub fn merge(&self, name: &str) -> Result<String> {
+        let worktree = self.find(name)?;
+        self.commit_pending(&worktree)?;
+
+        let head = self.repo.head()?;
+        if !head.is_branch() {
+            bail!("HEAD is detached; check out the branch to merge into first");
+        }
+        let target = head.shorthand().unwrap_or("HEAD").to_string();
+        let ours = head.peel_to_commit()?;
+        let theirs = self.repo.find_branch(&worktree.branch, BranchType::Local)?.get().peel_to_commit()?;
+        let annotated = self.repo.find_annotated_commit(theirs.id())?;
+        let (analysis, _) = self.repo.merge_analysis(&[&annotated])?;
+
+        let outcome = if analysis.is_up_to_date() {
+            format!("{} has no changes to merge", worktree.branch)
+        } else if analysis.is_fast_forward() {
+            // A safe checkout refuses to overwrite local changes, before touching anything
+            self.repo.checkout_tree(theirs.as_object(), Some(CheckoutBuilder::new().safe()))?;
+            self.repo.head()?.set_target(theirs.id(), &format!("merge {}: Fast-forward", worktree.branch))?;
+            format!("Fast-forwarded {} to {}", target, worktree.branch)
+        } else {
+            let mut index = self.repo.merge_commits(&ours, &theirs, Some(&MergeOptions::new()))?;
+            if index.has_conflicts() {
+                let paths: Vec<String> = index.conflicts()?
+                    .flatten()
+                    .filter_map(|conflict| conflict.our.or(conflict.their))
+                    .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                    .collect();
+                bail!(
+                    "Merging {} would conflict in {}; nothing was changed. Merge it by hand with `git merge {}`",
+                    worktree.branch,
+                    paths.join(", "),
+                    worktree.branch
+                );
+            }
+            let tree = self.repo.find_tree(index.write_tree_to(&self.repo)?)?;
+            self.repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))?;
+
+            let signature = self.signature()?;
+            let message = format!("Merge branch '{}'", worktree.branch);
+            let commit = self.repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[&ours, &theirs])?;
+            format!("Merged {} into {} ({})", worktree.branch, target, &commit.to_string()[..7])
+        };
+
+        self.discard(&worktree.name)?;
+        Ok(format!("{} and removed its worktree", outcome))
+    }
+
+    /// Remove the worktree and its branch if it has no changes, returning whether it did
+    pub fn discard_if_unchanged(&self, name: &str) -> Result<bool> {
+        if self.diff(name)?.files > 0 {
+            return Ok(false);
+        }
+        self.discard(name)?;
+        Ok(true)
+    }
+
+    /// Remove the worktree, its directory and its branch
+    pub fn discard(&self, name: &str) -> Result<()> {
+        let worktree = self.find(name)?;
+        let mut options = WorktreePruneOptions::new();
+        options.valid(true).locked(true).working_tree(true);
+        self.repo.find_worktree(&worktree.name)?.prune(Some(&mut options))?;
+        // Pruning leaves the directory when git no longer thinks it is valid
+        if worktree.path.exists() {
+            std::fs::remove_dir_all(&worktree.path)?;
+        }
+
+        if let Ok(mut branch) = self.repo.find_branch(&worktree.branch, BranchType::Local) {
+            branch.delete()?;
+        }
+        Ok(())
+    }
+
+    fn workdir(&self) -> Result<&Path> {
+        self.repo.workdir().ok_or_else(|| anyhow!("The repository is bare; it has no working tree"))
+    }
+
+    fn checkout(&self, worktree: &AgentWorktree) -> Result<Repository> {
+        Repository::open(&worktree.path)
+            .map_err(|_| anyhow!("The worktree at {} is missing; discard it", worktree.path.display()))
+    }
+
+    /// Where the worktree's branch left the current branch
+    fn base(&self, worktree: &AgentWorktree) -> Result<git2::Commit<'_>> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        let tip = self.repo.find_branch(&worktree.branch, BranchType::Local)?.get().peel_to_commit()?;
+        let base = self.repo.merge_base(head.id(), tip.id())?;
+        Ok(self.repo.find_commit(base)?)
+    }
+
+    /// Commit every change in the worktree to its branch
+    fn commit_pending(&self, worktree: &AgentWorktree) -> Result<()> {
+        let checkout = self.checkout(worktree)?;
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        if checkout.statuses(Some(&mut options))?.is_empty() {
+            return Ok(());
+        }
+
+        let mut index = checkout.index()?;
+        index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"], None)?;
+        index.write()?;
+        let tree = checkout.find_tree(index.write_tree()?)?;
+        let parent = checkout.head()?.peel_to_commit()?;
+        let signature = self.signature()?;
+        let message = format!("Changes from agent worktree {}", worktree.name);
+        checkout.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[&parent])?;
+        Ok(())
+    }
+
+    fn signature(&self) -> Result<git2::Signature<'static>> {
+        self.repo.signature()
+            .map_err(|_| anyhow!("Git has no author to commit as; set user.name and user.email in the git config"))
+    }
+}
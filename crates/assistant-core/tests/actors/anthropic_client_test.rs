@@ -0,0 +1,371 @@
+use assistant_core::{
+    actors::client::{ClientActor, ClientMessage},
+    config::Config,
+    messages::ChatMessage,
+    openai_compat::{
+        AnthropicClient, ChatCompletionRequest, ChatMessage as OpenAIMessage, FunctionCall,
+        FunctionDef, Tool, ToolCall, UserContent,
+    },
+    ractor::{Actor, ActorRef},
+};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use wiremock::{MockServer, Mock, ResponseTemplate};
+use wiremock::matchers::{method, path, header};
+
+struct TestSetup {
+    client_ref: ActorRef<ClientMessage>,
+    rx: mpsc::UnboundedReceiver<ChatMessage>,
+    mock_server: MockServer,
+}
+
+fn anthropic_config(base_url: String) -> Config {
+    Config {
+        provider: "anthropic".to_string(),
+        api_key: "test-anthropic-key".to_string(),
+        model: "claude-sonnet-4".to_string(),
+        base_url,
+        max_tokens: 1000,
+        ..Default::default()
+    }
+}
+
+async fn setup_anthropic_test() -> TestSetup {
+    let mock_server = MockServer::start().await;
+    let config = anthropic_config(mock_server.uri());
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    struct MockChatActor {
+        tx: mpsc::UnboundedSender<ChatMessage>,
+    }
+
+    impl Actor for MockChatActor {
+        type Msg = ChatMessage;
+        type State = ();
+        type Arguments = ();
+
+        async fn pre_start(
+            &self,
+            _myself: ActorRef<Self::Msg>,
+            _: Self::Arguments,
+        ) -> Result<Self::State, assistant_core::ractor::ActorProcessingErr> {
+            Ok(())
+        }
+
+        async fn handle(
+            &self,
+            _myself: ActorRef<Self::Msg>,
+            msg: Self::Msg,
+            _state: &mut Self::State,
+        ) -> Result<(), assistant_core::ractor::ActorProcessingErr> {
+            let _ = self.tx.send(msg);
+            Ok(())
+        }
+    }
+
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ())
+        .await
+        .expect("Failed to spawn mock chat");
+
+    let (client_ref, _) = Actor::spawn(None, ClientActor::new(config.clone()), config)
+        .await
+        .expect("Failed to spawn client actor");
+
+    client_ref
+        .send_message(ClientMessage::SetChatRef(chat_ref))
+        .expect("Failed to set chat ref");
+
+    TestSetup {
+        client_ref,
+        rx,
+        mock_server,
+    }
+}
+
+fn sse(events: &[Value]) -> String {
+    events
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect()
+}
+
+fn ls_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: FunctionDef {
+            name: "ls".to_string(),
+            description: "List directory contents".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "path": { "type": "string" } },
+                "required": ["path"]
+            }),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_anthropic_streaming_text_and_tool_use() {
+    let mut setup = setup_anthropic_test().await;
+
+    let body = sse(&[
+        json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4", "usage": {"input_tokens": 12, "output_tokens": 1}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "ping"}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Let me look"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_01", "name": "ls", "input": {}}}),
+        json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"path\": "}}),
+        json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"/tmp\"}"}}),
+        json!({"type": "content_block_stop", "index": 1}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 20}}),
+        json!({"type": "message_stop"}),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(header("x-api-key", "test-anthropic-key"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("content-type", "text/event-stream")
+            .set_body_string(body))
+        .expect(1)
+        .mount(&setup.mock_server)
+        .await;
+
+    let req_id = Uuid::new_v4();
+    setup.client_ref
+        .send_message(ClientMessage::Generate {
+            id: req_id,
+            messages: vec![
+                OpenAIMessage::System {
+                    content: "You are helpful.".to_string(),
+                    name: None,
+                },
+                OpenAIMessage::User {
+                    content: UserContent::Text("List /tmp".to_string()),
+                    name: None,
+                },
+            ],
+            tools: vec![ls_tool()],
+        })
+        .expect("Failed to send message");
+
+    let mut streamed = String::new();
+    loop {
+        match setup.rx.recv().await.expect("Failed to receive response") {
+            ChatMessage::StreamToken { token } => streamed.push_str(&token),
+            ChatMessage::Usage { .. } => {}
+            ChatMessage::AssistantResponse { id, content, tool_calls } => {
+                assert_eq!(id, req_id);
+                assert_eq!(content.as_deref(), Some("Let me look"));
+                assert_eq!(tool_calls.len(), 1);
+                assert_eq!(tool_calls[0].id, "toolu_01");
+                assert_eq!(tool_calls[0].tool_name, "ls");
+                assert_eq!(tool_calls[0].parameters["path"], "/tmp");
+                break;
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+    assert_eq!(streamed, "Let me look");
+
+    // The request must use Messages API conventions
+    let requests = setup.mock_server.received_requests().await.unwrap();
+    let sent: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(sent["system"], "You are helpful.");
+    assert_eq!(sent["stream"], true);
+    assert_eq!(sent["max_tokens"], 1000);
+    assert_eq!(sent["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(sent["messages"][0]["role"], "user");
+    assert_eq!(sent["messages"][0]["content"][0]["text"], "List /tmp");
+    assert_eq!(sent["tools"][0]["name"], "ls");
+    assert_eq!(sent["tools"][0]["input_schema"]["required"][0], "path");
+}
+
+#[tokio::test]
+async fn test_anthropic_tool_without_input_deltas() {
+    let mut setup = setup_anthropic_test().await;
+
+    let body = sse(&[
+        json!({"type": "message_start", "message": {"id": "msg_2", "model": "claude-sonnet-4", "usage": {"input_tokens": 5}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "tool_use", "id": "toolu_02", "name": "todo_read", "input": {}}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 3}}),
+        json!({"type": "message_stop"}),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&setup.mock_server)
+        .await;
+
+    setup.client_ref
+        .send_message(ClientMessage::Generate {
+            id: Uuid::new_v4(),
+            messages: vec![OpenAIMessage::User {
+                content: UserContent::Text("What's on my list?".to_string()),
+                name: None,
+            }],
+            tools: vec![],
+        })
+        .expect("Failed to send message");
+
+    let response = loop {
+        match setup.rx.recv().await.expect("Failed to receive response") {
+            ChatMessage::Usage { .. } => continue,
+            other => break other,
+        }
+    };
+    match response {
+        ChatMessage::AssistantResponse { content, tool_calls, .. } => {
+            assert!(content.is_none());
+            assert_eq!(tool_calls.len(), 1);
+            assert_eq!(tool_calls[0].tool_name, "todo_read");
+            assert_eq!(tool_calls[0].parameters, json!({}));
+        }
+        other => panic!("Expected AssistantResponse, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_anthropic_error_response() {
+    let mut setup = setup_anthropic_test().await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": "max_tokens: field required"
+            }
+        })))
+        .mount(&setup.mock_server)
+        .await;
+
+    let req_id = Uuid::new_v4();
+    setup.client_ref
+        .send_message(ClientMessage::Generate {
+            id: req_id,
+            messages: vec![],
+            tools: vec![],
+        })
+        .expect("Failed to send message");
+
+    match setup.rx.recv().await.expect("Failed to receive response") {
+        ChatMessage::Error { id, error } => {
+            assert_eq!(id, req_id);
+            assert!(error.contains("max_tokens: field required"));
+        }
+        other => panic!("Expected Error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_anthropic_history_mapping_and_non_streaming() {
+    let mock_server = MockServer::start().await;
+    let client = AnthropicClient::new(&anthropic_config(mock_server.uri()));
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_3",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4",
+            "content": [
+                {"type": "text", "text": "Both files read."},
+                {"type": "tool_use", "id": "toolu_05", "name": "ls", "input": {"path": "."}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 40, "output_tokens": 9}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let read_call = |id: &str, file: &str| ToolCall {
+        id: id.to_string(),
+        tool_type: "function".to_string(),
+        function: FunctionCall {
+            name: "read".to_string(),
+            arguments: json!({ "path": file }).to_string(),
+        },
+    };
+
+    let response = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: "claude-sonnet-4".to_string(),
+            messages: vec![
+                OpenAIMessage::User {
+                    content: UserContent::Array(vec![
+                        assistant_core::openai_compat::ContentPart::Text { text: "Compare".to_string() },
+                        assistant_core::openai_compat::ContentPart::Image {
+                            image_url: assistant_core::openai_compat::ImageUrl {
+                                url: "data:image/png;base64,iVBORw0KGgo=".to_string(),
+                                detail: None,
+                            },
+                        },
+                    ]),
+                    name: None,
+                },
+                OpenAIMessage::Assistant {
+                    content: None,
+                    name: None,
+                    tool_calls: Some(vec![read_call("toolu_03", "a.txt"), read_call("toolu_04", "b.txt")]),
+                },
+                OpenAIMessage::Tool {
+                    content: "contents of a".to_string(),
+                    tool_call_id: "toolu_03".to_string(),
+                },
+                OpenAIMessage::Tool {
+                    content: "contents of b".to_string(),
+                    tool_call_id: "toolu_04".to_string(),
+                },
+            ],
+            tools: None,
+            temperature: Some(0.2),
+            max_tokens: None,
+            stream: false,
+            stream_options: None,
+        })
+        .await
+        .expect("Completion failed");
+
+    // Response maps back to OpenAI shapes
+    assert_eq!(response.choices[0].finish_reason, "tool_calls");
+    assert_eq!(response.usage.total_tokens, 49);
+    match &response.choices[0].message {
+        OpenAIMessage::Assistant { content, tool_calls, .. } => {
+            assert_eq!(content.as_deref(), Some("Both files read."));
+            let calls = tool_calls.as_ref().unwrap();
+            assert_eq!(calls[0].id, "toolu_05");
+            assert_eq!(calls[0].function.name, "ls");
+            let args: Value = serde_json::from_str(&calls[0].function.arguments).unwrap();
+            assert_eq!(args["path"], ".");
+        }
+        other => panic!("Expected assistant message, got {:?}", other),
+    }
+
+    // History maps to native blocks, with tool results merged into one user turn
+    let requests = mock_server.received_requests().await.unwrap();
+    let sent: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let messages = sent["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0]["content"][1]["type"], "image");
+    assert_eq!(messages[0]["content"][1]["source"]["type"], "base64");
+    assert_eq!(messages[0]["content"][1]["source"]["media_type"], "image/png");
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+    assert_eq!(messages[1]["content"][1]["input"]["path"], "b.txt");
+    assert_eq!(messages[2]["role"], "user");
+    assert_eq!(messages[2]["content"][0]["type"], "tool_result");
+    assert_eq!(messages[2]["content"][0]["tool_use_id"], "toolu_03");
+    assert_eq!(messages[2]["content"][1]["tool_use_id"], "toolu_04");
+    assert_eq!(sent["max_tokens"], 4096);
+    assert_eq!(sent["stream"], false);
+    assert!(sent.get("system").is_none());
+}
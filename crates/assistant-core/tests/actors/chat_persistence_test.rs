@@ -1,6 +1,13 @@
 use assistant_core::{
-    actors::chat_persistence::{ChatPersistenceActor, ChatPersistenceMessage},
+    actors::{
+        chat_persistence::{ChatPersistenceActor, ChatPersistenceMessage},
+        conversation_manager::{ConversationManagerActor, ConversationManagerMessage},
+    },
     config::Config,
+    context,
+    messages::ChatMessage,
+    openai_compat::{ChatMessage as OpenAIMessage, UserContent},
+    persistence::Database,
     ractor::{Actor, ActorRef},
 };
 use std::time::Duration;
@@ -8,6 +15,8 @@ use tempfile::TempDir;
 use tokio::sync::oneshot;
 use uuid::Uuid;
 
+use crate::common::{next, spawn_recorder};
+
 async fn setup_test_actor() -> (ActorRef<ChatPersistenceMessage>, TempDir) {
     let temp_dir = TempDir::new().unwrap();
     let db_path = temp_dir.path().join("test.db");
@@ -73,6 +82,7 @@ async fn test_persist_assistant_message() {
             id: Uuid::new_v4(),
             session_id: session_id.clone(),
             response: "I'm here to help!".to_string(),
+            tool_calls: None,
         })
         .expect("Failed to send message");
     
@@ -198,6 +208,7 @@ async fn test_concurrent_sessions() {
                 id: Uuid::new_v4(),
                 session_id: session_id.clone(),
                 response: format!("Response for session {}", i),
+                tool_calls: None,
             })
             .expect("Failed to send message");
     }
@@ -295,4 +306,75 @@ async fn test_error_handling_in_operations() {
         .await
         .expect("Timeout waiting for completion")
         .expect("Failed to receive completion signal");
-}
\ No newline at end of file
+}
+async fn wait_for_completion(actor_ref: &ActorRef<ChatPersistenceMessage>) {
+    let (tx, rx) = oneshot::channel();
+    actor_ref
+        .send_message(ChatPersistenceMessage::WaitForCompletion { reply_to: tx })
+        .expect("Failed to send wait message");
+    tokio::time::timeout(Duration::from_secs(10), rx)
+        .await
+        .expect("Timeout waiting for completion")
+        .expect("Failed to receive completion signal");
+}
+
+#[tokio::test]
+async fn test_compacted_session_reloads_from_the_first_kept_prompt() {
+    let (actor_ref, temp_dir) = setup_test_actor().await;
+    let session_id = Uuid::new_v4().to_string();
+    
+    let mut prompts = Vec::new();
+    for turn in ["first", "second"] {
+        let id = Uuid::new_v4();
+        prompts.push(id);
+        actor_ref
+            .send_message(ChatPersistenceMessage::PersistUserPrompt {
+                id,
+                session_id: session_id.clone(),
+                prompt: format!("The {} question", turn),
+            })
+            .unwrap();
+        wait_for_completion(&actor_ref).await;
+        actor_ref
+            .send_message(ChatPersistenceMessage::PersistAssistantResponse {
+                id,
+                session_id: session_id.clone(),
+                response: format!("The {} answer", turn),
+                tool_calls: None,
+            })
+            .unwrap();
+        wait_for_completion(&actor_ref).await;
+    }
+    actor_ref
+        .send_message(ChatPersistenceMessage::PersistCompaction {
+            session_id: session_id.clone(),
+            summary: "The user asked a first question.".to_string(),
+            kept_from: Some(prompts[1]),
+        })
+        .unwrap();
+    wait_for_completion(&actor_ref).await;
+    
+    let database = Database::new(&temp_dir.path().join("test.db")).await.unwrap();
+    let manager = ConversationManagerActor::new(database).await.unwrap();
+    let (manager_ref, _) = Actor::spawn(None, manager, ()).await.unwrap();
+    let (chat_ref, mut chat_rx) = spawn_recorder::<ChatMessage>().await;
+    manager_ref
+        .send_message(ConversationManagerMessage::SwitchConversation { session_id, chat_ref })
+        .unwrap();
+    
+    let ChatMessage::SwitchSession { messages, row_ids, .. } = next(&mut chat_rx).await else {
+        panic!("Expected SwitchSession");
+    };
+    let texts: Vec<String> = messages.iter().map(|message| match message {
+        OpenAIMessage::System { content, .. } => content.clone(),
+        OpenAIMessage::User { content: UserContent::Text(text), .. } => text.clone(),
+        OpenAIMessage::Assistant { content, .. } => content.clone().unwrap_or_default(),
+        other => panic!("Unexpected message {:?}", other),
+    }).collect();
+    assert_eq!(texts, [
+        format!("{}The user asked a first question.", context::SUMMARY_PREFIX),
+        "The second question".to_string(),
+        "The second answer".to_string(),
+    ]);
+    assert_eq!(row_ids[1], Some(prompts[1]));
+}
@@ -0,0 +1,371 @@
+use assistant_core::{
+    actors::{
+        chat::ChatActor,
+        client::ClientMessage,
+        sub_agent_chat::SubAgentChatActor,
+    },
+    config::Config,
+    messages::{ChatMessage, DelegatorMessage, DisplayContext, ToolCall, ToolMessage, UserMessageContent},
+    openai_compat::{ChatMessage as OpenAIMessage, ContentPart, UserContent},
+    ractor::{Actor, ActorRef},
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::common::{next, spawn_recorder};
+
+struct TestSetup {
+    chat_ref: ActorRef<ChatMessage>,
+    client_rx: mpsc::UnboundedReceiver<ClientMessage>,
+    delegator_rx: mpsc::UnboundedReceiver<DelegatorMessage>,
+    display_rx: mpsc::UnboundedReceiver<ChatMessage>,
+}
+
+async fn setup_chat_test() -> TestSetup {
+    let config = Config::default();
+    let (client_ref, client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (delegator_ref, delegator_rx) = spawn_recorder::<DelegatorMessage>().await;
+    let (display_ref, display_rx) = spawn_recorder::<ChatMessage>().await;
+
+    let chat = ChatActor::new(config.clone(), Uuid::new_v4().to_string())
+        .with_client_ref(client_ref)
+        .with_delegator_ref(delegator_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config)
+        .await
+        .expect("Failed to spawn chat actor");
+
+    chat_ref
+        .send_message(ChatMessage::RegisterDisplay {
+            context: DisplayContext::CLI,
+            display_ref,
+        })
+        .expect("Failed to register display");
+
+    TestSetup {
+        chat_ref,
+        client_rx,
+        delegator_rx,
+        display_rx,
+    }
+}
+
+/// Send a prompt and answer the resulting generation with the given tool calls.
+/// Returns the request id and the routing ids the delegator received, in call order.
+async fn start_tool_turn(setup: &mut TestSetup, calls: &[(&str, &str)]) -> (Uuid, Vec<Uuid>) {
+    let request_id = Uuid::new_v4();
+    setup.chat_ref
+        .send_message(ChatMessage::UserPrompt {
+            id: request_id,
+            content: UserMessageContent::Text("Read both files".to_string()),
+            context: DisplayContext::CLI,
+            session_id: None,
+        })
+        .unwrap();
+    assert!(matches!(next(&mut setup.client_rx).await, ClientMessage::Generate { .. }));
+
+    setup.chat_ref
+        .send_message(ChatMessage::AssistantResponse {
+            id: request_id,
+            content: None,
+            tool_calls: calls
+                .iter()
+                .map(|(id, file)| ToolCall {
+                    id: id.to_string(),
+                    tool_name: "read".to_string(),
+                    parameters: json!({ "path": file }),
+                    delegate: false,
+                })
+                .collect(),
+        })
+        .unwrap();
+
+    let mut routing_ids = Vec::new();
+    for _ in calls {
+        match next(&mut setup.delegator_rx).await {
+            DelegatorMessage::RouteToolCall { id, .. } => routing_ids.push(id),
+            other => panic!("Expected RouteToolCall, got {:?}", other),
+        }
+    }
+
+    (request_id, routing_ids)
+}
+
+#[tokio::test]
+async fn test_parallel_tool_results_continue_once_with_provider_ids() {
+    let mut setup = setup_chat_test().await;
+    let (request_id, routing_ids) = start_tool_turn(&mut setup, &[("toolu_a", "a.txt"), ("toolu_b", "b.txt")]).await;
+
+    // Results arrive out of order
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[1], result: "contents of b".to_string() })
+        .unwrap();
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[0], result: "contents of a".to_string() })
+        .unwrap();
+
+    let (id, messages) = match next(&mut setup.client_rx).await {
+        ClientMessage::Generate { id, messages, .. } => (id, messages),
+        other => panic!("Expected Generate, got {:?}", other),
+    };
+    assert_eq!(id, request_id);
+
+    // The assistant message keeps the provider's ids and each result refers to one of them
+    let tail = &messages[messages.len() - 3..];
+    match &tail[0] {
+        OpenAIMessage::Assistant { tool_calls: Some(calls), .. } => {
+            let ids: Vec<_> = calls.iter().map(|c| c.id.as_str()).collect();
+            assert_eq!(ids, ["toolu_a", "toolu_b"]);
+        }
+        other => panic!("Expected assistant message with tool calls, got {:?}", other),
+    }
+    match (&tail[1], &tail[2]) {
+        (
+            OpenAIMessage::Tool { tool_call_id: first_id, content: first },
+            OpenAIMessage::Tool { tool_call_id: second_id, content: second },
+        ) => {
+            assert_eq!(first_id, "toolu_a");
+            assert!(first.contains("contents of a"));
+            assert_eq!(second_id, "toolu_b");
+            assert!(second.contains("contents of b"));
+        }
+        other => panic!("Expected two tool messages, got {:?}", other),
+    }
+
+    // Only one generation is started for the whole turn
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(setup.client_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_images_in_tool_results_are_shown_to_the_model() {
+    let mut setup = setup_chat_test().await;
+    let (_, routing_ids) = start_tool_turn(&mut setup, &[("call_1", "diagram.png"), ("call_2", "notes.txt")]).await;
+
+    let image = "data:image/png;base64,iVBORw0KGgo=";
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult {
+            id: routing_ids[0],
+            result: json!({ "success": true, "image": image, "format": "png" }).to_string(),
+        })
+        .unwrap();
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[1], result: "contents of notes".to_string() })
+        .unwrap();
+
+    let messages = match next(&mut setup.client_rx).await {
+        ClientMessage::Generate { messages, .. } => messages,
+        other => panic!("Expected Generate, got {:?}", other),
+    };
+    // The tool messages carry text only; the image follows them
+    let tail = &messages[messages.len() - 3..];
+    match &tail[0] {
+        OpenAIMessage::Tool { content, .. } => {
+            assert!(content.contains("<image_data_removed>"), "{}", content);
+            assert!(!content.contains("base64"), "{}", content);
+        }
+        other => panic!("Expected tool message, got {:?}", other),
+    }
+    assert!(matches!(&tail[1], OpenAIMessage::Tool { .. }));
+    match &tail[2] {
+        OpenAIMessage::User { content: UserContent::Array(parts), .. } => {
+            assert!(matches!(&parts[1], ContentPart::Text { text } if text == "From read:"));
+            assert!(matches!(&parts[2], ContentPart::Image { image_url } if image_url.url == image));
+        }
+        other => panic!("Expected user message with the image, got {:?}", other),
+    }
+
+    // Displays get the placeholder too
+    while let Ok(msg) = setup.display_rx.try_recv() {
+        if let ChatMessage::ToolResult { result, .. } = msg {
+            assert!(!result.contains("base64"), "{}", result);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_failed_tool_call_still_completes_turn() {
+    let mut setup = setup_chat_test().await;
+    let (_, routing_ids) = start_tool_turn(&mut setup, &[("call_1", "a.txt"), ("call_2", "missing.txt")]).await;
+
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[0], result: "contents of a".to_string() })
+        .unwrap();
+    setup.chat_ref
+        .send_message(ChatMessage::Error { id: routing_ids[1], error: "Tool not found: read".to_string() })
+        .unwrap();
+
+    let messages = match next(&mut setup.client_rx).await {
+        ClientMessage::Generate { messages, .. } => messages,
+        other => panic!("Expected Generate, got {:?}", other),
+    };
+    match messages.last() {
+        Some(OpenAIMessage::Tool { tool_call_id, content }) => {
+            assert_eq!(tool_call_id, "call_2");
+            assert!(content.contains("Error: Tool not found"));
+        }
+        other => panic!("Expected tool message, got {:?}", other),
+    }
+
+    // Displays see the failure as a tool result, not as the end of the request
+    while let Ok(msg) = setup.display_rx.try_recv() {
+        assert!(!matches!(msg, ChatMessage::Error { .. }), "Unexpected error sent to display");
+    }
+}
+
+#[tokio::test]
+async fn test_cancel_stops_turn_and_records_cancelled_results() {
+    let mut setup = setup_chat_test().await;
+    let (request_id, routing_ids) = start_tool_turn(&mut setup, &[("call_1", "a.txt"), ("call_2", "b.txt")]).await;
+
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[0], result: "contents of a".to_string() })
+        .unwrap();
+    setup.chat_ref.send_message(ChatMessage::Cancel).unwrap();
+
+    // Generation stops and only the unanswered call is cancelled
+    assert!(matches!(next(&mut setup.client_rx).await, ClientMessage::Cancel));
+    match next(&mut setup.delegator_rx).await {
+        DelegatorMessage::Cancel { id } => assert_eq!(id, routing_ids[1]),
+        other => panic!("Expected Cancel, got {:?}", other),
+    }
+
+    // Displays see the call cancelled and the request end
+    let mut cancelled_result = false;
+    loop {
+        match next(&mut setup.display_rx).await {
+            ChatMessage::ToolResult { id, result } if id == routing_ids[1] => {
+                assert!(result.contains("Cancelled"));
+                cancelled_result = true;
+            }
+            ChatMessage::Error { id, error } => {
+                assert_eq!(id, request_id);
+                assert!(error.contains("cancelled"));
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(cancelled_result);
+
+    // Whatever the cancelled request still sends is dropped
+    setup.chat_ref
+        .send_message(ChatMessage::AssistantResponse {
+            id: request_id,
+            content: Some("Too late".to_string()),
+            tool_calls: vec![],
+        })
+        .unwrap();
+    setup.chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[1], result: "contents of b".to_string() })
+        .unwrap();
+
+    // The next prompt sees a result for every call of the cancelled turn
+    setup.chat_ref
+        .send_message(ChatMessage::UserPrompt {
+            id: Uuid::new_v4(),
+            content: UserMessageContent::Text("Never mind".to_string()),
+            context: DisplayContext::CLI,
+            session_id: None,
+        })
+        .unwrap();
+    let messages = match next(&mut setup.client_rx).await {
+        ClientMessage::Generate { messages, .. } => messages,
+        other => panic!("Expected Generate, got {:?}", other),
+    };
+    let tail = &messages[messages.len() - 3..];
+    match (&tail[0], &tail[1], &tail[2]) {
+        (
+            OpenAIMessage::Tool { tool_call_id: first_id, content: first },
+            OpenAIMessage::Tool { tool_call_id: second_id, content: second },
+            OpenAIMessage::User { .. },
+        ) => {
+            assert_eq!(first_id, "call_1");
+            assert!(first.contains("contents of a"));
+            assert_eq!(second_id, "call_2");
+            assert!(second.contains("Cancelled"));
+        }
+        other => panic!("Expected two tool messages and the new prompt, got {:?}", other),
+    }
+    assert!(!messages.iter().any(|m| matches!(m, OpenAIMessage::Assistant { content: Some(c), .. } if c == "Too late")));
+}
+
+#[tokio::test]
+async fn test_sub_agent_parallel_tool_results_continue_once_with_provider_ids() {
+    let config = Config::default();
+    let (client_ref, mut client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (tool_ref, mut tool_rx) = spawn_recorder::<ToolMessage>().await;
+    let tool_actors = HashMap::from([("web_fetch".to_string(), tool_ref)]);
+    let chat = SubAgentChatActor::new(config.clone(), tool_actors, true).with_client_ref(client_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config)
+        .await
+        .expect("Failed to spawn sub-agent chat actor");
+
+    let request_id = Uuid::new_v4();
+    chat_ref
+        .send_message(ChatMessage::UserPrompt {
+            id: request_id,
+            content: UserMessageContent::Text("Fetch both pages".to_string()),
+            context: DisplayContext::SubAgent,
+            session_id: None,
+        })
+        .unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    chat_ref
+        .send_message(ChatMessage::AssistantResponse {
+            id: request_id,
+            content: None,
+            tool_calls: [("toolu_a", "https://a.example"), ("toolu_b", "https://b.example")]
+                .iter()
+                .map(|(id, url)| ToolCall {
+                    id: id.to_string(),
+                    tool_name: "web_fetch".to_string(),
+                    parameters: json!({ "url": url }),
+                    delegate: false,
+                })
+                .collect(),
+        })
+        .unwrap();
+    let mut routing_ids = Vec::new();
+    for _ in 0..2 {
+        match next(&mut tool_rx).await {
+            ToolMessage::Execute { id, .. } => routing_ids.push(id),
+            other => panic!("Expected Execute, got {:?}", other),
+        }
+    }
+
+    // Results arrive out of order
+    chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[1], result: "page b".to_string() })
+        .unwrap();
+    chat_ref
+        .send_message(ChatMessage::ToolResult { id: routing_ids[0], result: "page a".to_string() })
+        .unwrap();
+
+    let (id, messages) = match next(&mut client_rx).await {
+        ClientMessage::Generate { id, messages, .. } => (id, messages),
+        other => panic!("Expected Generate, got {:?}", other),
+    };
+    assert_eq!(id, request_id);
+
+    // Each result follows the assistant message and refers to its call's provider id
+    let tail = &messages[messages.len() - 3..];
+    assert!(matches!(&tail[0], OpenAIMessage::Assistant { tool_calls: Some(calls), .. } if calls.len() == 2));
+    match (&tail[1], &tail[2]) {
+        (
+            OpenAIMessage::Tool { tool_call_id: first_id, content: first },
+            OpenAIMessage::Tool { tool_call_id: second_id, content: second },
+        ) => {
+            assert_eq!((first_id.as_str(), first.as_str()), ("toolu_a", "page a"));
+            assert_eq!((second_id.as_str(), second.as_str()), ("toolu_b", "page b"));
+        }
+        other => panic!("Expected two tool messages, got {:?}", other),
+    }
+
+    // Only one generation is started for the whole turn
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(client_rx.try_recv().is_err());
+}
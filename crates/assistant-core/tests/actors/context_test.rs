@@ -0,0 +1,282 @@
+use assistant_core::{
+    actors::{
+        chat::ChatActor,
+        chat_persistence::ChatPersistenceMessage,
+        client::ClientMessage,
+    },
+    config::{Config, ContextConfig},
+    context::{self, TokenCounter},
+    messages::{ChatMessage, DelegatorMessage, DisplayContext, UIMessage, UserMessageContent},
+    openai_compat::{ChatMessage as OpenAIMessage, FunctionCall, ToolCall, UserContent},
+    ractor::{Actor, ActorRef},
+};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::common::{completion, next, spawn_recorder};
+
+fn user(text: &str) -> OpenAIMessage {
+    OpenAIMessage::User {
+        content: UserContent::Text(text.to_string()),
+        name: None,
+    }
+}
+
+fn assistant(text: &str) -> OpenAIMessage {
+    OpenAIMessage::Assistant {
+        content: Some(text.to_string()),
+        name: None,
+        tool_calls: None,
+    }
+}
+
+struct TestSetup {
+    chat_ref: ActorRef<ChatMessage>,
+    client_rx: mpsc::UnboundedReceiver<ClientMessage>,
+    display_rx: mpsc::UnboundedReceiver<ChatMessage>,
+    _delegator_rx: mpsc::UnboundedReceiver<DelegatorMessage>,
+}
+
+async fn setup_context_test(context: ContextConfig) -> TestSetup {
+    let config = Config {
+        context,
+        ..Default::default()
+    };
+    let (client_ref, client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (delegator_ref, delegator_rx) = spawn_recorder::<DelegatorMessage>().await;
+    let (display_ref, display_rx) = spawn_recorder::<ChatMessage>().await;
+
+    let chat = ChatActor::new(config.clone(), Uuid::new_v4().to_string())
+        .with_client_ref(client_ref)
+        .with_delegator_ref(delegator_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config)
+        .await
+        .expect("Failed to spawn chat actor");
+
+    chat_ref
+        .send_message(ChatMessage::RegisterDisplay {
+            context: DisplayContext::CLI,
+            display_ref,
+        })
+        .expect("Failed to register display");
+
+    TestSetup {
+        chat_ref,
+        client_rx,
+        display_rx,
+        _delegator_rx: delegator_rx,
+    }
+}
+
+fn prompt(setup: &TestSetup, text: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    setup.chat_ref
+        .send_message(ChatMessage::UserPrompt {
+            id,
+            content: UserMessageContent::Text(text.to_string()),
+            context: DisplayContext::CLI,
+            session_id: None,
+        })
+        .unwrap();
+    id
+}
+
+/// Send a prompt and answer its generation with a plain text response
+async fn complete_turn(setup: &mut TestSetup, text: &str, answer: &str) -> Uuid {
+    let id = prompt(setup, text);
+    assert!(matches!(next(&mut setup.client_rx).await, ClientMessage::Generate { .. }));
+    setup.chat_ref
+        .send_message(ChatMessage::AssistantResponse {
+            id,
+            content: Some(answer.to_string()),
+            tool_calls: vec![],
+        })
+        .unwrap();
+    id
+}
+
+/// Answer the next summary request with `summary`, returning the transcript it was given
+async fn answer_summary(setup: &mut TestSetup, summary: &str) -> String {
+    match next(&mut setup.client_rx).await {
+        ClientMessage::Complete { messages, reply_to } => {
+            let _ = reply_to.send(Ok(completion(summary)));
+            match messages.last() {
+                Some(OpenAIMessage::User { content: UserContent::Text(transcript), .. }) => transcript.clone(),
+                other => panic!("Expected transcript, got {:?}", other),
+            }
+        }
+        other => panic!("Expected Complete, got {:?}", other),
+    }
+}
+
+async fn next_generation(setup: &mut TestSetup) -> Vec<OpenAIMessage> {
+    match next(&mut setup.client_rx).await {
+        ClientMessage::Generate { messages, .. } => messages,
+        other => panic!("Expected Generate, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_heuristic_token_counting() {
+    let counter = TokenCounter::default();
+    assert_eq!(counter.count_text(""), 0);
+    assert_eq!(counter.count_text("abcd"), 1);
+    assert_eq!(counter.count_text("abcde"), 2);
+
+    // Each message carries a small framing overhead on top of its content
+    let messages = [user("abcdefgh"), assistant("abcd")];
+    assert_eq!(counter.count_messages(&messages), 2 + 1 + 2 * 4);
+}
+
+#[test]
+fn test_context_budget_leaves_room_for_output() {
+    let mut context = ContextConfig {
+        context_window: 10_000,
+        ..Default::default()
+    };
+    context.windows.insert("qwen3-coder".to_string(), 100_000);
+
+    assert_eq!(context.budget_for("gpt-4o", 1_000), 8_000);
+    assert_eq!(context.budget_for("gpt-4o", 5_000), 5_000);
+    assert_eq!(context.budget_for("qwen/qwen3-coder", 1_000), 80_000);
+}
+
+#[test]
+fn test_compaction_range_keeps_system_prompt_and_recent_turns() {
+    let tool_call = ToolCall {
+        id: "call_1".to_string(),
+        tool_type: "function".to_string(),
+        function: FunctionCall {
+            name: "read".to_string(),
+            arguments: r#"{"path":"a.txt"}"#.to_string(),
+        },
+    };
+    let messages = vec![
+        OpenAIMessage::System { content: "You are helpful".to_string(), name: None },
+        user("first"),
+        OpenAIMessage::Assistant { content: None, name: None, tool_calls: Some(vec![tool_call]) },
+        OpenAIMessage::Tool { content: "contents".to_string(), tool_call_id: "call_1".to_string() },
+        assistant("done"),
+        user("second"),
+        assistant("ok"),
+        user("third"),
+    ];
+
+    // The first turn, tool call and result included, goes; the last two turns stay
+    assert_eq!(context::compaction_range(&messages, 2), Some((1, 5)));
+    assert_eq!(context::compaction_range(&messages, 0), Some((1, 7)));
+    assert_eq!(context::compaction_range(&messages, 3), None);
+
+    // A previous summary on its own is not compacted again
+    let compacted = vec![messages[0].clone(), context::summary_message("earlier"), user("second"), user("third")];
+    assert_eq!(context::compaction_range(&compacted, 2), None);
+    assert!(context::is_summary(&compacted[1]));
+}
+
+#[tokio::test]
+async fn test_compact_command_summarizes_older_turns() {
+    let mut setup = setup_context_test(ContextConfig {
+        keep_recent_turns: 1,
+        ..Default::default()
+    })
+    .await;
+
+    complete_turn(&mut setup, "What is in a.txt?", "It holds the alpha notes").await;
+    complete_turn(&mut setup, "And b.txt?", "It holds the beta notes").await;
+
+    let compact_id = prompt(&setup, "/compact");
+    let transcript = answer_summary(&mut setup, "The user asked about a.txt (alpha notes).").await;
+    assert!(transcript.contains("What is in a.txt?"));
+    assert!(transcript.contains("alpha notes"));
+    assert!(!transcript.contains("b.txt"));
+
+    // The command is answered directly without a generation, and the summary's
+    // tokens count toward the session
+    let mut session_usage = None;
+    loop {
+        match next(&mut setup.display_rx).await {
+            ChatMessage::UIUpdate(UIMessage::UpdateStats(stats)) => session_usage = Some(stats.session),
+            ChatMessage::Complete { id, response } => {
+                assert_eq!(id, compact_id);
+                assert!(response.starts_with("Compacted conversation"), "{}", response);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let session_usage = session_usage.expect("No stats were shown");
+    assert_eq!((session_usage.prompt_tokens, session_usage.completion_tokens), (100, 10));
+
+    prompt(&setup, "And c.txt?");
+    let messages = next_generation(&mut setup).await;
+    assert_eq!(messages.len(), 5);
+    assert!(matches!(&messages[0], OpenAIMessage::System { content, .. } if !content.starts_with(context::SUMMARY_PREFIX)));
+    match &messages[1] {
+        OpenAIMessage::System { content, .. } => {
+            assert_eq!(content, &format!("{}The user asked about a.txt (alpha notes).", context::SUMMARY_PREFIX));
+        }
+        other => panic!("Expected summary, got {:?}", other),
+    }
+    assert!(matches!(&messages[2], OpenAIMessage::User { content: UserContent::Text(text), .. } if text == "And b.txt?"));
+}
+
+#[tokio::test]
+async fn test_conversation_is_compacted_when_over_budget() {
+    let mut setup = setup_context_test(ContextConfig {
+        context_window: 20_000,
+        keep_recent_turns: 1,
+        ..Default::default()
+    })
+    .await;
+
+    // A single turn larger than the budget can't be compacted and is sent as is
+    let long_prompt = "word ".repeat(20_000);
+    complete_turn(&mut setup, &long_prompt, "That was long").await;
+
+    // The next prompt pushes the older turn out
+    prompt(&setup, "Short question");
+    let transcript = answer_summary(&mut setup, "The user pasted a long text.").await;
+    assert!(transcript.contains("That was long"));
+
+    let messages = next_generation(&mut setup).await;
+    assert_eq!(messages.len(), 3);
+    assert!(context::is_summary(&messages[1]));
+    assert!(matches!(&messages[2], OpenAIMessage::User { content: UserContent::Text(text), .. } if text == "Short question"));
+}
+
+#[tokio::test]
+async fn test_compaction_is_persisted_with_the_first_kept_prompt() {
+    let mut setup = setup_context_test(ContextConfig {
+        keep_recent_turns: 1,
+        ..Default::default()
+    })
+    .await;
+    let (persistence_ref, mut persistence_rx) = spawn_recorder::<ChatPersistenceMessage>().await;
+    setup.chat_ref.send_message(ChatMessage::SetPersistenceRef(persistence_ref)).unwrap();
+
+    complete_turn(&mut setup, "What is in a.txt?", "It holds the alpha notes").await;
+    let kept = complete_turn(&mut setup, "And b.txt?", "It holds the beta notes").await;
+
+    prompt(&setup, "/compact");
+    let ClientMessage::Complete { reply_to, .. } = next(&mut setup.client_rx).await else {
+        panic!("Expected Complete");
+    };
+    // The chat keeps handling prompts while the summary is written
+    let worktree = prompt(&setup, "/worktree");
+    loop {
+        if let ChatMessage::Complete { id, .. } = next(&mut setup.display_rx).await
+            && id == worktree
+        {
+            break;
+        }
+    }
+    reply_to.send(Ok(completion("The user asked about a.txt."))).unwrap();
+
+    loop {
+        if let ChatPersistenceMessage::PersistCompaction { summary, kept_from, .. } = next(&mut persistence_rx).await {
+            assert_eq!(summary, "The user asked about a.txt.");
+            assert_eq!(kept_from, Some(kept));
+            break;
+        }
+    }
+}
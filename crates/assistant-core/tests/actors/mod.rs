@@ -1,4 +1,10 @@
+pub mod anthropic_client_test;
+pub mod chat_test;
 pub mod chat_persistence_test;
 pub mod client_test;
+pub mod context_test;
+pub mod permissions_test;
+pub mod retry_test;
 // pub mod delegator_test; // TODO: Update for new delegator interface
-pub mod supervisor_test;
\ No newline at end of file
+pub mod supervisor_test;
+pub mod usage_test;
\ No newline at end of file
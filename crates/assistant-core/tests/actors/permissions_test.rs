@@ -0,0 +1,395 @@
+use assistant_core::{
+    actors::{chat::ChatActor, client::ClientMessage, delegator::DelegatorActor, sub_agent_chat::SubAgentChatActor},
+    config::{Config, PermissionAction, PermissionRule, PermissionsConfig},
+    messages::{
+        ChatMessage, ConfirmationDecision, ConfirmationRequest, ConfirmationType, DelegatorMessage,
+        DisplayContext, ToolCall, ToolMessage, UIMessage, UserMessageContent,
+    },
+    permissions::{self, PermissionPolicy},
+    ractor::{Actor, ActorRef},
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::common::{next, spawn_recorder};
+
+fn rule(tool: &str, pattern: Option<&str>, action: PermissionAction) -> PermissionRule {
+    PermissionRule {
+        tool: tool.to_string(),
+        pattern: pattern.map(str::to_string),
+        action,
+    }
+}
+
+fn bash(command: &str) -> ToolCall {
+    ToolCall {
+        id: "call_1".to_string(),
+        tool_name: "bash".to_string(),
+        parameters: json!({ "command": command }),
+        delegate: false,
+    }
+}
+
+fn example_permissions() -> PermissionsConfig {
+    PermissionsConfig {
+        rules: vec![
+            rule("bash", Some("git status*"), PermissionAction::Allow),
+            rule("bash", Some("rm -rf*"), PermissionAction::Deny),
+        ],
+        ..Default::default()
+    }
+}
+
+struct DelegatorSetup {
+    delegator_ref: ActorRef<DelegatorMessage>,
+    chat_ref: ActorRef<ChatMessage>,
+    chat_rx: mpsc::UnboundedReceiver<ChatMessage>,
+    tool_rx: mpsc::UnboundedReceiver<ToolMessage>,
+}
+
+/// A delegator with a recording `bash` tool and a recording chat actor
+async fn setup_delegator(permissions: PermissionsConfig) -> DelegatorSetup {
+    let config = Config {
+        permissions,
+        ..Default::default()
+    };
+    let (delegator_ref, _) = Actor::spawn(None, DelegatorActor::new(config.clone()), config)
+        .await
+        .expect("Failed to spawn delegator");
+    let (tool_ref, tool_rx) = spawn_recorder::<ToolMessage>().await;
+    let (chat_ref, chat_rx) = spawn_recorder::<ChatMessage>().await;
+
+    delegator_ref
+        .send_message(DelegatorMessage::RegisterTool { name: "bash".to_string(), actor_ref: tool_ref })
+        .unwrap();
+
+    DelegatorSetup { delegator_ref, chat_ref, chat_rx, tool_rx }
+}
+
+fn route(setup: &DelegatorSetup, command: &str, session_id: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    setup.delegator_ref
+        .send_message(DelegatorMessage::RouteToolCall {
+            id,
+            call: bash(command),
+            chat_ref: setup.chat_ref.clone(),
+            session_id: session_id.to_string(),
+            turn: Uuid::new_v4(),
+        })
+        .unwrap();
+    id
+}
+
+async fn next_confirmation(setup: &mut DelegatorSetup) -> ConfirmationRequest {
+    match next(&mut setup.chat_rx).await {
+        ChatMessage::UIUpdate(UIMessage::ShowConfirmation(request)) => request,
+        other => panic!("Expected ShowConfirmation, got {:?}", other),
+    }
+}
+
+async fn next_execution(setup: &mut DelegatorSetup) -> Uuid {
+    match next(&mut setup.tool_rx).await {
+        ToolMessage::Execute { id, .. } => id,
+        other => panic!("Expected Execute, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_first_matching_rule_wins_and_unmatched_tools_fall_back() {
+    let policy = PermissionPolicy::new(example_permissions());
+
+    assert_eq!(policy.check(&bash("git status --short")), PermissionAction::Allow);
+    assert_eq!(policy.check(&bash("rm -rf target")), PermissionAction::Deny);
+    // bash is destructive, so unmatched commands are asked about
+    assert_eq!(policy.check(&bash("cargo build")), PermissionAction::Ask);
+
+    let read = ToolCall {
+        id: "call_2".to_string(),
+        tool_name: "read".to_string(),
+        parameters: json!({ "path": "src/main.rs" }),
+        delegate: false,
+    };
+    assert_eq!(policy.check(&read), PermissionAction::Allow);
+
+    let policy = PermissionPolicy::new(PermissionsConfig {
+        rules: vec![rule("*", Some("*.env"), PermissionAction::Deny)],
+        default_action: PermissionAction::Ask,
+        ..Default::default()
+    });
+    assert_eq!(policy.check(&read), PermissionAction::Ask);
+    let secrets = ToolCall { parameters: json!({ "path": "config/.env" }), ..read };
+    assert_eq!(policy.check(&secrets), PermissionAction::Deny);
+}
+
+#[test]
+fn test_chained_commands_are_checked_part_by_part() {
+    let policy = PermissionPolicy::new(example_permissions());
+
+    assert_eq!(policy.check(&bash("git status && rm -rf /")), PermissionAction::Deny);
+    assert_eq!(policy.check(&bash("git status; cargo build")), PermissionAction::Ask);
+    assert_eq!(policy.check(&bash("git status | sh")), PermissionAction::Ask);
+    assert_eq!(policy.check(&bash("git status $(curl example.com)")), PermissionAction::Ask);
+    // Redirections are not command separators
+    assert_eq!(policy.check(&bash("git status 2>&1")), PermissionAction::Allow);
+}
+
+#[test]
+fn test_background_job_management_uses_the_default_action() {
+    let policy = PermissionPolicy::new(example_permissions());
+
+    let poll = ToolCall { parameters: json!({ "action": "output", "job_id": 1 }), ..bash("") };
+    assert_eq!(policy.check(&poll), PermissionAction::Allow);
+    let run = ToolCall { parameters: json!({ "action": "run", "command": "npm run dev", "run_in_background": true }), ..bash("") };
+    assert_eq!(policy.check(&run), PermissionAction::Ask);
+}
+
+#[test]
+fn test_wildcard_patterns_and_confirmation_details() {
+    assert!(permissions::wildcard_match("git status*", "git status"));
+    assert!(permissions::wildcard_match("*.rs", "src/main.rs"));
+    assert!(permissions::wildcard_match("ca?go *", "cargo test"));
+    assert!(!permissions::wildcard_match("git status*", "git push"));
+
+    let edit = ToolCall {
+        id: "call_3".to_string(),
+        tool_name: "edit".to_string(),
+        parameters: json!({ "file_path": "src/lib.rs", "old_string": "a", "new_string": "b" }),
+        delegate: false,
+    };
+    let request = permissions::confirmation_request(Uuid::new_v4(), &edit);
+    match request.confirm_type {
+        ConfirmationType::Edit { file, diff } => {
+            assert_eq!(file, "src/lib.rs");
+            assert_eq!(diff, "- a\n+ b");
+        }
+        other => panic!("Expected Edit, got {:?}", other),
+    }
+
+    let multi_edit = ToolCall {
+        parameters: json!({
+            "action": "multi_edit",
+            "file_path": "src/lib.rs",
+            "edits": [{ "old_string": "a", "new_string": "b" }, { "old_string": "c", "new_string": "d" }]
+        }),
+        ..edit.clone()
+    };
+    let request = permissions::confirmation_request(Uuid::new_v4(), &multi_edit);
+    assert_eq!(request.description, "Make 2 edits to a file");
+    assert!(matches!(request.confirm_type, ConfirmationType::Edit { diff, .. } if diff == "- a\n+ b\n\n- c\n+ d"));
+
+    let patch = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-gone\n";
+    let apply_patch = ToolCall {
+        tool_name: "apply_patch".to_string(),
+        parameters: json!({ "patch": patch }),
+        ..edit
+    };
+    match permissions::confirmation_request(Uuid::new_v4(), &apply_patch).confirm_type {
+        ConfirmationType::Edit { file, diff } => {
+            assert_eq!(file, "src/lib.rs, old.rs");
+            assert_eq!(diff, patch);
+        }
+        other => panic!("Expected Edit, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_delegator_asks_and_remembers_approvals_per_session() {
+    let mut setup = setup_delegator(example_permissions()).await;
+
+    // Allowed by rule: runs without asking
+    let id = route(&setup, "git status", "session-a");
+    assert_eq!(next_execution(&mut setup).await, id);
+
+    // Unmatched: held back until the user answers
+    let id = route(&setup, "cargo build", "session-a");
+    let request = next_confirmation(&mut setup).await;
+    assert_eq!(request.id, id);
+    assert!(matches!(&request.confirm_type, ConfirmationType::Execute { command } if command == "cargo build"));
+    assert!(setup.tool_rx.try_recv().is_err());
+
+    setup.delegator_ref
+        .send_message(DelegatorMessage::ConfirmationResponse { id, decision: ConfirmationDecision::AllowForSession })
+        .unwrap();
+    assert_eq!(next_execution(&mut setup).await, id);
+
+    // The same call in the same session is not asked about again
+    let id = route(&setup, "cargo build", "session-a");
+    assert_eq!(next_execution(&mut setup).await, id);
+
+    // Another session is asked again, and a refusal reaches the chat as an error
+    let id = route(&setup, "cargo build", "session-b");
+    assert_eq!(next_confirmation(&mut setup).await.id, id);
+    setup.delegator_ref
+        .send_message(DelegatorMessage::ConfirmationResponse { id, decision: ConfirmationDecision::Deny })
+        .unwrap();
+    match next(&mut setup.chat_rx).await {
+        ChatMessage::Error { id: error_id, error } => {
+            assert_eq!(error_id, id);
+            assert!(error.starts_with("Permission denied"), "{}", error);
+        }
+        other => panic!("Expected Error, got {:?}", other),
+    }
+    assert!(setup.tool_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_delegator_refuses_denied_calls_without_asking() {
+    let mut setup = setup_delegator(example_permissions()).await;
+
+    let id = route(&setup, "rm -rf /", "session-a");
+    match next(&mut setup.chat_rx).await {
+        ChatMessage::Error { id: error_id, error } => {
+            assert_eq!(error_id, id);
+            assert!(error.contains("permission policy"), "{}", error);
+        }
+        other => panic!("Expected Error, got {:?}", other),
+    }
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(setup.tool_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_chat_relays_confirmations_between_delegator_and_display() {
+    let config = Config::default();
+    let (client_ref, mut client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (delegator_ref, mut delegator_rx) = spawn_recorder::<DelegatorMessage>().await;
+    let (display_ref, mut display_rx) = spawn_recorder::<ChatMessage>().await;
+
+    let session_id = Uuid::new_v4().to_string();
+    let chat = ChatActor::new(config.clone(), session_id.clone())
+        .with_client_ref(client_ref)
+        .with_delegator_ref(delegator_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config)
+        .await
+        .expect("Failed to spawn chat actor");
+    chat_ref
+        .send_message(ChatMessage::RegisterDisplay { context: DisplayContext::CLI, display_ref })
+        .unwrap();
+
+    // Start a turn that calls bash so the chat knows its display
+    let request_id = Uuid::new_v4();
+    chat_ref
+        .send_message(ChatMessage::UserPrompt {
+            id: request_id,
+            content: UserMessageContent::Text("Build it".to_string()),
+            context: DisplayContext::CLI,
+            session_id: None,
+        })
+        .unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+    chat_ref
+        .send_message(ChatMessage::AssistantResponse {
+            id: request_id,
+            content: None,
+            tool_calls: vec![bash("cargo build")],
+        })
+        .unwrap();
+    let (id, call) = match next(&mut delegator_rx).await {
+        DelegatorMessage::RouteToolCall { id, call, session_id: routed_session, .. } => {
+            assert_eq!(routed_session, session_id);
+            (id, call)
+        }
+        other => panic!("Expected RouteToolCall, got {:?}", other),
+    };
+
+    // The delegator's question reaches the display...
+    chat_ref
+        .send_message(ChatMessage::UIUpdate(UIMessage::ShowConfirmation(
+            permissions::confirmation_request(id, &call),
+        )))
+        .unwrap();
+    loop {
+        if let ChatMessage::UIUpdate(UIMessage::ShowConfirmation(request)) = next(&mut display_rx).await {
+            assert_eq!(request.id, id);
+            break;
+        }
+    }
+
+    // ...and the display's answer reaches the delegator
+    chat_ref
+        .send_message(ChatMessage::ConfirmationResponse { id, decision: ConfirmationDecision::Allow })
+        .unwrap();
+    match next(&mut delegator_rx).await {
+        DelegatorMessage::ConfirmationResponse { id: answered, decision } => {
+            assert_eq!(answered, id);
+            assert_eq!(decision, ConfirmationDecision::Allow);
+        }
+        other => panic!("Expected ConfirmationResponse, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_sub_agents_refuse_calls_the_policy_denies_or_would_ask_about() {
+    let config = Config {
+        permissions: PermissionsConfig {
+            rules: vec![rule("web_fetch", Some("*.internal/*"), PermissionAction::Deny)],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (client_ref, mut client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (display_ref, mut display_rx) = spawn_recorder::<ChatMessage>().await;
+    let mut tool_actors = HashMap::new();
+    let mut tool_rxs = HashMap::new();
+    for name in ["web_fetch", "web_search", "memory"] {
+        let (tool_ref, tool_rx) = spawn_recorder::<ToolMessage>().await;
+        tool_actors.insert(name.to_string(), tool_ref);
+        tool_rxs.insert(name, tool_rx);
+    }
+
+    let chat = SubAgentChatActor::new(config.clone(), tool_actors, true).with_client_ref(client_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config)
+        .await
+        .expect("Failed to spawn sub-agent chat actor");
+    chat_ref
+        .send_message(ChatMessage::RegisterDisplay { context: DisplayContext::SubAgent, display_ref })
+        .unwrap();
+    let request_id = Uuid::new_v4();
+    chat_ref
+        .send_message(ChatMessage::UserPrompt {
+            id: request_id,
+            content: UserMessageContent::Text("Look it up".to_string()),
+            context: DisplayContext::SubAgent,
+            session_id: None,
+        })
+        .unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    // memory is destructive, so the default policy would ask the user about it
+    let call = |id: &str, tool_name: &str, parameters: serde_json::Value| ToolCall {
+        id: id.to_string(),
+        tool_name: tool_name.to_string(),
+        parameters,
+        delegate: false,
+    };
+    chat_ref
+        .send_message(ChatMessage::AssistantResponse {
+            id: request_id,
+            content: None,
+            tool_calls: vec![
+                call("call_1", "web_fetch", json!({ "url": "http://metadata.internal/latest" })),
+                call("call_2", "memory", json!({ "action": "store", "content": "remember this" })),
+                call("call_3", "web_search", json!({ "query": "rust" })),
+            ],
+        })
+        .unwrap();
+
+    assert!(matches!(next(tool_rxs.get_mut("web_search").unwrap()).await, ToolMessage::Execute { .. }));
+    let mut refusals = Vec::new();
+    while refusals.len() < 2 {
+        if let ChatMessage::ToolResult { result, .. } = next(&mut display_rx).await {
+            refusals.push(result);
+        }
+    }
+    refusals.sort();
+    assert_eq!(refusals[0], "Error: Permission denied: the permission policy blocks this web_fetch call");
+    assert!(refusals[1].starts_with("Error: Permission denied: this memory call needs the user's confirmation"), "{}", refusals[1]);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(tool_rxs.get_mut("web_fetch").unwrap().try_recv().is_err());
+    assert!(tool_rxs.get_mut("memory").unwrap().try_recv().is_err());
+}
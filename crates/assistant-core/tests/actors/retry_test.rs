@@ -0,0 +1,305 @@
+use assistant_core::{
+    actors::client::{ClientActor, ClientMessage},
+    config::{Config, RetryConfig},
+    messages::ChatMessage,
+    openai_compat::{
+        retry::retry_after_from_headers, ChatMessage as OpenAIMessage, UserContent,
+    },
+    ractor::{Actor, ActorRef},
+};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use wiremock::{MockServer, Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+struct TestSetup {
+    client_ref: ActorRef<ClientMessage>,
+    rx: mpsc::UnboundedReceiver<ChatMessage>,
+    mock_server: MockServer,
+}
+
+async fn setup_retry_test(provider: &str, max_retries: u32) -> TestSetup {
+    let mock_server = MockServer::start().await;
+
+    let config = Config {
+        provider: provider.to_string(),
+        base_url: mock_server.uri(),
+        retry: RetryConfig {
+            max_retries,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 50,
+            jitter: true,
+        },
+        ..Default::default()
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    struct MockChatActor {
+        tx: mpsc::UnboundedSender<ChatMessage>,
+    }
+
+    impl Actor for MockChatActor {
+        type Msg = ChatMessage;
+        type State = ();
+        type Arguments = ();
+
+        async fn pre_start(
+            &self,
+            _myself: ActorRef<Self::Msg>,
+            _: Self::Arguments,
+        ) -> Result<Self::State, assistant_core::ractor::ActorProcessingErr> {
+            Ok(())
+        }
+
+        async fn handle(
+            &self,
+            _myself: ActorRef<Self::Msg>,
+            msg: Self::Msg,
+            _state: &mut Self::State,
+        ) -> Result<(), assistant_core::ractor::ActorProcessingErr> {
+            let _ = self.tx.send(msg);
+            Ok(())
+        }
+    }
+
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ())
+        .await
+        .expect("Failed to spawn mock chat");
+
+    let (client_ref, _) = Actor::spawn(None, ClientActor::new(config.clone()), config)
+        .await
+        .expect("Failed to spawn client actor");
+
+    client_ref
+        .send_message(ClientMessage::SetChatRef(chat_ref))
+        .expect("Failed to set chat ref");
+
+    TestSetup {
+        client_ref,
+        rx,
+        mock_server,
+    }
+}
+
+fn generate(setup: &TestSetup) -> Uuid {
+    let id = Uuid::new_v4();
+    setup.client_ref
+        .send_message(ClientMessage::Generate {
+            id,
+            messages: vec![OpenAIMessage::User {
+                content: UserContent::Text("Hello".to_string()),
+                name: None,
+            }],
+            tools: vec![],
+        })
+        .expect("Failed to send message");
+    id
+}
+
+/// Wait for the final AssistantResponse or Error, skipping stream tokens
+async fn final_message(rx: &mut mpsc::UnboundedReceiver<ChatMessage>) -> ChatMessage {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("Timed out waiting for response")
+            .expect("Channel closed");
+        if !matches!(msg, ChatMessage::StreamToken { .. }) {
+            return msg;
+        }
+    }
+}
+
+fn openai_sse(text: &str) -> String {
+    let chunk = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "gpt-4",
+        "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": null}]
+    });
+    format!("data: {}\n\ndata: [DONE]\n\n", chunk)
+}
+
+fn anthropic_sse(events: &[Value]) -> String {
+    events
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_retries_rate_limit_then_succeeds() {
+    let mut setup = setup_retry_test("openai", 3).await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(429)
+            .insert_header("retry-after", "0.02")
+            .set_body_json(json!({"error": {"message": "Rate limited", "type": "rate_limit"}})))
+        .up_to_n_times(2)
+        .mount(&setup.mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(openai_sse("Hi there")))
+        .mount(&setup.mock_server)
+        .await;
+
+    let id = generate(&setup);
+    match final_message(&mut setup.rx).await {
+        ChatMessage::AssistantResponse { id: resp_id, content, .. } => {
+            assert_eq!(resp_id, id);
+            assert_eq!(content.as_deref(), Some("Hi there"));
+        }
+        other => panic!("Expected AssistantResponse, got {:?}", other),
+    }
+
+    assert_eq!(setup.mock_server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_retries() {
+    let mut setup = setup_retry_test("openai", 2).await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("upstream unavailable"))
+        .mount(&setup.mock_server)
+        .await;
+
+    let id = generate(&setup);
+    match final_message(&mut setup.rx).await {
+        ChatMessage::Error { id: err_id, error } => {
+            assert_eq!(err_id, id);
+            assert!(error.contains("503"));
+            assert!(error.contains("upstream unavailable"));
+        }
+        other => panic!("Expected Error, got {:?}", other),
+    }
+
+    assert_eq!(setup.mock_server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let mut setup = setup_retry_test("openai", 3).await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {"message": "Invalid API key", "type": "auth_error"}
+        })))
+        .mount(&setup.mock_server)
+        .await;
+
+    generate(&setup);
+    match final_message(&mut setup.rx).await {
+        ChatMessage::Error { error, .. } => assert!(error.contains("Invalid API key")),
+        other => panic!("Expected Error, got {:?}", other),
+    }
+
+    assert_eq!(setup.mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stream_error_before_tokens_is_retried() {
+    let mut setup = setup_retry_test("anthropic", 3).await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(anthropic_sse(&[
+            json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude"}}),
+            json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+        ])))
+        .up_to_n_times(1)
+        .mount(&setup.mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(anthropic_sse(&[
+            json!({"type": "message_start", "message": {"id": "msg_2", "model": "claude"}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Recovered"}}),
+            json!({"type": "message_stop"}),
+        ])))
+        .mount(&setup.mock_server)
+        .await;
+
+    generate(&setup);
+    match final_message(&mut setup.rx).await {
+        ChatMessage::AssistantResponse { content, .. } => {
+            assert_eq!(content.as_deref(), Some("Recovered"));
+        }
+        other => panic!("Expected AssistantResponse, got {:?}", other),
+    }
+
+    assert_eq!(setup.mock_server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_stream_error_after_tokens_is_not_retried() {
+    let mut setup = setup_retry_test("anthropic", 3).await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(anthropic_sse(&[
+            json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude"}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Partial"}}),
+            json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+        ])))
+        .mount(&setup.mock_server)
+        .await;
+
+    generate(&setup);
+    match final_message(&mut setup.rx).await {
+        ChatMessage::Error { error, .. } => assert!(error.contains("Overloaded")),
+        other => panic!("Expected Error, got {:?}", other),
+    }
+
+    assert_eq!(setup.mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[test]
+fn test_retry_after_header_parsing() {
+    let headers = |pairs: &[(&'static str, &str)]| {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    };
+
+    assert_eq!(retry_after_from_headers(&headers(&[("retry-after", "7")])), Some(Duration::from_secs(7)));
+    assert_eq!(
+        retry_after_from_headers(&headers(&[("x-ratelimit-reset-requests", "6m0s")])),
+        Some(Duration::from_secs(360))
+    );
+    assert_eq!(
+        retry_after_from_headers(&headers(&[("x-ratelimit-reset-tokens", "250ms")])),
+        Some(Duration::from_millis(250))
+    );
+
+    // OpenRouter sends an epoch timestamp in milliseconds
+    let reset_at = chrono::Utc::now().timestamp_millis() + 5_000;
+    let delay = retry_after_from_headers(&headers(&[("x-ratelimit-reset", &reset_at.to_string())]))
+        .expect("Expected a delay");
+    assert!(delay > Duration::from_secs(3) && delay <= Duration::from_secs(5));
+
+    assert_eq!(retry_after_from_headers(&headers(&[("retry-after", "0")])), None);
+    assert_eq!(retry_after_from_headers(&HeaderMap::new()), None);
+
+    // Values too large for a duration are the longest one, which the backoff cap shortens
+    assert_eq!(retry_after_from_headers(&headers(&[("retry-after", "inf")])), Some(Duration::MAX));
+    assert_eq!(retry_after_from_headers(&headers(&[("retry-after", "1e30")])), Some(Duration::MAX));
+    assert_eq!(retry_after_from_headers(&headers(&[("retry-after", "-inf")])), None);
+    assert_eq!(retry_after_from_headers(&headers(&[("retry-after", "NaN")])), None);
+    assert!(retry_after_from_headers(&headers(&[("x-ratelimit-reset", "inf")])).is_some());
+    assert_eq!(retry_after_from_headers(&headers(&[("x-ratelimit-reset", "-1e30")])), None);
+    let long = format!("1{}s", "0".repeat(400));
+    assert_eq!(retry_after_from_headers(&headers(&[("x-ratelimit-reset-tokens", &long)])), Some(Duration::MAX));
+}
@@ -0,0 +1,279 @@
+use assistant_core::{
+    actors::client::{ClientActor, ClientMessage},
+    config::{Config, ModelPricing},
+    messages::{ChatMessage, TokenUsage},
+    openai_compat::{ChatMessage as OpenAIMessage, UserContent},
+    persistence::database::Database,
+    ractor::{Actor, ActorRef},
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use wiremock::{MockServer, Mock, ResponseTemplate};
+use wiremock::matchers::{method, path};
+
+struct TestSetup {
+    client_ref: ActorRef<ClientMessage>,
+    rx: mpsc::UnboundedReceiver<ChatMessage>,
+    mock_server: MockServer,
+}
+
+async fn setup_usage_test(provider: &str, model: &str, pricing: HashMap<String, ModelPricing>) -> TestSetup {
+    let mock_server = MockServer::start().await;
+
+    let config = Config {
+        provider: provider.to_string(),
+        base_url: mock_server.uri(),
+        model: model.to_string(),
+        pricing,
+        ..Default::default()
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    struct MockChatActor {
+        tx: mpsc::UnboundedSender<ChatMessage>,
+    }
+
+    impl Actor for MockChatActor {
+        type Msg = ChatMessage;
+        type State = ();
+        type Arguments = ();
+
+        async fn pre_start(
+            &self,
+            _myself: ActorRef<Self::Msg>,
+            _: Self::Arguments,
+        ) -> Result<Self::State, assistant_core::ractor::ActorProcessingErr> {
+            Ok(())
+        }
+
+        async fn handle(
+            &self,
+            _myself: ActorRef<Self::Msg>,
+            msg: Self::Msg,
+            _state: &mut Self::State,
+        ) -> Result<(), assistant_core::ractor::ActorProcessingErr> {
+            let _ = self.tx.send(msg);
+            Ok(())
+        }
+    }
+
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ())
+        .await
+        .expect("Failed to spawn mock chat");
+
+    let (client_ref, _) = Actor::spawn(None, ClientActor::new(config.clone()), config)
+        .await
+        .expect("Failed to spawn client actor");
+
+    client_ref
+        .send_message(ClientMessage::SetChatRef(chat_ref))
+        .expect("Failed to set chat ref");
+
+    TestSetup {
+        client_ref,
+        rx,
+        mock_server,
+    }
+}
+
+fn generate(setup: &TestSetup) -> Uuid {
+    let id = Uuid::new_v4();
+    setup.client_ref
+        .send_message(ClientMessage::Generate {
+            id,
+            messages: vec![OpenAIMessage::User {
+                content: UserContent::Text("Hello".to_string()),
+                name: None,
+            }],
+            tools: vec![],
+        })
+        .expect("Failed to send message");
+    id
+}
+
+/// Collect messages up to and including the final AssistantResponse or Error, skipping stream tokens
+async fn collect_messages(rx: &mut mpsc::UnboundedReceiver<ChatMessage>) -> Vec<ChatMessage> {
+    let mut messages = Vec::new();
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("Timed out waiting for response")
+            .expect("Channel closed");
+        match msg {
+            ChatMessage::StreamToken { .. } => continue,
+            ChatMessage::AssistantResponse { .. } | ChatMessage::Error { .. } => {
+                messages.push(msg);
+                return messages;
+            }
+            other => messages.push(other),
+        }
+    }
+}
+
+fn pricing(model: &str, input: f64, output: f64) -> HashMap<String, ModelPricing> {
+    HashMap::from([(
+        model.to_string(),
+        ModelPricing {
+            input_per_million: input,
+            output_per_million: output,
+        },
+    )])
+}
+
+#[tokio::test]
+async fn test_openai_stream_reports_usage_with_cost() {
+    let mut setup = setup_usage_test("openai", "openai/gpt-4o", pricing("gpt-4o", 2.5, 10.0)).await;
+
+    let content = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "delta": {"content": "Hi"}, "finish_reason": "stop"}]
+    });
+    let usage = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "gpt-4o",
+        "choices": [],
+        "usage": {"prompt_tokens": 1000, "completion_tokens": 200, "total_tokens": 1200}
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "data: {}\n\ndata: {}\n\ndata: [DONE]\n\n",
+            content, usage
+        )))
+        .mount(&setup.mock_server)
+        .await;
+
+    let id = generate(&setup);
+    let messages = collect_messages(&mut setup.rx).await;
+
+    // Usage is reported before the response so displays see current totals
+    match messages.as_slice() {
+        [ChatMessage::Usage { id: usage_id, model, usage, sub_agent }, ChatMessage::AssistantResponse { .. }] => {
+            assert_eq!(*usage_id, id);
+            assert_eq!(model, "openai/gpt-4o");
+            assert_eq!(usage.prompt_tokens, 1000);
+            assert_eq!(usage.completion_tokens, 200);
+            assert!((usage.cost - 0.0045).abs() < 1e-9);
+            assert!(sub_agent.is_none());
+        }
+        other => panic!("Expected Usage then AssistantResponse, got {:?}", other),
+    }
+
+    let requests = setup.mock_server.received_requests().await.unwrap();
+    let sent: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(sent["stream_options"]["include_usage"], true);
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_reports_usage_without_pricing() {
+    let mut setup = setup_usage_test("anthropic", "claude-sonnet-4", HashMap::new()).await;
+
+    let events = [
+        json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4", "usage": {"input_tokens": 42, "output_tokens": 1}}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 7}}),
+        json!({"type": "message_stop"}),
+    ];
+    let body: String = events
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect();
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&setup.mock_server)
+        .await;
+
+    generate(&setup);
+    let messages = collect_messages(&mut setup.rx).await;
+
+    match messages.first() {
+        Some(ChatMessage::Usage { usage, .. }) => {
+            assert_eq!(usage.prompt_tokens, 42);
+            assert_eq!(usage.completion_tokens, 7);
+            assert_eq!(usage.cost, 0.0);
+        }
+        other => panic!("Expected Usage, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_session_usage_accumulates_per_source() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Database::new(&temp_dir.path().join("usage.db")).await.unwrap();
+    let session_id = db.create_session(None).await.unwrap();
+
+    let usage = |prompt_tokens, completion_tokens, cost| TokenUsage {
+        prompt_tokens,
+        completion_tokens,
+        cost,
+    };
+
+    db.record_usage(&session_id, "main", "gpt-4o", &usage(100, 10, 0.5)).await.unwrap();
+    db.record_usage(&session_id, "main", "gpt-4o", &usage(200, 20, 0.25)).await.unwrap();
+    db.record_usage(&session_id, "web_search", "gpt-4o-mini", &usage(50, 5, 0.125)).await.unwrap();
+
+    let records = db.get_session_usage(&session_id).await.unwrap();
+    assert_eq!(records.len(), 2);
+
+    let main = records.iter().find(|r| r.source == "main").unwrap();
+    assert_eq!(main.model, "gpt-4o");
+    assert_eq!(main.prompt_tokens, 300);
+    assert_eq!(main.completion_tokens, 30);
+    assert_eq!(main.cost, 0.75);
+
+    let web_search = records.iter().find(|r| r.source == "web_search").unwrap();
+    assert_eq!(web_search.prompt_tokens, 50);
+
+    // Usage goes away with the session
+    db.delete_session(&session_id).await.unwrap();
+    assert!(db.get_session_usage(&session_id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_complete_replies_with_usage_and_cost() {
+    let setup = setup_usage_test("openai", "gpt-4o", pricing("gpt-4o", 2.5, 10.0)).await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "A summary"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 200, "total_tokens": 1200}
+        })))
+        .mount(&setup.mock_server)
+        .await;
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    setup.client_ref
+        .send_message(ClientMessage::Complete {
+            messages: vec![OpenAIMessage::User {
+                content: UserContent::Text("Summarize".to_string()),
+                name: None,
+            }],
+            reply_to: tx,
+        })
+        .expect("Failed to send message");
+
+    // The caller reports the usage, as the chat never sees this completion
+    let completion = rx.await.unwrap().unwrap();
+    assert_eq!(completion.text, "A summary");
+    assert_eq!(completion.model, "gpt-4o");
+    assert_eq!((completion.usage.prompt_tokens, completion.usage.completion_tokens), (1000, 200));
+    assert!((completion.usage.cost - 0.0045).abs() < 1e-9);
+}
@@ -1,10 +1,69 @@
+use assistant_core::actors::client::Completion;
 use assistant_core::config::Config;
+use assistant_core::messages::TokenUsage;
+use assistant_core::ractor::{Actor, ActorProcessingErr, ActorRef, Message};
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Create a test configuration with sensible defaults
+#[allow(dead_code)]
 pub fn test_config() -> Config {
-    let mut config = Config::default();
-    config.api_key = "test-api-key".to_string();
-    config.model = "gpt-4".to_string();
-    config.temperature = 0.7;
-    config
-}
\ No newline at end of file
+    Config {
+        api_key: "test-api-key".to_string(),
+        model: "gpt-4".to_string(),
+        temperature: 0.7,
+        ..Default::default()
+    }
+}
+
+/// Actor that forwards every message it receives to a channel
+struct Recorder<M> {
+    tx: mpsc::UnboundedSender<M>,
+}
+
+impl<M: Message> Actor for Recorder<M> {
+    type Msg = M;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(&self, _myself: ActorRef<Self::Msg>, _: ()) -> Result<Self::State, ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        let _ = self.tx.send(msg);
+        Ok(())
+    }
+}
+
+/// Spawn an actor whose messages can be read back from the returned receiver
+pub async fn spawn_recorder<M: Message>() -> (ActorRef<M>, mpsc::UnboundedReceiver<M>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (actor_ref, _) = Actor::spawn(None, Recorder { tx }, ())
+        .await
+        .expect("Failed to spawn recorder");
+    (actor_ref, rx)
+}
+
+/// The next message a recorder received, waiting up to five seconds for it
+pub async fn next<M>(rx: &mut mpsc::UnboundedReceiver<M>) -> M {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("Timed out waiting for message")
+        .expect("Channel closed")
+}
+
+/// A `ClientMessage::Complete` answer with `text`, which cost 100 prompt and 10 completion tokens
+#[allow(dead_code)]
+pub fn completion(text: &str) -> Completion {
+    Completion {
+        text: text.to_string(),
+        model: "test-model".to_string(),
+        usage: TokenUsage { prompt_tokens: 100, completion_tokens: 10, cost: 0.0 },
+    }
+}
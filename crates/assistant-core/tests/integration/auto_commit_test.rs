@@ -0,0 +1,265 @@
+use assistant_core::{
+    actors::{chat::ChatActor, client::ClientMessage},
+    auto_commit::{TurnChanges, clean_message},
+    config::Config,
+    messages::{ChatMessage, DelegatorMessage, DisplayContext, ToolCall, UIMessage, UserMessageContent},
+    ractor::Actor,
+};
+use git2::{Repository, Signature};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+use crate::common::{completion, next, spawn_recorder};
+
+/// A repository with an author configured and `a.txt` and `b.txt` committed
+fn repository() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Ada").unwrap();
+    config.set_str("user.email", "ada@example.com").unwrap();
+
+    fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+    fs::write(dir.path().join("b.txt"), "b\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.txt")).unwrap();
+    index.add_path(Path::new("b.txt")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("Ada", "ada@example.com").unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).unwrap();
+    dir
+}
+
+fn head_message(path: &Path) -> String {
+    let repo = Repository::open(path).unwrap();
+    let commit = repo.head().unwrap().peel_to_commit().unwrap();
+    commit.message().unwrap().to_string()
+}
+
+#[test]
+fn test_only_the_turns_files_are_committed() {
+    let dir = repository();
+    let root = dir.path().canonicalize().unwrap();
+    // The user has work of their own staged
+    fs::write(root.join("b.txt"), "user's change\n").unwrap();
+    let repo = Repository::open(&root).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("b.txt")).unwrap();
+    index.write().unwrap();
+
+    fs::write(root.join("a.txt"), "a, edited\n").unwrap();
+    fs::write(root.join("new.txt"), "created\n").unwrap();
+    let paths = [root.join("a.txt"), root.join("new.txt"), root.join("unchanged.txt"), "/elsewhere/c.txt".into()];
+    let changes = TurnChanges::collect(&root, &paths).unwrap().unwrap();
+    assert!(changes.diff.contains("-a\n+a, edited\n"), "{}", changes.diff);
+    assert!(changes.diff.contains("+created\n"), "{}", changes.diff);
+    assert!(!changes.diff.contains("user's change"), "{}", changes.diff);
+    assert_eq!(changes.fallback_message(), "Update 2 files");
+
+    changes.commit("Edit a and add new", "session-1").unwrap();
+    assert_eq!(head_message(&root), "Edit a and add new\n\nAssistant-Session: session-1\n");
+    let head = repo.head().unwrap().peel_to_commit().unwrap().tree().unwrap();
+    let blob = |name: &str| {
+        let entry = head.get_path(Path::new(name)).unwrap();
+        String::from_utf8(repo.find_blob(entry.id()).unwrap().content().to_vec()).unwrap()
+    };
+    assert_eq!(blob("a.txt"), "a, edited\n");
+    assert_eq!(blob("new.txt"), "created\n");
+    assert_eq!(blob("b.txt"), "b\n");
+
+    // The user's staged change is still staged, and the committed files are clean
+    let statuses = repo.statuses(None).unwrap();
+    let changed: Vec<(String, git2::Status)> = statuses.iter().map(|entry| (entry.path().unwrap().to_string(), entry.status())).collect();
+    assert_eq!(changed, [("b.txt".to_string(), git2::Status::INDEX_MODIFIED)]);
+
+    // Nothing left to commit
+    assert!(TurnChanges::collect(&root, &paths).unwrap().is_none());
+}
+
+#[test]
+fn test_files_are_committed_as_they_were_collected() {
+    let dir = repository();
+    let root = dir.path().canonicalize().unwrap();
+    fs::write(root.join("a.txt"), "a, edited\n").unwrap();
+    let changes = TurnChanges::collect(&root, &[root.join("a.txt")]).unwrap().unwrap();
+
+    // A later turn changes the file again before the commit is made
+    fs::write(root.join("a.txt"), "a, edited again\n").unwrap();
+    changes.commit("Edit a", "").unwrap();
+
+    let repo = Repository::open(&root).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap().tree().unwrap();
+    let entry = head.get_path(Path::new("a.txt")).unwrap();
+    assert_eq!(repo.find_blob(entry.id()).unwrap().content(), b"a, edited\n");
+    assert_eq!(repo.status_file(Path::new("a.txt")).unwrap(), git2::Status::WT_MODIFIED);
+}
+
+#[test]
+fn test_commit_messages_are_cleaned_of_fences_and_quotes() {
+    assert_eq!(clean_message("```\nFix the parser\n```"), "Fix the parser");
+    assert_eq!(clean_message("```text\nAdd tests\n\nCovers the edge cases.\n```\n"), "Add tests\n\nCovers the edge cases.");
+    assert_eq!(clean_message("  \"Update docs\"  "), "Update docs");
+}
+
+#[tokio::test]
+async fn test_completed_turns_are_committed_with_a_generated_message() {
+    let dir = repository();
+    let root = dir.path().canonicalize().unwrap();
+    let mut config = Config::default();
+    config.session.auto_commit = true;
+    config.session.workspace_path = Some(root.clone());
+    let session = Uuid::new_v4().to_string();
+
+    let (client_ref, mut client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (delegator_ref, mut delegator_rx) = spawn_recorder::<DelegatorMessage>().await;
+    let (display_ref, mut display_rx) = spawn_recorder::<ChatMessage>().await;
+    let chat = ChatActor::new(config.clone(), session.clone())
+        .with_client_ref(client_ref)
+        .with_delegator_ref(delegator_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config).await.unwrap();
+    chat_ref.send_message(ChatMessage::RegisterDisplay { context: DisplayContext::CLI, display_ref }).unwrap();
+
+    let request = Uuid::new_v4();
+    chat_ref.send_message(ChatMessage::UserPrompt {
+        id: request,
+        content: UserMessageContent::Text("Fix a.txt".to_string()),
+        context: DisplayContext::CLI,
+        session_id: None,
+    }).unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    let file = root.join("a.txt");
+    chat_ref.send_message(ChatMessage::AssistantResponse {
+        id: request,
+        content: None,
+        tool_calls: vec![ToolCall {
+            id: "call_1".to_string(),
+            tool_name: "write".to_string(),
+            parameters: json!({ "file_path": file.to_str().unwrap(), "content": "fixed\n" }),
+            delegate: false,
+        }],
+    }).unwrap();
+    let DelegatorMessage::RouteToolCall { id: call, .. } = next(&mut delegator_rx).await else {
+        panic!("Expected RouteToolCall");
+    };
+    // The tool's work, done by the test
+    fs::write(&file, "fixed\n").unwrap();
+    chat_ref.send_message(ChatMessage::ToolResult { id: call, result: "Wrote a.txt".to_string() }).unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    chat_ref.send_message(ChatMessage::Complete { id: request, response: "Fixed it".to_string() }).unwrap();
+    let ClientMessage::Complete { messages, reply_to } = next(&mut client_rx).await else {
+        panic!("Expected Complete");
+    };
+    let diff = serde_json::to_string(&messages).unwrap();
+    assert!(diff.contains("+fixed"), "{}", diff);
+
+    // The chat goes on while the message is being written
+    chat_ref.send_message(ChatMessage::UserPrompt {
+        id: Uuid::new_v4(),
+        content: UserMessageContent::Text("Thanks".to_string()),
+        context: DisplayContext::CLI,
+        session_id: None,
+    }).unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    // The commit message's tokens count toward the session
+    reply_to.send(Ok(completion("```\nFix a.txt\n```"))).unwrap();
+    loop {
+        if let ChatMessage::UIUpdate(UIMessage::UpdateStats(stats)) = next(&mut display_rx).await
+            && stats.session.prompt_tokens == 100
+        {
+            assert_eq!(stats.session.completion_tokens, 10);
+            break;
+        }
+    }
+
+    let expected = format!("Fix a.txt\n\nAssistant-Session: {}\n", session);
+    for _ in 0..50 {
+        if head_message(&root) == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The turn was not committed: {:?}", head_message(&root));
+}
+
+#[tokio::test]
+async fn test_files_the_user_had_changed_are_left_out_of_the_commit() {
+    let dir = repository();
+    let root = dir.path().canonicalize().unwrap();
+    // The user is in the middle of changing b.txt
+    fs::write(root.join("b.txt"), "b, the user's work\n").unwrap();
+    let mut config = Config::default();
+    config.session.auto_commit = true;
+    config.session.workspace_path = Some(root.clone());
+
+    let (client_ref, mut client_rx) = spawn_recorder::<ClientMessage>().await;
+    let (delegator_ref, mut delegator_rx) = spawn_recorder::<DelegatorMessage>().await;
+    let chat = ChatActor::new(config.clone(), Uuid::new_v4().to_string())
+        .with_client_ref(client_ref)
+        .with_delegator_ref(delegator_ref);
+    let (chat_ref, _) = Actor::spawn(None, chat, config).await.unwrap();
+
+    let request = Uuid::new_v4();
+    chat_ref.send_message(ChatMessage::UserPrompt {
+        id: request,
+        content: UserMessageContent::Text("Fix both files".to_string()),
+        context: DisplayContext::CLI,
+        session_id: None,
+    }).unwrap();
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    let write = |id: &str, name: &str| ToolCall {
+        id: id.to_string(),
+        tool_name: "write".to_string(),
+        parameters: json!({ "file_path": root.join(name).to_str().unwrap(), "content": "fixed\n" }),
+        delegate: false,
+    };
+    chat_ref.send_message(ChatMessage::AssistantResponse {
+        id: request,
+        content: None,
+        tool_calls: vec![write("call_1", "a.txt"), write("call_2", "b.txt")],
+    }).unwrap();
+    for _ in 0..2 {
+        let DelegatorMessage::RouteToolCall { id: call, call: ToolCall { parameters, .. }, .. } = next(&mut delegator_rx).await else {
+            panic!("Expected RouteToolCall");
+        };
+        fs::write(parameters["file_path"].as_str().unwrap(), "fixed\n").unwrap();
+        chat_ref.send_message(ChatMessage::ToolResult { id: call, result: "Wrote it".to_string() }).unwrap();
+    }
+    assert!(matches!(next(&mut client_rx).await, ClientMessage::Generate { .. }));
+
+    chat_ref.send_message(ChatMessage::Complete { id: request, response: "Fixed them".to_string() }).unwrap();
+    match next(&mut client_rx).await {
+        ClientMessage::Complete { messages, reply_to } => {
+            let diff = serde_json::to_string(&messages).unwrap();
+            assert!(diff.contains("a.txt"), "{}", diff);
+            assert!(!diff.contains("b.txt"), "{}", diff);
+            reply_to.send(Ok(completion("Fix a.txt"))).unwrap();
+        }
+        other => panic!("Expected Complete, got {:?}", other),
+    }
+
+    for _ in 0..50 {
+        if head_message(&root).starts_with("Fix a.txt") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let repo = Repository::open(&root).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert!(head.message().unwrap().starts_with("Fix a.txt"), "The turn was not committed: {:?}", head.message());
+    let blob = |name: &str| {
+        let entry = head.tree().unwrap().get_path(Path::new(name)).unwrap();
+        String::from_utf8(repo.find_blob(entry.id()).unwrap().content().to_vec()).unwrap()
+    };
+    assert_eq!(blob("a.txt"), "fixed\n");
+    assert_eq!(blob("b.txt"), "b\n");
+    assert_eq!(repo.status_file(Path::new("b.txt")).unwrap(), git2::Status::WT_MODIFIED);
+}
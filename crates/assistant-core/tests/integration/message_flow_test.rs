@@ -181,6 +181,7 @@ async fn test_user_prompt_persistence() {
             id: request_id,
             content: UserMessageContent::Text("Hello, assistant!".to_string()),
             context: DisplayContext::CLI,
+            session_id: None,
         })
         .expect("Failed to send user prompt");
     
@@ -224,6 +225,7 @@ async fn test_tool_call_persistence() {
             id: request_id,
             content: UserMessageContent::Text("Store a memory that I like pizza".to_string()),
             context: DisplayContext::CLI,
+            session_id: None,
         })
         .expect("Failed to send user prompt");
     
@@ -270,6 +272,7 @@ async fn test_concurrent_message_persistence() {
                     id: request_id,
                     content: UserMessageContent::Text(format!("Message {}", i)),
                     context: DisplayContext::CLI,
+                    session_id: None,
                 })
                 .expect("Failed to send message");
         });
@@ -315,6 +318,7 @@ async fn test_error_recovery() {
             id: Uuid::new_v4(),
             content: UserMessageContent::Text("This should still work".to_string()),
             context: DisplayContext::CLI,
+            session_id: None,
         })
         .expect("System should still accept messages after error");
     
@@ -1 +1,3 @@
-pub mod message_flow_test;
\ No newline at end of file
+pub mod message_flow_test;
+pub mod worktree_test;
+pub mod auto_commit_test;
\ No newline at end of file
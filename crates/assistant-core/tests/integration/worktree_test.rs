@@ -0,0 +1,144 @@
+use assistant_core::config::Config;
+use assistant_core::worktree::{self, Worktrees};
+use git2::{Repository, Signature};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A repository with an author configured and one commit of `app.txt`
+fn repository() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Ada").unwrap();
+    config.set_str("user.email", "ada@example.com").unwrap();
+    commit(&repo, "app.txt", "one\ntwo\nthree\n", "Initial commit");
+    dir
+}
+
+fn commit(repo: &Repository, path: &str, content: &str, message: &str) {
+    fs::write(repo.workdir().unwrap().join(path), content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(path)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("Ada", "ada@example.com").unwrap();
+    let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap();
+}
+
+#[test]
+fn test_worktree_changes_stay_apart_until_merged() {
+    let repo_dir = repository();
+    let location = TempDir::new().unwrap();
+    let worktrees = Worktrees::open(repo_dir.path()).unwrap();
+
+    let worktree = worktrees.create(location.path()).unwrap();
+    assert!(worktree.branch.starts_with("assistant/"));
+    assert_eq!(fs::read_to_string(worktree.path.join("app.txt")).unwrap(), "one\ntwo\nthree\n");
+    // Found again from inside the worktree, by name or by branch
+    let from_inside = Worktrees::open(&worktree.path).unwrap();
+    assert_eq!(from_inside.containing(&worktree.path).unwrap().name, worktree.name);
+    assert_eq!(worktrees.find(&worktree.branch).unwrap().path, worktree.path);
+
+    // The agent changes a file and adds one, without committing
+    fs::write(worktree.path.join("app.txt"), "one\n2\nthree\n").unwrap();
+    fs::write(worktree.path.join("new.txt"), "added\n").unwrap();
+    // Meanwhile the user works on something else in their checkout
+    fs::write(repo_dir.path().join("notes.txt"), "in progress\n").unwrap();
+    assert_eq!(fs::read_to_string(repo_dir.path().join("app.txt")).unwrap(), "one\ntwo\nthree\n");
+
+    let diff = worktrees.diff(&worktree.name).unwrap();
+    assert_eq!(diff.summary(), "2 files changed, +2 -1");
+    assert!(diff.patch.contains("-two\n+2\n"), "{}", diff.patch);
+    assert!(diff.patch.contains("+added\n"), "{}", diff.patch);
+
+    let repo = Repository::open(repo_dir.path()).unwrap();
+    let current = repo.head().unwrap().shorthand().unwrap().to_string();
+    let merged = worktrees.merge(&worktree.name).unwrap();
+    assert_eq!(merged, format!("Fast-forwarded {} to {} and removed its worktree", current, worktree.branch));
+    assert_eq!(fs::read_to_string(repo_dir.path().join("app.txt")).unwrap(), "one\n2\nthree\n");
+    assert_eq!(fs::read_to_string(repo_dir.path().join("new.txt")).unwrap(), "added\n");
+    assert_eq!(fs::read_to_string(repo_dir.path().join("notes.txt")).unwrap(), "in progress\n");
+    assert!(!worktree.path.exists());
+    assert!(worktrees.list().unwrap().is_empty());
+    assert!(repo.find_branch(&worktree.branch, git2::BranchType::Local).is_err());
+}
+
+#[test]
+fn test_conflicting_merges_change_nothing_and_discarding_removes_the_branch() {
+    let repo_dir = repository();
+    let location = TempDir::new().unwrap();
+    let worktrees = Worktrees::open(repo_dir.path()).unwrap();
+    let repo = Repository::open(repo_dir.path()).unwrap();
+
+    let worktree = worktrees.create(location.path()).unwrap();
+    fs::write(worktree.path.join("app.txt"), "one\nagent\nthree\n").unwrap();
+    commit(&repo, "app.txt", "one\nuser\nthree\n", "User change");
+    let head = repo.head().unwrap().target().unwrap();
+
+    let error = worktrees.merge(&worktree.name).unwrap_err().to_string();
+    assert_eq!(
+        error,
+        format!(
+            "Merging {branch} would conflict in app.txt; nothing was changed. Merge it by hand with `git merge {branch}`",
+            branch = worktree.branch
+        )
+    );
+    assert_eq!(repo.head().unwrap().target().unwrap(), head);
+    assert_eq!(fs::read_to_string(repo_dir.path().join("app.txt")).unwrap(), "one\nuser\nthree\n");
+    assert!(worktree.path.exists());
+
+    worktrees.discard(&worktree.name).unwrap();
+    assert!(!worktree.path.exists());
+    assert!(repo.find_branch(&worktree.branch, git2::BranchType::Local).is_err());
+    let missing = worktrees.diff(&worktree.name).unwrap_err().to_string();
+    assert!(missing.starts_with("There is no agent worktree"), "{}", missing);
+}
+
+#[test]
+fn test_unchanged_worktrees_are_removed_when_the_run_finishes() {
+    let repo_dir = repository();
+    let location = TempDir::new().unwrap();
+    let worktrees = Worktrees::open(repo_dir.path()).unwrap();
+
+    let unused = worktrees.create(location.path()).unwrap();
+    assert!(worktree::finish(&unused).unwrap());
+    assert!(!unused.path.exists());
+    assert!(worktrees.find(&unused.name).is_err());
+
+    // Changes, even uncommitted ones, keep the worktree for review
+    let used = worktrees.create(location.path()).unwrap();
+    fs::write(used.path.join("notes.txt"), "to review\n").unwrap();
+    assert!(!worktree::finish(&used).unwrap());
+    assert!(used.path.join("notes.txt").exists());
+    assert_eq!(worktrees.list().unwrap().len(), 1);
+}
+
+#[test]
+fn test_isolation_keeps_the_subdirectory_and_grants_only_the_branchs_git_files() {
+    let repo_dir = repository();
+    let repo = Repository::open(repo_dir.path()).unwrap();
+    fs::create_dir(repo_dir.path().join("src")).unwrap();
+    commit(&repo, "src/lib.txt", "library\n", "Add the library");
+    let location = TempDir::new().unwrap();
+    let before = std::env::current_dir().unwrap();
+
+    let mut config = Config::default();
+    config.session.workspace_path = Some(repo_dir.path().join("src"));
+    let worktree = worktree::isolate(&mut config, location.path()).unwrap();
+
+    assert_eq!(config.session.workspace_path, Some(worktree.path.join("src")));
+    assert_eq!(std::env::current_dir().unwrap(), before);
+
+    let git_dir = repo.path().canonicalize().unwrap();
+    let writable: Vec<_> = config.sandbox.writable_paths.iter().map(|path| path.canonicalize().unwrap()).collect();
+    assert!(!writable.contains(&git_dir));
+    assert!(writable.contains(&git_dir.join("worktrees").join(&worktree.name)));
+    assert!(writable.contains(&git_dir.join("objects")));
+    assert!(writable.contains(&git_dir.join("refs/heads/assistant")));
+    assert!(writable.iter().all(|path| !git_dir.starts_with(path)));
+
+    assert!(worktree::finish(&worktree).unwrap());
+}
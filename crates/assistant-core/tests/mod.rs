@@ -1,3 +1,4 @@
+mod common;
 mod tools;
 mod actors;
 mod integration;
\ No newline at end of file
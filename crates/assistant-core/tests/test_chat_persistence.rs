@@ -72,6 +72,7 @@ mod chat_persistence_tests {
                 id: Uuid::new_v4(),
                 session_id: session_id.clone(),
                 response: "I'm here to help!".to_string(),
+                tool_calls: None,
             })
             .expect("Failed to send message");
         
@@ -200,6 +201,7 @@ mod chat_persistence_tests {
                         id: Uuid::new_v4(),
                         session_id: session_id_clone,
                         response: format!("Response for session {}", i_clone),
+                        tool_calls: None,
                     })
                     .expect("Failed to send message");
             });
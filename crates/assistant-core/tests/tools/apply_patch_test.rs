@@ -0,0 +1,162 @@
+use assistant_core::actors::tools::apply_patch::ApplyPatchActor;
+use assistant_core::config::Config;
+use assistant_core::messages::{ChatMessage, ToolMessage};
+use ractor::{Actor, ActorProcessingErr, ActorRef};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Forwards tool results to the test
+struct MockChatActor {
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl Actor for MockChatActor {
+    type Msg = ChatMessage;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(&self, _myself: ActorRef<Self::Msg>, _args: ()) -> Result<Self::State, ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        if let ChatMessage::ToolResult { result, .. } = msg {
+            let _ = self.tx.send(result);
+        }
+        Ok(())
+    }
+}
+
+async fn apply_patch(patch: String) -> String {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ()).await.unwrap();
+    let config = Config::default();
+    let (tool_ref, _) = Actor::spawn(None, ApplyPatchActor::new(config.clone()), config).await.unwrap();
+
+    tool_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params: json!({ "patch": patch }), chat_ref }).unwrap();
+    let result = rx.recv().await.unwrap();
+    tool_ref.stop(None);
+    result
+}
+
+fn numbered_lines(count: usize) -> String {
+    (1..=count).map(|i| format!("line {}\n", i)).collect()
+}
+
+#[tokio::test]
+async fn test_apply_patch_across_files() {
+    let dir = TempDir::new().unwrap();
+    let path = |name: &str| dir.path().join(name).display().to_string();
+    fs::write(path("modified.txt"), numbered_lines(20)).unwrap();
+    fs::write(path("deleted.txt"), "goodbye\n").unwrap();
+    fs::write(path("old_name.txt"), "keep\nchange me\n").unwrap();
+
+    // The second hunk's line numbers are off by two, as if lines were added above it
+    let patch = format!(
+        "--- {modified}\n+++ {modified}\n@@ -2,3 +2,3 @@\n line 2\n-line 3\n+line three\n line 4\n@@ -15,3 +15,4 @@\n line 17\n line 18\n+line 18.5\n line 19\n\
+         --- /dev/null\n+++ {created}\n@@ -0,0 +1,2 @@\n+hello\n+world\n\
+         diff --git a/{deleted} b/{deleted}\ndeleted file mode 100644\n--- a/{deleted}\n+++ /dev/null\n@@ -1 +0,0 @@\n-goodbye\n\
+         diff --git a/{old} b/{new}\nsimilarity index 60%\nrename from {old}\nrename to {new}\n--- a/{old}\n+++ b/{new}\n@@ -1,2 +1,2 @@\n keep\n-change me\n+changed\n",
+        modified = path("modified.txt"),
+        created = path("nested/created.txt"),
+        deleted = path("deleted.txt"),
+        old = path("old_name.txt"),
+        new = path("new_name.txt"),
+    );
+
+    let result = apply_patch(patch).await;
+    assert!(result.starts_with("Successfully applied patch to 4 files"), "{}", result);
+    assert!(result.contains("modified.txt: modified (2 hunks)"), "{}", result);
+    assert!(result.contains("created.txt: created"), "{}", result);
+    assert!(result.contains("deleted.txt: deleted"), "{}", result);
+    assert!(result.contains("new_name.txt: renamed (1 hunk)"), "{}", result);
+
+    let modified = fs::read_to_string(path("modified.txt")).unwrap();
+    assert!(modified.starts_with("line 1\nline 2\nline three\nline 4\n"), "{}", modified);
+    assert!(modified.ends_with("line 18\nline 18.5\nline 19\nline 20\n"), "{}", modified);
+    assert_eq!(fs::read_to_string(path("nested/created.txt")).unwrap(), "hello\nworld\n");
+    assert!(!Path::new(&path("deleted.txt")).exists());
+    assert!(!Path::new(&path("old_name.txt")).exists());
+    assert_eq!(fs::read_to_string(path("new_name.txt")).unwrap(), "keep\nchanged\n");
+}
+
+#[tokio::test]
+async fn test_apply_patch_reports_failing_hunks_and_changes_nothing() {
+    let dir = TempDir::new().unwrap();
+    let path = |name: &str| dir.path().join(name).display().to_string();
+    fs::write(path("good.txt"), "alpha\nbeta\n").unwrap();
+    fs::write(path("bad.txt"), numbered_lines(10)).unwrap();
+
+    let patch = format!(
+        "--- {good}\n+++ {good}\n@@ -1,2 +1,2 @@\n alpha\n-beta\n+gamma\n\
+         --- {bad}\n+++ {bad}\n@@ -1,2 +1,2 @@\n line 1\n-line 2\n+line two\n@@ -6,2 +6,2 @@\n line 6\n-line seven\n+line 7\n\
+         --- /dev/null\n+++ {good}\n@@ -0,0 +1 @@\n+again\n",
+        good = path("good.txt"),
+        bad = path("bad.txt"),
+    );
+
+    let result = apply_patch(patch).await;
+    assert!(result.starts_with("Error: Patch not applied; no files were changed. 2 problems found"), "{}", result);
+    assert!(result.contains("bad.txt: hunk 2 of 2 (@@ -6,2 +6,2 @@) does not match the file"), "{}", result);
+    assert!(result.contains("Expected these lines near line 6:\n    line 6\n    line seven\n"), "{}", result);
+    assert!(result.contains("       6 | line 6\n       7 | line 7\n"), "{}", result);
+    assert!(result.contains("good.txt: cannot create the file, it already exists"), "{}", result);
+
+    assert_eq!(fs::read_to_string(path("good.txt")).unwrap(), "alpha\nbeta\n");
+    assert_eq!(fs::read_to_string(path("bad.txt")).unwrap(), numbered_lines(10));
+}
+
+#[tokio::test]
+async fn test_apply_patch_handles_missing_newlines_at_end_of_file() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("file.txt");
+    fs::write(&file, "first\nlast").unwrap();
+
+    let patch = format!(
+        "--- {0}\n+++ {0}\n@@ -1,2 +1,3 @@\n first\n-last\n\\ No newline at end of file\n+last\n+appended\n",
+        file.display()
+    );
+    let result = apply_patch(patch).await;
+    assert!(result.starts_with("Successfully applied patch"), "{}", result);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "first\nlast\nappended\n");
+
+    let patch = format!(
+        "--- {0}\n+++ {0}\n@@ -3 +3 @@\n-appended\n+no newline\n\\ No newline at end of file\n",
+        file.display()
+    );
+    let result = apply_patch(patch).await;
+    assert!(result.starts_with("Successfully applied patch"), "{}", result);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "first\nlast\nno newline");
+}
+
+#[tokio::test]
+async fn test_apply_patch_rejects_malformed_patches() {
+    let result = apply_patch("just some text\n".to_string()).await;
+    assert_eq!(result, "Error: Invalid patch - no file changes found");
+
+    let result = apply_patch("--- a/file\n+++ b/file\n@@ -1,3 +1,3 @@\n line\n-old\n".to_string()).await;
+    assert!(result.starts_with("Error: Invalid patch - line 5: hunk '@@ -1,3 +1,3 @@' ends early"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_apply_patch_rejects_hunk_lines_starting_with_other_characters() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("accents.txt");
+    fs::write(&file, "été\nhiver\n").unwrap();
+
+    // The context line lost its leading space, leaving a multi-byte character first
+    let patch = format!("--- {path}\n+++ {path}\n@@ -1,2 +1,2 @@\nété\n-hiver\n+automne\n", path = file.display());
+    let result = apply_patch(patch).await;
+    assert!(result.starts_with("Error: Invalid patch - line 4: unexpected line 'été'"), "{}", result);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "été\nhiver\n");
+}
+
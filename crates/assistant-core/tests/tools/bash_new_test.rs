@@ -1,10 +1,11 @@
 use assistant_core::{
     actors::tools::bash::BashActor,
-    config::Config,
+    config::{Config, tool_config::ToolConfig},
     messages::{ToolMessage, ChatMessage},
     ractor::{Actor, ActorRef},
 };
 use serde_json::json;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
@@ -12,18 +13,33 @@ struct TestSetup {
     bash_ref: ActorRef<ToolMessage>,
     chat_ref: ActorRef<ChatMessage>,
     rx: mpsc::UnboundedReceiver<ChatMessage>,
+    /// Output streamed while commands run
+    output_rx: mpsc::UnboundedReceiver<ChatMessage>,
 }
 
 async fn setup_bash_test() -> TestSetup {
+    setup_bash_test_with(Config::default()).await
+}
+
+async fn setup_persistent_shell_test() -> TestSetup {
     let mut config = Config::default();
+    let mut bash_config = ToolConfig::default();
+    bash_config.settings.insert("persistent_shell".to_string(), json!(true));
+    config.tools.configs.insert("bash".to_string(), bash_config);
+    setup_bash_test_with(config).await
+}
+
+async fn setup_bash_test_with(mut config: Config) -> TestSetup {
     config.api_key = "test-api-key".to_string();
     
-    // Create a channel to receive responses
+    // Create channels to receive responses and streamed output
     let (tx, rx) = mpsc::unbounded_channel();
+    let (output_tx, output_rx) = mpsc::unbounded_channel();
     
     // Create mock chat actor
     struct MockChatActor {
         tx: mpsc::UnboundedSender<ChatMessage>,
+        output_tx: mpsc::UnboundedSender<ChatMessage>,
     }
     
     impl Actor for MockChatActor {
@@ -45,12 +61,16 @@ async fn setup_bash_test() -> TestSetup {
             msg: Self::Msg,
             _state: &mut Self::State,
         ) -> Result<(), assistant_core::ractor::ActorProcessingErr> {
-            let _ = self.tx.send(msg);
+            if matches!(msg, ChatMessage::ToolOutput { .. }) {
+                let _ = self.output_tx.send(msg);
+            } else {
+                let _ = self.tx.send(msg);
+            }
             Ok(())
         }
     }
     
-    let mock_chat = MockChatActor { tx: tx.clone() };
+    let mock_chat = MockChatActor { tx: tx.clone(), output_tx };
     let (chat_ref, _) = Actor::spawn(
         None,
         mock_chat,
@@ -73,6 +93,7 @@ async fn setup_bash_test() -> TestSetup {
         bash_ref,
         chat_ref,
         rx,
+        output_rx,
     }
 }
 
@@ -280,6 +301,317 @@ async fn test_bash_timeout() {
     }
 }
 
+#[tokio::test]
+async fn test_bash_streams_output_while_running() {
+    let mut setup = setup_bash_test().await;
+    
+    let cmd_id = Uuid::new_v4();
+    let params = json!({
+        "command": "echo first; echo oops >&2; sleep 1; echo last"
+    });
+    
+    setup.bash_ref
+        .send_message(ToolMessage::Execute {
+            id: cmd_id,
+            params,
+            chat_ref: setup.chat_ref.clone(),
+        })
+        .expect("Failed to send command");
+    
+    // Both streams arrive line by line before the command finishes
+    let mut streamed = Vec::new();
+    while streamed.len() < 2 {
+        let output = tokio::time::timeout(std::time::Duration::from_millis(900), setup.output_rx.recv())
+            .await
+            .expect("Output was not streamed while the command ran");
+        match output {
+            Some(ChatMessage::ToolOutput { id, output }) => {
+                assert_eq!(id, cmd_id);
+                streamed.push(output);
+            }
+            other => panic!("Expected ToolOutput, got {:?}", other),
+        }
+    }
+    streamed.sort();
+    assert_eq!(streamed, ["first\n", "oops\n"]);
+    assert!(setup.rx.try_recv().is_err());
+    
+    // The result still carries the whole output, stdout first
+    let response = setup.rx.recv().await.expect("Failed to receive response");
+    match response {
+        ChatMessage::ToolResult { id, result } => {
+            assert_eq!(id, cmd_id);
+            assert_eq!(result, "first\nlast\n\noops\n");
+        }
+        _ => panic!("Expected ToolResult"),
+    }
+    match setup.output_rx.recv().await {
+        Some(ChatMessage::ToolOutput { output, .. }) => assert_eq!(output, "last\n"),
+        other => panic!("Expected ToolOutput, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_bash_streams_output_in_batches() {
+    for mut setup in [setup_bash_test().await, setup_persistent_shell_test().await] {
+        let cmd_id = Uuid::new_v4();
+        setup.bash_ref
+            .send_message(ToolMessage::Execute {
+                id: cmd_id,
+                params: json!({ "command": "for i in $(seq 1 500); do echo line $i; done" }),
+                chat_ref: setup.chat_ref.clone(),
+            })
+            .expect("Failed to send command");
+        
+        let expected: String = (1..=500).map(|i| format!("line {}\n", i)).collect();
+        match setup.rx.recv().await {
+            Some(ChatMessage::ToolResult { result, .. }) => assert_eq!(result, expected),
+            other => panic!("Expected ToolResult, got {:?}", other),
+        }
+        
+        // Far fewer messages than lines, together carrying all of them
+        let mut messages = 0;
+        let mut streamed = String::new();
+        while let Ok(ChatMessage::ToolOutput { output, .. }) = setup.output_rx.try_recv() {
+            messages += 1;
+            streamed.push_str(&output);
+        }
+        assert_eq!(streamed, expected);
+        assert!(messages < 50, "{} messages for 500 lines", messages);
+    }
+}
+
+#[tokio::test]
+async fn test_bash_cancel_kills_process_group() {
+    let mut setup = setup_bash_test().await;
+    
+    // The background sleep belongs to the command's process group
+    let cmd_id = Uuid::new_v4();
+    setup.bash_ref
+        .send_message(ToolMessage::Execute {
+            id: cmd_id,
+            params: json!({ "command": "sleep 300 & echo $!; wait" }),
+            chat_ref: setup.chat_ref.clone(),
+        })
+        .expect("Failed to send command");
+    
+    let pid = match tokio::time::timeout(Duration::from_secs(5), setup.output_rx.recv()).await {
+        Ok(Some(ChatMessage::ToolOutput { output, .. })) => output.trim().to_string(),
+        other => panic!("Expected the background pid, got {:?}", other),
+    };
+    assert!(process_running(&pid), "sleep should be running before the cancel");
+    
+    setup.bash_ref
+        .send_message(ToolMessage::Cancel { id: cmd_id })
+        .expect("Failed to send cancel");
+    
+    // A cancelled command sends no result
+    let response = tokio::time::timeout(Duration::from_millis(500), setup.rx.recv()).await;
+    assert!(response.is_err(), "Expected no result, got {:?}", response);
+    
+    // The sleep is killed with its process group
+    wait_for_exit(&pid).await;
+}
+
+/// Run a bash action and return its result
+async fn bash_action(setup: &mut TestSetup, params: serde_json::Value) -> String {
+    let id = Uuid::new_v4();
+    setup.bash_ref
+        .send_message(ToolMessage::Execute { id, params, chat_ref: setup.chat_ref.clone() })
+        .expect("Failed to send command");
+    match tokio::time::timeout(Duration::from_secs(5), setup.rx.recv()).await {
+        Ok(Some(ChatMessage::ToolResult { id: result_id, result })) => {
+            assert_eq!(result_id, id);
+            result
+        }
+        other => panic!("Expected ToolResult, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_bash_background_job_output_is_polled_incrementally() {
+    let mut setup = setup_bash_test().await;
+    
+    let started = bash_action(&mut setup, json!({
+        "command": "echo first; sleep 0.5; echo second",
+        "run_in_background": true
+    })).await;
+    assert!(started.contains("Started background job 1"), "{}", started);
+    
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let first = bash_action(&mut setup, json!({ "action": "output", "job_id": 1 })).await;
+    assert!(first.contains("(running)") && first.contains("first\n"), "{}", first);
+    assert!(!first.contains("second"));
+    
+    tokio::time::sleep(Duration::from_millis(750)).await;
+    let second = bash_action(&mut setup, json!({ "action": "output", "job_id": 1 })).await;
+    assert!(second.contains("exited with code 0") && second.contains("second\n"), "{}", second);
+    assert!(!second.contains("first"), "Output already read is not returned again: {}", second);
+    
+    let list = bash_action(&mut setup, json!({ "action": "list" })).await;
+    assert!(list.contains("echo first; sleep 0.5; echo second"), "{}", list);
+    
+    let missing = bash_action(&mut setup, json!({ "action": "output", "job_id": 7 })).await;
+    assert!(missing.contains("No background job 7"));
+}
+
+#[tokio::test]
+async fn test_bash_background_jobs_are_killed_on_request_and_on_stop() {
+    let mut setup = setup_bash_test().await;
+    
+    let mut pids = Vec::new();
+    for job_id in 1..=2 {
+        bash_action(&mut setup, json!({ "command": "sleep 300 & echo $!; wait", "run_in_background": true })).await;
+        let mut pid = String::new();
+        for _ in 0..50 {
+            let output = bash_action(&mut setup, json!({ "action": "output", "job_id": job_id })).await;
+            if let Some(line) = output.lines().nth(1).filter(|line| !line.starts_with('(')) {
+                pid = line.trim().to_string();
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(process_running(&pid), "job {} should be running", job_id);
+        pids.push(pid);
+    }
+    
+    let killed = bash_action(&mut setup, json!({ "action": "kill", "job_id": 1 })).await;
+    assert!(killed.contains("Killed job 1"), "{}", killed);
+    wait_for_exit(&pids[0]).await;
+    let list = bash_action(&mut setup, json!({ "action": "list" })).await;
+    assert!(list.starts_with("2 "), "{}", list);
+    
+    // Stopping the actor kills the jobs left
+    setup.bash_ref.stop(None);
+    wait_for_exit(&pids[1]).await;
+}
+
+#[tokio::test]
+async fn test_bash_background_jobs_belong_to_their_session() {
+    let mut setup = setup_bash_test().await;
+    
+    let mut pids = Vec::new();
+    for session in ["first", "second"] {
+        let started = bash_action(&mut setup, json!({
+            "command": "sleep 300 & echo $!; wait",
+            "run_in_background": true,
+            "session_id": session
+        })).await;
+        assert!(started.contains("Started background job 1"), "{}", started);
+        let mut pid = String::new();
+        for _ in 0..50 {
+            let output = bash_action(&mut setup, json!({ "action": "output", "job_id": 1, "session_id": session })).await;
+            if let Some(line) = output.lines().nth(1).filter(|line| !line.starts_with('(')) {
+                pid = line.trim().to_string();
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(process_running(&pid), "{}'s job should be running", session);
+        pids.push(pid);
+    }
+    
+    // Other sessions' jobs can't be seen or killed
+    let list = bash_action(&mut setup, json!({ "action": "list", "session_id": "third" })).await;
+    assert_eq!(list, "No background jobs");
+    let killed = bash_action(&mut setup, json!({ "action": "kill", "job_id": 1, "session_id": "third" })).await;
+    assert!(killed.contains("No background job 1"), "{}", killed);
+    
+    // Ending a session kills its jobs and leaves the others running
+    setup.bash_ref
+        .send_message(ToolMessage::EndSession { session_id: "first".to_string() })
+        .expect("Failed to end session");
+    wait_for_exit(&pids[0]).await;
+    assert!(process_running(&pids[1]));
+    let list = bash_action(&mut setup, json!({ "action": "list", "session_id": "second" })).await;
+    assert!(list.starts_with("1 "), "{}", list);
+    
+    setup.bash_ref.stop(None);
+    wait_for_exit(&pids[1]).await;
+}
+
+#[tokio::test]
+async fn test_persistent_shell_keeps_environment_functions_and_directory() {
+    let mut setup = setup_persistent_shell_test().await;
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().canonicalize().unwrap();
+    
+    let setup_result = bash_action(&mut setup, json!({
+        "command": format!("export GREETING=hello\ngreet() {{ echo \"$GREETING, $1\"; }}\ncd '{}'", dir_path.display())
+    })).await;
+    assert_eq!(setup_result, "");
+    
+    let greeting = bash_action(&mut setup, json!({ "command": "greet world; pwd" })).await;
+    assert_eq!(greeting, format!("hello, world\n{}\n", dir_path.display()));
+    
+    let failed = bash_action(&mut setup, json!({ "command": "printf partial; echo oops >&2; false" })).await;
+    assert_eq!(failed, "partial\noops\n\n\nCommand exited with code: 1");
+    
+    let reset = bash_action(&mut setup, json!({ "action": "reset" })).await;
+    assert!(reset.contains("Shell reset"), "{}", reset);
+    let after_reset = bash_action(&mut setup, json!({ "command": "echo \"[$GREETING]\"; pwd; type greet" })).await;
+    assert!(after_reset.starts_with("[]\n"), "{}", after_reset);
+    assert!(!after_reset.contains(&dir_path.display().to_string()), "{}", after_reset);
+    assert!(after_reset.contains("not found"), "{}", after_reset);
+}
+
+#[tokio::test]
+async fn test_persistent_shell_belongs_to_its_session() {
+    let mut setup = setup_persistent_shell_test().await;
+    let run = |session: &'static str, command: &'static str| json!({ "command": command, "session_id": session });
+    
+    bash_action(&mut setup, run("first", "export OWNER=first")).await;
+    bash_action(&mut setup, run("second", "export OWNER=second")).await;
+    assert_eq!(bash_action(&mut setup, run("first", "echo $OWNER")).await, "first\n");
+    assert_eq!(bash_action(&mut setup, run("second", "echo $OWNER")).await, "second\n");
+    
+    // A session that ended gets a new shell if its id comes back
+    setup.bash_ref
+        .send_message(ToolMessage::EndSession { session_id: "first".to_string() })
+        .expect("Failed to end session");
+    assert_eq!(bash_action(&mut setup, run("first", "echo \"[$OWNER]\"")).await, "[]\n");
+    assert_eq!(bash_action(&mut setup, run("second", "echo $OWNER")).await, "second\n");
+}
+
+#[tokio::test]
+async fn test_persistent_shell_restarts_after_timeout_and_exit() {
+    let mut setup = setup_persistent_shell_test().await;
+    
+    bash_action(&mut setup, json!({ "command": "export KEPT=yes" })).await;
+    let timed_out = bash_action(&mut setup, json!({ "command": "echo before; sleep 30", "timeout": 300 })).await;
+    assert!(timed_out.starts_with("before\n"), "{}", timed_out);
+    assert!(timed_out.contains("timed out after 300ms"), "{}", timed_out);
+    
+    let restarted = bash_action(&mut setup, json!({ "command": "echo \"[$KEPT]\"" })).await;
+    assert_eq!(restarted, "[]\n");
+    
+    let exited = bash_action(&mut setup, json!({ "command": "exit 3" })).await;
+    assert!(exited.contains("The shell exited with code 3"), "{}", exited);
+    let after_exit = bash_action(&mut setup, json!({ "command": "echo alive" })).await;
+    assert_eq!(after_exit, "alive\n");
+}
+
+async fn wait_for_exit(pid: &str) {
+    let mut waited = Duration::ZERO;
+    while process_running(pid) && waited < Duration::from_secs(5) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        waited += Duration::from_millis(50);
+    }
+    assert!(!process_running(pid), "process {} should have been killed", pid);
+}
+
+/// Whether a process exists and hasn't exited; an unreaped zombie counts as exited
+fn process_running(pid: &str) -> bool {
+    std::process::Command::new("ps")
+        .args(["-o", "stat=", "-p", pid])
+        .output()
+        .map(|output| {
+            let stat = String::from_utf8_lossy(&output.stdout);
+            !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+        })
+        .unwrap_or(false)
+}
+
 #[tokio::test]
 async fn test_bash_working_directory() {
     let mut setup = setup_bash_test().await;
@@ -35,8 +35,10 @@ impl Actor for MockChatActor {
         msg: Self::Msg,
         _state: &mut Self::State,
     ) -> Result<(), ractor::ActorProcessingErr> {
-        // Forward message to test channel
-        let _ = self.sender.send(msg);
+        // Forward results to test channel; streamed output is covered in bash_new_test
+        if !matches!(msg, ChatMessage::ToolOutput { .. }) {
+            let _ = self.sender.send(msg);
+        }
         Ok(())
     }
 }
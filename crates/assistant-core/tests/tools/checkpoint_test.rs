@@ -0,0 +1,142 @@
+use assistant_core::{
+    actors::{delegator::DelegatorActor, tools::checkpoint::CheckpointActor},
+    config::{Config, PermissionAction, PermissionRule, PermissionsConfig},
+    messages::{ChatMessage, DelegatorMessage, ToolCall, ToolMessage},
+    permissions::PermissionPolicy,
+    persistence::{CheckpointManager, Database},
+    ractor::Actor,
+};
+use serde_json::json;
+use std::fs;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+use crate::common::{next, spawn_recorder};
+
+/// A config whose sessions database lives in `dir`, with a session already created
+async fn session_config(dir: &TempDir) -> (Config, String) {
+    let mut config = Config::default();
+    config.session.database_path = Some(dir.path().join("assistant.db"));
+    let db = Database::new(config.session.database_path.as_ref().unwrap()).await.unwrap();
+    let session_id = db.create_session(None).await.unwrap();
+    (config, session_id)
+}
+
+async fn run_checkpoint_tool(config: Config, params: serde_json::Value) -> String {
+    let (chat_ref, mut chat_rx) = spawn_recorder::<ChatMessage>().await;
+    let actor = CheckpointActor::new(config.clone()).await.unwrap();
+    let (tool_ref, _) = Actor::spawn(None, actor, config).await.unwrap();
+
+    tool_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params, chat_ref }).unwrap();
+    let result = loop {
+        if let ChatMessage::ToolResult { result, .. } = next(&mut chat_rx).await {
+            break result;
+        }
+    };
+    tool_ref.stop(None);
+    result
+}
+
+#[tokio::test]
+async fn test_restoring_a_turn_undoes_it_and_every_later_turn() {
+    let dir = TempDir::new().unwrap();
+    let (config, session) = session_config(&dir).await;
+    let manager = CheckpointManager::open(&config).await.unwrap();
+    let existing = dir.path().join("existing.txt");
+    let created = dir.path().join("created.txt");
+    fs::write(&existing, "original\n").unwrap();
+
+    manager.snapshot(&session, "turn-a", std::slice::from_ref(&existing)).await.unwrap();
+    fs::write(&existing, "first change\n").unwrap();
+    // A second snapshot in the same turn keeps the state from before the turn
+    manager.snapshot(&session, "turn-a", std::slice::from_ref(&existing)).await.unwrap();
+    fs::write(&existing, "second change\n").unwrap();
+
+    manager.snapshot(&session, "turn-b", &[existing.clone(), created.clone()]).await.unwrap();
+    fs::write(&existing, "third change\n").unwrap();
+    fs::write(&created, "new file\n").unwrap();
+
+    let turns = manager.list(&session).await.unwrap();
+    assert_eq!(turns.len(), 2);
+    assert_eq!((turns[0].number, turns[0].paths.len()), (1, 1));
+    assert_eq!((turns[1].number, turns[1].paths.len()), (2, 2));
+
+    let error = manager.restore(&session, 3).await.unwrap_err();
+    assert_eq!(error.to_string(), "There is no turn 3; this session has checkpoints for turns 1 to 2");
+
+    let (number, restored) = manager.undo_last(&session).await.unwrap();
+    assert_eq!(number, 2);
+    assert_eq!(restored.len(), 2);
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "second change\n");
+    assert!(!created.exists());
+
+    let restored = manager.restore(&session, 1).await.unwrap();
+    assert_eq!(restored.len(), 1);
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "original\n");
+    assert!(manager.list(&session).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_delegator_checkpoints_files_before_routing_writes() {
+    let dir = TempDir::new().unwrap();
+    let (mut config, session) = session_config(&dir).await;
+    config.permissions = PermissionsConfig {
+        rules: vec![PermissionRule { tool: "write".to_string(), pattern: None, action: PermissionAction::Allow }],
+        ..Default::default()
+    };
+    let file = dir.path().join("notes.txt");
+    fs::write(&file, "before\n").unwrap();
+
+    let (delegator_ref, _) = Actor::spawn(None, DelegatorActor::new(config.clone()), config.clone()).await.unwrap();
+    let (tool_ref, mut tool_rx) = spawn_recorder::<ToolMessage>().await;
+    let (chat_ref, _chat_rx) = spawn_recorder::<ChatMessage>().await;
+    delegator_ref
+        .send_message(DelegatorMessage::RegisterTool { name: "write".to_string(), actor_ref: tool_ref })
+        .unwrap();
+
+    delegator_ref
+        .send_message(DelegatorMessage::RouteToolCall {
+            id: Uuid::new_v4(),
+            call: ToolCall {
+                id: "call_1".to_string(),
+                tool_name: "write".to_string(),
+                parameters: json!({ "file_path": file.to_str().unwrap(), "content": "after\n" }),
+                delegate: false,
+            },
+            chat_ref,
+            session_id: session.clone(),
+            turn: Uuid::new_v4(),
+        })
+        .unwrap();
+    match next(&mut tool_rx).await {
+        // The tool is told the session, to track what it has read
+        ToolMessage::Execute { params, .. } => assert_eq!(params["session_id"], json!(session)),
+        other => panic!("Expected Execute, got {:?}", other),
+    }
+    fs::write(&file, "after\n").unwrap();
+
+    let listed = run_checkpoint_tool(config.clone(), json!({ "session_id": session })).await;
+    assert!(listed.starts_with("Turn 1 ("), "{}", listed);
+    assert!(listed.contains("notes.txt"), "{}", listed);
+
+    let restored = run_checkpoint_tool(config.clone(), json!({ "action": "restore", "turn": 1, "session_id": session })).await;
+    assert!(restored.starts_with("Restored 1 file to its state before turn 1"), "{}", restored);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "before\n");
+
+    let outside_session = run_checkpoint_tool(config, json!({})).await;
+    assert_eq!(outside_session, "Error: Checkpoints are only available within a session");
+}
+
+#[test]
+fn test_listing_checkpoints_needs_no_confirmation_but_restoring_does() {
+    let policy = PermissionPolicy::new(PermissionsConfig::default());
+    let call = |parameters| ToolCall {
+        id: "call_1".to_string(),
+        tool_name: "checkpoint".to_string(),
+        parameters,
+        delegate: false,
+    };
+
+    assert_eq!(policy.check(&call(json!({}))), PermissionAction::Allow);
+    assert_eq!(policy.check(&call(json!({ "action": "restore", "turn": 1 }))), PermissionAction::Ask);
+}
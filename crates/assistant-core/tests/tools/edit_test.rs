@@ -369,4 +369,55 @@ async fn test_invalid_parameters() {
         }
         _ => panic!("Expected ToolResult message"),
     }
-}
\ No newline at end of file
+}
+
+async fn run_edit(config: Config, chat_ref: ActorRef<ChatMessage>, rx: &mut mpsc::UnboundedReceiver<ChatMessage>, params: serde_json::Value) -> String {
+    let (edit_ref, _) = Actor::spawn(None, EditActor::new(config.clone()), config).await.unwrap();
+    edit_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params, chat_ref }).unwrap();
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { result, .. } => result,
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
+#[tokio::test]
+async fn test_multi_edit_applies_edits_in_order() {
+    let (temp_dir, config, chat_ref, mut rx) = setup_test().await;
+    let file_path = temp_dir.path().join("lib.rs");
+    fs::write(&file_path, "fn old() {}\nfn caller() { old(); }\n").unwrap();
+
+    let result = run_edit(config, chat_ref, &mut rx, json!({
+        "action": "multi_edit",
+        "file_path": file_path.to_str().unwrap(),
+        "edits": [
+            { "old_string": "old", "new_string": "renamed", "expected_replacements": 2 },
+            // Sees the result of the first edit
+            { "old_string": "fn renamed() {}", "new_string": "fn renamed() -> u8 { 0 }" }
+        ]
+    })).await;
+
+    assert!(result.contains("Successfully edited file"), "{}", result);
+    assert!(result.contains("Replacements made: 3"), "{}", result);
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "fn renamed() -> u8 { 0 }\nfn caller() { renamed(); }\n");
+}
+
+#[tokio::test]
+async fn test_multi_edit_writes_nothing_when_an_edit_fails() {
+    let (temp_dir, config, chat_ref, mut rx) = setup_test().await;
+    let file_path = temp_dir.path().join("lib.rs");
+    let original_content = "let a = 1;\nlet b = 2;\n";
+    fs::write(&file_path, original_content).unwrap();
+
+    let result = run_edit(config, chat_ref, &mut rx, json!({
+        "action": "multi_edit",
+        "file_path": file_path.to_str().unwrap(),
+        "edits": [
+            { "old_string": "let a = 1;", "new_string": "let a = 10;" },
+            { "old_string": "let c = 3;", "new_string": "let c = 30;" }
+        ]
+    })).await;
+
+    assert!(result.starts_with("Error: Edit 2 of 2: No matches found"), "{}", result);
+    assert!(result.contains("the file was not changed"), "{}", result);
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), original_content);
+}
@@ -493,7 +493,6 @@ async fn test_file_not_found_errors() {
 }
 
 #[tokio::test]
-#[ignore = "Workspace validation not yet implemented - validate_path_access is a placeholder"]
 async fn test_workspace_security() {
     let mut setup = setup_file_system_test().await;
     
@@ -522,4 +521,146 @@ async fn test_workspace_security() {
         }
         _ => panic!("Expected ToolResult"),
     }
-}
\ No newline at end of file
+}
+
+/// Run one tool call and return its result
+async fn call_tool(setup: &mut TestSetup, tool_ref: &ActorRef<ToolMessage>, params: serde_json::Value) -> String {
+    let id = Uuid::new_v4();
+    tool_ref
+        .send_message(ToolMessage::Execute { id, params, chat_ref: setup.chat_ref.clone() })
+        .expect("Failed to send tool call");
+    match setup.rx.recv().await.expect("Failed to receive response") {
+        ChatMessage::ToolResult { id: result_id, result } => {
+            assert_eq!(result_id, id);
+            result
+        }
+        other => panic!("Expected ToolResult, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_workspace_traversal_and_symlink_escapes() {
+    let mut setup = setup_file_system_test().await;
+    let workspace = setup.temp_dir.path().to_path_buf();
+    let outside = TempDir::new().unwrap();
+    std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+    std::fs::create_dir(workspace.join("sub")).unwrap();
+    
+    // `..` out of the workspace
+    let traversal = workspace.join("sub/../..").join(outside.path().file_name().unwrap()).join("secret.txt");
+    let read_ref = setup.read_ref.clone();
+    let result = call_tool(&mut setup, &read_ref, json!({ "path": traversal.to_string_lossy() })).await;
+    assert!(result.contains("Access denied"), "{}", result);
+    
+    // `..` that stays inside is fine
+    let ls_ref = setup.ls_ref.clone();
+    let result = call_tool(&mut setup, &ls_ref, json!({ "path": workspace.join("sub/..").to_string_lossy() })).await;
+    assert!(!result.contains("Access denied"), "{}", result);
+    
+    // A symlink to a directory outside
+    std::os::unix::fs::symlink(outside.path(), workspace.join("link")).unwrap();
+    let result = call_tool(&mut setup, &read_ref, json!({ "path": workspace.join("link/secret.txt").to_string_lossy() })).await;
+    assert!(result.contains("Access denied"), "{}", result);
+    let grep_ref = setup.grep_ref.clone();
+    let result = call_tool(&mut setup, &grep_ref, json!({ "pattern": "secret", "path": workspace.join("link").to_string_lossy() })).await;
+    assert!(result.contains("Access denied"), "{}", result);
+    
+    // A dangling symlink to a file that writing would create outside
+    let target = outside.path().join("created.txt");
+    std::os::unix::fs::symlink(&target, workspace.join("dangling")).unwrap();
+    let write_ref = setup.write_ref.clone();
+    let result = call_tool(&mut setup, &write_ref, json!({
+        "file_path": workspace.join("dangling").to_string_lossy(),
+        "content": "escaped"
+    })).await;
+    assert!(result.contains("Access denied"), "{}", result);
+    assert!(!target.exists());
+}
+
+#[tokio::test]
+async fn test_workspace_extra_roots_and_tool_exceptions() {
+    let mut setup = setup_file_system_test().await;
+    let shared = TempDir::new().unwrap();
+    let registry = TempDir::new().unwrap();
+    std::fs::write(registry.path().join("lib.rs"), "pub fn registry() {}").unwrap();
+    
+    let mut config = setup.config.clone();
+    config.session.extra_roots = vec![shared.path().to_path_buf()];
+    let read_config = assistant_core::config::tool_config::ToolConfig {
+        allowed_paths: vec![registry.path().to_path_buf()],
+        ..Default::default()
+    };
+    config.tools.configs.insert("read".to_string(), read_config);
+    
+    let (read_ref, _) = Actor::spawn(None, ReadActor::new(config.clone()), config.clone()).await.unwrap();
+    let (write_ref, _) = Actor::spawn(None, WriteActor::new(config.clone()), config.clone()).await.unwrap();
+    
+    // Only `read` has the exception
+    let registry_file = registry.path().join("lib.rs");
+    let result = call_tool(&mut setup, &read_ref, json!({ "path": registry_file.to_string_lossy() })).await;
+    assert!(result.contains("pub fn registry"), "{}", result);
+    let result = call_tool(&mut setup, &write_ref, json!({
+        "file_path": registry_file.to_string_lossy(),
+        "content": "overwritten"
+    })).await;
+    assert!(result.contains("Access denied"), "{}", result);
+    
+    // Extra roots are open to every tool
+    let shared_file = shared.path().join("notes.txt");
+    let result = call_tool(&mut setup, &write_ref, json!({
+        "file_path": shared_file.to_string_lossy(),
+        "content": "shared"
+    })).await;
+    assert!(!result.contains("Error"), "{}", result);
+    assert_eq!(std::fs::read_to_string(shared_file).unwrap(), "shared");
+}
+
+#[tokio::test]
+async fn test_glob_patterns_stay_in_the_workspace() {
+    let mut setup = setup_file_system_test().await;
+    let workspace = setup.temp_dir.path().to_path_buf();
+    let glob_ref = setup.glob_ref.clone();
+    
+    for pattern in ["/etc/*", "../*", "src/../../*"] {
+        let result = call_tool(&mut setup, &glob_ref, json!({ "pattern": pattern })).await;
+        assert!(result.contains("Access denied"), "{}: {}", pattern, result);
+    }
+    
+    // Matches reached through a symlink to a directory outside are left out
+    let outside = TempDir::new().unwrap();
+    std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+    std::fs::write(workspace.join("notes.txt"), "notes").unwrap();
+    std::os::unix::fs::symlink(outside.path(), workspace.join("link")).unwrap();
+    let result = call_tool(&mut setup, &glob_ref, json!({ "pattern": "**/*.txt", "path": workspace.to_string_lossy() })).await;
+    assert!(result.contains("notes.txt"), "{}", result);
+    assert!(!result.contains("secret.txt"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_grep_globs_stay_in_the_workspace() {
+    let mut setup = setup_file_system_test().await;
+    let workspace = setup.temp_dir.path().to_path_buf();
+    let grep_ref = setup.grep_ref.clone();
+    
+    for glob in ["/etc/*", "../*"] {
+        let result = call_tool(&mut setup, &grep_ref, json!({
+            "pattern": "root",
+            "path": workspace.to_string_lossy(),
+            "glob": glob
+        })).await;
+        assert!(result.contains("Access denied"), "{}: {}", glob, result);
+    }
+    
+    // Files reached through a symlink to a directory outside aren't searched
+    let outside = TempDir::new().unwrap();
+    std::fs::write(outside.path().join("secret.txt"), "token=secret").unwrap();
+    std::fs::write(workspace.join("notes.txt"), "token=notes").unwrap();
+    std::os::unix::fs::symlink(outside.path(), workspace.join("link")).unwrap();
+    let result = call_tool(&mut setup, &grep_ref, json!({
+        "pattern": "token",
+        "path": workspace.to_string_lossy(),
+        "glob": "**/*.txt"
+    })).await;
+    assert!(result.contains("notes.txt"), "{}", result);
+    assert!(!result.contains("secret"), "{}", result);
+}
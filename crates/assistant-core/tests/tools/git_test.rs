@@ -0,0 +1,182 @@
+use assistant_core::{
+    actors::tools::git::GitActor,
+    config::{Config, PermissionAction, PermissionsConfig},
+    messages::{ChatMessage, ToolCall, ToolMessage},
+    permissions::PermissionPolicy,
+    ractor::{Actor, ActorProcessingErr, ActorRef},
+};
+use git2::{Repository, Signature};
+use serde_json::{Value, json};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Forwards tool results to the test
+struct MockChatActor {
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl Actor for MockChatActor {
+    type Msg = ChatMessage;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(&self, _myself: ActorRef<Self::Msg>, _args: ()) -> Result<Self::State, ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        if let ChatMessage::ToolResult { result, .. } = msg {
+            let _ = self.tx.send(result);
+        }
+        Ok(())
+    }
+}
+
+/// Run the git tool in `repo`, returning its result as JSON, or as a string when it failed
+async fn git(repo: &Path, mut params: Value) -> Value {
+    params["repository"] = json!(repo.to_str().unwrap());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ()).await.unwrap();
+    let (tool_ref, _) = Actor::spawn(None, GitActor::new(Config::default()), Config::default()).await.unwrap();
+
+    tool_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params, chat_ref }).unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+    tool_ref.stop(None);
+    serde_json::from_str(&result).unwrap_or(Value::String(result))
+}
+
+/// A repository with an author configured and two commits
+fn repository() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Ada").unwrap();
+    config.set_str("user.email", "ada@example.com").unwrap();
+
+    commit_file(&repo, "src/main.rs", "fn main() {\n    println!(\"hello\");\n}\n", "Add main");
+    commit_file(&repo, "README.md", "# Demo\n", "Add readme");
+    dir
+}
+
+fn commit_file(repo: &Repository, path: &str, content: &str, message: &str) {
+    let file = repo.workdir().unwrap().join(path);
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(&file, content).unwrap();
+
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(path)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("Ada", "ada@example.com").unwrap();
+    let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents).unwrap();
+}
+
+#[tokio::test]
+async fn test_status_diff_and_commit() {
+    let dir = repository();
+    let path = dir.path();
+    fs::write(path.join("src/main.rs"), "fn main() {\n    println!(\"hello, world\");\n}\n").unwrap();
+    fs::write(path.join("notes.txt"), "todo\n").unwrap();
+
+    let status = git(path, json!({ "action": "status" })).await;
+    assert_eq!(status["unstaged"], json!([{ "path": "src/main.rs", "status": "modified" }]));
+    assert_eq!(status["untracked"], json!(["notes.txt"]));
+    assert_eq!(status["staged"], json!([]));
+    assert!(status["branch"].is_string(), "{}", status);
+
+    let diff = git(path, json!({ "action": "diff" })).await;
+    assert_eq!(diff["total"], json!({ "files": 1, "additions": 1, "deletions": 1 }));
+    let patch = diff["files"][0]["patch"].as_str().unwrap();
+    assert!(patch.contains("-    println!(\"hello\");\n+    println!(\"hello, world\");\n"), "{}", patch);
+    let stat = git(path, json!({ "action": "diff", "stat_only": true })).await;
+    assert!(stat["files"][0].get("patch").is_none(), "{}", stat);
+
+    let committed = git(path, json!({ "action": "commit", "message": "Greet the world", "all": true })).await;
+    assert_eq!(committed["summary"], "Greet the world");
+    assert_eq!(committed["files_changed"], 1);
+    // Untracked files are only committed when named
+    let status = git(path, json!({ "action": "status" })).await;
+    assert_eq!(status["untracked"], json!(["notes.txt"]));
+    assert_eq!(status["unstaged"], json!([]));
+
+    let nothing = git(path, json!({ "action": "commit", "message": "Again" })).await;
+    assert_eq!(nothing, json!("Error: Nothing to commit; stage changes with `paths` or `all` first"));
+
+    let committed = git(path, json!({ "action": "commit", "message": "Add notes", "paths": ["notes.txt"] })).await;
+    assert_eq!(committed["files_changed"], 1);
+    let between = git(path, json!({ "action": "diff", "from": "HEAD~2", "to": "HEAD", "stat_only": true })).await;
+    assert_eq!(between["total"]["files"], 2, "{}", between);
+}
+
+#[tokio::test]
+async fn test_log_blame_show_and_branches() {
+    let dir = repository();
+    let path = dir.path();
+
+    let log = git(path, json!({ "action": "log" })).await;
+    let summaries: Vec<&str> = log["commits"].as_array().unwrap().iter().map(|c| c["summary"].as_str().unwrap()).collect();
+    assert_eq!(summaries, ["Add readme", "Add main"]);
+    assert_eq!(log["commits"][0]["author"], "Ada");
+
+    let filtered = git(path, json!({ "action": "log", "paths": ["src"] })).await;
+    assert_eq!(filtered["commits"].as_array().unwrap().len(), 1);
+    assert_eq!(filtered["commits"][0]["summary"], "Add main");
+
+    // Uncommitted lines are blamed on no commit
+    fs::write(path.join("src/main.rs"), "// entry point\nfn main() {\n    println!(\"hello\");\n}\n").unwrap();
+    let blame = git(path, json!({ "action": "blame", "path": "src/main.rs", "start_line": 1, "end_line": 3 })).await;
+    let hunks = blame["hunks"].as_array().unwrap();
+    assert_eq!(hunks.len(), 2, "{}", blame);
+    assert_eq!(hunks[0]["commit"], Value::Null);
+    assert_eq!(hunks[0]["lines"], json!([1, 1]));
+    assert_eq!(hunks[1]["summary"], "Add main");
+    assert_eq!(hunks[1]["lines"], json!([2, 3]));
+    assert_eq!(hunks[1]["text"], json!(["fn main() {", "    println!(\"hello\");"]));
+
+    let show = git(path, json!({ "action": "show", "rev": "HEAD~1" })).await;
+    assert_eq!(show["message"], "Add main");
+    assert_eq!(show["files"][0]["path"], "src/main.rs");
+    assert_eq!(show["files"][0]["status"], "added");
+    assert_eq!(show["parents"], json!([]));
+
+    let created = git(path, json!({ "action": "branch", "name": "feature", "start_point": "HEAD~1" })).await;
+    assert_eq!(created["created"], "feature");
+    let branches = git(path, json!({ "action": "branch" })).await;
+    let names: Vec<&str> = branches["branches"].as_array().unwrap().iter().map(|b| b["name"].as_str().unwrap()).collect();
+    assert!(names.contains(&"feature"), "{}", branches);
+    assert_eq!(names.len(), 2);
+
+    let missing = git(path, json!({ "action": "show", "rev": "no-such-ref" })).await;
+    assert_eq!(missing, json!("Error: 'no-such-ref' does not name a commit"));
+    let outside = TempDir::new().unwrap();
+    let not_a_repo = git(outside.path(), json!({ "action": "status" })).await;
+    assert!(not_a_repo.as_str().unwrap().ends_with("is not in a git repository"), "{}", not_a_repo);
+}
+
+#[test]
+fn test_reading_the_repository_needs_no_confirmation_but_changing_it_does() {
+    let policy = PermissionPolicy::new(PermissionsConfig::default());
+    let call = |parameters| ToolCall {
+        id: "call_1".to_string(),
+        tool_name: "git".to_string(),
+        parameters,
+        delegate: false,
+    };
+
+    assert_eq!(policy.check(&call(json!({ "action": "status" }))), PermissionAction::Allow);
+    assert_eq!(policy.check(&call(json!({ "action": "log", "paths": ["src"] }))), PermissionAction::Allow);
+    assert_eq!(policy.check(&call(json!({ "action": "branch" }))), PermissionAction::Allow);
+    assert_eq!(policy.check(&call(json!({ "action": "branch", "name": "feature" }))), PermissionAction::Ask);
+    assert_eq!(policy.check(&call(json!({ "action": "commit", "message": "Fix" }))), PermissionAction::Ask);
+}
@@ -1,16 +1,23 @@
 mod ls_test;
 mod write_test;
 mod edit_test;
+mod apply_patch_test;
+mod checkpoint_test;
 mod glob_test;
 mod grep_test;
 mod bash_test;
 mod bash_new_test;
 mod web_fetch_test;
 mod web_search_test;
+mod url_policy_test;
 // mod memory_test; // Old interface - disabled
 mod memory_new_test;
 mod todo_write_test;
 mod todo_test;
 mod file_system_test;
 mod read_many_files_test;
-mod read_test;
\ No newline at end of file
+mod read_test;
+mod tool_registry_test;
+mod sandbox_test;
+mod stale_read_test;
+mod git_test;
\ No newline at end of file
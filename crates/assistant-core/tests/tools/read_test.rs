@@ -349,4 +349,152 @@ async fn test_read_with_offset_beyond_file() {
         }
         _ => panic!("Expected ToolResult message"),
     }
-}
\ No newline at end of file
+}
+/// Read `params` with a fresh actor and return the result
+async fn run_read(params: serde_json::Value) -> String {
+    let (config, chat_ref, mut rx) = setup_test().await;
+    let (read_ref, _) = Actor::spawn(None, ReadActor::new(config.clone()), config).await.unwrap();
+    read_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params, chat_ref }).unwrap();
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { result, .. } => result,
+        other => panic!("Expected ToolResult message, got {:?}", other),
+    }
+}
+
+/// A PDF with one page per entry of `pages`, each showing that text
+fn simple_pdf(pages: &[&str]) -> Vec<u8> {
+    let font = 3 + 2 * pages.len();
+    let kids = (0..pages.len()).map(|i| format!("{} 0 R", 3 + 2 * i)).collect::<Vec<_>>().join(" ");
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()),
+    ];
+    for (i, text) in pages.iter().enumerate() {
+        let stream = format!("BT /F1 24 Tf 72 700 Td ({}) Tj ET", text);
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Resources << /Font << /F1 {} 0 R >> >> /Contents {} 0 R >>",
+            font,
+            4 + 2 * i
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", stream.len(), stream));
+    }
+    objects.push("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string());
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).into_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).into_bytes());
+    pdf
+}
+
+#[tokio::test]
+async fn test_read_image_returns_it_for_the_model_to_see() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("pixel.png");
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0\x1f\x15\xc4\x89";
+    std::fs::write(&file_path, png).unwrap();
+
+    let result = run_read(json!({ "path": file_path.to_str().unwrap() })).await;
+    let json: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(json["format"], "png");
+    assert_eq!(json["size_bytes"], png.len());
+    assert!(json["image"].as_str().unwrap().starts_with("data:image/png;base64,iVBORw0KGgo"));
+
+    let (without_image, image) = assistant_core::actors::tools::take_image(&result).unwrap();
+    assert_eq!(image, json["image"].as_str().unwrap());
+    assert!(!without_image.contains("base64,"));
+}
+
+#[tokio::test]
+async fn test_read_binary_file_is_refused() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("program");
+    std::fs::write(&file_path, b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0").unwrap();
+
+    let result = run_read(json!({ "path": file_path.to_str().unwrap() })).await;
+    assert!(result.starts_with(&format!("Error: '{}' is a binary file (16 bytes)", file_path.display())), "{}", result);
+}
+
+#[tokio::test]
+async fn test_read_notebook_renders_cells_and_outputs() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("analysis.ipynb");
+    let notebook = json!({
+        "nbformat": 4,
+        "nbformat_minor": 5,
+        "metadata": { "language_info": { "name": "python" } },
+        "cells": [
+            { "cell_type": "markdown", "metadata": {}, "source": ["# Analysis\n", "Totals by month"] },
+            {
+                "cell_type": "code",
+                "execution_count": 2,
+                "metadata": {},
+                "source": "print(sum([1, 2]))\ntotals",
+                "outputs": [
+                    { "output_type": "stream", "name": "stdout", "text": ["3\n"] },
+                    { "output_type": "execute_result", "execution_count": 2, "metadata": {}, "data": { "text/plain": ["month  total\n", "jan    3"] } },
+                    { "output_type": "display_data", "metadata": {}, "data": { "image/png": "iVBORw0KGgo=" } }
+                ]
+            },
+            {
+                "cell_type": "code",
+                "execution_count": 3,
+                "metadata": {},
+                "source": "1 / 0",
+                "outputs": [{
+                    "output_type": "error",
+                    "ename": "ZeroDivisionError",
+                    "evalue": "division by zero",
+                    "traceback": ["\u{1b}[0;31mZeroDivisionError\u{1b}[0m: division by zero"]
+                }]
+            }
+        ]
+    });
+    std::fs::write(&file_path, notebook.to_string()).unwrap();
+
+    let result = run_read(json!({ "path": file_path.to_str().unwrap() })).await;
+    assert_eq!(result, "--- Cell 1 (markdown) ---\n# Analysis\nTotals by month\n\n\
+        --- Cell 2 (code, In [2]) ---\n```python\nprint(sum([1, 2]))\ntotals\n```\n\
+        Output:\n3\nOutput:\nmonth  total\njan    3\nOutput:\n[image/png output]\n\n\
+        --- Cell 3 (code, In [3]) ---\n```python\n1 / 0\n```\nOutput:\nZeroDivisionError: division by zero");
+}
+
+#[tokio::test]
+async fn test_read_pdf_extracts_the_requested_pages() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("report.pdf");
+    std::fs::write(&file_path, simple_pdf(&["First page", "Second page", "Third page"])).unwrap();
+    let path = file_path.to_str().unwrap();
+
+    let result = run_read(json!({ "path": path })).await;
+    assert!(result.starts_with("--- Page 1 of 3 ---\nFirst page\n\n--- Page 2 of 3 ---\nSecond page"), "{}", result);
+
+    let result = run_read(json!({ "path": path, "pages": "3,1" })).await;
+    assert_eq!(result, "--- Page 3 of 3 ---\nThird page\n\n--- Page 1 of 3 ---\nFirst page");
+
+    let result = run_read(json!({ "path": path, "pages": "2-5" })).await;
+    assert_eq!(result, "Error: Invalid parameters - pages '2-5' are out of range; the PDF has 3 pages");
+}
+
+#[tokio::test]
+async fn test_read_pdf_reports_files_the_extractor_crashes_on() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("broken.pdf");
+    // The page's font is a number rather than a font, which the extractor panics on;
+    // the padding keeps the cross-reference offsets right
+    let font = "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>";
+    let pdf = String::from_utf8(simple_pdf(&["Broken"])).unwrap()
+        .replace(font, &format!("{:<width$}", "7", width = font.len()));
+    std::fs::write(&file_path, pdf).unwrap();
+
+    let result = run_read(json!({ "path": file_path.to_str().unwrap() })).await;
+    assert!(result.starts_with("Error: Cannot extract text from PDF"), "{}", result);
+}
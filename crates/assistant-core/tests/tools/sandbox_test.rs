@@ -0,0 +1,185 @@
+use assistant_core::{
+    actors::tools::{apply_patch::ApplyPatchActor, bash::BashActor, write::WriteActor},
+    config::Config,
+    messages::{ChatMessage, ToolMessage},
+    ractor::{Actor, ActorProcessingErr, ActorRef},
+    sandbox::landlock_abi,
+};
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Forwards tool results to the test, dropping streamed output
+struct MockChatActor {
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl Actor for MockChatActor {
+    type Msg = ChatMessage;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(&self, _myself: ActorRef<Self::Msg>, _args: ()) -> Result<Self::State, ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        if let ChatMessage::ToolResult { result, .. } = msg {
+            let _ = self.tx.send(result);
+        }
+        Ok(())
+    }
+}
+
+/// A sandboxed config whose workspace is `workspace`
+fn sandbox_config(workspace: &Path) -> Config {
+    let mut config = Config::default();
+    config.sandbox.enabled = true;
+    config.session.workspace_path = Some(workspace.to_path_buf());
+    config
+}
+
+/// A directory outside the workspace and the temp directories
+fn outside_dir() -> TempDir {
+    tempfile::tempdir_in(env!("CARGO_TARGET_TMPDIR")).unwrap()
+}
+
+async fn run_tool<A>(actor: A, config: Config, params: serde_json::Value) -> String
+where
+    A: Actor<Msg = ToolMessage, Arguments = Config>,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ()).await.unwrap();
+    let (tool_ref, _) = Actor::spawn(None, actor, config).await.unwrap();
+
+    tool_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params, chat_ref }).unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+    tool_ref.stop(None);
+    result
+}
+
+async fn run_bash(config: Config, command: String) -> String {
+    run_tool(BashActor::new(config.clone()), config, json!({ "command": command })).await
+}
+
+#[tokio::test]
+async fn test_sandboxed_bash_only_writes_inside_writable_roots() {
+    if landlock_abi().is_none() {
+        eprintln!("Landlock is unavailable, skipping");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    let outside = outside_dir();
+    std::fs::write(outside.path().join("readable.txt"), "still readable\n").unwrap();
+    let config = sandbox_config(workspace.path());
+
+    let inside = run_bash(config.clone(), format!(
+        "echo ok > '{0}/file.txt' && cat '{0}/file.txt' && cat '{1}/readable.txt' && echo quiet > /dev/null",
+        workspace.path().display(),
+        outside.path().display(),
+    )).await;
+    assert_eq!(inside, "ok\nstill readable\n");
+
+    let blocked = run_bash(config, format!("echo no > '{}/file.txt'", outside.path().display())).await;
+    assert!(blocked.contains("Permission denied"), "{}", blocked);
+    assert!(blocked.contains("The sandbox may have blocked this"), "{}", blocked);
+    assert!(!outside.path().join("file.txt").exists());
+}
+
+#[tokio::test]
+async fn test_sandboxed_bash_applies_resource_limits() {
+    if landlock_abi().is_none() {
+        eprintln!("Landlock is unavailable, skipping");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    let mut config = sandbox_config(workspace.path());
+    config.sandbox.max_cpu_seconds = Some(30);
+    config.sandbox.max_memory_mb = Some(512);
+    config.sandbox.max_processes = Some(4096);
+
+    let limits = run_bash(config, "ulimit -t; ulimit -v; ulimit -u".to_string()).await;
+    assert_eq!(limits, "30\n524288\n4096\n");
+}
+
+#[tokio::test]
+async fn test_sandboxed_bash_can_be_denied_the_network() {
+    if landlock_abi().is_none_or(|abi| abi < 4) {
+        eprintln!("Landlock network rules are unavailable, skipping");
+        return;
+    }
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connect = format!("exec 3<>/dev/tcp/127.0.0.1/{} && echo connected", port);
+
+    let workspace = TempDir::new().unwrap();
+    let mut config = sandbox_config(workspace.path());
+    let allowed = run_bash(config.clone(), connect.clone()).await;
+    assert_eq!(allowed, "connected\n");
+
+    config.sandbox.allow_network = false;
+    let denied = run_bash(config, connect).await;
+    assert!(denied.contains("Permission denied"), "{}", denied);
+    assert!(denied.contains("TCP connections are refused"), "{}", denied);
+}
+
+#[tokio::test]
+async fn test_write_tool_refuses_paths_outside_the_sandbox() {
+    let workspace = TempDir::new().unwrap();
+    let outside = outside_dir();
+    let mut config = sandbox_config(workspace.path());
+    // The file tools may use the directory, but the sandbox doesn't let them write there
+    config.session.extra_roots = vec![outside.path().to_path_buf()];
+
+    let outside_file = outside.path().join("file.txt");
+    let refused = run_tool(WriteActor::new(config.clone()), config.clone(), json!({
+        "file_path": outside_file.to_str().unwrap(),
+        "content": "no"
+    })).await;
+    assert!(refused.starts_with("Error: Sandbox: writing to"), "{}", refused);
+    assert!(!outside_file.exists());
+
+    let inside_file = workspace.path().join("file.txt");
+    let written = run_tool(WriteActor::new(config.clone()), config, json!({
+        "file_path": inside_file.to_str().unwrap(),
+        "content": "yes"
+    })).await;
+    assert!(!written.starts_with("Error"), "{}", written);
+    assert_eq!(std::fs::read_to_string(inside_file).unwrap(), "yes");
+}
+
+#[tokio::test]
+async fn test_apply_patch_refuses_to_leave_the_sandbox_through_dotdot_or_symlinks() {
+    let workspace = TempDir::new().unwrap();
+    let outside = outside_dir();
+    let mut config = sandbox_config(workspace.path());
+    config.session.extra_roots = vec![outside.path().to_path_buf()];
+    let create = |path: &Path| format!("--- /dev/null\n+++ {}\n@@ -0,0 +1 @@\n+escaped\n", path.display());
+
+    // Back up out of the workspace to the root, then down into the other directory
+    let depth = workspace.path().components().count() - 1;
+    let mut traversal = workspace.path().join("sub");
+    for _ in 0..=depth {
+        traversal.push("..");
+    }
+    traversal.push(outside.path().strip_prefix("/").unwrap());
+    traversal.push("dotdot.txt");
+    let refused = run_tool(ApplyPatchActor::new(config.clone()), config.clone(), json!({ "patch": create(&traversal) })).await;
+    assert!(refused.contains("Sandbox: writing to"), "{}", refused);
+    assert!(!outside.path().join("dotdot.txt").exists());
+
+    std::os::unix::fs::symlink(outside.path(), workspace.path().join("link")).unwrap();
+    let through_link = workspace.path().join("link/linked.txt");
+    let refused = run_tool(ApplyPatchActor::new(config.clone()), config, json!({ "patch": create(&through_link) })).await;
+    assert!(refused.contains("Sandbox: writing to"), "{}", refused);
+    assert!(!outside.path().join("linked.txt").exists());
+}
+
@@ -0,0 +1,158 @@
+use assistant_core::{
+    actors::tools::{edit::EditActor, read::ReadActor, write::WriteActor},
+    config::Config,
+    file_tracker::FileTracker,
+    messages::{ChatMessage, ToolMessage},
+    ractor::{Actor, ActorProcessingErr, ActorRef},
+};
+use serde_json::json;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Forwards tool results to the test
+struct MockChatActor {
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl Actor for MockChatActor {
+    type Msg = ChatMessage;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(&self, _myself: ActorRef<Self::Msg>, _args: ()) -> Result<Self::State, ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        _state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        if let ChatMessage::ToolResult { result, .. } = msg {
+            let _ = self.tx.send(result);
+        }
+        Ok(())
+    }
+}
+
+async fn run_tool<A>(actor: A, params: serde_json::Value) -> String
+where
+    A: Actor<Msg = ToolMessage, Arguments = Config>,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (chat_ref, _) = Actor::spawn(None, MockChatActor { tx }, ()).await.unwrap();
+    let (tool_ref, _) = Actor::spawn(None, actor, Config::default()).await.unwrap();
+
+    tool_ref.send_message(ToolMessage::Execute { id: Uuid::new_v4(), params, chat_ref }).unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+    tool_ref.stop(None);
+    result
+}
+
+async fn read(session: &str, path: &str) -> String {
+    run_tool(ReadActor::new(Config::default()), json!({ "path": path, "session_id": session })).await
+}
+
+async fn edit(session: &str, path: &str, old_string: &str, new_string: &str) -> String {
+    run_tool(EditActor::new(Config::default()), json!({
+        "file_path": path,
+        "old_string": old_string,
+        "new_string": new_string,
+        "session_id": session
+    })).await
+}
+
+#[tokio::test]
+async fn test_edit_refuses_files_changed_since_they_were_read() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("config.toml");
+    let path = file.to_str().unwrap();
+    let session = Uuid::new_v4().to_string();
+    fs::write(&file, "name = \"app\"\nversion = 1\ndebug = false\n").unwrap();
+
+    read(&session, path).await;
+    // Changed in an editor after the model read it
+    fs::write(&file, "name = \"app\"\nversion = 2\ndebug = false\n").unwrap();
+
+    let refused = edit(&session, path, "debug = false", "debug = true").await;
+    assert!(refused.starts_with(&format!("Error: '{}' has changed on disk since you last read it", path)), "{}", refused);
+    assert!(refused.contains("so it was not edited"), "{}", refused);
+    assert!(refused.contains("```diff\n--- last read\n+++ on disk now\n"), "{}", refused);
+    assert!(refused.contains("\n-version = 1\n+version = 2\n"), "{}", refused);
+    assert!(refused.ends_with("Read the file again and redo the change against its current contents."), "{}", refused);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "name = \"app\"\nversion = 2\ndebug = false\n");
+
+    // Once read again, the file can be edited, and the tool's own edits don't make it stale
+    read(&session, path).await;
+    let edited = edit(&session, path, "debug = false", "debug = true").await;
+    assert!(edited.contains("Successfully edited file"), "{}", edited);
+    let edited = edit(&session, path, "version = 2", "version = 3").await;
+    assert!(edited.contains("Successfully edited file"), "{}", edited);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "name = \"app\"\nversion = 3\ndebug = true\n");
+}
+
+#[tokio::test]
+async fn test_write_refuses_files_changed_since_they_were_read_in_that_session() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("notes.md");
+    let path = file.to_str().unwrap();
+    let session = Uuid::new_v4().to_string();
+    fs::write(&file, "draft\n").unwrap();
+
+    read(&session, path).await;
+    fs::write(&file, "draft\nedited elsewhere\n").unwrap();
+
+    let write = |session: String| run_tool(WriteActor::new(Config::default()), json!({
+        "file_path": path,
+        "content": "rewritten\n",
+        "session_id": session
+    }));
+    let refused = write(session.clone()).await;
+    assert!(refused.contains("so it was not overwritten"), "{}", refused);
+    assert!(refused.contains("+edited elsewhere\n"), "{}", refused);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "draft\nedited elsewhere\n");
+
+    // Another session never read the file, so has nothing to be out of date with
+    let written = write(Uuid::new_v4().to_string()).await;
+    assert!(!written.starts_with("Error"), "{}", written);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "rewritten\n");
+}
+
+#[test]
+fn test_rewriting_the_same_contents_is_not_a_change() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("data.txt");
+    let tracker = FileTracker::new();
+    fs::write(&file, "same\n").unwrap();
+    tracker.record("session", &file, b"same\n");
+
+    fs::write(&file, "same\n").unwrap();
+    assert!(tracker.check("session", &file).is_ok());
+
+    fs::write(&file, "different\n").unwrap();
+    let stale = tracker.check("session", &file).unwrap_err();
+    assert_eq!(stale.diff.as_deref(), Some("--- last read\n+++ on disk now\n@@ -1 +1 @@\n-same\n+different\n"));
+
+    // Deleted files have nothing left to overwrite
+    fs::remove_file(&file).unwrap();
+    assert!(tracker.check("session", &file).is_ok());
+}
+
+#[test]
+fn test_ending_a_session_forgets_only_its_files() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("data.txt");
+    let tracker = FileTracker::new();
+    fs::write(&file, "before\n").unwrap();
+    tracker.record("ended", &file, b"before\n");
+    tracker.record("open", &file, b"before\n");
+
+    tracker.end_session("ended");
+    fs::write(&file, "after\n").unwrap();
+    assert!(tracker.check("ended", &file).is_ok());
+    assert!(tracker.check("open", &file).is_err());
+}
@@ -0,0 +1,51 @@
+use assistant_core::actors::tools::{ToolRegistry, ToolDescription, ReadActor};
+use assistant_core::config::Config;
+use std::collections::HashSet;
+
+#[test]
+fn test_every_tool_has_a_valid_schema() {
+    let specs = ToolRegistry::specs();
+    let names: HashSet<_> = specs.iter().map(|spec| spec.name).collect();
+    assert_eq!(names.len(), specs.len(), "Tool names must be unique");
+
+    for spec in &specs {
+        assert!(!spec.description.is_empty(), "{} has no description", spec.name);
+        assert!(!(spec.read_only && spec.destructive), "{} can't be both read-only and destructive", spec.name);
+        assert_eq!(spec.parameters["type"], "object", "{} parameters must be an object", spec.name);
+
+        // Every required parameter is described
+        let properties = spec.parameters["properties"].as_object().expect("properties");
+        for required in spec.parameters["required"].as_array().expect("required") {
+            let required = required.as_str().unwrap();
+            assert!(properties.contains_key(required), "{} requires undocumented '{}'", spec.name, required);
+        }
+    }
+}
+
+#[test]
+fn test_registered_tools_are_all_offered() {
+    let names = ToolRegistry::available_tools();
+    for name in ["read_many_files", "memory", "tui_control", "tui_agent"] {
+        assert!(names.contains(&name), "{} is missing", name);
+    }
+
+    let registry = ToolRegistry::new(Config::default());
+    let offered: Vec<_> = registry.tool_definitions().into_iter().map(|tool| tool.function.name).collect();
+    assert_eq!(offered, names);
+}
+
+#[test]
+fn test_tool_definitions_respect_configuration() {
+    let mut config = Config::default();
+    config.tools.exclude.push("bash".to_string());
+    let registry = ToolRegistry::new(config);
+
+    let definitions = registry.tool_definitions();
+    assert!(definitions.iter().all(|tool| tool.function.name != "bash"));
+    assert!(!registry.enabled_tools().contains(&"bash"));
+
+    let read = definitions.iter().find(|tool| tool.function.name == ReadActor::NAME).unwrap();
+    assert_eq!(read.tool_type, "function");
+    assert_eq!(read.function.description, ReadActor::DESCRIPTION);
+    assert!(ReadActor::spec().read_only);
+}
@@ -0,0 +1,93 @@
+use assistant_core::config::UrlPolicyConfig;
+use assistant_core::url_policy::UrlPolicy;
+use reqwest::Url;
+
+fn check(policy: &UrlPolicy, url: &str) -> Result<(), String> {
+    policy.check_url(&Url::parse(url).unwrap()).map_err(|violation| violation.to_string())
+}
+
+#[test]
+fn test_url_policy_blocks_internal_addresses() {
+    let policy = UrlPolicy::new(&UrlPolicyConfig::default());
+    
+    for url in [
+        "http://127.0.0.1:8080/admin",
+        "http://0.0.0.0/",
+        "http://[::1]/",
+        "http://[::ffff:127.0.0.1]/",
+        "http://[::127.0.0.1]/",
+        "http://[2002:7f00:1::]/",
+    ] {
+        let error = check(&policy, url).unwrap_err();
+        assert!(error.contains("loopback address"), "{}: {}", url, error);
+    }
+    for url in [
+        "http://169.254.169.254/latest/meta-data/",
+        "http://10.0.0.1/",
+        "http://172.16.5.4/",
+        "http://192.168.1.1/",
+        "http://100.64.0.1/",
+        "http://[fd00::1]/",
+        "http://[fe80::1]/",
+        "http://[64:ff9b::a9fe:a9fe]/",
+        "http://[::10.0.0.1]/",
+        "http://[2002:a9fe:a9fe::1]/",
+        "http://[fec0::1]/",
+    ] {
+        let error = check(&policy, url).unwrap_err();
+        assert!(error.contains("allow_private_networks"), "{}: {}", url, error);
+    }
+    for url in ["http://224.0.0.1/", "http://255.255.255.255/", "http://192.0.2.1/"] {
+        assert!(check(&policy, url).is_err(), "{}", url);
+    }
+    
+    assert!(check(&policy, "http://93.184.215.14/").is_ok());
+    assert!(check(&policy, "https://[2606:4700::6810:85e5]/").is_ok());
+    assert!(check(&policy, "https://[2002:5db8:d70e::1]/").is_ok());
+    assert!(check(&policy, "https://example.com/").is_ok());
+}
+
+#[test]
+fn test_url_policy_allows_local_addresses_when_configured() {
+    let policy = UrlPolicy::new(&UrlPolicyConfig {
+        allow_loopback: true,
+        ..Default::default()
+    });
+    assert!(check(&policy, "http://127.0.0.1:8080/").is_ok());
+    assert!(check(&policy, "http://192.168.1.1/").is_err());
+    
+    let policy = UrlPolicy::new(&UrlPolicyConfig {
+        allow_private_networks: true,
+        ..Default::default()
+    });
+    assert!(check(&policy, "http://192.168.1.1/").is_ok());
+    assert!(check(&policy, "http://127.0.0.1/").is_err());
+    
+    let policy = UrlPolicy::new(&UrlPolicyConfig::default()).trusting(["127.0.0.1".to_string()]);
+    assert!(check(&policy, "http://127.0.0.1:8888/search").is_ok());
+    assert!(check(&policy, "http://127.0.0.2/").is_err());
+}
+
+#[test]
+fn test_url_policy_restricts_schemes_and_domains() {
+    let policy = UrlPolicy::new(&UrlPolicyConfig {
+        allowed_domains: vec!["docs.rs".to_string(), "*.rust-lang.org".to_string()],
+        denied_domains: vec!["internal.docs.rs".to_string()],
+        ..Default::default()
+    });
+    
+    assert!(check(&policy, "https://docs.rs/serde").is_ok());
+    assert!(check(&policy, "https://DOCS.rs./serde").is_ok());
+    assert!(check(&policy, "https://api.docs.rs/").is_ok());
+    assert!(check(&policy, "https://doc.rust-lang.org/book/").is_ok());
+    assert!(check(&policy, "https://rust-lang.org/").is_ok());
+    
+    let error = check(&policy, "https://evildocs.rs/").unwrap_err();
+    assert!(error.contains("not in an allowed domain"), "{}", error);
+    let error = check(&policy, "https://a.internal.docs.rs/").unwrap_err();
+    assert!(error.contains("denied domain 'internal.docs.rs'"), "{}", error);
+    let error = check(&policy, "file:///etc/passwd").unwrap_err();
+    assert!(error.contains("scheme 'file'"), "{}", error);
+    let error = check(&policy, "ftp://docs.rs/").unwrap_err();
+    assert!(error.contains("allowed schemes are: http, https"), "{}", error);
+}
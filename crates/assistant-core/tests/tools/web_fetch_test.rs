@@ -1,13 +1,19 @@
 use assistant_core::actors::tools::web_fetch::WebFetchActor;
 use assistant_core::messages::ToolMessage;
 use assistant_core::messages::ChatMessage;
-use assistant_core::config::Config;
+use assistant_core::config::{Config, tool_config::ToolConfig};
+use assistant_core::persistence::{Database, HttpCache};
+use assistant_core::persistence::http_cache::{CachedResponse, expires_at};
+use chrono::Utc;
+use reqwest::header::HeaderMap;
+use std::time::Duration;
 use ractor::{Actor, ActorRef};
 use serde_json::json;
 use tokio::sync::mpsc;
+use tempfile::TempDir;
 use uuid::Uuid;
 use wiremock::{MockServer, Mock, ResponseTemplate};
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{body_string_contains, header, method, path};
 
 // Mock ChatActor for testing
 struct MockChatActor {
@@ -44,6 +50,9 @@ impl Actor for MockChatActor {
 async fn setup_test() -> (Config, ActorRef<ChatMessage>, mpsc::UnboundedReceiver<ChatMessage>) {
     let mut config = Config::default();
     config.api_key = "test-api-key".to_string();
+    config.http_cache.enabled = false;
+    // The mock servers listen on 127.0.0.1
+    config.url_policy.allow_loopback = true;
     
     let (tx, rx) = mpsc::unbounded_channel();
     let mock_chat = MockChatActor { sender: tx.clone() };
@@ -382,6 +391,369 @@ async fn test_web_fetch_prompt_analysis() {
     }
 }
 
+/// Fetch `url` with `params` added and return the tool's result
+async fn fetch(config: Config, url: String, params: serde_json::Value) -> String {
+    let (_, chat_ref, mut rx) = setup_test().await;
+    let (web_fetch_ref, _) = Actor::spawn(None, WebFetchActor::new(config.clone()), config).await.unwrap();
+    
+    let mut request = json!({ "url": url, "prompt": "Read the page" });
+    request.as_object_mut().unwrap().extend(params.as_object().unwrap().clone());
+    let id = Uuid::new_v4();
+    web_fetch_ref.send_message(ToolMessage::Execute { id, params: request, chat_ref }).unwrap();
+    
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { id: res_id, result } => {
+            assert_eq!(res_id, id);
+            result
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
+#[tokio::test]
+async fn test_web_fetch_extracts_main_content_and_links() {
+    let (config, _, _) = setup_test().await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/blog/post"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_raw(r#"
+                <html>
+                <head><title>Ownership in Rust</title><script>trackVisitor();</script></head>
+                <body>
+                    <header><a href="/">Home</a> <a href="/about">About us</a></header>
+                    <nav><ul><li><a href="/archive">Archive</a></li><li><a href="/tags">Tags</a></li></ul></nav>
+                    <div class="cookie-banner">We use cookies to improve your experience. Accept all?</div>
+                    <div id="content">
+                        <h1>Ownership in Rust</h1>
+                        <p>Every value in Rust has a single owner, and the value is dropped when its owner goes out of scope.</p>
+                        <p>References borrow a value without taking ownership, as the <a href="../book/ch04.html#refs">book explains</a>.</p>
+                        <p>The rules are checked at compile time, so they cost nothing at run time; see <a href="https://doc.rust-lang.org/nomicon/">the Nomicon</a>.</p>
+                    </div>
+                    <aside class="sidebar">Popular posts: <a href="/popular">Ten tips</a></aside>
+                    <footer>Copyright 2024, all rights reserved. <a href="/privacy">Privacy</a></footer>
+                </body>
+                </html>
+            "#, "text/html; charset=utf-8"))
+        .mount(&mock_server)
+        .await;
+    
+    let result = fetch(config, format!("{}/blog/post", mock_server.uri()), json!({})).await;
+    assert!(result.contains("Title: Ownership in Rust"), "{}", result);
+    assert!(result.contains("single owner"), "{}", result);
+    assert!(result.contains("checked at compile time"), "{}", result);
+    for furniture in ["trackVisitor", "About us", "Archive", "cookies", "Popular posts", "Copyright"] {
+        assert!(!result.contains(furniture), "{} in {}", furniture, result);
+    }
+    
+    let links = result.split("Links:\n").nth(1).unwrap().split("\n\n").next().unwrap();
+    assert_eq!(
+        links,
+        format!(
+            "- [book explains]({}/book/ch04.html)\n- [the Nomicon](https://doc.rust-lang.org/nomicon/)",
+            mock_server.uri()
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_web_fetch_reads_long_content_by_page_and_offset() {
+    let (config, _, _) = setup_test().await;
+    let mock_server = MockServer::start().await;
+    // 25,000 characters: "0000 " to "4999 "
+    let content: String = (0..5000).map(|i| format!("{:04} ", i)).collect();
+    Mock::given(method("GET"))
+        .and(path("/long.txt"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string(content)
+            .insert_header("content-type", "text/plain"))
+        .mount(&mock_server)
+        .await;
+    let url = format!("{}/long.txt", mock_server.uri());
+    
+    let first = fetch(config.clone(), url.clone(), json!({})).await;
+    assert!(first.contains("Showing characters 1-20000 of 25000"), "{}", first);
+    assert!(first.contains("Content:\n0000 0001 "), "{}", first);
+    assert!(first.contains("3999 ...\n\n[Content truncated - 5000 characters omitted; fetch again with \"page\": 2 or \"offset\": 20000 to read on]"), "{}", first);
+    assert!(!first.contains("4000"), "{}", first);
+    
+    let second = fetch(config.clone(), url.clone(), json!({ "page": 2 })).await;
+    assert!(second.contains("Showing characters 20001-25000 of 25000"), "{}", second);
+    assert!(second.contains("Content:\n4000 4001 "), "{}", second);
+    assert!(second.contains("4999 \n\nAnalysis based on prompt"), "{}", second);
+    assert!(!second.contains("Content truncated"), "{}", second);
+    
+    let offset = fetch(config.clone(), url.clone(), json!({ "offset": 24990, "page": 1 })).await;
+    assert!(offset.contains("Showing characters 24991-25000 of 25000"), "{}", offset);
+    assert!(offset.contains("Content:\n4998 4999 \n"), "{}", offset);
+    
+    let past = fetch(config.clone(), url.clone(), json!({ "page": 3 })).await;
+    assert!(past.starts_with("Error: Offset 40000 is past the end"), "{}", past);
+    
+    let huge = fetch(config, url, json!({ "page": u64::MAX })).await;
+    assert!(huge.starts_with(&format!("Error: Page {} is out of range", u64::MAX)), "{}", huge);
+}
+
+/// A config caching responses in a database in `dir`
+fn with_cache(mut config: Config, dir: &TempDir) -> Config {
+    config.http_cache.enabled = true;
+    config.session.database_path = Some(dir.path().join("assistant.db"));
+    config
+}
+
+#[tokio::test]
+async fn test_web_fetch_caches_pages_until_they_expire() {
+    let (config, _, _) = setup_test().await;
+    let dir = TempDir::new().unwrap();
+    let config = with_cache(config, &dir);
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/docs"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string("Version 1 of the docs")
+            .insert_header("cache-control", "public, max-age=3600"))
+        // The first fetch and the one bypassing the cache
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+    let url = format!("{}/docs", mock_server.uri());
+    
+    let first = fetch(config.clone(), url.clone(), json!({})).await;
+    assert!(first.contains("Version 1 of the docs"), "{}", first);
+    assert!(!first.contains("Cached copy"), "{}", first);
+    
+    let second = fetch(config.clone(), url.clone(), json!({})).await;
+    assert!(second.contains("Version 1 of the docs"), "{}", second);
+    assert!(second.contains("Cached copy from: "), "{}", second);
+    
+    let bypassed = fetch(config, url, json!({ "no_cache": true })).await;
+    assert!(bypassed.contains("Version 1 of the docs"), "{}", bypassed);
+    assert!(!bypassed.contains("Cached copy"), "{}", bypassed);
+}
+
+#[tokio::test]
+async fn test_web_fetch_revalidates_stale_pages() {
+    let (config, _, _) = setup_test().await;
+    let dir = TempDir::new().unwrap();
+    let config = with_cache(config, &dir);
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/changelog"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/changelog"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string("Released 2.0")
+            .insert_header("etag", "\"v1\"")
+            .insert_header("cache-control", "no-cache"))
+        .with_priority(2)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let url = format!("{}/changelog", mock_server.uri());
+    
+    let first = fetch(config.clone(), url.clone(), json!({})).await;
+    assert!(first.contains("Released 2.0"), "{}", first);
+    
+    // Asked again, the server only confirms the copy is current
+    let second = fetch(config, url, json!({})).await;
+    assert!(second.contains("Released 2.0"), "{}", second);
+    assert!(second.contains("Cached copy from: "), "{}", second);
+}
+
+#[tokio::test]
+async fn test_http_cache_keeps_recent_responses_within_its_size() {
+    let dir = TempDir::new().unwrap();
+    let db = Database::new(&dir.path().join("assistant.db")).await.unwrap();
+    let cache = HttpCache::new(db, 250);
+    let expires_at = Utc::now() + chrono::Duration::hours(1);
+    let response = |body: &str| CachedResponse::new("https://example.com", &HeaderMap::new(), body.as_bytes().to_vec(), expires_at);
+    
+    cache.put("a", &response(&"a".repeat(100))).await.unwrap();
+    cache.put("b", &response(&"b".repeat(100))).await.unwrap();
+    // Using `a` makes `b` the least recently used
+    assert!(cache.get("a").await.unwrap().unwrap().is_fresh());
+    cache.put("c", &response(&"c".repeat(100))).await.unwrap();
+    
+    assert!(cache.get("a").await.unwrap().is_some());
+    assert!(cache.get("b").await.unwrap().is_none());
+    assert_eq!(cache.get("c").await.unwrap().unwrap().body, "c".repeat(100).into_bytes());
+}
+
+#[test]
+fn test_http_cache_expiry_follows_response_headers() {
+    let ttl = Duration::from_secs(60);
+    let headers = |pairs: &[(&'static str, &str)]| {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    };
+    let seconds_left = |pairs: &[(&'static str, &str)]| {
+        expires_at(&headers(pairs), ttl).map(|time| (time - Utc::now()).num_seconds())
+    };
+    
+    assert_eq!(seconds_left(&[("cache-control", "private, no-store")]), None);
+    assert!(seconds_left(&[("cache-control", "no-cache")]).unwrap() <= 0);
+    assert!((3590..=3600).contains(&seconds_left(&[("cache-control", "max-age=3600"), ("expires", "0")]).unwrap()));
+    assert!(seconds_left(&[("expires", "Thu, 01 Dec 1994 16:00:00 GMT")]).unwrap() <= 0);
+    assert!((50..=60).contains(&seconds_left(&[]).unwrap()));
+}
+
+/// Point web_fetch's analysis model at the mock server
+fn with_analysis_model(mut config: Config, mock_server: &MockServer) -> Config {
+    config.tools.configs.insert("web_fetch".to_string(), ToolConfig {
+        provider: Some("openai".to_string()),
+        base_url: Some(mock_server.uri()),
+        model: Some("small-model".to_string()),
+        ..Default::default()
+    });
+    config
+}
+
+fn completion(content: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 0,
+        "model": "small-model",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": "stop"
+        }],
+        "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+    }))
+}
+
+#[tokio::test]
+async fn test_web_fetch_answers_prompt_with_model() {
+    let (config, chat_ref, mut rx) = setup_test().await;
+    let mock_server = MockServer::start().await;
+    let config = with_analysis_model(config, &mock_server);
+    
+    // Long enough to be read in two parts, with the answer in the second
+    let mut page: String = (0..300).map(|i| format!("Filler line {} about nothing in particular.\n", i)).collect();
+    page.push_str("The borrow checker enforces ownership at compile time.\n");
+    Mock::given(method("GET"))
+        .and(path("/book"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string(page)
+            .insert_header("content-type", "text/plain"))
+        .mount(&mock_server)
+        .await;
+    
+    // Combining the notes
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("Notes on part 2"))
+        .respond_with(completion("Ownership is checked when compiling.\n> The borrow checker enforces ownership at compile time."))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    // Reading the part with the answer
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("The borrow checker enforces"))
+        .respond_with(completion("> The borrow checker enforces ownership at compile time."))
+        .with_priority(2)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    // Reading the filler
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(completion("NONE"))
+        .with_priority(3)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    
+    let web_fetch_actor = WebFetchActor::new(config.clone());
+    let (web_fetch_ref, _) = Actor::spawn(None, web_fetch_actor, config).await.unwrap();
+    
+    let id = Uuid::new_v4();
+    web_fetch_ref.send_message(ToolMessage::Execute {
+        id,
+        params: json!({
+            "url": format!("{}/book", mock_server.uri()),
+            "prompt": "How does Rust check ownership?"
+        }),
+        chat_ref,
+    }).unwrap();
+    
+    // Each of the three requests is charged to the tool call before its result
+    for _ in 0..3 {
+        match rx.recv().await.unwrap() {
+            ChatMessage::Usage { id: res_id, model, usage, sub_agent } => {
+                assert_eq!(res_id, id);
+                assert_eq!(model, "small-model");
+                assert_eq!((usage.prompt_tokens, usage.completion_tokens), (1, 1));
+                assert_eq!(sub_agent.as_deref(), Some("web_fetch"));
+            }
+            other => panic!("Expected Usage message, got {:?}", other),
+        }
+    }
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { id: res_id, result } => {
+            assert_eq!(res_id, id);
+            assert!(result.contains("read in 2 parts"), "{}", result);
+            assert!(result.ends_with("Ownership is checked when compiling.\n> The borrow checker enforces ownership at compile time."), "{}", result);
+            assert!(!result.contains("Analysis based on prompt"), "{}", result);
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
+#[tokio::test]
+async fn test_web_fetch_falls_back_when_model_fails() {
+    let (config, chat_ref, mut rx) = setup_test().await;
+    let mock_server = MockServer::start().await;
+    let config = with_analysis_model(config, &mock_server);
+    
+    Mock::given(method("GET"))
+        .and(path("/page"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string("Rust is a systems programming language.")
+            .insert_header("content-type", "text/plain"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&mock_server)
+        .await;
+    
+    let web_fetch_actor = WebFetchActor::new(config.clone());
+    let (web_fetch_ref, _) = Actor::spawn(None, web_fetch_actor, config).await.unwrap();
+    
+    let id = Uuid::new_v4();
+    web_fetch_ref.send_message(ToolMessage::Execute {
+        id,
+        params: json!({
+            "url": format!("{}/page", mock_server.uri()),
+            "prompt": "Find information about Rust"
+        }),
+        chat_ref,
+    }).unwrap();
+    
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { id: res_id, result } => {
+            assert_eq!(res_id, id);
+            assert!(result.contains("Analysis based on prompt"), "{}", result);
+            assert!(result.contains("sections potentially relevant"), "{}", result);
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
 #[tokio::test]
 async fn test_web_fetch_invalid_parameters() {
     let (config, chat_ref, mut rx) = setup_test().await;
@@ -413,4 +785,74 @@ async fn test_web_fetch_invalid_parameters() {
         }
         _ => panic!("Expected ToolResult message"),
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_web_fetch_refuses_internal_addresses() {
+    let (mut config, _, _) = setup_test().await;
+    config.url_policy.allow_loopback = false;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("admin panel"))
+        .mount(&mock_server)
+        .await;
+    let port = mock_server.address().port();
+    
+    // Written into the URL, and found by resolving the host name
+    for url in [
+        "http://169.254.169.254/latest/meta-data/".to_string(),
+        mock_server.uri(),
+        format!("http://localhost:{}/", port),
+    ] {
+        let result = fetch(config.clone(), url.clone(), json!({})).await;
+        assert!(result.starts_with("Error: URL policy violation"), "{}: {}", url, result);
+        assert!(!result.contains("admin panel"), "{}", result);
+    }
+    let result = fetch(config.clone(), format!("http://localhost:{}/", port), json!({})).await;
+    assert!(result.contains("'localhost' resolves to"), "{}", result);
+    assert!(result.contains("url_policy.allow_loopback"), "{}", result);
+    
+    let result = fetch(config, "file:///etc/passwd".to_string(), json!({})).await;
+    assert!(result.contains("scheme 'file'"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_web_fetch_checks_every_redirect() {
+    let (mut config, _, _) = setup_test().await;
+    config.url_policy.denied_domains = vec!["corp.internal".to_string()];
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/metadata"))
+        .respond_with(ResponseTemplate::new(302).insert_header("Location", "http://169.254.169.254/latest/meta-data/"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/hop"))
+        .respond_with(ResponseTemplate::new(301).insert_header("Location", "/intranet"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/intranet"))
+        .respond_with(ResponseTemplate::new(302).insert_header("Location", "http://wiki.corp.internal/"))
+        .mount(&mock_server)
+        .await;
+    
+    let result = fetch(config.clone(), format!("{}/metadata", mock_server.uri()), json!({})).await;
+    assert!(result.starts_with("Error: URL policy violation"), "{}", result);
+    assert!(result.contains("169.254.169.254"), "{}", result);
+    assert!(result.contains("link-local"), "{}", result);
+    
+    let result = fetch(config, format!("{}/hop", mock_server.uri()), json!({})).await;
+    assert!(result.contains("'http://wiki.corp.internal/' is in the denied domain 'corp.internal'"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_web_fetch_only_fetches_allowed_domains() {
+    let (mut config, _, _) = setup_test().await;
+    config.url_policy.allowed_domains = vec!["docs.rs".to_string()];
+    
+    let result = fetch(config, "https://example.com/".to_string(), json!({})).await;
+    
+    assert!(result.contains("'https://example.com/' is not in an allowed domain; allowed domains are: docs.rs"), "{}", result);
+}
+
@@ -2,10 +2,21 @@ use assistant_core::actors::tools::web_search::WebSearchActor;
 use assistant_core::messages::ToolMessage;
 use assistant_core::messages::ChatMessage;
 use assistant_core::config::Config;
+use assistant_core::persistence::{Database, HttpCache};
+use assistant_core::persistence::http_cache::CachedResponse;
+use assistant_core::search::{
+    BraveBackend, DuckDuckGoBackend, SearchBackend, SearchResult, SearxngBackend, TavilyBackend,
+};
+use assistant_core::config::tool_config::ToolConfig;
+use reqwest::Client;
+use reqwest::header::HeaderMap;
 use ractor::{Actor, ActorRef};
 use serde_json::json;
 use tokio::sync::mpsc;
+use tempfile::TempDir;
 use uuid::Uuid;
+use wiremock::{MockServer, Mock, ResponseTemplate};
+use wiremock::matchers::{body_json, header, method, path, query_param};
 
 // Mock ChatActor for testing
 struct MockChatActor {
@@ -42,6 +53,7 @@ impl Actor for MockChatActor {
 async fn setup_test() -> (Config, ActorRef<ChatMessage>, mpsc::UnboundedReceiver<ChatMessage>) {
     let mut config = Config::default();
     config.api_key = "test-api-key".to_string();
+    config.http_cache.enabled = false;
     
     let (tx, rx) = mpsc::unbounded_channel();
     let mock_chat = MockChatActor { sender: tx.clone() };
@@ -184,4 +196,284 @@ async fn test_web_search_zero_limit() {
         }
         _ => panic!("Expected ToolResult message"),
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_web_search_answers_repeated_queries_from_the_cache() {
+    let (mut config, chat_ref, mut rx) = setup_test().await;
+    let dir = TempDir::new().unwrap();
+    config.http_cache.enabled = true;
+    config.session.database_path = Some(dir.path().join("assistant.db"));
+    
+    // Results kept from an earlier search
+    let results = vec![SearchResult {
+        title: "What is Ownership?".to_string(),
+        url: "https://doc.rust-lang.org/book/ch04-01-what-is-ownership.html".to_string(),
+        description: "Ownership is a set of rules that govern how a Rust program manages memory.".to_string(),
+    }];
+    let db = Database::new(config.session.database_path.as_ref().unwrap()).await.unwrap();
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let cached = CachedResponse::new("search:duckduckgo:5:rust ownership", &HeaderMap::new(), serde_json::to_vec(&results).unwrap(), expires_at);
+    HttpCache::new(db, 1024 * 1024).put("search:duckduckgo:5:rust ownership", &cached).await.unwrap();
+    
+    let web_search_actor = WebSearchActor::new(config.clone());
+    let (web_search_ref, _) = Actor::spawn(None, web_search_actor, config).await.unwrap();
+    let id = Uuid::new_v4();
+    web_search_ref.send_message(ToolMessage::Execute {
+        id,
+        params: json!({ "query": " rust ownership " }),
+        chat_ref,
+    }).unwrap();
+    
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { id: res_id, result } => {
+            assert_eq!(res_id, id);
+            assert!(result.contains("1. What is Ownership?"), "{}", result);
+            assert!(result.contains("URL: https://doc.rust-lang.org/book/ch04-01-what-is-ownership.html"), "{}", result);
+            assert!(result.contains("Total results shown: 1"), "{}", result);
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
+#[tokio::test]
+async fn test_duckduckgo_backend_reads_the_html_results_page() {
+    let mock_server = MockServer::start().await;
+    let page = r#"
+        <html><body>
+        <div class="result results_links results_links_deep web-result">
+            <h2><a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fdoc.rust-lang.org%2Fbook%2F&rut=1">The Rust Book</a></h2>
+            <a class="result__snippet">An introductory book about Rust.</a>
+        </div>
+        <div class="result results_links results_links_deep web-result">
+            <h2><a class="result__a" href="https://www.rust-lang.org/">Rust Programming Language</a></h2>
+            <a class="result__snippet">A language empowering everyone.</a>
+        </div>
+        </body></html>
+    "#;
+    Mock::given(method("GET"))
+        .and(path("/html/"))
+        .and(query_param("q", "rust book"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(page, "text/html"))
+        .mount(&mock_server)
+        .await;
+    
+    let backend = DuckDuckGoBackend::new(Client::new(), Some(format!("{}/html/", mock_server.uri())));
+    let results = backend.search("rust book", 1).await.unwrap();
+    
+    assert_eq!(results, vec![SearchResult {
+        title: "The Rust Book".to_string(),
+        url: "https://doc.rust-lang.org/book/".to_string(),
+        description: "An introductory book about Rust.".to_string(),
+    }]);
+}
+
+#[tokio::test]
+async fn test_searxng_backend_reads_json_results() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search"))
+        .and(query_param("q", "rust book"))
+        .and(query_param("format", "json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "query": "rust book",
+            "results": [
+                { "title": "The Rust Book", "url": "https://doc.rust-lang.org/book/", "content": "An introductory book about Rust.", "engine": "bing" },
+                { "title": "Rust by Example", "url": "https://doc.rust-lang.org/rust-by-example/", "engine": "google" },
+                { "title": "Rustlings", "url": "https://rustlings.rust-lang.org/", "content": "Small exercises." }
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let backend = SearxngBackend::new(Client::new(), mock_server.uri());
+    let results = backend.search("rust book", 2).await.unwrap();
+    
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].title, "The Rust Book");
+    assert_eq!(results[0].description, "An introductory book about Rust.");
+    assert_eq!(results[1].url, "https://doc.rust-lang.org/rust-by-example/");
+    assert_eq!(results[1].description, "");
+}
+
+#[tokio::test]
+async fn test_brave_backend_sends_its_key_and_reads_web_results() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/web/search"))
+        .and(query_param("q", "rust book"))
+        .and(query_param("count", "5"))
+        .and(header("X-Subscription-Token", "brave-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "search",
+            "web": {
+                "results": [
+                    { "title": "The <strong>Rust</strong> Book", "url": "https://doc.rust-lang.org/book/", "description": "An introductory <strong>book</strong> about Rust." }
+                ]
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let backend = BraveBackend::new(Client::new(), "brave-key".to_string(), Some(mock_server.uri()));
+    let results = backend.search("rust book", 5).await.unwrap();
+    
+    assert_eq!(results, vec![SearchResult {
+        title: "The Rust Book".to_string(),
+        url: "https://doc.rust-lang.org/book/".to_string(),
+        description: "An introductory book about Rust.".to_string(),
+    }]);
+}
+
+#[tokio::test]
+async fn test_tavily_backend_posts_the_query_with_its_key() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/search"))
+        .and(header("Authorization", "Bearer tavily-key"))
+        .and(body_json(json!({ "query": "rust book", "max_results": 3 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "query": "rust book",
+            "results": [
+                { "title": "The Rust Book", "url": "https://doc.rust-lang.org/book/", "content": "An introductory book about Rust.", "score": 0.98 }
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let backend = TavilyBackend::new(Client::new(), "tavily-key".to_string(), Some(mock_server.uri()));
+    let results = backend.search("rust book", 3).await.unwrap();
+    
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].title, "The Rust Book");
+    assert_eq!(results[0].description, "An introductory book about Rust.");
+}
+
+#[tokio::test]
+async fn test_backend_errors_carry_the_http_status() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("format json is not allowed"))
+        .mount(&mock_server)
+        .await;
+    
+    let backend = SearxngBackend::new(Client::new(), mock_server.uri());
+    let error = backend.search("rust book", 5).await.unwrap_err().to_string();
+    
+    assert!(error.contains("403"), "{}", error);
+    assert!(error.contains("format json is not allowed"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_web_search_falls_back_to_the_next_backend() {
+    let (mut config, chat_ref, mut rx) = setup_test().await;
+    let failing = MockServer::start().await;
+    let working = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("instance overloaded"))
+        .mount(&failing)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/web/search"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "web": { "results": [
+                { "title": "The Rust Book", "url": "https://doc.rust-lang.org/book/", "description": "An introductory book about Rust." }
+            ] }
+        })))
+        .mount(&working)
+        .await;
+    config.tools.configs.insert("web_search".to_string(), ToolConfig {
+        settings: [("backends".to_string(), json!([
+            { "type": "searxng", "base_url": failing.uri() },
+            { "type": "brave", "api_key": "brave-key", "base_url": working.uri() }
+        ]))].into_iter().collect(),
+        ..Default::default()
+    });
+    
+    let web_search_actor = WebSearchActor::new(config.clone());
+    let (web_search_ref, _) = Actor::spawn(None, web_search_actor, config).await.unwrap();
+    let id = Uuid::new_v4();
+    web_search_ref.send_message(ToolMessage::Execute {
+        id,
+        params: json!({ "query": "rust book" }),
+        chat_ref,
+    }).unwrap();
+    
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { result, .. } => {
+            assert!(result.contains("Search results for 'rust book' from brave"), "{}", result);
+            assert!(result.contains("1. The Rust Book"), "{}", result);
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
+#[tokio::test]
+async fn test_web_search_reports_every_failed_backend() {
+    let (mut config, chat_ref, mut rx) = setup_test().await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&mock_server)
+        .await;
+    config.tools.configs.insert("web_search".to_string(), ToolConfig {
+        settings: [("backends".to_string(), json!([
+            { "type": "searxng", "base_url": mock_server.uri() },
+            { "type": "duckduckgo", "base_url": format!("{}/html/", mock_server.uri()) }
+        ]))].into_iter().collect(),
+        ..Default::default()
+    });
+    
+    let web_search_actor = WebSearchActor::new(config.clone());
+    let (web_search_ref, _) = Actor::spawn(None, web_search_actor, config).await.unwrap();
+    web_search_ref.send_message(ToolMessage::Execute {
+        id: Uuid::new_v4(),
+        params: json!({ "query": "rust book" }),
+        chat_ref,
+    }).unwrap();
+    
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { result, .. } => {
+            assert!(result.starts_with("Error: Every search backend failed"), "{}", result);
+            assert!(result.contains("- searxng: HTTP 503"), "{}", result);
+            assert!(result.contains("- duckduckgo: HTTP 503"), "{}", result);
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
+#[tokio::test]
+async fn test_web_search_checks_backend_redirects() {
+    let (mut config, chat_ref, mut rx) = setup_test().await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search"))
+        .respond_with(ResponseTemplate::new(302).insert_header("Location", "http://169.254.169.254/latest/meta-data/"))
+        .mount(&mock_server)
+        .await;
+    // The configured instance itself is trusted, though it is on 127.0.0.1
+    config.tools.configs.insert("web_search".to_string(), ToolConfig {
+        settings: [("backends".to_string(), json!([
+            { "type": "searxng", "base_url": mock_server.uri() }
+        ]))].into_iter().collect(),
+        ..Default::default()
+    });
+    
+    let web_search_actor = WebSearchActor::new(config.clone());
+    let (web_search_ref, _) = Actor::spawn(None, web_search_actor, config).await.unwrap();
+    web_search_ref.send_message(ToolMessage::Execute {
+        id: Uuid::new_v4(),
+        params: json!({ "query": "rust book" }),
+        chat_ref,
+    }).unwrap();
+    
+    match rx.recv().await.unwrap() {
+        ChatMessage::ToolResult { result, .. } => {
+            assert!(result.contains("- searxng: URL policy violation: 'http://169.254.169.254/latest/meta-data/'"), "{}", result);
+        }
+        _ => panic!("Expected ToolResult message"),
+    }
+}
+
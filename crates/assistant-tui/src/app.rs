@@ -7,7 +7,7 @@ use crate::{
 use anyhow::Result;
 use assistant_core::{
     config::Config,
-    messages::{ChatMessage, UserMessageContent, DisplayContext},
+    messages::{ChatMessage, ConfirmationDecision, UserMessageContent, DisplayContext, UIMessage},
     ractor::{Actor, ActorRef},
     actors::conversation_manager::{ConversationManagerActor, ConversationManagerMessage},
     persistence::database::Database,
@@ -93,6 +93,27 @@ impl TuiApp {
                     display_ref,
                 })?;
                 
+                if let Some(worktree) = &actors.worktree {
+                    state.add_message(
+                        MessageType::Info,
+                        format!(
+                            "Working in {} on branch {}. Review with `assistant worktree diff {}`, then `merge` or `discard` it",
+                            worktree.path.display(),
+                            worktree.branch,
+                            worktree.name
+                        ),
+                    );
+                }
+                // Relative paths in commands then resolve inside the worktree
+                if let Some(workspace) = &actors.workspace
+                    && let Err(e) = std::env::set_current_dir(workspace)
+                {
+                    state.add_message(
+                        MessageType::Error,
+                        format!("Failed to change into the worktree at {}: {}", workspace.display(), e),
+                    );
+                }
+                
                 Some(actors)
             }
             Err(e) => {
@@ -115,6 +136,20 @@ impl TuiApp {
     }
     
     pub async fn run(&mut self) -> Result<()> {
+        // Finish the worktree however the loop ends
+        let result = self.event_loop().await;
+        
+        // A worktree the agent changed nothing in is only clutter
+        if let Some(worktree) = self.actor_system.as_ref().and_then(|actors| actors.worktree.as_ref())
+            && let Err(e) = assistant_core::worktree::finish(worktree)
+        {
+            tracing::warn!("Failed to check worktree {} for changes: {}", worktree.name, e);
+        }
+        
+        result
+    }
+    
+    async fn event_loop(&mut self) -> Result<()> {
         let mut event_stream = EventStream::new();
         
         loop {
@@ -168,11 +203,15 @@ impl TuiApp {
             crate::state::ViewMode::ConversationList => self.handle_conversation_list_action(action).await,
             crate::state::ViewMode::RenameDialog { .. } => self.handle_rename_dialog_action(action).await,
             crate::state::ViewMode::DeleteConfirmation { .. } => self.handle_delete_confirmation_action(action).await,
+            crate::state::ViewMode::ToolConfirmation => self.handle_tool_confirmation_action(action).await,
         }
     }
     
     async fn handle_chat_action(&mut self, action: Action) -> Result<()> {
         match action {
+            Action::Quit | Action::Escape if self.state.request_in_progress => {
+                self.cancel_request();
+            }
             Action::Quit => {
                 self.state.should_quit = true;
             }
@@ -336,6 +375,36 @@ impl TuiApp {
         Ok(())
     }
     
+    async fn handle_tool_confirmation_action(&mut self, action: Action) -> Result<()> {
+        let decision = match action {
+            Action::InsertChar('y') | Action::InsertChar('Y') => ConfirmationDecision::Allow,
+            Action::InsertChar('a') | Action::InsertChar('A') => ConfirmationDecision::AllowForSession,
+            Action::Escape | Action::InsertChar('n') | Action::InsertChar('N') => ConfirmationDecision::Deny,
+            _ => return Ok(()),
+        };
+        
+        if let Some(request) = self.state.pending_confirmations.pop_front() {
+            let answer = match decision {
+                ConfirmationDecision::Allow => "allowed",
+                ConfirmationDecision::AllowForSession => "allowed for this session",
+                ConfirmationDecision::Deny => "denied",
+            };
+            self.state.add_message(MessageType::Info, format!("🔒 {} call {}", request.tool_name, answer));
+            
+            if let Some(ref actor_system) = self.actor_system {
+                let _ = actor_system.chat.send_message(ChatMessage::ConfirmationResponse {
+                    id: request.id,
+                    decision,
+                });
+            }
+        }
+        
+        if self.state.pending_confirmations.is_empty() {
+            self.state.view_mode = crate::state::ViewMode::Chat;
+        }
+        Ok(())
+    }
+    
     async fn send_to_assistant(&mut self, input: String) {
         if let Some(ref actor_system) = self.actor_system {
             // Create a new session if needed (only when actually sending a message)
@@ -382,6 +451,7 @@ impl TuiApp {
             }
             
             let message_id = self.state.start_streaming_message(MessageType::Assistant);
+            self.state.request_in_progress = true;
             
             if let Err(e) = actor_system.chat.send_message(ChatMessage::UserPrompt { 
                 id: Uuid::new_v4(),
@@ -390,6 +460,7 @@ impl TuiApp {
                 session_id: self.state.current_session_id.clone(),
             }) {
                 self.state.finish_streaming_message(message_id);
+                self.state.request_in_progress = false;
                 self.state.add_message(
                     MessageType::Error, 
                     format!("Failed to send message: {}", e)
@@ -403,6 +474,22 @@ impl TuiApp {
         }
     }
     
+    /// Stop the prompt being answered; the chat actor records its unfinished tool calls as cancelled
+    fn cancel_request(&mut self) {
+        if let Some(actor_system) = &self.actor_system
+            && let Err(e) = actor_system.chat.send_message(ChatMessage::Cancel)
+        {
+            self.state.add_message(MessageType::Error, format!("Failed to cancel: {}", e));
+            return;
+        }
+        
+        if let Some(msg) = self.state.messages.iter_mut().rev().find(|m| m.is_streaming) {
+            msg.is_streaming = false;
+        }
+        self.state.is_streaming = false;
+        self.state.request_in_progress = false;
+    }
+    
     fn handle_chat_message(&mut self, msg: ChatMessage) {
         match msg {
             ChatMessage::StreamToken { token } => {
@@ -420,6 +507,7 @@ impl TuiApp {
                 }
             }
             ChatMessage::Complete { id: _, response } => {
+                self.state.request_in_progress = false;
                 // Check if we have a streaming message to complete
                 if let Some(msg) = self.state.messages.iter_mut().rev().find(|m| m.is_streaming) {
                     // If the streaming message is empty but we have a response, use the response
@@ -437,6 +525,7 @@ impl TuiApp {
                 }
             }
             ChatMessage::Error { id: _, error } => {
+                self.state.request_in_progress = false;
                 self.state.add_message(MessageType::Error, error);
             }
             ChatMessage::ToolRequest { id: _, call } => {
@@ -452,7 +541,12 @@ impl TuiApp {
                     format!("🔧 Calling {}: {}", call.tool_name, params_str)
                 );
             }
-            ChatMessage::ToolResult { id: _, result } => {
+            ChatMessage::ToolOutput { id, output } => {
+                self.state.append_tool_output(id, &output);
+            }
+            ChatMessage::ToolResult { id, result } => {
+                self.state.tool_outputs.remove(&id);
+                
                 // First, finish any existing streaming message
                 if let Some(msg) = self.state.messages.iter_mut().rev().find(|m| m.is_streaming) {
                     msg.is_streaming = false;
@@ -463,6 +557,10 @@ impl TuiApp {
                 self.state.add_message(MessageType::Info, format!("📋 Tool result: {}", result));
             }
             ChatMessage::AssistantResponse { id: _, content, tool_calls } => {
+                // A response without tool calls answers the prompt
+                if tool_calls.is_empty() {
+                    self.state.request_in_progress = false;
+                }
                 // When we get an assistant response, check if we need to start a new message
                 if tool_calls.is_empty() && content.is_some() {
                     // This is a text response after tool calls or initial response
@@ -475,6 +573,14 @@ impl TuiApp {
                 }
                 // Tool calls are handled separately via ToolRequest messages
             }
+            ChatMessage::UIUpdate(UIMessage::UpdateStats(stats)) => {
+                self.state.stats = Some(stats);
+            }
+            ChatMessage::UIUpdate(UIMessage::ShowConfirmation(request)) => {
+                // The tool call waits until the dialog is answered
+                self.state.pending_confirmations.push_back(request);
+                self.state.view_mode = crate::state::ViewMode::ToolConfirmation;
+            }
             _ => {}
         }
     }
@@ -1,7 +1,12 @@
 use assistant_core::Config;
+use assistant_core::messages::{ConfirmationRequest, Stats};
 use assistant_core::persistence::schema::SessionSummary;
+use assistant_core::uuid::Uuid;
 use chrono::{DateTime, Utc};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+
+/// Lines of a running tool's output kept on screen
+const TOOL_OUTPUT_LINES: usize = 20;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum MessageType {
@@ -41,6 +46,8 @@ pub enum ViewMode {
     RenameDialog { session_id: String },
     /// Delete confirmation dialog
     DeleteConfirmation { session_id: String },
+    /// Permission dialog for the first of `AppState::pending_confirmations`
+    ToolConfirmation,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -194,6 +201,14 @@ pub struct AppState {
     pub terminal_size: (u16, u16),
     pub conversation_list: ConversationListState,
     pub current_session_id: Option<String>,
+    /// Latest token usage statistics reported by the chat actor
+    pub stats: Option<Stats>,
+    /// Tool calls waiting for the user's permission, oldest first
+    pub pending_confirmations: VecDeque<ConfirmationRequest>,
+    /// Message showing the live output of each running tool call
+    pub tool_outputs: HashMap<Uuid, usize>,
+    /// Whether a prompt is being answered, so Esc and Ctrl+C cancel it
+    pub request_in_progress: bool,
 }
 
 impl AppState {
@@ -212,6 +227,10 @@ impl AppState {
             terminal_size: (80, 24),
             conversation_list: ConversationListState::new(),
             current_session_id: None,
+            stats: None,
+            pending_confirmations: VecDeque::new(),
+            tool_outputs: HashMap::new(),
+            request_in_progress: false,
         };
         
         // Add welcome message
@@ -257,6 +276,28 @@ impl AppState {
         }
     }
 
+    /// Append output of a running tool call to its message, keeping only the last lines
+    pub fn append_tool_output(&mut self, tool_id: Uuid, output: &str) {
+        let message_id = match self.tool_outputs.get(&tool_id) {
+            Some(&message_id) => message_id,
+            None => {
+                self.add_message(MessageType::Tool { name: "output".to_string() }, String::new());
+                self.tool_outputs.insert(tool_id, self.message_id_counter);
+                self.message_id_counter
+            }
+        };
+        
+        if let Some(msg) = self.messages.iter_mut().find(|m| m.id == message_id) {
+            msg.content.push_str(output);
+            let lines: Vec<&str> = msg.content.lines().collect();
+            if lines.len() > TOOL_OUTPUT_LINES {
+                msg.content = lines[lines.len() - TOOL_OUTPUT_LINES..].join("\n");
+                msg.content.push('\n');
+            }
+        }
+        self.scroll_to_bottom();
+    }
+
     pub fn finish_streaming_message(&mut self, id: usize) {
         if let Some(msg) = self.messages.iter_mut().find(|m| m.id == id) {
             msg.is_streaming = false;
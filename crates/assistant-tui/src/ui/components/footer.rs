@@ -17,6 +17,24 @@ pub fn render_footer(area: Rect, buf: &mut Buffer, state: &AppState) {
     
     spans.push(Span::raw(" | "));
     
+    if let Some(stats) = &state.stats {
+        spans.push(Span::styled(
+            format!(
+                "Tokens: {} (last: {}) | Cost: ${:.4}",
+                stats.session.total_tokens(),
+                stats.request.total_tokens(),
+                stats.session.cost,
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
+        
+        spans.push(Span::raw(" | "));
+    }
+    
+    if state.request_in_progress {
+        spans.push(Span::styled("Esc: Cancel | ", Style::default().fg(Color::DarkGray)));
+    }
+    
     spans.push(Span::styled(
         "Ctrl+C: Exit | Ctrl+L: Clear | Ctrl+S: Sessions | Ctrl+O: Errors | Tab: Complete",
         Style::default().fg(Color::DarkGray),
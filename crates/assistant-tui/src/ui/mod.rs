@@ -12,6 +12,7 @@ pub fn render(frame: &mut Frame, state: &AppState) {
         ViewMode::ConversationList => render_conversation_list_view(frame, state),
         ViewMode::RenameDialog { session_id } => render_rename_dialog(frame, state, session_id),
         ViewMode::DeleteConfirmation { session_id } => render_delete_confirmation(frame, state, session_id),
+        ViewMode::ToolConfirmation => render_tool_confirmation(frame, state),
     }
 }
 
@@ -119,4 +120,79 @@ fn render_delete_confirmation(frame: &mut Frame, state: &AppState, session_id: &
         .alignment(ratatui::layout::Alignment::Center);
     
     frame.render_widget(paragraph, dialog_area);
-}
\ No newline at end of file
+}
+fn render_tool_confirmation(frame: &mut Frame, state: &AppState) {
+    use assistant_core::messages::ConfirmationType;
+    use ratatui::{
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+        widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    };
+    
+    // Render the chat view in the background
+    render_chat_view(frame, state);
+    
+    let Some(request) = state.pending_confirmations.front() else {
+        return;
+    };
+    
+    // Calculate centered position for dialog
+    let area = frame.area();
+    let dialog_width = 80.min(area.width - 4);
+    let dialog_height = 16.min(area.height - 4);
+    let x = (area.width - dialog_width) / 2;
+    let y = (area.height - dialog_height) / 2;
+    
+    let dialog_area = ratatui::layout::Rect::new(x, y, dialog_width, dialog_height);
+    
+    // Clear the area for the dialog
+    frame.render_widget(Clear, dialog_area);
+    
+    let mut text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(request.tool_name.as_str(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!(": {}", request.description)),
+        ]),
+        Line::from(""),
+    ];
+    match &request.confirm_type {
+        ConfirmationType::Execute { command } => text.push(Line::from(format!("$ {}", command))),
+        ConfirmationType::Edit { file, diff } => {
+            text.push(Line::from(format!("File: {}", file)));
+            text.extend(diff.lines().map(|line| {
+                let color = if line.starts_with('-') { Color::Red } else { Color::Green };
+                Line::styled(line.to_string(), Style::default().fg(color))
+            }));
+        }
+        ConfirmationType::WebAccess { url } => text.push(Line::from(format!("URL: {}", url))),
+    }
+    text.push(Line::from(""));
+    text.push(Line::from(vec![
+        Span::styled("Y", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::raw("es / "),
+        Span::styled("N", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        Span::raw("o / "),
+        Span::styled("A", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw("lways this session"),
+    ]));
+    
+    let waiting = state.pending_confirmations.len();
+    let title = if waiting > 1 {
+        format!(" Tool Permission (1 of {}) ", waiting)
+    } else {
+        " Tool Permission ".to_string()
+    };
+    
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .wrap(Wrap { trim: false });
+    
+    frame.render_widget(paragraph, dialog_area);
+}